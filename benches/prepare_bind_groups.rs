@@ -0,0 +1,176 @@
+//! Per-frame cost of the bind group preparation of the naive and HE-WBOIT paths.
+//!
+//! Both `prepare_wboit_composite_bind_group` and `prepare_histo_wboit_bind_groups` rebuild
+//! their bind groups every frame. For a few camera counts this reports how many bind groups
+//! each frame creates per camera, and the average wall time of one run of the system.
+//!
+//! Runs headless on whatever adapter is available (software rasterizers included) and skips
+//! without one: `cargo bench --bench prepare_bind_groups`.
+
+use std::time::{Duration, Instant};
+
+use bevy::core_pipeline::core_3d::graph::Core3d;
+use bevy::ecs::system::ScheduleSystem;
+use bevy::prelude::*;
+use bevy::render::camera::ExtractedCamera;
+use bevy::render::render_graph::RenderSubGraph;
+use bevy::render::render_resource::{BindGroup, BindGroupId};
+use bevy::render::settings::{Backends, RenderCreation, WgpuSettings};
+use bevy::render::{RenderApp, RenderPlugin};
+use bevy::window::ExitCondition;
+use bevy_wboit::histogram::cdf_build::CdfBuildBindGroup;
+use bevy_wboit::histogram::composite::{
+    HistoAccumBindGroups, HistoCompositeBindGroup, prepare_histo_wboit_bind_groups,
+};
+use bevy_wboit::histogram::textures::prepare_histogram_wboit_textures;
+use bevy_wboit::naive::composite::{WboitCompositeBindGroup, prepare_wboit_composite_bind_group};
+use bevy_wboit::textures::prepare_wboit_textures;
+use bevy_wboit::{HEWboitPlugin, HEWboitSettings, WboitPlugin, WboitSettings};
+
+const CAMERA_COUNTS: [usize; 3] = [1, 4, 16];
+const FRAMES: u32 = 200;
+const VIEWPORT: UVec2 = UVec2::new(1280, 720);
+
+fn main() {
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::all(),
+        ..default()
+    });
+    if instance
+        .enumerate_adapters(wgpu::Backends::all())
+        .is_empty()
+    {
+        println!("no GPU adapter, skipping");
+        return;
+    }
+
+    for cameras in CAMERA_COUNTS {
+        bench(
+            "naive",
+            cameras,
+            WboitSettings::default(),
+            prepare_wboit_textures,
+            prepare_wboit_composite_bind_group,
+            |entity| {
+                let composite = entity.get::<WboitCompositeBindGroup>()?;
+                Some(vec![composite.0.clone()])
+            },
+        );
+    }
+    for cameras in CAMERA_COUNTS {
+        bench(
+            "he",
+            cameras,
+            HEWboitSettings::default(),
+            prepare_histogram_wboit_textures,
+            prepare_histo_wboit_bind_groups,
+            |entity| {
+                let [accum_a, accum_b] = entity.get::<HistoAccumBindGroups>()?.0.clone();
+                let cdf_build = entity.get::<CdfBuildBindGroup>()?;
+                let composite = entity.get::<HistoCompositeBindGroup>()?;
+                Some(vec![
+                    accum_a,
+                    accum_b,
+                    cdf_build.0.clone(),
+                    composite.0.clone(),
+                ])
+            },
+        );
+    }
+}
+
+/// Prepare textures for `cameras` render world cameras with `settings`, then run
+/// `prepare_bind_groups` for `FRAMES` frames and report its cost.
+fn bench<M1, M2>(
+    path: &str,
+    cameras: usize,
+    settings: impl Component + Clone,
+    prepare_textures: impl IntoScheduleConfigs<ScheduleSystem, M1>,
+    prepare_bind_groups: impl IntoScheduleConfigs<ScheduleSystem, M2>,
+    bind_groups: impl Fn(EntityRef) -> Option<Vec<BindGroup>>,
+) {
+    let mut app = app();
+    let world = app.get_sub_app_mut(RenderApp).unwrap().world_mut();
+    let camera_entities: Vec<_> = (0..cameras)
+        .map(|order| world.spawn((camera(order as isize), settings.clone())).id())
+        .collect();
+
+    let mut textures = Schedule::default();
+    textures.add_systems(prepare_textures);
+    textures.run(world);
+    let mut bind_group_schedule = Schedule::default();
+    bind_group_schedule.add_systems(prepare_bind_groups);
+    // Warm up, and initialize the schedule outside of the timed frames.
+    bind_group_schedule.run(world);
+
+    let ids = |world: &World| -> Vec<BindGroupId> {
+        camera_entities
+            .iter()
+            .flat_map(|&entity| bind_groups(world.entity(entity)).expect("bind groups prepared"))
+            .map(|bind_group| bind_group.id())
+            .collect()
+    };
+    let mut created = 0;
+    let mut elapsed = Duration::ZERO;
+    for _ in 0..FRAMES {
+        let before = ids(world);
+        let start = Instant::now();
+        bind_group_schedule.run(world);
+        elapsed += start.elapsed();
+        created += ids(world).iter().filter(|id| !before.contains(id)).count();
+    }
+
+    println!(
+        "{path:>5} x{cameras:<2}: {:.1} bind groups created per camera per frame, {:?} per frame",
+        created as f64 / (cameras as u32 * FRAMES) as f64,
+        elapsed / FRAMES,
+    );
+}
+
+fn camera(order: isize) -> ExtractedCamera {
+    ExtractedCamera {
+        target: None,
+        physical_viewport_size: Some(VIEWPORT),
+        physical_target_size: Some(VIEWPORT),
+        viewport: None,
+        render_graph: Core3d.intern(),
+        order,
+        output_mode: default(),
+        msaa_writeback: false,
+        clear_color: default(),
+        sorted_camera_index_for_target: 0,
+        exposure: 1.0,
+        hdr: false,
+    }
+}
+
+/// A windowless app with both WBOIT paths, finished but never updated: software adapters
+/// cannot compile every Bevy shader, and the systems under test are run by hand.
+fn app() -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        DefaultPlugins
+            .build()
+            .disable::<bevy::winit::WinitPlugin>()
+            .disable::<bevy::render::pipelined_rendering::PipelinedRenderingPlugin>()
+            .disable::<bevy::log::LogPlugin>()
+            .set(WindowPlugin {
+                primary_window: None,
+                exit_condition: ExitCondition::DontExit,
+                close_when_requested: false,
+            })
+            .set(RenderPlugin {
+                render_creation: RenderCreation::Automatic(WgpuSettings {
+                    backends: Some(Backends::all()),
+                    ..default()
+                }),
+                synchronous_pipeline_compilation: true,
+                ..default()
+            }),
+        WboitPlugin,
+        HEWboitPlugin,
+    ));
+    app.finish();
+    app.cleanup();
+    app
+}
@@ -0,0 +1,94 @@
+//! CPU cost of the `WboitAccum3d` phase sort, with `WboitSettings::sort_accum` on and off.
+//!
+//! For a few item counts this fills one view's accum phase with items at scattered distances,
+//! as the queue leaves them, and reports the average wall time of one run of
+//! `sort_wboit_accum_phases`. Needs no GPU: `cargo bench --bench sort_accum`.
+
+use std::time::{Duration, Instant};
+
+use bevy::prelude::*;
+use bevy::render::camera::CameraProjection;
+use bevy::render::render_phase::{
+    DrawFunctionId, DrawFunctions, PhaseItemExtraIndex, RenderCommandState, SetItemPipeline,
+    ViewSortedRenderPhases,
+};
+use bevy::render::render_resource::CachedRenderPipelineId;
+use bevy::render::view::{ExtractedView, RetainedViewEntity};
+use bevy_wboit::WboitSettings;
+use bevy_wboit::phase::WboitAccum3d;
+use bevy_wboit::queue::sort_wboit_accum_phases;
+
+const ITEM_COUNTS: [u32; 3] = [1_000, 10_000, 100_000];
+const FRAMES: u32 = 100;
+
+fn main() {
+    for items in ITEM_COUNTS {
+        let sorted = bench(items, true);
+        let unsorted = bench(items, false);
+        println!("{items:>6} items: sorted {sorted:?} per frame, unsorted {unsorted:?} per frame");
+    }
+}
+
+/// Average time of one `sort_wboit_accum_phases` run over a phase of `items` unsorted items,
+/// on a camera with `sort_accum`.
+fn bench(items: u32, sort_accum: bool) -> Duration {
+    let mut world = World::new();
+    world.init_resource::<ViewSortedRenderPhases<WboitAccum3d>>();
+    let camera = world.spawn_empty().id();
+    let retained = RetainedViewEntity::new(camera.into(), None, 0);
+    world.entity_mut(camera).insert((
+        ExtractedView {
+            retained_view_entity: retained,
+            clip_from_view: PerspectiveProjection::default().get_clip_from_view(),
+            world_from_view: GlobalTransform::IDENTITY,
+            clip_from_world: None,
+            hdr: false,
+            viewport: UVec4::new(0, 0, 1280, 720),
+            color_grading: default(),
+        },
+        WboitSettings {
+            sort_accum,
+            ..default()
+        },
+    ));
+    let sort = world.register_system(sort_wboit_accum_phases);
+    let draw_function = DrawFunctions::<WboitAccum3d>::default()
+        .write()
+        .add(RenderCommandState::<WboitAccum3d, SetItemPipeline>::new(
+            &mut world,
+        ));
+
+    let mut elapsed = Duration::ZERO;
+    // One extra run first, to initialize the system outside of the timed frames.
+    for frame in 0..=FRAMES {
+        fill(&mut world, retained, draw_function, items);
+        let start = Instant::now();
+        world.run_system(sort).unwrap();
+        if frame > 0 {
+            elapsed += start.elapsed();
+        }
+    }
+    elapsed / FRAMES
+}
+
+/// Replace the phase of `view` with `items` items at scattered distances.
+fn fill(world: &mut World, view: RetainedViewEntity, draw_function: DrawFunctionId, items: u32) {
+    let mut phases = world.resource_mut::<ViewSortedRenderPhases<WboitAccum3d>>();
+    phases.insert_or_clear(view);
+    let phase = phases.get_mut(&view).unwrap();
+    // A fixed LCG, so every run sorts the same sequence.
+    let mut state = 0x2545_f491_u32;
+    for index in 0..items {
+        state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        let entity = Entity::from_raw(index);
+        phase.add(WboitAccum3d {
+            distance: -((state >> 8) as f32 / (1 << 24) as f32) * 1000.0,
+            pipeline: CachedRenderPipelineId::INVALID,
+            entity: (entity, entity.into()),
+            draw_function,
+            batch_range: 0..1,
+            extra_index: PhaseItemExtraIndex::None,
+            indexed: false,
+        });
+    }
+}
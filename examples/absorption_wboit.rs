@@ -0,0 +1,130 @@
+//! Colored absorption through stacked layers with `HEWboitSettings::absorption_color`.
+//!
+//! Three stacks of pale panes recede from the camera, each a few world units deeper than the
+//! last. The composite tints every stack by the absorption color raised to the distance
+//! where its opacity sits (read from the depth CDF), so the far stacks take on more of the
+//! color than the near one even though every pane has the same material. Press A to cycle
+//! the absorption color and Up/Down to change how densely the panes are stacked.
+
+use bevy::prelude::*;
+use bevy_wboit::{HEWboitPlugin, HEWboitSettings};
+
+/// Absorption presets, as (name, transmitted color per world unit).
+const ABSORPTION: [(&str, LinearRgba); 3] = [
+    ("none", LinearRgba::WHITE),
+    ("deep water", LinearRgba::rgb(0.80, 0.93, 0.97)),
+    ("amber smoke", LinearRgba::rgb(0.97, 0.90, 0.78)),
+];
+
+#[derive(Component)]
+struct Pane {
+    stack: usize,
+    layer: usize,
+}
+
+#[derive(Resource)]
+struct Absorption {
+    preset: usize,
+    spacing: f32,
+}
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, HEWboitPlugin))
+        .insert_resource(Absorption {
+            preset: 1,
+            spacing: 0.3,
+        })
+        .add_systems(Startup, setup)
+        .add_systems(Update, (handle_keys, apply).chain())
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        Camera3d::default(),
+        Transform::from_xyz(0.0, 1.0, 4.0).looking_at(Vec3::new(0.0, 0.5, -6.0), Vec3::Y),
+        HEWboitSettings {
+            max_depth: 30.0,
+            ..default()
+        },
+        Msaa::Off,
+    ));
+    commands.spawn((
+        DirectionalLight::default(),
+        Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, -0.8, 0.4, 0.0)),
+    ));
+    commands.spawn((
+        Mesh3d(meshes.add(Plane3d::default().mesh().size(20.0, 40.0))),
+        MeshMaterial3d(materials.add(Color::srgb(0.85, 0.85, 0.85))),
+        Transform::from_xyz(0.0, -0.8, -10.0),
+    ));
+
+    let pane = meshes.add(Rectangle::new(1.6, 1.6));
+    let glass = materials.add(StandardMaterial {
+        base_color: Color::srgba(0.95, 0.95, 0.95, 0.3),
+        alpha_mode: AlphaMode::Blend,
+        unlit: true,
+        double_sided: true,
+        cull_mode: None,
+        ..default()
+    });
+    for stack in 0..3 {
+        for layer in 0..6 {
+            commands.spawn((
+                Mesh3d(pane.clone()),
+                MeshMaterial3d(glass.clone()),
+                Transform::default(),
+                Pane { stack, layer },
+            ));
+        }
+    }
+
+    commands.spawn((
+        Text::default(),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+    ));
+}
+
+fn handle_keys(keys: Res<ButtonInput<KeyCode>>, mut absorption: ResMut<Absorption>) {
+    if keys.just_pressed(KeyCode::KeyA) {
+        absorption.preset = (absorption.preset + 1) % ABSORPTION.len();
+    }
+    if keys.just_pressed(KeyCode::ArrowUp) {
+        absorption.spacing = (absorption.spacing + 0.1).min(1.0);
+    }
+    if keys.just_pressed(KeyCode::ArrowDown) {
+        absorption.spacing = (absorption.spacing - 0.1).max(0.1);
+    }
+}
+
+fn apply(
+    absorption: Res<Absorption>,
+    mut settings: Single<&mut HEWboitSettings>,
+    mut panes: Query<(&mut Transform, &Pane)>,
+    mut text: Single<&mut Text>,
+) {
+    if !absorption.is_changed() {
+        return;
+    }
+    let (name, color) = ABSORPTION[absorption.preset];
+    settings.absorption_color = color;
+    for (mut transform, pane) in &mut panes {
+        let x = (pane.stack as f32 - 1.0) * 2.2;
+        let z = -(pane.stack as f32) * 6.0 - pane.layer as f32 * absorption.spacing;
+        transform.translation = Vec3::new(x, 0.5, z);
+    }
+    text.0 = format!(
+        "A absorption: {name}\nUp/Down layer spacing: {:.1}",
+        absorption.spacing
+    );
+}
@@ -0,0 +1,90 @@
+//! Dropping HE-WBOIT to naive WBOIT under frame-time pressure with `WboitAdaptiveQuality`.
+//!
+//! Press Space to toggle a simulated 25 ms frame time (through `frame_time_ms`); the camera
+//! switches to naive WBOIT after a second and back once the load is gone.
+
+use bevy::prelude::*;
+use bevy_wboit::{HEWboitSettings, WboitAdaptiveDegraded, WboitAdaptiveQuality, WboitPlugin};
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, WboitPlugin))
+        .insert_resource(WboitAdaptiveQuality {
+            budget_ms: 20.0,
+            ..default()
+        })
+        .add_systems(Startup, setup)
+        .add_systems(Update, (toggle_load, show_mode))
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        Camera3d::default(),
+        Transform::from_xyz(0.0, 2.0, 8.0).looking_at(Vec3::ZERO, Vec3::Y),
+        HEWboitSettings {
+            max_depth: 20.0,
+            ..default()
+        },
+        Msaa::Off,
+    ));
+
+    let pane = meshes.add(Rectangle::new(1.5, 1.5));
+    for i in 0..24 {
+        let hue = i as f32 * 15.0;
+        commands.spawn((
+            Mesh3d(pane.clone()),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: Color::hsla(hue, 0.8, 0.5, 0.35),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            })),
+            Transform::from_xyz(
+                (i % 6) as f32 * 0.6 - 1.5,
+                (i / 6) as f32 * 0.4 - 0.6,
+                -0.5 * i as f32,
+            ),
+        ));
+    }
+
+    commands.spawn((
+        Text::default(),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+    ));
+}
+
+fn toggle_load(keys: Res<ButtonInput<KeyCode>>, mut adaptive: ResMut<WboitAdaptiveQuality>) {
+    if keys.just_pressed(KeyCode::Space) {
+        adaptive.frame_time_ms = match adaptive.frame_time_ms {
+            Some(_) => None,
+            None => Some(25.0),
+        };
+    }
+}
+
+fn show_mode(
+    adaptive: Res<WboitAdaptiveQuality>,
+    cameras: Query<Has<WboitAdaptiveDegraded>, With<Camera3d>>,
+    mut text: Single<&mut Text>,
+) {
+    let degraded = cameras.iter().any(|degraded| degraded);
+    text.0 = format!(
+        "simulated load: {} (Space)\nmode: {}",
+        if adaptive.frame_time_ms.is_some() {
+            "on"
+        } else {
+            "off"
+        },
+        if degraded { "naive (degraded)" } else { "HE" },
+    );
+}
@@ -0,0 +1,149 @@
+//! Transparents in front of Bevy's atmospheric scattering.
+//!
+//! The atmosphere's sky node runs between `MainOpaquePass` and `MainTransparentPass`, and the
+//! WBOIT passes run after `MainTransparentPass`, so the sky and aerial perspective are always
+//! in the view target before the composite blends transparents over them. The spheres recede
+//! into the haze to check that they sit in front of it rather than being covered by it.
+
+use bevy::core_pipeline::tonemapping::Tonemapping;
+use bevy::pbr::{Atmosphere, AtmosphereSettings, light_consts::lux};
+use bevy::prelude::*;
+use bevy::render::camera::Exposure;
+use bevy_wboit::{WboitPlugin, WboitSettings};
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, WboitPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(Update, (toggle_wboit, move_sun))
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    // Atmospheric scattering requires an HDR camera.
+    commands.spawn((
+        Camera3d::default(),
+        Camera {
+            hdr: true,
+            ..default()
+        },
+        Transform::from_xyz(0.0, 2.0, 10.0).looking_at(Vec3::new(0.0, 2.0, 0.0), Vec3::Y),
+        Atmosphere::EARTH,
+        AtmosphereSettings::default(),
+        Exposure::SUNLIGHT,
+        Tonemapping::AcesFitted,
+        WboitSettings::default(),
+        Msaa::Off,
+    ));
+
+    commands.spawn((
+        DirectionalLight {
+            illuminance: lux::RAW_SUNLIGHT,
+            ..default()
+        },
+        Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, -0.3, 0.6, 0.0)),
+    ));
+
+    // Large opaque ground, fading into aerial perspective towards the horizon.
+    commands.spawn((
+        Mesh3d(meshes.add(Plane3d::default().mesh().size(20000.0, 20000.0))),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            base_color: Color::srgb(0.3, 0.35, 0.25),
+            perceptual_roughness: 0.9,
+            ..default()
+        })),
+    ));
+
+    // Transparent spheres receding from the camera, growing so they stay visible.
+    let sphere = meshes.add(Sphere::new(1.0).mesh().ico(5).unwrap());
+    let colors = [
+        Color::srgba(1.0, 0.2, 0.2, 0.5),
+        Color::srgba(0.2, 1.0, 0.3, 0.5),
+        Color::srgba(0.2, 0.4, 1.0, 0.5),
+        Color::srgba(1.0, 0.9, 0.2, 0.5),
+        Color::srgba(1.0, 0.3, 1.0, 0.5),
+    ];
+    for (i, color) in colors.into_iter().enumerate() {
+        let distance = 4.0 * 4.0f32.powi(i as i32);
+        let radius = 1.0 + distance * 0.08;
+        commands.spawn((
+            Mesh3d(sphere.clone()),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: color,
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            })),
+            Transform::from_xyz(-distance * 0.15, radius, -distance)
+                .with_scale(Vec3::splat(radius)),
+        ));
+    }
+
+    commands.spawn((
+        Text::new(
+            "1: No OIT  |  2: WBOIT\n\
+             Arrow keys: Move sun",
+        ),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+    ));
+}
+
+fn toggle_wboit(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    camera: Query<Entity, With<Camera3d>>,
+) {
+    let Ok(camera_entity) = camera.single() else {
+        return;
+    };
+
+    if keys.just_pressed(KeyCode::Digit1) {
+        commands.entity(camera_entity).remove::<WboitSettings>();
+        info!("Switched to standard transparency (no OIT)");
+    }
+
+    if keys.just_pressed(KeyCode::Digit2) {
+        commands
+            .entity(camera_entity)
+            .insert(WboitSettings::default());
+        info!("Switched to naive WBOIT");
+    }
+}
+
+/// Move the sun to see the transparents against different sky and haze colors.
+fn move_sun(
+    time: Res<Time>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut suns: Query<&mut Transform, With<DirectionalLight>>,
+) {
+    let mut pitch = 0.0f32;
+    let mut yaw = 0.0f32;
+    if keys.pressed(KeyCode::ArrowUp) {
+        pitch -= 1.0;
+    }
+    if keys.pressed(KeyCode::ArrowDown) {
+        pitch += 1.0;
+    }
+    if keys.pressed(KeyCode::ArrowLeft) {
+        yaw += 1.0;
+    }
+    if keys.pressed(KeyCode::ArrowRight) {
+        yaw -= 1.0;
+    }
+    if pitch == 0.0 && yaw == 0.0 {
+        return;
+    }
+    for mut transform in &mut suns {
+        let dt = time.delta_secs() * 0.5;
+        transform.rotate_y(yaw * dt);
+        transform.rotate_local_x(pitch * dt);
+    }
+}
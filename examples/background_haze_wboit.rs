@@ -0,0 +1,101 @@
+//! A WBOIT haze layer composited before the opaque pass (`WboitTaaMode::BeforeOpaque`).
+//!
+//! A few large, faint haze sheets are spread through the scene, some of them between the
+//! camera and the opaque pillars. With the background placement the haze only shows where no
+//! opaque surface is, so the pillars stay crisp in front of it; press T to compare with the
+//! default placement, where the nearer sheets tint the pillars behind them.
+
+use bevy::prelude::*;
+use bevy_wboit::{WboitPlugin, WboitSettings, WboitTaaMode};
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, WboitPlugin))
+        .insert_resource(ClearColor(Color::srgb(0.05, 0.06, 0.1)))
+        .add_systems(Startup, setup)
+        .add_systems(Update, toggle_placement)
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        Camera3d::default(),
+        Transform::from_xyz(0.0, 1.5, 9.0).looking_at(Vec3::new(0.0, 1.0, 0.0), Vec3::Y),
+        WboitSettings {
+            taa_mode: WboitTaaMode::BeforeOpaque,
+            ..default()
+        },
+        Msaa::Off,
+    ));
+
+    commands.spawn((
+        DirectionalLight {
+            illuminance: 6000.0,
+            ..default()
+        },
+        Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, -0.7, 0.5, 0.0)),
+    ));
+
+    // Opaque foreground: a row of pillars at different depths.
+    let pillar = meshes.add(Cuboid::new(0.6, 3.0, 0.6));
+    let stone = materials.add(Color::srgb(0.55, 0.5, 0.45));
+    for i in 0..5 {
+        let x = -4.0 + 2.0 * i as f32;
+        let z = -2.0 + (i % 2) as f32 * 3.0;
+        commands.spawn((
+            Mesh3d(pillar.clone()),
+            MeshMaterial3d(stone.clone()),
+            Transform::from_xyz(x, 0.5, z),
+        ));
+    }
+
+    // Haze sheets facing the camera, from behind the pillars to in front of some of them.
+    let sheet = meshes.add(Rectangle::new(30.0, 12.0));
+    for (i, z) in [-8.0, -4.0, -0.5, 2.5].into_iter().enumerate() {
+        let haze = materials.add(StandardMaterial {
+            base_color: Color::srgba(0.6, 0.7, 0.9, 0.2),
+            emissive: LinearRgba::rgb(0.05, 0.06, 0.1) * i as f32,
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        });
+        commands.spawn((
+            Mesh3d(sheet.clone()),
+            MeshMaterial3d(haze),
+            Transform::from_xyz(0.0, 2.0, z),
+        ));
+    }
+
+    commands.spawn((
+        Text::new(""),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+    ));
+}
+
+fn toggle_placement(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut cameras: Query<&mut WboitSettings>,
+    mut text: Query<&mut Text>,
+) {
+    let Ok(mut settings) = cameras.single_mut() else {
+        return;
+    };
+    if keys.just_pressed(KeyCode::KeyT) {
+        settings.taa_mode = match settings.taa_mode {
+            WboitTaaMode::BeforeOpaque => WboitTaaMode::BeforeTaa,
+            _ => WboitTaaMode::BeforeOpaque,
+        };
+    }
+    if let Ok(mut text) = text.single_mut() {
+        text.0 = format!("Composite placement: {:?} (T to toggle)", settings.taa_mode);
+    }
+}
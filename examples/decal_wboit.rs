@@ -0,0 +1,130 @@
+//! Frost decals lying on a glass pane and on a wall, with `WboitDepthOffset`.
+//!
+//! Each decal is a quad exactly coplanar with the surface under it. Without an offset the
+//! decal on the opaque wall flickers in and out of the depth test, and the one on the glass
+//! gets the same depth weight as the pane it sits on. With the offset both are rasterized a
+//! centimeter towards the camera and show cleanly. Press O to toggle the offset.
+
+use bevy::asset::RenderAssetUsages;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy_wboit::{WboitDepthOffset, WboitPlugin, WboitSettings};
+
+const OFFSET: WboitDepthOffset = WboitDepthOffset(0.01);
+
+#[derive(Component)]
+struct Decal;
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, WboitPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(Update, toggle_offset)
+        .run();
+}
+
+/// White frost: a soft ring pattern that fades out towards the edges.
+fn frost_image() -> Image {
+    const SIZE: u32 = 128;
+    let data = (0..SIZE * SIZE)
+        .flat_map(|i| {
+            let p = Vec2::new((i % SIZE) as f32, (i / SIZE) as f32) / SIZE as f32 * 2.0 - 1.0;
+            let r = p.length();
+            let rings = 0.5 + 0.5 * (r * 40.0 + (p.y.atan2(p.x) * 6.0).sin() * 2.0).sin();
+            let alpha = (rings * (1.0 - r).clamp(0.0, 1.0) * 255.0) as u8;
+            [255, 255, 255, alpha]
+        })
+        .collect();
+    Image::new(
+        Extent3d {
+            width: SIZE,
+            height: SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    )
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    commands.spawn((
+        Camera3d::default(),
+        Transform::from_xyz(1.0, 1.2, 4.5).looking_at(Vec3::new(0.0, 1.0, 0.0), Vec3::Y),
+        WboitSettings::default(),
+        Msaa::Off,
+    ));
+    commands.spawn((
+        DirectionalLight::default(),
+        Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, -0.5, 0.4, 0.0)),
+    ));
+
+    // Opaque wall, with the glass pane standing in front of its left half.
+    commands.spawn((
+        Mesh3d(meshes.add(Rectangle::new(6.0, 3.0))),
+        MeshMaterial3d(materials.add(Color::srgb(0.35, 0.3, 0.3))),
+        Transform::from_xyz(0.0, 1.5, -1.0),
+    ));
+    commands.spawn((
+        Mesh3d(meshes.add(Rectangle::new(2.0, 2.0))),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            base_color: Color::srgba(0.3, 0.6, 0.9, 0.35),
+            alpha_mode: AlphaMode::Blend,
+            ..default()
+        })),
+        Transform::from_xyz(-1.2, 1.0, 0.0),
+    ));
+
+    let decal = meshes.add(Rectangle::new(1.4, 1.4));
+    let frost = materials.add(StandardMaterial {
+        base_color_texture: Some(images.add(frost_image())),
+        alpha_mode: AlphaMode::Blend,
+        unlit: true,
+        ..default()
+    });
+    for position in [Vec3::new(-1.2, 1.0, 0.0), Vec3::new(1.5, 1.5, -1.0)] {
+        commands.spawn((
+            Mesh3d(decal.clone()),
+            MeshMaterial3d(frost.clone()),
+            Transform::from_translation(position),
+            OFFSET,
+            Decal,
+        ));
+    }
+
+    commands.spawn((
+        Text::new("WboitDepthOffset: 0.01 (O to toggle)"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+    ));
+}
+
+fn toggle_offset(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    decals: Query<(Entity, Has<WboitDepthOffset>), With<Decal>>,
+    mut text: Single<&mut Text>,
+) {
+    if !keys.just_pressed(KeyCode::KeyO) {
+        return;
+    }
+    for (entity, has_offset) in &decals {
+        if has_offset {
+            commands.entity(entity).remove::<WboitDepthOffset>();
+            text.0 = "WboitDepthOffset: none (O to toggle)".into();
+        } else {
+            commands.entity(entity).insert(OFFSET);
+            text.0 = format!("WboitDepthOffset: {} (O to toggle)", OFFSET.0);
+        }
+    }
+}
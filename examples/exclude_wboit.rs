@@ -0,0 +1,107 @@
+//! A crisp glass pane drawn on top of WBOIT smoke.
+//!
+//! The smoke puffs use `StandardMaterial` and go through WBOIT. The pane uses a separate
+//! material type (a `StandardMaterial` wrapped in an `ExtendedMaterial` with an empty
+//! extension) that is excluded from WBOIT with `exclude_material_from_wboit`, so it is drawn
+//! with ordinary sorted blending after the composite, keeping its sharp highlights on top of
+//! the smoke instead of being averaged into it.
+
+use bevy::pbr::{ExtendedMaterial, MaterialExtension};
+use bevy::prelude::*;
+use bevy::render::render_resource::AsBindGroup;
+use bevy_wboit::{WboitAppExt, WboitPlugin, WboitSettings};
+
+/// Marks a `StandardMaterial` as crisp glass; adds no bindings or shader changes.
+#[derive(Asset, AsBindGroup, Reflect, Debug, Clone, Default)]
+struct CrispGlass {}
+
+impl MaterialExtension for CrispGlass {}
+
+type CrispGlassMaterial = ExtendedMaterial<StandardMaterial, CrispGlass>;
+
+fn main() {
+    App::new()
+        .add_plugins((
+            DefaultPlugins,
+            WboitPlugin,
+            MaterialPlugin::<CrispGlassMaterial>::default(),
+        ))
+        .exclude_material_from_wboit::<CrispGlassMaterial>()
+        .add_systems(Startup, setup)
+        .add_systems(Update, drift_smoke)
+        .run();
+}
+
+/// Smoke puff with its drift phase.
+#[derive(Component)]
+struct Smoke(f32);
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut glass_materials: ResMut<Assets<CrispGlassMaterial>>,
+) {
+    commands.spawn((
+        Camera3d::default(),
+        Transform::from_xyz(0.0, 1.5, 7.0).looking_at(Vec3::new(0.0, 1.0, 0.0), Vec3::Y),
+        WboitSettings::default(),
+        Msaa::Off,
+    ));
+
+    commands.spawn((
+        PointLight {
+            intensity: 2_000_000.0,
+            ..default()
+        },
+        Transform::from_xyz(2.0, 4.0, 4.0),
+    ));
+
+    commands.spawn((
+        Mesh3d(meshes.add(Plane3d::default().mesh().size(20.0, 20.0))),
+        MeshMaterial3d(materials.add(Color::srgb(0.25, 0.25, 0.28))),
+    ));
+
+    // Smoke behind the pane, through WBOIT.
+    let puff = meshes.add(Sphere::new(1.0).mesh().ico(4).unwrap());
+    let smoke = materials.add(StandardMaterial {
+        base_color: Color::srgba(0.7, 0.7, 0.75, 0.25),
+        alpha_mode: AlphaMode::Blend,
+        unlit: true,
+        ..default()
+    });
+    for i in 0..12 {
+        let phase = i as f32 * 0.9;
+        commands.spawn((
+            Mesh3d(puff.clone()),
+            MeshMaterial3d(smoke.clone()),
+            Transform::from_xyz((i % 4) as f32 - 1.5, 0.8 + (i / 4) as f32 * 0.6, -1.5)
+                .with_scale(Vec3::splat(0.8 + 0.1 * (i % 3) as f32)),
+            Smoke(phase),
+        ));
+    }
+
+    // Glass pane in front, excluded from WBOIT.
+    commands.spawn((
+        Mesh3d(meshes.add(Cuboid::new(3.0, 2.0, 0.05))),
+        MeshMaterial3d(glass_materials.add(CrispGlassMaterial {
+            base: StandardMaterial {
+                base_color: Color::srgba(0.6, 0.8, 1.0, 0.2),
+                alpha_mode: AlphaMode::Blend,
+                perceptual_roughness: 0.05,
+                reflectance: 1.0,
+                ..default()
+            },
+            extension: CrispGlass {},
+        })),
+        Transform::from_xyz(0.0, 1.2, 0.5).with_rotation(Quat::from_rotation_y(0.3)),
+    ));
+}
+
+fn drift_smoke(time: Res<Time>, mut puffs: Query<(&mut Transform, &Smoke)>) {
+    for (mut transform, smoke) in &mut puffs {
+        let t = time.elapsed_secs() * 0.4 + smoke.0;
+        transform.translation.x += 0.2 * t.sin() * time.delta_secs();
+        transform.translation.y += 0.1 * (t * 1.3).cos() * time.delta_secs();
+    }
+}
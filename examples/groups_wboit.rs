@@ -0,0 +1,125 @@
+//! Layering a foreground glass HUD over world smoke with `WboitGroups`.
+//!
+//! The smoke puffs are in group 0 and the HUD panes, children of the camera, in group 1. With
+//! groups on, the HUD is composited over the already resolved smoke; press G to draw both in
+//! one WBOIT pass instead, where dense smoke bleeds into the HUD colors.
+
+use bevy::prelude::*;
+use bevy_wboit::{WboitGroup, WboitGroups, WboitPlugin, WboitSettings};
+
+#[derive(Component)]
+struct Puff(Vec3);
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, WboitPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(Update, (drift_smoke, toggle_groups))
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let camera = commands
+        .spawn((
+            Camera3d::default(),
+            Transform::from_xyz(0.0, 1.5, 8.0).looking_at(Vec3::ZERO, Vec3::Y),
+            WboitSettings::default(),
+            WboitGroups(vec![0, 1]),
+            Msaa::Off,
+        ))
+        .id();
+
+    commands.spawn((
+        Mesh3d(meshes.add(Plane3d::default().mesh().size(20.0, 20.0))),
+        MeshMaterial3d(materials.add(Color::srgb(0.3, 0.35, 0.3))),
+    ));
+    commands.spawn((
+        DirectionalLight::default(),
+        Transform::from_xyz(3.0, 6.0, 2.0).looking_at(Vec3::ZERO, Vec3::Y),
+    ));
+
+    // World smoke, group 0 (the default).
+    let puff = meshes.add(Sphere::new(0.8));
+    let smoke = materials.add(StandardMaterial {
+        base_color: Color::srgba(0.7, 0.7, 0.75, 0.25),
+        alpha_mode: AlphaMode::Blend,
+        ..default()
+    });
+    for i in 0..40 {
+        let angle = i as f32 * 2.4;
+        let origin = Vec3::new(
+            angle.cos() * 2.5,
+            0.8 + (i % 5) as f32 * 0.3,
+            angle.sin() * 2.5,
+        );
+        commands.spawn((
+            Mesh3d(puff.clone()),
+            MeshMaterial3d(smoke.clone()),
+            Transform::from_translation(origin),
+            Puff(origin),
+        ));
+    }
+
+    // Foreground glass HUD, group 1, attached to the camera.
+    let pane = meshes.add(Rectangle::new(0.5, 0.25));
+    let hud_colors = [
+        Color::srgba(0.1, 0.8, 1.0, 0.4),
+        Color::srgba(1.0, 0.6, 0.1, 0.4),
+    ];
+    for (i, color) in hud_colors.into_iter().enumerate() {
+        let side = if i == 0 { -1.0 } else { 1.0 };
+        commands.spawn((
+            Mesh3d(pane.clone()),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: color,
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            })),
+            Transform::from_xyz(side * 0.45, -0.3, -1.5),
+            WboitGroup(1),
+            ChildOf(camera),
+        ));
+    }
+
+    commands.spawn((
+        Text::new("WboitGroups: [0, 1] (G to toggle)"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+    ));
+}
+
+fn drift_smoke(time: Res<Time>, mut puffs: Query<(&Puff, &mut Transform)>) {
+    let t = time.elapsed_secs();
+    for (puff, mut transform) in &mut puffs {
+        let phase = puff.0.x + puff.0.z;
+        transform.translation = puff.0 + Vec3::new((t * 0.3 + phase).sin() * 0.4, 0.0, 0.0);
+    }
+}
+
+fn toggle_groups(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    camera: Single<(Entity, Has<WboitGroups>), With<Camera3d>>,
+    mut text: Single<&mut Text>,
+) {
+    if !keys.just_pressed(KeyCode::KeyG) {
+        return;
+    }
+    let (entity, grouped) = *camera;
+    if grouped {
+        commands.entity(entity).remove::<WboitGroups>();
+        text.0 = "WboitGroups: none (G to toggle)".into();
+    } else {
+        commands.entity(entity).insert(WboitGroups(vec![0, 1]));
+        text.0 = "WboitGroups: [0, 1] (G to toggle)".into();
+    }
+}
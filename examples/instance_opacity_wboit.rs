@@ -0,0 +1,93 @@
+//! Per-instance fading with `WboitInstanceOpacity`.
+//!
+//! A ring of auras shares one mesh and one material, yet each fades in and out on its own
+//! phase: the opacity travels per instance in the mesh's `MeshTag`, so the auras still batch.
+//! Press Space to pause the fading and hold every aura at a different fixed opacity.
+
+use bevy::prelude::*;
+use bevy_wboit::{WboitInstanceOpacity, WboitPlugin, WboitSettings};
+
+const AURAS: usize = 8;
+
+#[derive(Component)]
+struct Aura(usize);
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, WboitPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(Update, fade)
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        Camera3d::default(),
+        Transform::from_xyz(0.0, 5.0, 7.0).looking_at(Vec3::ZERO, Vec3::Y),
+        WboitSettings::default(),
+        Msaa::Off,
+    ));
+    commands.spawn((
+        DirectionalLight::default(),
+        Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, -1.0, 0.4, 0.0)),
+    ));
+    commands.spawn((
+        Mesh3d(meshes.add(Plane3d::default().mesh().size(12.0, 12.0))),
+        MeshMaterial3d(materials.add(Color::srgb(0.25, 0.25, 0.3))),
+        Transform::from_xyz(0.0, -0.5, 0.0),
+    ));
+
+    let sphere = meshes.add(Sphere::new(0.7).mesh().ico(4).unwrap());
+    let aura = materials.add(StandardMaterial {
+        base_color: Color::srgba(0.3, 0.9, 1.0, 0.6),
+        emissive: LinearRgba::rgb(0.1, 0.4, 0.5),
+        alpha_mode: AlphaMode::Blend,
+        ..default()
+    });
+    for i in 0..AURAS {
+        let angle = i as f32 / AURAS as f32 * std::f32::consts::TAU;
+        commands.spawn((
+            Mesh3d(sphere.clone()),
+            MeshMaterial3d(aura.clone()),
+            Transform::from_xyz(angle.cos() * 3.0, 0.3, angle.sin() * 3.0),
+            WboitInstanceOpacity(1.0),
+            Aura(i),
+        ));
+    }
+
+    commands.spawn((
+        Text::new("Space: pause fading"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+    ));
+}
+
+fn fade(
+    time: Res<Time>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut paused: Local<bool>,
+    mut auras: Query<(&mut WboitInstanceOpacity, &Aura)>,
+) {
+    if keys.just_pressed(KeyCode::Space) {
+        *paused = !*paused;
+    }
+    for (mut opacity, aura) in &mut auras {
+        let phase = aura.0 as f32 / AURAS as f32;
+        let value = if *paused {
+            // Evenly spread from fully transparent to fully opaque around the ring.
+            phase
+        } else {
+            0.5 + 0.5 * (time.elapsed_secs() * 1.5 + phase * std::f32::consts::TAU).sin()
+        };
+        // Only touch changed values: each change re-uploads the instance's mesh data.
+        opacity.set_if_neq(WboitInstanceOpacity(value));
+    }
+}
@@ -0,0 +1,88 @@
+//! Bright transparents on an LDR camera with `WboitSettings::composite_tonemap`.
+//!
+//! Unlit panes with colors well above `1.0` overlap on a non-HDR camera. Without a tonemap
+//! the composite output is clipped by the LDR target; press T to cycle through Reinhard and
+//! ACES, which roll the highlights off instead.
+
+use bevy::prelude::*;
+use bevy_wboit::{WboitCompositeTonemap, WboitPlugin, WboitSettings};
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, WboitPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(Update, cycle_tonemap)
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        Camera3d::default(),
+        Transform::from_xyz(0.0, 0.0, 6.0).looking_at(Vec3::ZERO, Vec3::Y),
+        WboitSettings::default(),
+        Msaa::Off,
+    ));
+
+    commands.spawn((
+        Mesh3d(meshes.add(Plane3d::new(Vec3::Z, Vec2::splat(6.0)))),
+        MeshMaterial3d(materials.add(Color::srgb(0.2, 0.2, 0.25))),
+        Transform::from_xyz(0.0, 0.0, -3.0),
+    ));
+    commands.spawn(PointLight::default());
+
+    let pane = meshes.add(Rectangle::new(2.0, 2.0));
+    let colors = [
+        LinearRgba::new(6.0, 1.5, 0.3, 0.5),
+        LinearRgba::new(0.3, 4.0, 1.0, 0.5),
+        LinearRgba::new(0.5, 1.0, 8.0, 0.5),
+    ];
+    for (i, color) in colors.into_iter().enumerate() {
+        commands.spawn((
+            Mesh3d(pane.clone()),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: color.into(),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            })),
+            Transform::from_xyz(
+                i as f32 * 0.8 - 0.8,
+                i as f32 * 0.4 - 0.4,
+                -(i as f32) * 0.5,
+            ),
+        ));
+    }
+
+    commands.spawn((
+        Text::new("composite_tonemap: None (T to cycle)"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+    ));
+}
+
+fn cycle_tonemap(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut settings: Single<&mut WboitSettings>,
+    mut text: Single<&mut Text>,
+) {
+    if !keys.just_pressed(KeyCode::KeyT) {
+        return;
+    }
+    settings.composite_tonemap = match settings.composite_tonemap {
+        None => Some(WboitCompositeTonemap::Reinhard),
+        Some(WboitCompositeTonemap::Reinhard) => Some(WboitCompositeTonemap::Aces),
+        Some(WboitCompositeTonemap::Aces) => None,
+    };
+    text.0 = format!(
+        "composite_tonemap: {:?} (T to cycle)",
+        settings.composite_tonemap
+    );
+}
@@ -0,0 +1,129 @@
+//! Masking the WBOIT composite with a screen-space gradient.
+//!
+//! The camera has a `WboitCompositeMask` with a radial gradient generated in code: the
+//! transparent spheres are fully visible in the middle of the screen and fade out towards the
+//! edges, while the opaque floor is unaffected.
+
+use bevy::asset::RenderAssetUsages;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy_wboit::{WboitCompositeMask, WboitPlugin, WboitSettings};
+
+/// Edge length of the generated mask image.
+const MASK_SIZE: u32 = 256;
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, WboitPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(Update, toggle_mask)
+        .run();
+}
+
+/// Resource keeping the mask image around while the mask is toggled off.
+#[derive(Resource)]
+struct MaskImage(Handle<Image>);
+
+/// White in the center, falling off to black at the edges.
+fn radial_gradient() -> Image {
+    let size = MASK_SIZE;
+    let mut data = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            let uv = Vec2::new(x as f32, y as f32) / (size - 1) as f32;
+            let d = (uv - Vec2::splat(0.5)).length() * 2.0;
+            let value = (1.0 - (d - 0.3) / 0.6).clamp(0.0, 1.0);
+            let v = (value * 255.0) as u8;
+            data.extend_from_slice(&[v, v, v, 255]);
+        }
+    }
+    Image::new(
+        Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8Unorm,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let mask = images.add(radial_gradient());
+    commands.insert_resource(MaskImage(mask.clone()));
+
+    commands.spawn((
+        Camera3d::default(),
+        Transform::from_xyz(0.0, 2.0, 8.0).looking_at(Vec3::new(0.0, 1.0, 0.0), Vec3::Y),
+        WboitSettings::default(),
+        WboitCompositeMask(mask),
+        Msaa::Off,
+    ));
+
+    commands.spawn((
+        DirectionalLight {
+            illuminance: 8000.0,
+            ..default()
+        },
+        Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, -0.7, 0.3, 0.0)),
+    ));
+
+    commands.spawn((
+        Mesh3d(meshes.add(Plane3d::default().mesh().size(20.0, 20.0))),
+        MeshMaterial3d(materials.add(Color::srgb(0.3, 0.3, 0.3))),
+    ));
+
+    // A row of transparent spheres across the whole screen.
+    let sphere = meshes.add(Sphere::new(0.7).mesh().ico(4).unwrap());
+    for i in -4..=4 {
+        let hue = (i + 4) as f32 * 40.0;
+        commands.spawn((
+            Mesh3d(sphere.clone()),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: Color::hsla(hue, 0.8, 0.5, 0.6),
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            })),
+            Transform::from_xyz(i as f32 * 1.2, 1.0, 0.0),
+        ));
+    }
+
+    commands.spawn((
+        Text::new("M: Toggle composite mask"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+    ));
+}
+
+fn toggle_mask(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mask: Res<MaskImage>,
+    camera: Query<(Entity, Has<WboitCompositeMask>), With<Camera3d>>,
+) {
+    if !keys.just_pressed(KeyCode::KeyM) {
+        return;
+    }
+    let Ok((entity, masked)) = camera.single() else {
+        return;
+    };
+    if masked {
+        commands.entity(entity).remove::<WboitCompositeMask>();
+    } else {
+        commands
+            .entity(entity)
+            .insert(WboitCompositeMask(mask.0.clone()));
+    }
+    info!("Composite mask: {}", !masked);
+}
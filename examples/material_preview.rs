@@ -0,0 +1,319 @@
+//! Preview a transparent material through WBOIT in isolation.
+//!
+//! A stack of overlapping quads with the previewed material sits in front of a backdrop.
+//! Change the material preset, the overlap count, the backdrop, the light and the OIT mode to
+//! check how the material composites, how it saturates under overlap and how it compares with
+//! plain sorted blending.
+//!
+//! Keys: M material preset, Up/Down overlap count, B backdrop, L light, 1/2/3 no OIT, WBOIT,
+//! HE-WBOIT.
+
+use bevy::asset::RenderAssetUsages;
+use bevy::image::ImageSampler;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy_wboit::{HEWboitPlugin, HEWboitSettings, WboitPlugin, WboitSettings};
+
+const MAX_OVERLAP: usize = 32;
+
+/// Material presets, as (name, material).
+fn presets() -> Vec<(&'static str, StandardMaterial)> {
+    vec![
+        (
+            "tinted glass (Blend)",
+            StandardMaterial {
+                base_color: Color::srgba(0.3, 0.7, 1.0, 0.3),
+                alpha_mode: AlphaMode::Blend,
+                perceptual_roughness: 0.1,
+                ..default()
+            },
+        ),
+        (
+            "smoke (Premultiplied)",
+            StandardMaterial {
+                base_color: Color::srgba(0.2, 0.2, 0.2, 0.25),
+                alpha_mode: AlphaMode::Premultiplied,
+                unlit: true,
+                ..default()
+            },
+        ),
+        (
+            "glow (Add)",
+            StandardMaterial {
+                base_color: Color::srgba(1.0, 0.5, 0.1, 0.5),
+                emissive: LinearRgba::rgb(0.6, 0.2, 0.0),
+                alpha_mode: AlphaMode::Add,
+                unlit: true,
+                ..default()
+            },
+        ),
+        (
+            "frosted (Blend, rough, double sided)",
+            StandardMaterial {
+                base_color: Color::srgba(0.9, 0.95, 1.0, 0.5),
+                alpha_mode: AlphaMode::Blend,
+                perceptual_roughness: 0.9,
+                double_sided: true,
+                cull_mode: None,
+                ..default()
+            },
+        ),
+    ]
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Backdrop {
+    Dark,
+    Light,
+    Checker,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum OitMode {
+    None,
+    Wboit,
+    HeWboit,
+}
+
+#[derive(Resource)]
+struct Preview {
+    preset: usize,
+    overlap: usize,
+    backdrop: Backdrop,
+    light: usize,
+    mode: OitMode,
+    material: Handle<StandardMaterial>,
+    backdrop_material: Handle<StandardMaterial>,
+    checker: Handle<Image>,
+    quad: Handle<Mesh>,
+}
+
+#[derive(Component)]
+struct PreviewInstance;
+
+#[derive(Component)]
+struct PreviewLabel;
+
+/// Light presets, as (name, illuminance, pitch below the horizon).
+const LIGHTS: [(&str, f32, f32); 3] = [
+    ("front", 8000.0, 0.3),
+    ("grazing", 8000.0, 1.3),
+    ("off", 0.0, 0.3),
+];
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, WboitPlugin, HEWboitPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(
+            Update,
+            (
+                handle_keys,
+                (
+                    respawn_instances,
+                    apply_backdrop,
+                    apply_light,
+                    apply_mode,
+                    update_label,
+                )
+                    .run_if(resource_changed::<Preview>),
+            )
+                .chain(),
+        )
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    commands.spawn((
+        Camera3d::default(),
+        Transform::from_xyz(1.5, 0.8, 4.0).looking_at(Vec3::new(0.0, 0.0, -0.5), Vec3::Y),
+        WboitSettings::default(),
+        Msaa::Off,
+    ));
+    commands.spawn((DirectionalLight::default(), Transform::default()));
+
+    let backdrop_material = materials.add(StandardMaterial {
+        unlit: true,
+        ..default()
+    });
+    commands.spawn((
+        Mesh3d(meshes.add(Rectangle::new(12.0, 8.0))),
+        MeshMaterial3d(backdrop_material.clone()),
+        Transform::from_xyz(0.0, 0.0, -3.0),
+    ));
+
+    let mut checker = Image::new(
+        Extent3d {
+            width: 16,
+            height: 16,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        (0..16 * 16)
+            .flat_map(|i| {
+                let on = (i % 16 + i / 16) % 2 == 0;
+                if on {
+                    [230, 230, 230, 255]
+                } else {
+                    [40, 40, 40, 255]
+                }
+            })
+            .collect(),
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    checker.sampler = ImageSampler::nearest();
+
+    commands.insert_resource(Preview {
+        preset: 0,
+        overlap: 4,
+        backdrop: Backdrop::Checker,
+        light: 0,
+        mode: OitMode::Wboit,
+        material: materials.add(presets()[0].1.clone()),
+        backdrop_material,
+        checker: images.add(checker),
+        quad: meshes.add(Rectangle::new(1.5, 1.5)),
+    });
+
+    commands.spawn((
+        Text::default(),
+        PreviewLabel,
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+    ));
+}
+
+fn handle_keys(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut preview: ResMut<Preview>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if keys.just_pressed(KeyCode::KeyM) {
+        let presets = presets();
+        preview.preset = (preview.preset + 1) % presets.len();
+        if let Some(material) = materials.get_mut(&preview.material) {
+            *material = presets[preview.preset].1.clone();
+        }
+    }
+    if keys.just_pressed(KeyCode::ArrowUp) {
+        preview.overlap = (preview.overlap + 1).min(MAX_OVERLAP);
+    }
+    if keys.just_pressed(KeyCode::ArrowDown) {
+        preview.overlap = preview.overlap.saturating_sub(1).max(1);
+    }
+    if keys.just_pressed(KeyCode::KeyB) {
+        preview.backdrop = match preview.backdrop {
+            Backdrop::Dark => Backdrop::Light,
+            Backdrop::Light => Backdrop::Checker,
+            Backdrop::Checker => Backdrop::Dark,
+        };
+    }
+    if keys.just_pressed(KeyCode::KeyL) {
+        preview.light = (preview.light + 1) % LIGHTS.len();
+    }
+    for (key, mode) in [
+        (KeyCode::Digit1, OitMode::None),
+        (KeyCode::Digit2, OitMode::Wboit),
+        (KeyCode::Digit3, OitMode::HeWboit),
+    ] {
+        if keys.just_pressed(key) {
+            preview.mode = mode;
+        }
+    }
+}
+
+/// Stack `overlap` quads behind each other, each shifted a little so the overlap steps are
+/// visible side by side.
+fn respawn_instances(
+    mut commands: Commands,
+    preview: Res<Preview>,
+    instances: Query<Entity, With<PreviewInstance>>,
+    mut spawned: Local<Option<usize>>,
+) {
+    if *spawned == Some(preview.overlap) {
+        return;
+    }
+    *spawned = Some(preview.overlap);
+    for entity in &instances {
+        commands.entity(entity).despawn();
+    }
+    let n = preview.overlap;
+    for i in 0..n {
+        let t = i as f32 - (n - 1) as f32 * 0.5;
+        commands.spawn((
+            Mesh3d(preview.quad.clone()),
+            MeshMaterial3d(preview.material.clone()),
+            Transform::from_xyz(t * 0.15, t * 0.05, -(i as f32) * 0.08),
+            PreviewInstance,
+        ));
+    }
+}
+
+fn apply_backdrop(preview: Res<Preview>, mut materials: ResMut<Assets<StandardMaterial>>) {
+    let Some(material) = materials.get_mut(&preview.backdrop_material) else {
+        return;
+    };
+    (material.base_color, material.base_color_texture) = match preview.backdrop {
+        Backdrop::Dark => (Color::srgb(0.02, 0.02, 0.03), None),
+        Backdrop::Light => (Color::srgb(0.9, 0.9, 0.9), None),
+        Backdrop::Checker => (Color::WHITE, Some(preview.checker.clone())),
+    };
+}
+
+fn apply_light(preview: Res<Preview>, mut light: Single<(&mut DirectionalLight, &mut Transform)>) {
+    let (_, illuminance, pitch) = LIGHTS[preview.light];
+    let (light, transform) = &mut *light;
+    light.illuminance = illuminance;
+    **transform = Transform::from_rotation(Quat::from_euler(EulerRot::YXZ, 0.4, -pitch, 0.0));
+}
+
+fn apply_mode(
+    mut commands: Commands,
+    preview: Res<Preview>,
+    camera: Single<Entity, With<Camera3d>>,
+    mut applied: Local<Option<OitMode>>,
+) {
+    if *applied == Some(preview.mode) {
+        return;
+    }
+    *applied = Some(preview.mode);
+    let mut camera = commands.entity(*camera);
+    match preview.mode {
+        OitMode::None => {
+            camera.remove::<(WboitSettings, HEWboitSettings)>();
+        }
+        OitMode::Wboit => {
+            camera
+                .remove::<HEWboitSettings>()
+                .insert(WboitSettings::default());
+        }
+        OitMode::HeWboit => {
+            camera.remove::<WboitSettings>().insert(HEWboitSettings {
+                max_depth: 10.0,
+                ..default()
+            });
+        }
+    }
+}
+
+fn update_label(preview: Res<Preview>, mut label: Single<&mut Text, With<PreviewLabel>>) {
+    label.0 = format!(
+        "M material: {}\nUp/Down overlap: {}\nB backdrop: {:?}\nL light: {}\n\
+         1/2/3 mode: {:?}",
+        presets()[preview.preset].0,
+        preview.overlap,
+        preview.backdrop,
+        LIGHTS[preview.light].0,
+        preview.mode,
+    );
+}
@@ -0,0 +1,96 @@
+//! Material-less WBOIT with `WboitMinimalPlugin`.
+//!
+//! The rings have no material at all: each is a vertex-colored torus with a `WboitMinimal`
+//! tint, accumulated unlit into the naive WBOIT targets. An opaque cube with a regular
+//! material sits in the middle for reference. Press T to cycle the tint of the rings.
+
+use bevy::prelude::*;
+use bevy_wboit::{WboitMinimal, WboitMinimalPlugin, WboitSettings};
+
+const TINTS: [Color; 3] = [
+    Color::srgba(1.0, 1.0, 1.0, 0.5),
+    Color::srgba(1.0, 0.6, 0.3, 0.35),
+    Color::srgba(0.4, 0.8, 1.0, 0.7),
+];
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, WboitMinimalPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(Update, (spin, cycle_tint))
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        Camera3d::default(),
+        Transform::from_xyz(0.0, 2.5, 6.0).looking_at(Vec3::ZERO, Vec3::Y),
+        WboitSettings::default(),
+        Msaa::Off,
+    ));
+    commands.spawn((
+        DirectionalLight::default(),
+        Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, -0.8, 0.4, 0.0)),
+    ));
+
+    commands.spawn((
+        Mesh3d(meshes.add(Cuboid::new(0.8, 0.8, 0.8))),
+        MeshMaterial3d(materials.add(Color::srgb(0.6, 0.6, 0.6))),
+    ));
+
+    let mut torus = Torus::new(1.2, 1.6).mesh().build();
+    let colors: Vec<[f32; 4]> = torus
+        .attribute(Mesh::ATTRIBUTE_POSITION)
+        .and_then(|positions| positions.as_float3())
+        .unwrap_or_default()
+        .iter()
+        .map(|&[x, _, z]| {
+            let hue = (z.atan2(x).to_degrees() + 180.0) % 360.0;
+            LinearRgba::from(Color::hsl(hue, 0.9, 0.5)).to_f32_array()
+        })
+        .collect();
+    torus.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    let torus = meshes.add(torus);
+
+    for i in 0..3 {
+        commands.spawn((
+            Mesh3d(torus.clone()),
+            WboitMinimal { tint: TINTS[0] },
+            Transform::from_rotation(Quat::from_rotation_x(i as f32 * 1.0)),
+        ));
+    }
+
+    commands.spawn((
+        Text::new("T: cycle ring tint"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+    ));
+}
+
+fn spin(time: Res<Time>, mut rings: Query<&mut Transform, With<WboitMinimal>>) {
+    for mut transform in &mut rings {
+        transform.rotate_y(time.delta_secs() * 0.5);
+    }
+}
+
+fn cycle_tint(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut rings: Query<&mut WboitMinimal>,
+    mut index: Local<usize>,
+) {
+    if !keys.just_pressed(KeyCode::KeyT) {
+        return;
+    }
+    *index = (*index + 1) % TINTS.len();
+    for mut ring in &mut rings {
+        ring.tint = TINTS[*index];
+    }
+}
@@ -0,0 +1,158 @@
+//! Front emphasis in deep transparent stacks with `WboitSettings::nearest_depth_falloff`.
+//!
+//! Twelve tinted sheets are stacked closely in front of the camera. Plain WBOIT weights them
+//! almost equally, so the front sheet's color drowns in the average of the ones behind it.
+//! With the falloff, a depth-only prepass records the nearest transparent depth per pixel and
+//! fragments behind it lose weight exponentially with their distance to it, so the front sheet
+//! dominates like it would with sorted blending. The left half renders the stack without the
+//! falloff and the right half with it; Up/Down changes the right half's falloff distance.
+
+use bevy::prelude::*;
+use bevy::render::camera::Viewport;
+use bevy::window::{PrimaryWindow, WindowResized};
+use bevy_wboit::{WboitPlugin, WboitSettings};
+
+const SHEETS: usize = 12;
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, WboitPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(Update, (set_viewports, adjust_falloff, update_label))
+        .run();
+}
+
+/// Which half of the window a camera renders to.
+#[derive(Component)]
+struct Half(u32);
+
+#[derive(Component)]
+struct FalloffLabel;
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (i, nearest_depth_falloff) in [None, Some(0.3)].into_iter().enumerate() {
+        commands.spawn((
+            Camera3d::default(),
+            Camera {
+                order: i as isize,
+                // Only the first camera clears the shared window texture.
+                clear_color: if i == 0 {
+                    ClearColorConfig::Default
+                } else {
+                    ClearColorConfig::None
+                },
+                ..default()
+            },
+            Transform::from_xyz(2.5, 1.5, 7.0).looking_at(Vec3::new(0.0, 1.0, 0.0), Vec3::Y),
+            WboitSettings {
+                nearest_depth_falloff,
+                ..default()
+            },
+            Msaa::Off,
+            Half(i as u32),
+        ));
+    }
+
+    commands.spawn((
+        DirectionalLight {
+            illuminance: 8000.0,
+            ..default()
+        },
+        Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, -0.8, 0.4, 0.0)),
+    ));
+
+    commands.spawn((
+        Mesh3d(meshes.add(Plane3d::default().mesh().size(20.0, 20.0))),
+        MeshMaterial3d(materials.add(Color::srgb(0.35, 0.35, 0.35))),
+    ));
+
+    // Sheets 0.15 apart, front one at z = 1, cycling through the hues back to front.
+    let sheet = meshes.add(Rectangle::new(2.5, 2.0));
+    for i in 0..SHEETS {
+        let hue = 360.0 * i as f32 / SHEETS as f32;
+        commands.spawn((
+            Mesh3d(sheet.clone()),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: Color::hsla(hue, 0.9, 0.55, 0.45),
+                alpha_mode: AlphaMode::Blend,
+                cull_mode: None,
+                unlit: true,
+                ..default()
+            })),
+            Transform::from_xyz(0.0, 1.2, 1.0 - 0.15 * (SHEETS - 1 - i) as f32),
+        ));
+    }
+
+    commands.spawn((
+        Text::default(),
+        FalloffLabel,
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+    ));
+}
+
+/// Keep each camera on its half of the window.
+fn set_viewports(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut resize_events: EventReader<WindowResized>,
+    mut cameras: Query<(&mut Camera, &Half)>,
+    mut initialized: Local<bool>,
+) {
+    if resize_events.read().count() == 0 && *initialized {
+        return;
+    }
+    let Ok(window) = windows.single() else {
+        return;
+    };
+    *initialized = true;
+    let size = window.physical_size();
+    let half = UVec2::new((size.x / 2).max(1), size.y.max(1));
+    for (mut camera, position) in &mut cameras {
+        camera.viewport = Some(Viewport {
+            physical_position: UVec2::new(position.0 * half.x, 0),
+            physical_size: half,
+            ..default()
+        });
+    }
+}
+
+fn adjust_falloff(keys: Res<ButtonInput<KeyCode>>, mut settings: Query<&mut WboitSettings>) {
+    for mut settings in &mut settings {
+        // Only the right half's camera has a falloff to change.
+        let Some(falloff) = settings.nearest_depth_falloff.as_mut() else {
+            continue;
+        };
+        if keys.just_pressed(KeyCode::ArrowUp) {
+            *falloff *= 2.0;
+        }
+        if keys.just_pressed(KeyCode::ArrowDown) {
+            *falloff *= 0.5;
+        }
+    }
+}
+
+fn update_label(
+    settings: Query<(&WboitSettings, &Half), Changed<WboitSettings>>,
+    mut label: Query<&mut Text, With<FalloffLabel>>,
+) {
+    let Ok(mut text) = label.single_mut() else {
+        return;
+    };
+    for (settings, half) in &settings {
+        let Some(falloff) = settings.nearest_depth_falloff.filter(|_| half.0 == 1) else {
+            continue;
+        };
+        text.0 = format!(
+            "Left: falloff off  |  Right: falloff {falloff:.3} world units\n\
+             Up/Down: Double/halve the falloff distance"
+        );
+    }
+}
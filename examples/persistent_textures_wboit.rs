@@ -0,0 +1,259 @@
+//! Reading the previous frame's WBOIT targets with `WboitSettings::persistent_textures`.
+//!
+//! A custom render node right before the WBOIT accum pass resolves what the previous frame
+//! accumulated (the accum target and `revealage[1 - frame_index]`, not yet overwritten) and
+//! blends it at half strength under this frame's transparents, leaving a one-frame echo
+//! behind the moving cubes. Press P to toggle persistent textures; without them the
+//! `TextureCache` may hand out other textures, so the echo is not guaranteed to be last
+//! frame's.
+
+use bevy::core_pipeline::core_3d::graph::{Core3d, Node3d};
+use bevy::core_pipeline::fullscreen_vertex_shader::fullscreen_shader_vertex_state;
+use bevy::ecs::query::QueryItem;
+use bevy::prelude::*;
+use bevy::render::RenderApp;
+use bevy::render::render_graph::{
+    NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode, ViewNodeRunner,
+};
+use bevy::render::render_resource::binding_types::texture_2d;
+use bevy::render::render_resource::{
+    BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries, BlendState, CachedRenderPipelineId,
+    ColorTargetState, ColorWrites, FragmentState, PipelineCache, RenderPassDescriptor,
+    RenderPipelineDescriptor, ShaderStages, TextureSampleType,
+};
+use bevy::render::renderer::{RenderContext, RenderDevice};
+use bevy::render::view::ViewTarget;
+use bevy_wboit::naive::accum_pass::WboitAccumPass;
+use bevy_wboit::textures::WboitTextures;
+use bevy_wboit::{WboitPlugin, WboitSettings};
+
+/// Resolves the previous frame's accum and revealage like the composite, at half coverage.
+const ECHO_SHADER: &str = r"
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+@group(0) @binding(0) var accum_tex: texture_2d<f32>;
+@group(0) @binding(1) var revealage_tex: texture_2d<f32>;
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let coords = vec2<i32>(in.position.xy);
+    let accum = textureLoad(accum_tex, coords, 0);
+    let revealage = textureLoad(revealage_tex, coords, 0).r;
+    if accum.a < 1e-5 {
+        return vec4(0.0);
+    }
+    let alpha = (1.0 - revealage) * 0.5;
+    return vec4(accum.rgb / accum.a * alpha, alpha);
+}
+";
+
+#[derive(RenderLabel, Debug, Clone, Hash, PartialEq, Eq)]
+struct EchoPass;
+
+#[derive(Resource, Clone)]
+struct EchoShader(Handle<Shader>);
+
+#[derive(Resource)]
+struct EchoPipeline {
+    layout: BindGroupLayout,
+    pipeline_id: CachedRenderPipelineId,
+}
+
+impl FromWorld for EchoPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+        let layout = render_device.create_bind_group_layout(
+            "echo_bind_group_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    texture_2d(TextureSampleType::Float { filterable: false }),
+                    texture_2d(TextureSampleType::Float { filterable: false }),
+                ),
+            ),
+        );
+        let shader = world.resource::<EchoShader>().0.clone();
+        let pipeline_id =
+            world
+                .resource_mut::<PipelineCache>()
+                .queue_render_pipeline(RenderPipelineDescriptor {
+                    label: Some("echo_pipeline".into()),
+                    layout: vec![layout.clone()],
+                    vertex: fullscreen_shader_vertex_state(),
+                    fragment: Some(FragmentState {
+                        shader,
+                        shader_defs: vec![],
+                        entry_point: "fragment".into(),
+                        targets: vec![Some(ColorTargetState {
+                            format: ViewTarget::TEXTURE_FORMAT_HDR,
+                            blend: Some(BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                            write_mask: ColorWrites::COLOR,
+                        })],
+                    }),
+                    primitive: default(),
+                    depth_stencil: None,
+                    multisample: default(),
+                    push_constant_ranges: vec![],
+                    zero_initialize_workgroup_memory: false,
+                });
+        Self {
+            layout,
+            pipeline_id,
+        }
+    }
+}
+
+#[derive(Default)]
+struct EchoNode;
+
+impl ViewNode for EchoNode {
+    type ViewQuery = (&'static ViewTarget, &'static WboitTextures);
+
+    fn run<'w>(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext<'w>,
+        (view_target, textures): QueryItem<Self::ViewQuery>,
+        world: &'w World,
+    ) -> Result<(), NodeRunError> {
+        let echo = world.resource::<EchoPipeline>();
+        let Some(pipeline) = world
+            .resource::<PipelineCache>()
+            .get_render_pipeline(echo.pipeline_id)
+        else {
+            return Ok(());
+        };
+
+        let previous_revealage = &textures.revealage[1 - textures.frame_index];
+        let bind_group = render_context.render_device().create_bind_group(
+            "echo_bind_group",
+            &echo.layout,
+            &BindGroupEntries::sequential((
+                &textures.accum.default_view,
+                &previous_revealage.default_view,
+            )),
+        );
+        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("echo_pass"),
+            color_attachments: &[Some(view_target.get_color_attachment())],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_render_pipeline(pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+        Ok(())
+    }
+}
+
+struct EchoPlugin;
+
+impl Plugin for EchoPlugin {
+    fn build(&self, app: &mut App) {
+        let shader = app
+            .world_mut()
+            .resource_mut::<Assets<Shader>>()
+            .add(Shader::from_wgsl(ECHO_SHADER, "echo.wgsl"));
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .insert_resource(EchoShader(shader))
+            .add_render_graph_node::<ViewNodeRunner<EchoNode>>(Core3d, EchoPass)
+            .add_render_graph_edges(
+                Core3d,
+                (Node3d::MainTransparentPass, EchoPass, WboitAccumPass),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.init_resource::<EchoPipeline>();
+        }
+    }
+}
+
+#[derive(Component)]
+struct Orbit(f32);
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, WboitPlugin, EchoPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(Update, (orbit, toggle_persistent))
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        Camera3d::default(),
+        Camera {
+            hdr: true,
+            ..default()
+        },
+        Transform::from_xyz(0.0, 4.0, 6.0).looking_at(Vec3::ZERO, Vec3::Y),
+        WboitSettings {
+            persistent_textures: true,
+            ..default()
+        },
+        Msaa::Off,
+    ));
+    commands.spawn((
+        DirectionalLight::default(),
+        Transform::from_xyz(2.0, 4.0, 3.0).looking_at(Vec3::ZERO, Vec3::Y),
+    ));
+    commands.spawn((
+        Mesh3d(meshes.add(Plane3d::default().mesh().size(8.0, 8.0))),
+        MeshMaterial3d(materials.add(Color::srgb(0.2, 0.2, 0.25))),
+        Transform::from_xyz(0.0, -0.6, 0.0),
+    ));
+
+    let cube = meshes.add(Cuboid::new(0.8, 0.8, 0.8));
+    for i in 0..3 {
+        commands.spawn((
+            Mesh3d(cube.clone()),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: Color::hsla(i as f32 * 120.0, 0.9, 0.6, 0.5),
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            })),
+            Orbit(i as f32 * std::f32::consts::TAU / 3.0),
+        ));
+    }
+
+    commands.spawn((
+        Text::new("persistent_textures: true (P to toggle)"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+    ));
+}
+
+fn orbit(time: Res<Time>, mut cubes: Query<(&mut Transform, &Orbit)>) {
+    for (mut transform, orbit) in &mut cubes {
+        let angle = orbit.0 + time.elapsed_secs() * 2.5;
+        transform.translation = Vec3::new(angle.cos() * 2.0, 0.0, angle.sin() * 2.0);
+    }
+}
+
+fn toggle_persistent(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut settings: Single<&mut WboitSettings>,
+    mut text: Single<&mut Text>,
+) {
+    if keys.just_pressed(KeyCode::KeyP) {
+        settings.persistent_textures = !settings.persistent_textures;
+        text.0 = format!(
+            "persistent_textures: {} (P to toggle)",
+            settings.persistent_textures
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! Reading the transparent color under a crosshair with `WboitPixelProbe`.
+//!
+//! Overlapping colored panes drift across the screen center; the probe reports the resolved
+//! transparent layer at the center pixel, shown as text and as a swatch in the corner.
+
+use bevy::prelude::*;
+use bevy_wboit::{WboitPixelProbe, WboitPixelProbed, WboitPlugin, WboitSettings};
+
+#[derive(Component)]
+struct Pane(f32);
+
+#[derive(Component)]
+struct Swatch;
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, WboitPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(Update, (move_panes, center_probe, show_probe))
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        Camera3d::default(),
+        Transform::from_xyz(0.0, 0.0, 6.0).looking_at(Vec3::ZERO, Vec3::Y),
+        WboitSettings::default(),
+        WboitPixelProbe::default(),
+        Msaa::Off,
+    ));
+
+    let pane = meshes.add(Rectangle::new(2.0, 2.0));
+    let colors = [
+        Color::srgba(1.0, 0.2, 0.2, 0.5),
+        Color::srgba(0.2, 1.0, 0.2, 0.5),
+        Color::srgba(0.2, 0.4, 1.0, 0.5),
+    ];
+    for (i, color) in colors.into_iter().enumerate() {
+        commands.spawn((
+            Mesh3d(pane.clone()),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: color,
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            })),
+            Transform::from_xyz(0.0, 0.0, -(i as f32)),
+            Pane(i as f32 * 2.1),
+        ));
+    }
+
+    commands.spawn((
+        Text::new("probing..."),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+    ));
+    commands.spawn((
+        Swatch,
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(40.0),
+            left: Val::Px(10.0),
+            width: Val::Px(48.0),
+            height: Val::Px(48.0),
+            ..default()
+        },
+        BackgroundColor(Color::NONE),
+    ));
+    // Crosshair at the probed pixel.
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Percent(50.0),
+            top: Val::Percent(50.0),
+            width: Val::Px(4.0),
+            height: Val::Px(4.0),
+            ..default()
+        },
+        BackgroundColor(Color::WHITE),
+    ));
+}
+
+fn move_panes(time: Res<Time>, mut panes: Query<(&mut Transform, &Pane)>) {
+    for (mut transform, pane) in &mut panes {
+        let t = time.elapsed_secs() * 0.6 + pane.0;
+        transform.translation.x = t.sin() * 1.5;
+        transform.translation.y = (t * 1.3).cos() * 1.2;
+    }
+}
+
+/// Keep the probe at the center of the viewport.
+fn center_probe(mut cameras: Query<(&Camera, &mut WboitPixelProbe)>) {
+    for (camera, mut probe) in &mut cameras {
+        if let Some(size) = camera.physical_viewport_size() {
+            probe.pos = size / 2;
+        }
+    }
+}
+
+fn show_probe(
+    mut probed: EventReader<WboitPixelProbed>,
+    mut text: Query<&mut Text>,
+    mut swatch: Query<&mut BackgroundColor, With<Swatch>>,
+) {
+    let Some(last) = probed.read().last() else {
+        return;
+    };
+    let color = last.color;
+    if let Ok(mut text) = text.single_mut() {
+        text.0 = format!(
+            "center pixel: rgb ({:.2}, {:.2}, {:.2}) coverage {:.2}",
+            color.red, color.green, color.blue, color.alpha
+        );
+    }
+    if let Ok(mut swatch) = swatch.single_mut() {
+        // Un-premultiply for display over the UI background.
+        let alpha = color.alpha.max(1e-5);
+        swatch.0 = LinearRgba::new(
+            color.red / alpha,
+            color.green / alpha,
+            color.blue / alpha,
+            color.alpha,
+        )
+        .into();
+    }
+}
@@ -0,0 +1,148 @@
+//! A custom render graph node running after the WBOIT composite, via `WboitPostComposite`.
+//!
+//! `PostCompositePass` is ordered between `WboitPostComposite` and `Node3d::EndMainPass`, so
+//! it sees the view target with the transparent spheres already composited. It only opens an
+//! empty render pass on the view target (visible in GPU captures as `post_composite_example`)
+//! and counts its runs, shown on screen; a real node would draw its effect there.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use bevy::core_pipeline::core_3d::graph::{Core3d, Node3d};
+use bevy::ecs::query::QueryItem;
+use bevy::prelude::*;
+use bevy::render::RenderApp;
+use bevy::render::render_graph::{
+    NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode, ViewNodeRunner,
+};
+use bevy::render::render_resource::RenderPassDescriptor;
+use bevy::render::renderer::RenderContext;
+use bevy::render::view::ViewTarget;
+use bevy_wboit::{WboitPlugin, WboitPostComposite, WboitSettings};
+
+/// Number of times the post-composite node ran, shared by both worlds.
+#[derive(Resource, Clone, Default)]
+struct PostCompositeRuns(Arc<AtomicU32>);
+
+#[derive(RenderLabel, Debug, Clone, Hash, PartialEq, Eq)]
+struct PostCompositePass;
+
+#[derive(Default)]
+struct PostCompositeNode;
+
+impl ViewNode for PostCompositeNode {
+    type ViewQuery = (&'static ViewTarget, Has<WboitSettings>);
+
+    fn run<'w>(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext<'w>,
+        (view_target, is_wboit): QueryItem<Self::ViewQuery>,
+        world: &'w World,
+    ) -> Result<(), NodeRunError> {
+        if !is_wboit {
+            return Ok(());
+        }
+        // The transparents are composited into the view target by now.
+        let _render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("post_composite_example"),
+            color_attachments: &[Some(view_target.get_color_attachment())],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        world
+            .resource::<PostCompositeRuns>()
+            .0
+            .fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+struct PostCompositePlugin;
+
+impl Plugin for PostCompositePlugin {
+    fn build(&self, app: &mut App) {
+        let runs = PostCompositeRuns::default();
+        app.insert_resource(runs.clone());
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .insert_resource(runs)
+            .add_render_graph_node::<ViewNodeRunner<PostCompositeNode>>(Core3d, PostCompositePass)
+            .add_render_graph_edges(
+                Core3d,
+                (WboitPostComposite, PostCompositePass, Node3d::EndMainPass),
+            );
+    }
+}
+
+fn main() {
+    App::new()
+        // WboitPlugin registers WboitPostComposite, so it goes first.
+        .add_plugins((DefaultPlugins, WboitPlugin, PostCompositePlugin))
+        .add_systems(Startup, setup)
+        .add_systems(Update, show_runs)
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        Camera3d::default(),
+        Transform::from_xyz(0.0, 1.5, 5.0).looking_at(Vec3::ZERO, Vec3::Y),
+        WboitSettings::default(),
+        Msaa::Off,
+    ));
+    commands.spawn((
+        DirectionalLight::default(),
+        Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, -0.8, 0.4, 0.0)),
+    ));
+
+    commands.spawn((
+        Mesh3d(meshes.add(Plane3d::default().mesh().size(8.0, 8.0))),
+        MeshMaterial3d(materials.add(Color::srgb(0.3, 0.3, 0.3))),
+        Transform::from_xyz(0.0, -1.0, 0.0),
+    ));
+
+    let sphere = meshes.add(Sphere::new(0.8));
+    for (i, color) in [
+        Color::srgba(1.0, 0.3, 0.3, 0.5),
+        Color::srgba(0.3, 1.0, 0.3, 0.5),
+        Color::srgba(0.3, 0.3, 1.0, 0.5),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        commands.spawn((
+            Mesh3d(sphere.clone()),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: color,
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            })),
+            Transform::from_xyz(i as f32 - 1.0, 0.0, -(i as f32) * 0.5),
+        ));
+    }
+
+    commands.spawn((
+        Text::default(),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+    ));
+}
+
+fn show_runs(runs: Res<PostCompositeRuns>, mut text: Single<&mut Text>) {
+    text.0 = format!(
+        "post-composite node runs: {}",
+        runs.0.load(Ordering::Relaxed)
+    );
+}
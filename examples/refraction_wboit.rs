@@ -0,0 +1,281 @@
+//! Screen-space refraction from `WboitSettings::accumulate_normals`.
+//!
+//! A custom render node between the WBOIT accum and composite passes offsets the opaque
+//! background behind the transparents by their accumulated view-space normal, before the
+//! transparent layer is composited on top. Press R to toggle the normal accumulation (and with
+//! it the distortion).
+
+use bevy::core_pipeline::core_3d::graph::Core3d;
+use bevy::core_pipeline::fullscreen_vertex_shader::fullscreen_shader_vertex_state;
+use bevy::ecs::query::QueryItem;
+use bevy::prelude::*;
+use bevy::render::RenderApp;
+use bevy::render::render_graph::{
+    NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode, ViewNodeRunner,
+};
+use bevy::render::render_resource::binding_types::{sampler, texture_2d};
+use bevy::render::render_resource::{
+    BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries, CachedRenderPipelineId,
+    ColorTargetState, ColorWrites, FragmentState, Operations, PipelineCache,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPipelineDescriptor, Sampler,
+    SamplerBindingType, SamplerDescriptor, ShaderStages, TextureSampleType,
+};
+use bevy::render::renderer::{RenderContext, RenderDevice};
+use bevy::render::view::ViewTarget;
+use bevy_wboit::naive::accum_pass::WboitAccumPass;
+use bevy_wboit::naive::composite::WboitCompositePass;
+use bevy_wboit::textures::WboitTextures;
+use bevy_wboit::{WboitPlugin, WboitSettings};
+
+/// Offsets the background sample by the average transparent normal, scaled by coverage. Only
+/// full-resolution accum targets are handled (they cover the render target like the view).
+const REFRACTION_SHADER: &str = r"
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+@group(0) @binding(0) var screen: texture_2d<f32>;
+@group(0) @binding(1) var screen_sampler: sampler;
+@group(0) @binding(2) var accum_tex: texture_2d<f32>;
+@group(0) @binding(3) var normal_tex: texture_2d<f32>;
+
+const STRENGTH: f32 = 0.06;
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let coords = vec2<i32>(in.position.xy);
+    let weight = textureLoad(accum_tex, coords, 0).a;
+    var offset = vec2(0.0);
+    if weight > 1e-5 {
+        let normal = textureLoad(normal_tex, coords, 0).xy / weight;
+        // View space y is up, uv y is down.
+        offset = vec2(-normal.x, normal.y) * STRENGTH;
+    }
+    return textureSampleLevel(screen, screen_sampler, in.uv + offset, 0.0);
+}
+";
+
+#[derive(RenderLabel, Debug, Clone, Hash, PartialEq, Eq)]
+struct RefractionPass;
+
+#[derive(Resource, Clone)]
+struct RefractionShader(Handle<Shader>);
+
+#[derive(Resource)]
+struct RefractionPipeline {
+    layout: BindGroupLayout,
+    sampler: Sampler,
+    pipeline_id: CachedRenderPipelineId,
+}
+
+impl FromWorld for RefractionPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+        let layout = render_device.create_bind_group_layout(
+            "refraction_bind_group_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    texture_2d(TextureSampleType::Float { filterable: true }),
+                    sampler(SamplerBindingType::Filtering),
+                    texture_2d(TextureSampleType::Float { filterable: true }),
+                    texture_2d(TextureSampleType::Float { filterable: true }),
+                ),
+            ),
+        );
+        let sampler = render_device.create_sampler(&SamplerDescriptor::default());
+        let shader = world.resource::<RefractionShader>().0.clone();
+        let pipeline_id =
+            world
+                .resource_mut::<PipelineCache>()
+                .queue_render_pipeline(RenderPipelineDescriptor {
+                    label: Some("refraction_pipeline".into()),
+                    layout: vec![layout.clone()],
+                    vertex: fullscreen_shader_vertex_state(),
+                    fragment: Some(FragmentState {
+                        shader,
+                        shader_defs: vec![],
+                        entry_point: "fragment".into(),
+                        targets: vec![Some(ColorTargetState {
+                            format: ViewTarget::TEXTURE_FORMAT_HDR,
+                            blend: None,
+                            write_mask: ColorWrites::ALL,
+                        })],
+                    }),
+                    primitive: default(),
+                    depth_stencil: None,
+                    multisample: default(),
+                    push_constant_ranges: vec![],
+                    zero_initialize_workgroup_memory: false,
+                });
+        Self {
+            layout,
+            sampler,
+            pipeline_id,
+        }
+    }
+}
+
+#[derive(Default)]
+struct RefractionNode;
+
+impl ViewNode for RefractionNode {
+    type ViewQuery = (&'static ViewTarget, &'static WboitTextures);
+
+    fn run<'w>(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext<'w>,
+        (view_target, textures): QueryItem<Self::ViewQuery>,
+        world: &'w World,
+    ) -> Result<(), NodeRunError> {
+        let Some(normal) = textures.normal.as_ref() else {
+            return Ok(());
+        };
+        let refraction = world.resource::<RefractionPipeline>();
+        let Some(pipeline) = world
+            .resource::<PipelineCache>()
+            .get_render_pipeline(refraction.pipeline_id)
+        else {
+            return Ok(());
+        };
+
+        let post_process = view_target.post_process_write();
+        let bind_group = render_context.render_device().create_bind_group(
+            "refraction_bind_group",
+            &refraction.layout,
+            &BindGroupEntries::sequential((
+                post_process.source,
+                &refraction.sampler,
+                &textures.accum.default_view,
+                &normal.default_view,
+            )),
+        );
+        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("refraction_pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: post_process.destination,
+                resolve_target: None,
+                ops: Operations::default(),
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_render_pipeline(pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+        Ok(())
+    }
+}
+
+struct RefractionPlugin;
+
+impl Plugin for RefractionPlugin {
+    fn build(&self, app: &mut App) {
+        let shader = app
+            .world_mut()
+            .resource_mut::<Assets<Shader>>()
+            .add(Shader::from_wgsl(REFRACTION_SHADER, "refraction.wgsl"));
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .insert_resource(RefractionShader(shader))
+            .add_render_graph_node::<ViewNodeRunner<RefractionNode>>(Core3d, RefractionPass)
+            .add_render_graph_edges(Core3d, (WboitAccumPass, RefractionPass, WboitCompositePass));
+    }
+
+    fn finish(&self, app: &mut App) {
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.init_resource::<RefractionPipeline>();
+        }
+    }
+}
+
+#[derive(Component)]
+struct Spin;
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, WboitPlugin, RefractionPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(Update, (spin, toggle_normals))
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        Camera3d::default(),
+        Camera {
+            hdr: true,
+            ..default()
+        },
+        Transform::from_xyz(0.0, 0.0, 5.0).looking_at(Vec3::ZERO, Vec3::Y),
+        WboitSettings {
+            accumulate_normals: true,
+            ..default()
+        },
+        Msaa::Off,
+    ));
+    commands.spawn((
+        DirectionalLight::default(),
+        Transform::from_xyz(2.0, 3.0, 4.0).looking_at(Vec3::ZERO, Vec3::Y),
+    ));
+
+    // Striped backdrop, so the distortion is easy to see.
+    let stripe = meshes.add(Cuboid::new(0.25, 6.0, 0.1));
+    let colors = [Color::srgb(0.9, 0.9, 0.9), Color::srgb(0.15, 0.2, 0.4)];
+    for i in 0..32 {
+        commands.spawn((
+            Mesh3d(stripe.clone()),
+            MeshMaterial3d(materials.add(colors[i % 2])),
+            Transform::from_xyz(i as f32 * 0.25 - 4.0, 0.0, -2.0),
+        ));
+    }
+
+    commands.spawn((
+        Mesh3d(meshes.add(Sphere::new(1.0).mesh().uv(48, 24))),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            base_color: Color::srgba(0.8, 0.9, 1.0, 0.15),
+            alpha_mode: AlphaMode::Blend,
+            perceptual_roughness: 0.1,
+            ..default()
+        })),
+        Spin,
+    ));
+
+    commands.spawn((
+        Text::new("accumulate_normals: on (R to toggle)"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+    ));
+}
+
+fn spin(time: Res<Time>, mut spheres: Query<&mut Transform, With<Spin>>) {
+    for mut transform in &mut spheres {
+        transform.translation.x = time.elapsed_secs().sin() * 1.5;
+    }
+}
+
+fn toggle_normals(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut settings: Single<&mut WboitSettings>,
+    mut text: Single<&mut Text>,
+) {
+    if keys.just_pressed(KeyCode::KeyR) {
+        settings.accumulate_normals = !settings.accumulate_normals;
+        let state = if settings.accumulate_normals {
+            "on"
+        } else {
+            "off"
+        };
+        text.0 = format!("accumulate_normals: {state} (R to toggle)");
+    }
+}
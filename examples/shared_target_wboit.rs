@@ -0,0 +1,135 @@
+//! Two cameras accumulating into one set of WBOIT targets with `WboitSharedTarget`.
+//!
+//! Both cameras look at the scene from the same place into the window, each drawing half of
+//! the interleaved spheres (render layers 0 and 1), like a portal view drawn into the main
+//! one. Shared, their transparents blend in one weighted average and a single composite
+//! resolves them. Toggle sharing with S: each camera then composites on its own, and the
+//! second camera's spheres are layered over the first one's, even where they are behind them.
+
+use bevy::prelude::*;
+use bevy::render::view::RenderLayers;
+use bevy_wboit::{WboitPlugin, WboitSettings, WboitSharedTarget};
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, WboitPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(Update, toggle_sharing)
+        .run();
+}
+
+/// The two cameras, in rendering order.
+#[derive(Resource)]
+struct SharingCameras(Vec<Entity>);
+
+#[derive(Component)]
+struct SharingLabel;
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let camera_transform =
+        Transform::from_xyz(0.0, 2.0, 8.0).looking_at(Vec3::new(0.0, 1.0, 0.0), Vec3::Y);
+
+    let first = commands
+        .spawn((
+            Camera3d::default(),
+            camera_transform,
+            WboitSettings::default(),
+            Msaa::Off,
+            RenderLayers::layer(0),
+        ))
+        .id();
+    // Draws over the first camera's image: no clear, and no opaques of its own.
+    let second = commands
+        .spawn((
+            Camera3d::default(),
+            Camera {
+                order: 1,
+                clear_color: ClearColorConfig::None,
+                ..default()
+            },
+            camera_transform,
+            WboitSettings::default(),
+            Msaa::Off,
+            RenderLayers::layer(1),
+        ))
+        .id();
+    let cameras = vec![first, second];
+    commands.insert_resource(WboitSharedTarget {
+        cameras: cameras.clone(),
+    });
+    commands.insert_resource(SharingCameras(cameras));
+
+    commands.spawn((
+        DirectionalLight {
+            illuminance: 8000.0,
+            ..default()
+        },
+        Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, -0.8, 0.4, 0.0)),
+        RenderLayers::from_layers(&[0, 1]),
+    ));
+
+    commands.spawn((
+        Mesh3d(meshes.add(Plane3d::default().mesh().size(20.0, 20.0))),
+        MeshMaterial3d(materials.add(Color::srgb(0.35, 0.35, 0.35))),
+    ));
+
+    // A row of spheres receding from the camera, alternating between the two cameras.
+    let sphere = meshes.add(Sphere::new(0.9).mesh().ico(4).unwrap());
+    let colors = [
+        Color::srgba(1.0, 0.2, 0.2, 0.5),
+        Color::srgba(0.2, 0.4, 1.0, 0.5),
+    ];
+    for i in 0..6 {
+        commands.spawn((
+            Mesh3d(sphere.clone()),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: colors[i % 2],
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            })),
+            Transform::from_xyz(-1.5 + 0.6 * i as f32, 1.0, 1.5 - 0.8 * i as f32),
+            RenderLayers::layer(i % 2),
+        ));
+    }
+
+    commands.spawn((
+        Text::new("S: Toggle the shared target (on)"),
+        SharingLabel,
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+    ));
+}
+
+fn toggle_sharing(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    cameras: Res<SharingCameras>,
+    shared_target: Option<Res<WboitSharedTarget>>,
+    mut label: Query<&mut Text, With<SharingLabel>>,
+) {
+    if !keys.just_pressed(KeyCode::KeyS) {
+        return;
+    }
+    let shared = shared_target.is_none();
+    if shared {
+        commands.insert_resource(WboitSharedTarget {
+            cameras: cameras.0.clone(),
+        });
+    } else {
+        commands.remove_resource::<WboitSharedTarget>();
+    }
+    if let Ok(mut text) = label.single_mut() {
+        text.0 = format!(
+            "S: Toggle the shared target ({})",
+            if shared { "on" } else { "off" }
+        );
+    }
+}
@@ -0,0 +1,281 @@
+use bevy::asset::RenderAssetUsages;
+use bevy::core_pipeline::Skybox;
+use bevy::core_pipeline::tonemapping::Tonemapping;
+use bevy::pbr::LightProbe;
+use bevy::prelude::*;
+use bevy::render::render_resource::{
+    Extent3d, TextureDimension, TextureFormat, TextureViewDescriptor, TextureViewDimension,
+};
+use bevy_wboit::{HEWboitPlugin, HEWboitSettings, WboitPlugin, WboitSettings};
+
+/// Edge length of each procedurally generated cubemap face.
+const CUBEMAP_SIZE: u32 = 64;
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, WboitPlugin, HEWboitPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(
+            Update,
+            (toggle_mode, toggle_skybox_brightness, orbit_camera),
+        )
+        .run();
+}
+
+/// Build a sky-gradient cubemap with a bright "sun" spot on the +X face.
+///
+/// Generated in code so the example has no asset dependencies. Face order follows
+/// wgpu's cube layout: +X, -X, +Y, -Y, +Z, -Z.
+fn sky_cubemap() -> Image {
+    let size = CUBEMAP_SIZE;
+    let mut data = Vec::with_capacity((size * size * 6 * 4) as usize);
+    for face in 0..6 {
+        for y in 0..size {
+            for x in 0..size {
+                let u = x as f32 / (size - 1) as f32;
+                let v = y as f32 / (size - 1) as f32;
+                let (r, g, b) = match face {
+                    // +Y: zenith
+                    2 => (0.25, 0.45, 0.9),
+                    // -Y: ground
+                    3 => (0.15, 0.12, 0.1),
+                    // Sides: horizon gradient, lighter towards the bottom
+                    _ => {
+                        let t = v;
+                        let base = (0.25 + 0.55 * t, 0.45 + 0.4 * t, 0.9 + 0.05 * t);
+                        // Sun disc on +X to exercise bright background halos.
+                        let sun = if face == 0 {
+                            let d = Vec2::new(u - 0.5, v - 0.4).length();
+                            (1.0 - d * 8.0).max(0.0)
+                        } else {
+                            0.0
+                        };
+                        (base.0 + sun, base.1 + sun, base.2 + sun)
+                    }
+                };
+                let to_u8 = |c: f32| (c.clamp(0.0, 1.0) * 255.0) as u8;
+                data.extend_from_slice(&[to_u8(r), to_u8(g), to_u8(b), 255]);
+            }
+        }
+    }
+
+    let mut image = Image::new(
+        Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 6,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    image.texture_view_descriptor = Some(TextureViewDescriptor {
+        dimension: Some(TextureViewDimension::Cube),
+        ..default()
+    });
+    image
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let cubemap = images.add(sky_cubemap());
+
+    // HDR camera with a bright skybox behind the transparents.
+    commands.spawn((
+        Camera3d::default(),
+        Camera {
+            hdr: true,
+            ..default()
+        },
+        Tonemapping::TonyMcMapface,
+        Transform::from_xyz(0., 1., 7.).looking_at(Vec3::ZERO, Vec3::Y),
+        Skybox {
+            image: cubemap.clone(),
+            brightness: 2000.0,
+            ..default()
+        },
+        EnvironmentMapLight {
+            diffuse_map: cubemap.clone(),
+            specular_map: cubemap.clone(),
+            intensity: 1000.0,
+            ..default()
+        },
+        WboitSettings::default(),
+        Msaa::Off,
+    ));
+
+    // Reflection probe around the scene center, so transparents receive local reflections
+    // in addition to the camera's environment light.
+    commands.spawn((
+        LightProbe,
+        EnvironmentMapLight {
+            diffuse_map: cubemap.clone(),
+            specular_map: cubemap,
+            intensity: 2000.0,
+            ..default()
+        },
+        Transform::from_scale(Vec3::splat(6.0)),
+    ));
+
+    commands.spawn((
+        DirectionalLight {
+            illuminance: 10000.0,
+            ..default()
+        },
+        Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, -0.5, 0.5, 0.0)),
+    ));
+
+    // Opaque, reflective pedestal so part of the frame is opaque and part is pure skybox.
+    commands.spawn((
+        Mesh3d(meshes.add(Cylinder::new(1.5, 0.3))),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            base_color: Color::srgb(0.8, 0.8, 0.8),
+            metallic: 1.0,
+            perceptual_roughness: 0.2,
+            ..default()
+        })),
+        Transform::from_xyz(0.0, -1.5, 0.0),
+    ));
+
+    // Transparent glass-like spheres, overlapping each other and the skybox.
+    let sphere = meshes.add(Sphere::new(0.8).mesh().ico(5).unwrap());
+    let configs = [
+        (
+            Color::srgba(0.9, 0.9, 1.0, 0.2),
+            Vec3::new(-1.2, 0.0, 0.0),
+            0.05,
+        ),
+        (
+            Color::srgba(1.0, 0.3, 0.2, 0.5),
+            Vec3::new(0.0, 0.2, -0.6),
+            0.3,
+        ),
+        (
+            Color::srgba(0.2, 1.0, 0.4, 0.35),
+            Vec3::new(1.2, 0.0, 0.0),
+            0.1,
+        ),
+        (
+            Color::srgba(0.2, 0.4, 1.0, 0.6),
+            Vec3::new(0.0, -0.3, 0.8),
+            0.6,
+        ),
+    ];
+    for (color, pos, roughness) in configs {
+        commands.spawn((
+            Mesh3d(sphere.clone()),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: color,
+                alpha_mode: AlphaMode::Blend,
+                perceptual_roughness: roughness,
+                cull_mode: None,
+                ..default()
+            })),
+            Transform::from_translation(pos),
+        ));
+    }
+
+    commands.spawn((
+        Text::new(
+            "1: No OIT  |  2: WBOIT  |  3: HE-WBOIT\n\
+             B: Toggle skybox brightness\n\
+             Arrow keys: Orbit",
+        ),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+    ));
+}
+
+fn toggle_mode(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    camera: Query<Entity, With<Camera3d>>,
+) {
+    let Ok(camera_entity) = camera.single() else {
+        return;
+    };
+
+    if keys.just_pressed(KeyCode::Digit1) {
+        commands
+            .entity(camera_entity)
+            .remove::<WboitSettings>()
+            .remove::<HEWboitSettings>();
+        info!("Switched to standard transparency (no OIT)");
+    }
+
+    if keys.just_pressed(KeyCode::Digit2) {
+        commands
+            .entity(camera_entity)
+            .remove::<HEWboitSettings>()
+            .insert(WboitSettings::default());
+        info!("Switched to naive WBOIT");
+    }
+
+    if keys.just_pressed(KeyCode::Digit3) {
+        commands
+            .entity(camera_entity)
+            .remove::<WboitSettings>()
+            .insert(HEWboitSettings::default());
+        info!("Switched to HE-WBOIT");
+    }
+}
+
+/// Switch between a dim and a very bright skybox to check for halos around transparents.
+fn toggle_skybox_brightness(keys: Res<ButtonInput<KeyCode>>, mut skyboxes: Query<&mut Skybox>) {
+    if !keys.just_pressed(KeyCode::KeyB) {
+        return;
+    }
+    for mut skybox in &mut skyboxes {
+        skybox.brightness = if skybox.brightness > 2000.0 {
+            500.0
+        } else {
+            20000.0
+        };
+        info!("Skybox brightness: {}", skybox.brightness);
+    }
+}
+
+fn orbit_camera(
+    time: Res<Time>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut camera: Query<&mut Transform, With<Camera3d>>,
+) {
+    let Ok(mut transform) = camera.single_mut() else {
+        return;
+    };
+
+    let mut yaw = 0.0f32;
+    if keys.pressed(KeyCode::ArrowLeft) {
+        yaw += 1.0;
+    }
+    if keys.pressed(KeyCode::ArrowRight) {
+        yaw -= 1.0;
+    }
+    if yaw != 0.0 {
+        transform.rotate_around(Vec3::ZERO, Quat::from_rotation_y(yaw * time.delta_secs()));
+    }
+
+    let mut pitch = 0.0f32;
+    if keys.pressed(KeyCode::ArrowUp) {
+        pitch += 1.0;
+    }
+    if keys.pressed(KeyCode::ArrowDown) {
+        pitch -= 1.0;
+    }
+    if pitch != 0.0 {
+        let right = transform.right();
+        transform.rotate_around(
+            Vec3::ZERO,
+            Quat::from_axis_angle(*right, pitch * time.delta_secs()),
+        );
+    }
+}
@@ -0,0 +1,132 @@
+//! Smoke puffs intersecting the ground, with and without the soft-particle fade.
+//!
+//! Each puff is a camera-facing quad that cuts through the ground plane. Without
+//! `WboitSettings::soft_particle_distance` the intersection is a hard line; with it the puffs
+//! fade out as they approach the ground.
+
+use bevy::prelude::*;
+use bevy_wboit::{WboitPlugin, WboitSettings};
+
+/// Fade distance (world units) used when soft particles are on.
+const SOFT_DISTANCE: f32 = 0.6;
+
+/// Camera-facing smoke quad.
+#[derive(Component)]
+struct Billboard;
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, WboitPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(Update, (toggle_soft_particles, drift, face_camera).chain())
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        Camera3d::default(),
+        Transform::from_xyz(0.0, 2.0, 8.0).looking_at(Vec3::new(0.0, 0.3, 0.0), Vec3::Y),
+        WboitSettings {
+            soft_particle_distance: SOFT_DISTANCE,
+            ..default()
+        },
+        Msaa::Off,
+    ));
+
+    commands.spawn((
+        DirectionalLight {
+            illuminance: 3000.0,
+            ..default()
+        },
+        Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, -0.8, 0.4, 0.0)),
+    ));
+
+    // Opaque ground and a crate the smoke rolls around.
+    commands.spawn((
+        Mesh3d(meshes.add(Plane3d::default().mesh().size(20.0, 20.0))),
+        MeshMaterial3d(materials.add(Color::srgb(0.3, 0.3, 0.32))),
+    ));
+    commands.spawn((
+        Mesh3d(meshes.add(Cuboid::new(1.0, 1.0, 1.0))),
+        MeshMaterial3d(materials.add(Color::srgb(0.6, 0.4, 0.2))),
+        Transform::from_xyz(0.8, 0.5, -0.5),
+    ));
+
+    // Smoke puffs centered close to the ground, so the lower half of each quad is clipped.
+    let quad = meshes.add(Rectangle::new(1.6, 1.6));
+    let smoke = materials.add(StandardMaterial {
+        base_color: Color::srgba(0.85, 0.85, 0.9, 0.35),
+        alpha_mode: AlphaMode::Blend,
+        unlit: true,
+        cull_mode: None,
+        ..default()
+    });
+    for i in 0..24 {
+        let t = i as f32 / 24.0;
+        let angle = t * std::f32::consts::TAU * 2.0;
+        let radius = 0.5 + 2.5 * t;
+        commands.spawn((
+            Mesh3d(quad.clone()),
+            MeshMaterial3d(smoke.clone()),
+            Transform::from_xyz(radius * angle.cos(), 0.2 + 0.3 * t, radius * angle.sin()),
+            Billboard,
+        ));
+    }
+
+    commands.spawn((
+        Text::new("S: Toggle soft particles"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+    ));
+}
+
+fn toggle_soft_particles(keys: Res<ButtonInput<KeyCode>>, mut settings: Query<&mut WboitSettings>) {
+    if !keys.just_pressed(KeyCode::KeyS) {
+        return;
+    }
+    for mut settings in &mut settings {
+        settings.soft_particle_distance = if settings.soft_particle_distance > 0.0 {
+            0.0
+        } else {
+            SOFT_DISTANCE
+        };
+        info!(
+            "Soft particle distance: {}",
+            settings.soft_particle_distance
+        );
+    }
+}
+
+/// Slowly bob the puffs up and down through the ground.
+fn drift(time: Res<Time>, mut puffs: Query<&mut Transform, With<Billboard>>) {
+    for (i, mut transform) in puffs.iter_mut().enumerate() {
+        let phase = i as f32 * 0.7;
+        transform.translation.y +=
+            (time.elapsed_secs() * 0.8 + phase).cos() * 0.2 * time.delta_secs();
+    }
+}
+
+fn face_camera(
+    camera: Query<&Transform, (With<Camera3d>, Without<Billboard>)>,
+    mut puffs: Query<&mut Transform, With<Billboard>>,
+) {
+    let Ok(camera) = camera.single() else {
+        return;
+    };
+    for mut transform in &mut puffs {
+        let target = Vec3::new(
+            camera.translation.x,
+            transform.translation.y,
+            camera.translation.z,
+        );
+        transform.look_at(target, Vec3::Y);
+    }
+}
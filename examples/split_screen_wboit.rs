@@ -0,0 +1,130 @@
+//! Two WBOIT cameras sharing one window through sub-viewports.
+//!
+//! The left camera accumulates at full resolution, the right one at half resolution. Each
+//! composite must line up with its own viewport: the spheres should sit on their shadows in
+//! both halves, and neither camera's transparents may spill into the other half. The label
+//! lists each camera's `WboitStatus` through `WboitCameras`.
+
+use bevy::prelude::*;
+use bevy::render::camera::Viewport;
+use bevy::window::{PrimaryWindow, WindowResized};
+use bevy_wboit::{WboitCameras, WboitPlugin, WboitSettings};
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, WboitPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(Update, (set_viewports, show_status))
+        .run();
+}
+
+/// Which half of the window a camera renders to.
+#[derive(Component)]
+struct Half(u32);
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (i, accum_scale) in [1.0, 0.5].into_iter().enumerate() {
+        commands.spawn((
+            Camera3d::default(),
+            Camera {
+                order: i as isize,
+                // Only the first camera clears the shared window texture.
+                clear_color: if i == 0 {
+                    ClearColorConfig::Default
+                } else {
+                    ClearColorConfig::None
+                },
+                ..default()
+            },
+            Transform::from_xyz(-2.0 + 4.0 * i as f32, 2.5, 7.0).looking_at(Vec3::ZERO, Vec3::Y),
+            WboitSettings {
+                accum_scale,
+                ..default()
+            },
+            Msaa::Off,
+            Half(i as u32),
+        ));
+    }
+
+    commands.spawn((
+        DirectionalLight {
+            illuminance: 8000.0,
+            shadows_enabled: true,
+            ..default()
+        },
+        Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, -1.2, 0.3, 0.0)),
+    ));
+
+    commands.spawn((
+        Mesh3d(meshes.add(Plane3d::default().mesh().size(20.0, 20.0))),
+        MeshMaterial3d(materials.add(Color::srgb(0.3, 0.3, 0.3))),
+        Transform::from_xyz(0.0, -1.0, 0.0),
+    ));
+
+    let sphere = meshes.add(Sphere::new(0.8).mesh().ico(4).unwrap());
+    let colors = [
+        Color::srgba(1.0, 0.2, 0.2, 0.5),
+        Color::srgba(0.2, 1.0, 0.3, 0.5),
+        Color::srgba(0.2, 0.4, 1.0, 0.5),
+    ];
+    for (i, color) in colors.into_iter().enumerate() {
+        commands.spawn((
+            Mesh3d(sphere.clone()),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: color,
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            })),
+            Transform::from_xyz(-1.2 + 1.2 * i as f32, 0.0, 0.6 * i as f32 - 0.6),
+        ));
+    }
+
+    commands.spawn((
+        Text::new("Left: full resolution  |  Right: accum_scale 0.5"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+    ));
+}
+
+/// Keep each camera on its half of the window.
+fn set_viewports(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut resize_events: EventReader<WindowResized>,
+    mut cameras: Query<(&mut Camera, &Half)>,
+    mut initialized: Local<bool>,
+) {
+    if resize_events.read().count() == 0 && *initialized {
+        return;
+    }
+    let Ok(window) = windows.single() else {
+        return;
+    };
+    *initialized = true;
+    let size = window.physical_size();
+    let half = UVec2::new((size.x / 2).max(1), size.y.max(1));
+    for (mut camera, position) in &mut cameras {
+        camera.viewport = Some(Viewport {
+            physical_position: UVec2::new(position.0 * half.x, 0),
+            physical_size: half,
+            ..default()
+        });
+    }
+}
+
+fn show_status(cameras: WboitCameras, mut text: Single<&mut Text>) {
+    let mut label = String::from("Left: full resolution  |  Right: accum_scale 0.5");
+    for (camera, mode, status) in cameras.iter() {
+        label.push_str(&format!("\nCamera {camera}: {mode:?} ({status:?})"));
+    }
+    if text.0 != label {
+        text.0 = label;
+    }
+}
@@ -0,0 +1,147 @@
+//! Stacking a WBOIT overlay over a separately rendered base scene.
+//!
+//! The overlay camera renders only the transparent spheres (render layer 1) into an image
+//! cleared to transparent, with `WboitSettings::output_alpha` so the composite leaves the
+//! transparent coverage in the image's alpha. The image is then drawn over the base camera's
+//! opaque scene as a fullscreen UI node. Toggle `output_alpha` with A: without it the overlay
+//! image stays fully transparent and the spheres vanish.
+//!
+//! The overlay holds premultiplied color while the UI blends with straight alpha, so the
+//! spheres look slightly darker than when rendered directly; a custom premultiplied blit
+//! would match exactly.
+
+use bevy::asset::RenderAssetUsages;
+use bevy::prelude::*;
+use bevy::render::camera::RenderTarget;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages};
+use bevy::render::view::RenderLayers;
+use bevy_wboit::{WboitPlugin, WboitSettings};
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, WboitPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(Update, toggle_output_alpha)
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+    windows: Query<&Window>,
+) {
+    let size = windows
+        .single()
+        .map(|window| window.physical_size())
+        .unwrap_or(UVec2::new(1280, 720));
+    let mut overlay = Image::new_fill(
+        Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 0],
+        TextureFormat::Bgra8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    overlay.texture_descriptor.usage =
+        TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST | TextureUsages::RENDER_ATTACHMENT;
+    let overlay = images.add(overlay);
+
+    let camera_transform =
+        Transform::from_xyz(0.0, 2.0, 8.0).looking_at(Vec3::new(0.0, 0.5, 0.0), Vec3::Y);
+
+    // Base camera: opaque scene only, straight to the window.
+    commands.spawn((Camera3d::default(), camera_transform, Msaa::Off));
+
+    // Overlay camera: transparents only, into the transparent image. Rendered first so the
+    // image is up to date when the UI draws it.
+    commands.spawn((
+        Camera3d::default(),
+        Camera {
+            order: -1,
+            target: RenderTarget::Image(overlay.clone().into()),
+            clear_color: ClearColorConfig::Custom(Color::NONE),
+            ..default()
+        },
+        camera_transform,
+        WboitSettings {
+            output_alpha: true,
+            ..default()
+        },
+        Msaa::Off,
+        RenderLayers::layer(1),
+    ));
+
+    // Both layers need the light.
+    commands.spawn((
+        DirectionalLight {
+            illuminance: 8000.0,
+            ..default()
+        },
+        Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, -0.8, 0.4, 0.0)),
+        RenderLayers::from_layers(&[0, 1]),
+    ));
+
+    commands.spawn((
+        Mesh3d(meshes.add(Plane3d::default().mesh().size(20.0, 20.0))),
+        MeshMaterial3d(materials.add(Color::srgb(0.35, 0.35, 0.35))),
+    ));
+    commands.spawn((
+        Mesh3d(meshes.add(Cuboid::new(1.5, 1.5, 1.5))),
+        MeshMaterial3d(materials.add(Color::srgb(0.8, 0.5, 0.2))),
+        Transform::from_xyz(0.0, 0.75, -1.5),
+    ));
+
+    let sphere = meshes.add(Sphere::new(0.8).mesh().ico(4).unwrap());
+    let colors = [
+        Color::srgba(1.0, 0.2, 0.2, 0.5),
+        Color::srgba(0.2, 1.0, 0.3, 0.5),
+        Color::srgba(0.2, 0.4, 1.0, 0.5),
+    ];
+    for (i, color) in colors.into_iter().enumerate() {
+        commands.spawn((
+            Mesh3d(sphere.clone()),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: color,
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            })),
+            Transform::from_xyz(-1.2 + 1.2 * i as f32, 1.0, 0.5 * i as f32),
+            RenderLayers::layer(1),
+        ));
+    }
+
+    commands.spawn((
+        ImageNode::new(overlay),
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            ..default()
+        },
+    ));
+
+    commands.spawn((
+        Text::new("A: Toggle output_alpha on the overlay camera"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+    ));
+}
+
+fn toggle_output_alpha(keys: Res<ButtonInput<KeyCode>>, mut settings: Query<&mut WboitSettings>) {
+    if !keys.just_pressed(KeyCode::KeyA) {
+        return;
+    }
+    for mut settings in &mut settings {
+        settings.output_alpha = !settings.output_alpha;
+        info!("Output alpha: {}", settings.output_alpha);
+    }
+}
@@ -0,0 +1,113 @@
+//! WBOIT under temporal anti-aliasing.
+//!
+//! Thin opaque bars stand right behind transparent panes that share their edges, so any
+//! misregistration between the transparent and opaque layers shows up as a flickering seam.
+//! With `WboitTaaMode::BeforeTaa` the transparents are drawn with the same jittered projection
+//! as the opaque pass and resolved by TAA together with it; with `AfterTaa` they are drawn
+//! unjittered onto the resolved image. Press T to switch the placement and J to toggle TAA.
+
+use bevy::core_pipeline::experimental::taa::{TemporalAntiAliasPlugin, TemporalAntiAliasing};
+use bevy::prelude::*;
+use bevy_wboit::{WboitPlugin, WboitSettings, WboitTaaMode};
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, TemporalAntiAliasPlugin, WboitPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(Update, (toggle_placement, toggle_taa))
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        Camera3d::default(),
+        Camera {
+            hdr: true,
+            ..default()
+        },
+        Transform::from_xyz(0.0, 0.5, 6.0).looking_at(Vec3::new(0.0, 0.5, 0.0), Vec3::Y),
+        WboitSettings::default(),
+        TemporalAntiAliasing::default(),
+        Msaa::Off,
+    ));
+    commands.spawn((
+        DirectionalLight::default(),
+        Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, -0.6, 0.3, 0.0)),
+    ));
+
+    let bar = meshes.add(Cuboid::new(0.1, 2.5, 0.1));
+    let pane = meshes.add(Rectangle::new(0.8, 2.5));
+    let dark = materials.add(Color::srgb(0.1, 0.1, 0.12));
+    for i in 0..4 {
+        let x = -2.4 + 1.6 * i as f32;
+        commands.spawn((
+            Mesh3d(bar.clone()),
+            MeshMaterial3d(dark.clone()),
+            Transform::from_xyz(x, 0.5, -0.1),
+        ));
+        // The pane's left edge sits on the bar's center line, so half the bar is behind it.
+        commands.spawn((
+            Mesh3d(pane.clone()),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: Color::hsla(i as f32 * 80.0, 0.8, 0.6, 0.5),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            })),
+            Transform::from_xyz(x + 0.4, 0.5, 0.0),
+        ));
+    }
+
+    commands.spawn((
+        Text::new(label(WboitTaaMode::BeforeTaa, true)),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+    ));
+}
+
+fn label(mode: WboitTaaMode, taa: bool) -> String {
+    let taa = if taa { "on" } else { "off" };
+    format!("Composite placement: {mode:?} (T)\nTAA: {taa} (J)")
+}
+
+fn toggle_placement(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut camera: Single<(&mut WboitSettings, Has<TemporalAntiAliasing>)>,
+    mut text: Single<&mut Text>,
+) {
+    if keys.just_pressed(KeyCode::KeyT) {
+        let (settings, taa) = &mut *camera;
+        settings.taa_mode = match settings.taa_mode {
+            WboitTaaMode::BeforeTaa => WboitTaaMode::AfterTaa,
+            _ => WboitTaaMode::BeforeTaa,
+        };
+        text.0 = label(settings.taa_mode, *taa);
+    }
+}
+
+fn toggle_taa(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    camera: Single<(Entity, &WboitSettings, Has<TemporalAntiAliasing>)>,
+    mut text: Single<&mut Text>,
+) {
+    if keys.just_pressed(KeyCode::KeyJ) {
+        let (entity, settings, taa) = *camera;
+        if taa {
+            commands.entity(entity).remove::<TemporalAntiAliasing>();
+        } else {
+            commands
+                .entity(entity)
+                .insert(TemporalAntiAliasing::default());
+        }
+        text.0 = label(settings.taa_mode, !taa);
+    }
+}
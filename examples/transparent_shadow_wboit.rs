@@ -0,0 +1,101 @@
+//! Transparent shadow casters with `WboitShadowMaterial`.
+//!
+//! Two 35% opaque panes stand in front of a wall under a directional light. The left one is a
+//! plain `StandardMaterial` and casts a solid shadow; the right one is a `WboitShadowMaterial`
+//! and casts a shadow as faint as it is opaque. Up/Down changes its `shadow_opacity`.
+
+use bevy::pbr::ShadowFilteringMethod;
+use bevy::prelude::*;
+use bevy_wboit::{
+    WboitSettings, WboitShadowExtension, WboitShadowMaterial, WboitShadowMaterialPlugin,
+};
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, WboitShadowMaterialPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(Update, adjust_shadow_opacity)
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut shadow_materials: ResMut<Assets<WboitShadowMaterial>>,
+) {
+    commands.spawn((
+        Camera3d::default(),
+        Transform::from_xyz(0.0, 2.0, 7.0).looking_at(Vec3::new(0.0, 1.0, 0.0), Vec3::Y),
+        WboitSettings::default(),
+        // Gaussian filtering averages the stochastic pattern into a smooth partial shadow.
+        ShadowFilteringMethod::Gaussian,
+        Msaa::Off,
+    ));
+    commands.spawn((
+        DirectionalLight {
+            shadows_enabled: true,
+            ..default()
+        },
+        Transform::from_xyz(1.0, 4.0, 5.0).looking_at(Vec3::new(0.0, 1.0, -2.0), Vec3::Y),
+    ));
+
+    commands.spawn((
+        Mesh3d(meshes.add(Plane3d::default().mesh().size(12.0, 12.0))),
+        MeshMaterial3d(materials.add(Color::srgb(0.7, 0.7, 0.7))),
+    ));
+    commands.spawn((
+        Mesh3d(meshes.add(Cuboid::new(8.0, 4.0, 0.2))),
+        MeshMaterial3d(materials.add(Color::srgb(0.8, 0.8, 0.75))),
+        Transform::from_xyz(0.0, 2.0, -2.5),
+    ));
+
+    let pane = meshes.add(Cuboid::new(1.6, 2.0, 0.05));
+    let glass = StandardMaterial {
+        base_color: Color::srgba(0.3, 0.6, 1.0, 0.35),
+        alpha_mode: AlphaMode::Blend,
+        ..default()
+    };
+    commands.spawn((
+        Mesh3d(pane.clone()),
+        MeshMaterial3d(materials.add(glass.clone())),
+        Transform::from_xyz(-1.2, 1.2, 0.0),
+    ));
+    commands.spawn((
+        Mesh3d(pane),
+        MeshMaterial3d(shadow_materials.add(WboitShadowMaterial {
+            base: glass,
+            extension: WboitShadowExtension::default(),
+        })),
+        Transform::from_xyz(1.2, 1.2, 0.0),
+    ));
+
+    commands.spawn((
+        Text::new("shadow_opacity: 1.0 (Up/Down)"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+    ));
+}
+
+fn adjust_shadow_opacity(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut shadow_materials: ResMut<Assets<WboitShadowMaterial>>,
+    mut text: Single<&mut Text>,
+) {
+    let step = if keys.just_pressed(KeyCode::ArrowUp) {
+        0.25
+    } else if keys.just_pressed(KeyCode::ArrowDown) {
+        -0.25
+    } else {
+        return;
+    };
+    for (_, material) in shadow_materials.iter_mut() {
+        let opacity = &mut material.extension.shadow_opacity;
+        *opacity = (*opacity + step).clamp(0.0, 3.0);
+        text.0 = format!("shadow_opacity: {opacity:.2} (Up/Down)");
+    }
+}
@@ -0,0 +1,115 @@
+//! WBOIT transparents in a transparent window, composited over the desktop.
+//!
+//! The window is created with `transparent: true` and `CompositeAlphaMode::PreMultiplied`, and
+//! cleared to `Color::NONE`. The opaque cube writes alpha 1 and hides the desktop behind it;
+//! with `WboitSettings::output_alpha` the composite writes the transparent spheres' coverage
+//! into the window alpha, so the desktop shows through them. Toggle `output_alpha` with A:
+//! without it the spheres over the empty background are dropped by the compositor and only
+//! the part in front of the cube stays visible.
+//!
+//! Transparent windows are platform dependent: the compositor must support `PreMultiplied`
+//! (most Linux and Windows setups do; macOS only offers `PostMultiplied`, see
+//! `Window::transparent`).
+
+use bevy::prelude::*;
+use bevy::window::CompositeAlphaMode;
+use bevy_wboit::{WboitPlugin, WboitSettings};
+
+fn main() {
+    App::new()
+        .insert_resource(ClearColor(Color::NONE))
+        .add_plugins((
+            DefaultPlugins.set(WindowPlugin {
+                primary_window: Some(Window {
+                    title: "Transparent window WBOIT".into(),
+                    transparent: true,
+                    composite_alpha_mode: CompositeAlphaMode::PreMultiplied,
+                    decorations: false,
+                    ..default()
+                }),
+                ..default()
+            }),
+            WboitPlugin,
+        ))
+        .add_systems(Startup, setup)
+        .add_systems(Update, (toggle_output_alpha, rotate))
+        .run();
+}
+
+#[derive(Component)]
+struct Spin;
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        Camera3d::default(),
+        Transform::from_xyz(0.0, 1.0, 6.0).looking_at(Vec3::ZERO, Vec3::Y),
+        WboitSettings {
+            output_alpha: true,
+            ..default()
+        },
+        Msaa::Off,
+    ));
+
+    commands.spawn((
+        DirectionalLight {
+            illuminance: 8000.0,
+            ..default()
+        },
+        Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, -0.8, 0.4, 0.0)),
+    ));
+
+    commands.spawn((
+        Mesh3d(meshes.add(Cuboid::new(1.2, 1.2, 1.2))),
+        MeshMaterial3d(materials.add(Color::srgb(0.8, 0.5, 0.2))),
+        Transform::from_xyz(0.0, 0.0, -1.0),
+        Spin,
+    ));
+
+    let sphere = meshes.add(Sphere::new(0.7).mesh().ico(4).unwrap());
+    let colors = [
+        Color::srgba(1.0, 0.2, 0.2, 0.5),
+        Color::srgba(0.2, 1.0, 0.3, 0.5),
+        Color::srgba(0.2, 0.4, 1.0, 0.5),
+    ];
+    for (i, color) in colors.into_iter().enumerate() {
+        commands.spawn((
+            Mesh3d(sphere.clone()),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: color,
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            })),
+            Transform::from_xyz(-1.4 + 1.4 * i as f32, 0.0, 0.4 * i as f32),
+        ));
+    }
+
+    commands.spawn((
+        Text::new("A: Toggle output_alpha"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+    ));
+}
+
+fn rotate(time: Res<Time>, mut spinning: Query<&mut Transform, With<Spin>>) {
+    for mut transform in &mut spinning {
+        transform.rotate_y(time.delta_secs() * 0.5);
+    }
+}
+
+fn toggle_output_alpha(keys: Res<ButtonInput<KeyCode>>, mut settings: Query<&mut WboitSettings>) {
+    if !keys.just_pressed(KeyCode::KeyA) {
+        return;
+    }
+    for mut settings in &mut settings {
+        settings.output_alpha = !settings.output_alpha;
+        info!("Output alpha: {}", settings.output_alpha);
+    }
+}
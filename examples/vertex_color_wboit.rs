@@ -0,0 +1,110 @@
+//! Vertex-colored transparent meshes through WBOIT.
+//!
+//! The spheres have a white `StandardMaterial` and get their color and alpha from
+//! `Mesh::ATTRIBUTE_COLOR`: a hue gradient around the vertical axis, fading from opaque at the
+//! bottom to almost clear at the top. The colors must match what the standard transparent
+//! pass draws. Press 1 / 2 / 3 for no OIT, naive WBOIT and HE-WBOIT.
+
+use bevy::prelude::*;
+use bevy_wboit::{HEWboitPlugin, HEWboitSettings, WboitPlugin, WboitSettings};
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, WboitPlugin, HEWboitPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(Update, toggle_mode)
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        Camera3d::default(),
+        Transform::from_xyz(0.0, 2.0, 7.0).looking_at(Vec3::ZERO, Vec3::Y),
+        WboitSettings::default(),
+        Msaa::Off,
+    ));
+
+    commands.spawn((
+        DirectionalLight {
+            illuminance: 8000.0,
+            ..default()
+        },
+        Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, -0.8, 0.4, 0.0)),
+    ));
+
+    commands.spawn((
+        Mesh3d(meshes.add(Plane3d::default().mesh().size(20.0, 20.0))),
+        MeshMaterial3d(materials.add(Color::srgb(0.3, 0.3, 0.3))),
+        Transform::from_xyz(0.0, -1.0, 0.0),
+    ));
+
+    let mut sphere = Sphere::new(0.9).mesh().uv(48, 24);
+    let colors: Vec<[f32; 4]> = sphere
+        .attribute(Mesh::ATTRIBUTE_POSITION)
+        .and_then(|positions| positions.as_float3())
+        .unwrap_or_default()
+        .iter()
+        .map(|&[x, y, z]| {
+            let hue = (z.atan2(x).to_degrees() + 180.0) % 360.0;
+            let alpha = 0.9 - 0.8 * (y / 0.9 * 0.5 + 0.5);
+            LinearRgba::from(Color::hsla(hue, 0.9, 0.55, alpha)).to_f32_array()
+        })
+        .collect();
+    sphere.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    let sphere = meshes.add(sphere);
+
+    let material = materials.add(StandardMaterial {
+        base_color: Color::WHITE,
+        alpha_mode: AlphaMode::Blend,
+        ..default()
+    });
+    for i in 0..3 {
+        commands.spawn((
+            Mesh3d(sphere.clone()),
+            MeshMaterial3d(material.clone()),
+            Transform::from_xyz(-1.3 + 1.3 * i as f32, 0.0, 0.6 * i as f32 - 0.6)
+                .with_rotation(Quat::from_rotation_y(i as f32 * 2.0)),
+        ));
+    }
+
+    commands.spawn((
+        Text::new("1: No OIT  |  2: WBOIT  |  3: HE-WBOIT"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+    ));
+}
+
+fn toggle_mode(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    camera: Query<Entity, With<Camera3d>>,
+) {
+    let Ok(camera) = camera.single() else {
+        return;
+    };
+    if keys.just_pressed(KeyCode::Digit1) {
+        commands
+            .entity(camera)
+            .remove::<(WboitSettings, HEWboitSettings)>();
+    }
+    if keys.just_pressed(KeyCode::Digit2) {
+        commands
+            .entity(camera)
+            .remove::<HEWboitSettings>()
+            .insert(WboitSettings::default());
+    }
+    if keys.just_pressed(KeyCode::Digit3) {
+        commands
+            .entity(camera)
+            .remove::<WboitSettings>()
+            .insert(HEWboitSettings::default());
+    }
+}
@@ -0,0 +1,102 @@
+//! A transparent water volume intersecting opaque terrain, with a depth test bias.
+//!
+//! With `WboitSettings::depth_test_bias` at zero, the terrain clips the water box exactly where
+//! it pokes through. Raising the bias keeps the water visible up to that distance behind the
+//! terrain surface, so the shoreline is covered by a thin film instead of a hard cut.
+
+use bevy::prelude::*;
+use bevy_wboit::{WboitPlugin, WboitSettings};
+
+/// Depth test bias values cycled with B, in world units.
+const BIASES: [f32; 3] = [0.0, 0.3, 1.0];
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, WboitPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(Update, cycle_bias)
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        Camera3d::default(),
+        Transform::from_xyz(0.0, 4.0, 10.0).looking_at(Vec3::new(0.0, 0.0, 0.0), Vec3::Y),
+        WboitSettings::default(),
+        Msaa::Off,
+    ));
+
+    commands.spawn((
+        DirectionalLight {
+            illuminance: 8000.0,
+            shadows_enabled: true,
+            ..default()
+        },
+        Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, -0.9, 0.4, 0.0)),
+    ));
+
+    // Terrain: a flat floor with a few hills poking up through the water level.
+    let terrain = materials.add(StandardMaterial {
+        base_color: Color::srgb(0.45, 0.4, 0.3),
+        perceptual_roughness: 0.95,
+        ..default()
+    });
+    commands.spawn((
+        Mesh3d(meshes.add(Plane3d::default().mesh().size(20.0, 20.0))),
+        MeshMaterial3d(terrain.clone()),
+        Transform::from_xyz(0.0, -1.0, 0.0),
+    ));
+    let hill = meshes.add(Sphere::new(1.0).mesh().uv(32, 18));
+    for (position, radius) in [
+        (Vec3::new(-2.5, -1.0, 0.0), 1.6),
+        (Vec3::new(1.5, -1.4, -1.5), 2.0),
+        (Vec3::new(3.0, -0.8, 2.0), 1.0),
+    ] {
+        commands.spawn((
+            Mesh3d(hill.clone()),
+            MeshMaterial3d(terrain.clone()),
+            Transform::from_translation(position).with_scale(Vec3::splat(radius)),
+        ));
+    }
+
+    // Water volume whose top surface sits at y = 0.
+    commands.spawn((
+        Mesh3d(meshes.add(Cuboid::new(12.0, 1.0, 8.0))),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            base_color: Color::srgba(0.1, 0.4, 0.7, 0.45),
+            alpha_mode: AlphaMode::Blend,
+            perceptual_roughness: 0.1,
+            ..default()
+        })),
+        Transform::from_xyz(0.0, -0.5, 0.0),
+    ));
+
+    commands.spawn((
+        Text::new("B: Cycle depth test bias (0.0, 0.3, 1.0)"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+    ));
+}
+
+fn cycle_bias(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut index: Local<usize>,
+    mut settings: Query<&mut WboitSettings>,
+) {
+    if !keys.just_pressed(KeyCode::KeyB) {
+        return;
+    }
+    *index = (*index + 1) % BIASES.len();
+    for mut settings in &mut settings {
+        settings.depth_test_bias = BIASES[*index];
+        info!("Depth test bias: {}", settings.depth_test_bias);
+    }
+}
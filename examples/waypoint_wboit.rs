@@ -0,0 +1,113 @@
+//! An always-visible transparent waypoint marker behind an opaque wall.
+//!
+//! The marker has `WboitAlwaysVisible`, so its accum pipeline skips the opaque depth test and
+//! it stays visible through the wall, while the regular transparent sphere next to it is
+//! hidden where the wall covers it.
+
+use bevy::prelude::*;
+use bevy_wboit::{WboitAlwaysVisible, WboitPlugin, WboitSettings};
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, WboitPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(Update, (toggle_always_visible, bob_marker))
+        .run();
+}
+
+/// Marker for the waypoint entity.
+#[derive(Component)]
+struct Waypoint;
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        Camera3d::default(),
+        Transform::from_xyz(0.0, 1.5, 7.0).looking_at(Vec3::new(0.0, 1.0, 0.0), Vec3::Y),
+        WboitSettings::default(),
+        Msaa::Off,
+    ));
+
+    commands.spawn((
+        DirectionalLight {
+            illuminance: 8000.0,
+            ..default()
+        },
+        Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, -0.7, 0.3, 0.0)),
+    ));
+
+    commands.spawn((
+        Mesh3d(meshes.add(Plane3d::default().mesh().size(20.0, 20.0))),
+        MeshMaterial3d(materials.add(Color::srgb(0.3, 0.3, 0.3))),
+    ));
+
+    // Opaque wall between the camera and the markers.
+    commands.spawn((
+        Mesh3d(meshes.add(Cuboid::new(5.0, 3.0, 0.3))),
+        MeshMaterial3d(materials.add(Color::srgb(0.55, 0.5, 0.45))),
+        Transform::from_xyz(0.0, 1.5, 0.0),
+    ));
+
+    // Always-visible waypoint behind the wall.
+    commands.spawn((
+        Mesh3d(meshes.add(Cone::new(0.4, 0.8))),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            base_color: Color::srgba(1.0, 0.8, 0.1, 0.6),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        })),
+        Transform::from_xyz(-0.8, 1.5, -3.0)
+            .with_rotation(Quat::from_rotation_x(std::f32::consts::PI)),
+        WboitAlwaysVisible,
+        Waypoint,
+    ));
+
+    // Regular transparent sphere behind the wall, for comparison.
+    commands.spawn((
+        Mesh3d(meshes.add(Sphere::new(0.5).mesh().ico(4).unwrap())),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            base_color: Color::srgba(0.2, 0.6, 1.0, 0.6),
+            alpha_mode: AlphaMode::Blend,
+            ..default()
+        })),
+        Transform::from_xyz(0.8, 1.5, -3.0),
+    ));
+
+    commands.spawn((
+        Text::new("V: Toggle WboitAlwaysVisible on the waypoint"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+    ));
+}
+
+fn toggle_always_visible(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    waypoints: Query<(Entity, Has<WboitAlwaysVisible>), With<Waypoint>>,
+) {
+    if !keys.just_pressed(KeyCode::KeyV) {
+        return;
+    }
+    for (entity, always_visible) in &waypoints {
+        if always_visible {
+            commands.entity(entity).remove::<WboitAlwaysVisible>();
+        } else {
+            commands.entity(entity).insert(WboitAlwaysVisible);
+        }
+        info!("Waypoint always visible: {}", !always_visible);
+    }
+}
+
+fn bob_marker(time: Res<Time>, mut waypoints: Query<&mut Transform, With<Waypoint>>) {
+    for mut transform in &mut waypoints {
+        transform.translation.y = 1.5 + 0.2 * (time.elapsed_secs() * 2.0).sin();
+    }
+}
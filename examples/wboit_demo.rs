@@ -1,12 +1,34 @@
 use bevy::core_pipeline::tonemapping::Tonemapping;
 use bevy::prelude::*;
-use bevy_wboit::{HEWboitPlugin, HEWboitSettings, WboitPlugin, WboitSettings};
+use bevy::render::view::NoFrustumCulling;
+use bevy_wboit::{
+    HEWboitAutoDepth, HEWboitDepthMode, HEWboitPlugin, HEWboitSettings, WboitDebug,
+    WboitInvalidatePipelines, WboitPlugin, WboitSettings,
+};
 
 fn main() {
     App::new()
         .add_plugins((DefaultPlugins, WboitPlugin, HEWboitPlugin))
         .add_systems(Startup, setup)
-        .add_systems(Update, (toggle_mode, rotate_camera))
+        .add_systems(
+            Update,
+            (
+                toggle_mode,
+                toggle_thickness,
+                toggle_half_res,
+                cycle_quality,
+                toggle_sort_accum,
+                recompile_pipelines,
+                toggle_max_opacity,
+                fade_transparents,
+                toggle_animated_weight,
+                cycle_debug_view,
+                adjust_equalization,
+                toggle_auto_depth,
+                cycle_histogram_downscale,
+                rotate_camera,
+            ),
+        )
         .run();
 }
 
@@ -20,7 +42,7 @@ fn setup(
         Camera3d::default(),
         Tonemapping::None,
         Transform::from_xyz(0., 2., 8.).looking_at(Vec3::ZERO, Vec3::Y),
-        WboitSettings,
+        WboitSettings::default(),
         Msaa::Off,
     ));
 
@@ -51,10 +73,7 @@ fn setup(
         (Color::srgba(0.0, 1.0, 0.0, 0.4), Vec3::new(0.5, 0.0, -0.5)),
         (Color::srgba(0.0, 0.0, 1.0, 0.5), Vec3::new(0.0, 0.0, 1.0)),
         (Color::srgba(1.0, 1.0, 0.0, 0.3), Vec3::new(1.0, 0.5, 0.0)),
-        (
-            Color::srgba(1.0, 0.0, 1.0, 0.35),
-            Vec3::new(-0.5, 0.5, 0.5),
-        ),
+        (Color::srgba(1.0, 0.0, 1.0, 0.35), Vec3::new(-0.5, 0.5, 0.5)),
     ];
 
     for (color, pos) in configs {
@@ -70,9 +89,64 @@ fn setup(
         ));
     }
 
+    // Dense cluster of small transparent spheres, to check that heavy overdraw stays sane
+    // with `max_opacity`.
+    let small_sphere = meshes.add(Sphere::new(0.25).mesh().ico(3).unwrap());
+    let cluster_material = materials.add(StandardMaterial {
+        base_color: Color::srgba(1.0, 0.6, 0.2, 0.4),
+        alpha_mode: AlphaMode::Blend,
+        cull_mode: None,
+        ..default()
+    });
+    for i in 0..64 {
+        let (x, y, z) = ((i % 4) as f32, ((i / 4) % 4) as f32, (i / 16) as f32);
+        commands.spawn((
+            Mesh3d(small_sphere.clone()),
+            MeshMaterial3d(cluster_material.clone()),
+            Transform::from_xyz(-3.5 + x * 0.2, -0.5 + y * 0.2, -1.5 + z * 0.2),
+        ));
+    }
+
+    // Tall translucent wall along the right side, extending past the camera. It is partially
+    // off-screen and crosses the near plane; `NoFrustumCulling` keeps it queued regardless.
+    commands.spawn((
+        Mesh3d(meshes.add(Plane3d::new(Vec3::X, Vec2::new(10.0, 3.0)).mesh())),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            base_color: Color::srgba(0.2, 0.8, 1.0, 0.25),
+            alpha_mode: AlphaMode::Blend,
+            cull_mode: None,
+            ..default()
+        })),
+        Transform::from_xyz(3.5, 0.5, 4.0),
+        NoFrustumCulling,
+    ));
+
+    // Additive energy field hovering over the ground: it only adds light, so the plane and
+    // spheres behind it stay visible instead of being darkened.
+    commands.spawn((
+        Mesh3d(sphere.clone()),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            base_color: Color::srgba(0.1, 0.4, 1.0, 0.6),
+            emissive: LinearRgba::rgb(0.1, 0.6, 2.0),
+            alpha_mode: AlphaMode::Add,
+            unlit: true,
+            ..default()
+        })),
+        Transform::from_xyz(2.0, -0.8, 1.5).with_scale(Vec3::splat(0.7)),
+    ));
+
     // Instructions
     commands.spawn((
-        Text::new("1: No OIT  |  2: WBOIT  |  3: HE-WBOIT\nDrag mouse to rotate"),
+        Text::new(
+            "1: No OIT  |  2: WBOIT  |  3: HE-WBOIT\n\
+             T: Toggle thickness absorption  |  H: Toggle half-res accum\n\
+             Q: Cycle WBOIT quality (0-3)  |  O: Toggle max opacity cap\n\
+             F: Fade transparents out/in  |  P: Toggle pulsing (animated weight)\n\
+             D: Cycle debug view  |  [ / ]: HE equalization strength\n\
+             A: Toggle HE auto depth range  |  G: Cycle HE histogram downscale\n\
+             S: Toggle accum phase sort  |  R: Recompile WBOIT pipelines\n\
+             Drag mouse to rotate",
+        ),
         Node {
             position_type: PositionType::Absolute,
             top: Val::Px(10.0),
@@ -105,7 +179,7 @@ fn toggle_mode(
         commands
             .entity(camera_entity)
             .remove::<HEWboitSettings>()
-            .insert(WboitSettings);
+            .insert(WboitSettings::default());
         info!("Switched to naive WBOIT");
     }
 
@@ -119,6 +193,201 @@ fn toggle_mode(
     }
 }
 
+/// Toggle thickness absorption: regions far above the ground plane darken and thicken.
+fn toggle_thickness(keys: Res<ButtonInput<KeyCode>>, mut settings: Query<&mut WboitSettings>) {
+    if !keys.just_pressed(KeyCode::KeyT) {
+        return;
+    }
+    for mut settings in &mut settings {
+        settings.thickness_absorption = if settings.thickness_absorption > 0.0 {
+            0.0
+        } else {
+            0.4
+        };
+        info!("Thickness absorption: {}", settings.thickness_absorption);
+    }
+}
+
+/// Toggle half-resolution accumulation (upsampled in the composite).
+fn toggle_half_res(keys: Res<ButtonInput<KeyCode>>, mut settings: Query<&mut WboitSettings>) {
+    if !keys.just_pressed(KeyCode::KeyH) {
+        return;
+    }
+    for mut settings in &mut settings {
+        settings.accum_scale = if settings.accum_scale < 1.0 { 1.0 } else { 0.5 };
+        info!("Accum scale: {}", settings.accum_scale);
+    }
+}
+
+/// Step through the `WboitSettings::quality` levels (3 hands the camera to HE-WBOIT).
+fn cycle_quality(keys: Res<ButtonInput<KeyCode>>, mut settings: Query<&mut WboitSettings>) {
+    if !keys.just_pressed(KeyCode::KeyQ) {
+        return;
+    }
+    for mut settings in &mut settings {
+        settings.quality = (settings.quality + 1) % 4;
+        info!("WBOIT quality: {}", settings.quality);
+    }
+}
+
+/// Toggle the per-frame distance sort of the accum phase. The image must not change; only
+/// the CPU time of the phase sort does.
+fn toggle_sort_accum(keys: Res<ButtonInput<KeyCode>>, mut settings: Query<&mut WboitSettings>) {
+    if !keys.just_pressed(KeyCode::KeyS) {
+        return;
+    }
+    for mut settings in &mut settings {
+        settings.sort_accum = !settings.sort_accum;
+        info!("Sort accum phase: {}", settings.sort_accum);
+    }
+}
+
+/// Recompile the WBOIT pipelines, e.g. after editing `wboit_fragment.wgsl`.
+fn recompile_pipelines(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut invalidate: EventWriter<WboitInvalidatePipelines>,
+) {
+    if keys.just_pressed(KeyCode::KeyR) {
+        invalidate.write(WboitInvalidatePipelines);
+    }
+}
+
+/// Toggle the composite coverage cap, most visible on the dense orange cluster.
+fn toggle_max_opacity(keys: Res<ButtonInput<KeyCode>>, mut settings: Query<&mut WboitSettings>) {
+    if !keys.just_pressed(KeyCode::KeyO) {
+        return;
+    }
+    for mut settings in &mut settings {
+        settings.max_opacity = if settings.max_opacity < 1.0 {
+            1.0
+        } else {
+            0.85
+        };
+        info!("Max opacity: {}", settings.max_opacity);
+    }
+}
+
+/// Fade the whole transparent layer out or in over half a second via `global_opacity`.
+fn fade_transparents(
+    time: Res<Time>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut fading_out: Local<bool>,
+    mut settings: Query<&mut WboitSettings>,
+) {
+    if keys.just_pressed(KeyCode::KeyF) {
+        *fading_out = !*fading_out;
+        info!(
+            "Fading transparents {}",
+            if *fading_out { "out" } else { "in" }
+        );
+    }
+    let target = if *fading_out { 0.0 } else { 1.0 };
+    let step = 2.0 * time.delta_secs();
+    for mut settings in &mut settings {
+        if settings.global_opacity != target {
+            let delta = (target - settings.global_opacity).clamp(-step, step);
+            settings.global_opacity += delta;
+        }
+    }
+}
+
+/// Toggle the noise-driven pulsing dissolve on all WBOIT transparents.
+fn toggle_animated_weight(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut settings: Query<&mut WboitSettings>,
+) {
+    if !keys.just_pressed(KeyCode::KeyP) {
+        return;
+    }
+    for mut settings in &mut settings {
+        settings.animated_weight = !settings.animated_weight;
+        info!("Animated weight: {}", settings.animated_weight);
+    }
+}
+
+/// Cycle the debug views: overdraw heatmap, weight heatmap, transparency only, off.
+fn cycle_debug_view(keys: Res<ButtonInput<KeyCode>>, mut settings: Query<&mut WboitSettings>) {
+    if !keys.just_pressed(KeyCode::KeyD) {
+        return;
+    }
+    for mut settings in &mut settings {
+        settings.debug = match settings.debug {
+            WboitDebug::None => WboitDebug::Overdraw,
+            WboitDebug::Overdraw => WboitDebug::Weight,
+            WboitDebug::Weight => WboitDebug::TransparencyOnly,
+            WboitDebug::TransparencyOnly => WboitDebug::None,
+        };
+        info!("WBOIT debug: {:?}", settings.debug);
+    }
+}
+
+/// Slide the HE-WBOIT equalization strength between plain depth weighting (0) and full
+/// histogram equalization (1) while `[` or `]` is held.
+fn adjust_equalization(
+    time: Res<Time>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut settings: Query<&mut HEWboitSettings>,
+) {
+    let mut direction = 0.0;
+    if keys.pressed(KeyCode::BracketLeft) {
+        direction -= 1.0;
+    }
+    if keys.pressed(KeyCode::BracketRight) {
+        direction += 1.0;
+    }
+    if direction == 0.0 {
+        return;
+    }
+    for mut settings in &mut settings {
+        settings.equalization_strength =
+            (settings.equalization_strength + direction * 0.5 * time.delta_secs()).clamp(0.0, 1.0);
+        info!(
+            "HE equalization strength: {:.2}",
+            settings.equalization_strength
+        );
+    }
+}
+
+/// Switch the HE-WBOIT histogram range between the fixed `max_depth` and the depth of the
+/// visible transparents.
+fn toggle_auto_depth(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut settings: Query<(&mut HEWboitSettings, Option<&HEWboitAutoDepth>)>,
+) {
+    if !keys.just_pressed(KeyCode::KeyA) {
+        return;
+    }
+    for (mut settings, auto_depth) in &mut settings {
+        settings.depth_mode = match settings.depth_mode {
+            HEWboitDepthMode::Fixed => HEWboitDepthMode::Auto,
+            HEWboitDepthMode::Auto => HEWboitDepthMode::Fixed,
+        };
+        info!(
+            "HE depth mode: {:?} (last auto estimate: {:?})",
+            settings.depth_mode,
+            auto_depth.map(|depth| depth.0)
+        );
+    }
+}
+
+/// Cycle how many HE-WBOIT tiles per axis share one depth histogram: 1, 2, 4.
+fn cycle_histogram_downscale(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut settings: Query<&mut HEWboitSettings>,
+) {
+    if !keys.just_pressed(KeyCode::KeyG) {
+        return;
+    }
+    for mut settings in &mut settings {
+        settings.histogram_downscale = match settings.histogram_downscale {
+            1 => 2,
+            2 => 4,
+            _ => 1,
+        };
+        info!("HE histogram downscale: {}", settings.histogram_downscale);
+    }
+}
+
 fn rotate_camera(
     time: Res<Time>,
     keys: Res<ButtonInput<KeyCode>>,
@@ -156,10 +425,7 @@ fn rotate_camera(
         let delta = mouse_motion.delta;
         if delta != Vec2::ZERO {
             let sensitivity = 0.005;
-            transform.rotate_around(
-                Vec3::ZERO,
-                Quat::from_rotation_y(-delta.x * sensitivity),
-            );
+            transform.rotate_around(Vec3::ZERO, Quat::from_rotation_y(-delta.x * sensitivity));
             let right = transform.right();
             transform.rotate_around(
                 Vec3::ZERO,
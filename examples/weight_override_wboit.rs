@@ -0,0 +1,136 @@
+//! Two WBOIT cameras with the same settings but different `WboitWeightOverride`s.
+//!
+//! Both halves show the same stack of overlapping spheres from the same point of view. The
+//! left camera uses the unweighted average, so the far spheres tint the near ones as much as
+//! the other way around; the right one uses the depth weight, so the nearest sphere dominates
+//! where they overlap. Press W to swap the overrides.
+
+use bevy::prelude::*;
+use bevy::render::camera::Viewport;
+use bevy::window::{PrimaryWindow, WindowResized};
+use bevy_wboit::{WboitPlugin, WboitSettings, WboitWeightOverride};
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, WboitPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(Update, (set_viewports, swap_overrides))
+        .run();
+}
+
+/// Which half of the window a camera renders to.
+#[derive(Component)]
+struct Half(u32);
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let overrides = [WboitWeightOverride::Unweighted, WboitWeightOverride::Depth];
+    for (i, weight_override) in overrides.into_iter().enumerate() {
+        commands.spawn((
+            Camera3d::default(),
+            Camera {
+                order: i as isize,
+                // Only the first camera clears the shared window texture.
+                clear_color: if i == 0 {
+                    ClearColorConfig::Default
+                } else {
+                    ClearColorConfig::None
+                },
+                ..default()
+            },
+            Transform::from_xyz(0.0, 1.5, 7.0).looking_at(Vec3::ZERO, Vec3::Y),
+            WboitSettings::default(),
+            weight_override,
+            Msaa::Off,
+            Half(i as u32),
+        ));
+    }
+
+    commands.spawn((
+        DirectionalLight {
+            illuminance: 8000.0,
+            ..default()
+        },
+        Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, -1.0, 0.3, 0.0)),
+    ));
+
+    commands.spawn((
+        Mesh3d(meshes.add(Plane3d::default().mesh().size(20.0, 20.0))),
+        MeshMaterial3d(materials.add(Color::srgb(0.3, 0.3, 0.3))),
+        Transform::from_xyz(0.0, -1.0, 0.0),
+    ));
+
+    // A row of spheres receding from the camera, each partly covering the next.
+    let sphere = meshes.add(Sphere::new(0.8).mesh().ico(4).unwrap());
+    let colors = [
+        Color::srgba(1.0, 0.2, 0.2, 0.6),
+        Color::srgba(0.2, 1.0, 0.3, 0.6),
+        Color::srgba(0.2, 0.4, 1.0, 0.6),
+        Color::srgba(1.0, 0.9, 0.2, 0.6),
+    ];
+    for (i, color) in colors.into_iter().enumerate() {
+        commands.spawn((
+            Mesh3d(sphere.clone()),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: color,
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            })),
+            Transform::from_xyz(-0.9 + 0.6 * i as f32, 0.0, 1.5 - 2.0 * i as f32),
+        ));
+    }
+
+    commands.spawn((
+        Text::new("Left: Unweighted  |  Right: Depth  |  W: Swap"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+    ));
+}
+
+/// Keep each camera on its half of the window.
+fn set_viewports(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut resize_events: EventReader<WindowResized>,
+    mut cameras: Query<(&mut Camera, &Half)>,
+    mut initialized: Local<bool>,
+) {
+    if resize_events.read().count() == 0 && *initialized {
+        return;
+    }
+    let Ok(window) = windows.single() else {
+        return;
+    };
+    *initialized = true;
+    let size = window.physical_size();
+    let half = UVec2::new((size.x / 2).max(1), size.y.max(1));
+    for (mut camera, position) in &mut cameras {
+        camera.viewport = Some(Viewport {
+            physical_position: UVec2::new(position.0 * half.x, 0),
+            physical_size: half,
+            ..default()
+        });
+    }
+}
+
+fn swap_overrides(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut overrides: Query<(&mut WboitWeightOverride, &Half)>,
+) {
+    if !keys.just_pressed(KeyCode::KeyW) {
+        return;
+    }
+    for (mut weight_override, half) in &mut overrides {
+        *weight_override = match *weight_override {
+            WboitWeightOverride::Unweighted => WboitWeightOverride::Depth,
+            WboitWeightOverride::Depth => WboitWeightOverride::Unweighted,
+        };
+        info!("Camera {}: {:?}", half.0, *weight_override);
+    }
+}
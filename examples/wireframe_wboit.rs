@@ -0,0 +1,144 @@
+//! A transparent wireframe cube around overlapping transparent spheres.
+//!
+//! The cube edges are a `LineList` mesh with a regular blended `StandardMaterial`, so they go
+//! through the WBOIT accum pass like any other transparent and blend with the spheres in both
+//! directions (in front of and behind them) without sorting.
+
+use bevy::asset::RenderAssetUsages;
+use bevy::prelude::*;
+use bevy::render::mesh::PrimitiveTopology;
+use bevy_wboit::{WboitPlugin, WboitSettings};
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, WboitPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(Update, (toggle_wboit, spin_cube))
+        .run();
+}
+
+/// Marker for the wireframe cube.
+#[derive(Component)]
+struct WireCube;
+
+/// Edges of an axis-aligned cube with half extent `h`, as a line list.
+fn cube_edges(h: f32) -> Mesh {
+    let corners: Vec<[f32; 3]> = (0..8)
+        .map(|i| {
+            let x = if i & 1 == 0 { -h } else { h };
+            let y = if i & 2 == 0 { -h } else { h };
+            let z = if i & 4 == 0 { -h } else { h };
+            [x, y, z]
+        })
+        .collect();
+    // Pairs of corner indices differing in exactly one axis bit.
+    let mut positions = Vec::new();
+    for a in 0..8usize {
+        for bit in [1usize, 2, 4] {
+            let b = a | bit;
+            if b != a {
+                positions.push(corners[a]);
+                positions.push(corners[b]);
+            }
+        }
+    }
+    let normals = vec![[0.0, 1.0, 0.0]; positions.len()];
+    Mesh::new(PrimitiveTopology::LineList, RenderAssetUsages::default())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        Camera3d::default(),
+        Transform::from_xyz(0.0, 2.0, 6.0).looking_at(Vec3::ZERO, Vec3::Y),
+        WboitSettings::default(),
+        Msaa::Off,
+    ));
+
+    commands.spawn((
+        DirectionalLight {
+            illuminance: 8000.0,
+            ..default()
+        },
+        Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, -0.6, 0.4, 0.0)),
+    ));
+
+    commands.spawn((
+        Mesh3d(meshes.add(Plane3d::default().mesh().size(10.0, 10.0))),
+        MeshMaterial3d(materials.add(Color::srgb(0.3, 0.3, 0.3))),
+        Transform::from_xyz(0.0, -1.5, 0.0),
+    ));
+
+    // Solid transparent spheres inside the cube.
+    let sphere = meshes.add(Sphere::new(0.7).mesh().ico(4).unwrap());
+    for (color, x) in [
+        (Color::srgba(1.0, 0.2, 0.2, 0.5), -0.5),
+        (Color::srgba(0.2, 0.4, 1.0, 0.5), 0.5),
+    ] {
+        commands.spawn((
+            Mesh3d(sphere.clone()),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: color,
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            })),
+            Transform::from_xyz(x, 0.0, 0.0),
+        ));
+    }
+
+    // Transparent wireframe cube enclosing them.
+    commands.spawn((
+        Mesh3d(meshes.add(cube_edges(1.3))),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            base_color: Color::srgba(1.0, 1.0, 0.3, 0.6),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        })),
+        WireCube,
+    ));
+
+    commands.spawn((
+        Text::new("1: No OIT  |  2: WBOIT"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+    ));
+}
+
+fn toggle_wboit(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    camera: Query<Entity, With<Camera3d>>,
+) {
+    let Ok(camera_entity) = camera.single() else {
+        return;
+    };
+
+    if keys.just_pressed(KeyCode::Digit1) {
+        commands.entity(camera_entity).remove::<WboitSettings>();
+        info!("Switched to standard transparency (no OIT)");
+    }
+
+    if keys.just_pressed(KeyCode::Digit2) {
+        commands
+            .entity(camera_entity)
+            .insert(WboitSettings::default());
+        info!("Switched to naive WBOIT");
+    }
+}
+
+fn spin_cube(time: Res<Time>, mut cubes: Query<&mut Transform, With<WireCube>>) {
+    for mut transform in &mut cubes {
+        transform.rotate_y(time.delta_secs() * 0.4);
+        transform.rotate_x(time.delta_secs() * 0.2);
+    }
+}
@@ -0,0 +1,496 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::render::render_resource::{PipelineCache, WgpuAdapterInfo};
+use bevy::render::renderer::RenderAdapterInfo;
+use bevy::render::view::{ExtractedView, ViewDepthTexture};
+use bevy::render::{Render, RenderApp, RenderSet};
+
+use crate::histogram::composite::HistoCompositePipelineId;
+use crate::histogram::pipeline::{HISTO_CDF_BUILD_SHADER_HANDLE, HISTO_FRAGMENT_SHADER_HANDLE};
+use crate::histogram::textures::HistoWboitWarmup;
+use crate::minimal::WBOIT_MINIMAL_SHADER_HANDLE;
+use crate::naive::composite::{WBOIT_COMPOSITE_SHADER_HANDLE, WboitCompositePipelineId};
+use crate::pipeline::{WBOIT_FRAGMENT_SHADER_HANDLE, WBOIT_VERTEX_SHADER_HANDLE};
+use crate::settings::{HEWboitSettings, WboitQualityManagedHE, WboitSettings};
+use crate::shadow::WBOIT_SHADOW_SHADER_HANDLE;
+use crate::textures::WboitTextures;
+
+/// A `StandardMaterial` setting that is likely to look different under WBOIT than under
+/// sorted alpha blending.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum WboitMaterialWarning {
+    /// `AlphaMode::Add` outputs zero alpha. Naive WBOIT draws it as pure additive light, but
+    /// HE-WBOIT gives it zero weight, so the surface disappears on HE cameras.
+    Additive,
+    /// `AlphaMode::Multiply` relies on multiplicative blending with the framebuffer, which
+    /// WBOIT's weighted average cannot express.
+    Multiply,
+    /// Both faces of a closed mesh accumulate, so its coverage is counted twice compared to
+    /// back-face culled sorted blending.
+    DoubleSided,
+    /// Emissive color is scaled by the depth weight (up to 8192), which can overflow the
+    /// half-float accum target near the camera.
+    Emissive,
+    /// `AlphaMode::Blend` with an opaque base color and no texture: the surface is opaque but
+    /// its color is still averaged with other transparent layers instead of occluding them.
+    OpaqueBlend,
+}
+
+impl fmt::Display for WboitMaterialWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            Self::Additive => {
+                "is additive (AlphaMode::Add); naive WBOIT draws it as glow, but it has zero \
+                 HE-WBOIT weight and will not be visible on HE cameras. Use \
+                 AlphaMode::Premultiplied with a non-zero alpha there instead"
+            }
+            Self::Multiply => {
+                "is multiplicative (AlphaMode::Multiply); WBOIT cannot reproduce multiplicative \
+                 blending. Use AlphaMode::Blend with a dark base color instead"
+            }
+            Self::DoubleSided => {
+                "is double-sided without back-face culling; both faces accumulate, so closed \
+                 meshes look about twice as opaque as with sorted blending. Lower the alpha or \
+                 enable back-face culling"
+            }
+            Self::Emissive => {
+                "has a non-zero emissive color; bright emissive transparents can overflow the \
+                 half-float accum target close to the camera. Keep WboitSettings::sanitize_output \
+                 enabled or lower the emissive intensity"
+            }
+            Self::OpaqueBlend => {
+                "uses AlphaMode::Blend with an opaque base color; it will be averaged with other \
+                 transparent layers instead of hiding them. Use AlphaMode::Opaque instead"
+            }
+        };
+        f.write_str(message)
+    }
+}
+
+/// List the settings of `material` that are likely to render differently under WBOIT.
+///
+/// Materials that do not go through the transparent phase (`Opaque`, `Mask`, ...) have no
+/// warnings.
+pub fn wboit_material_warnings(material: &StandardMaterial) -> Vec<WboitMaterialWarning> {
+    let mut warnings = Vec::new();
+    match material.alpha_mode {
+        AlphaMode::Blend | AlphaMode::Premultiplied => {}
+        AlphaMode::Add => warnings.push(WboitMaterialWarning::Additive),
+        AlphaMode::Multiply => warnings.push(WboitMaterialWarning::Multiply),
+        _ => return warnings,
+    }
+
+    if material.cull_mode.is_none() {
+        warnings.push(WboitMaterialWarning::DoubleSided);
+    }
+    if material.emissive != LinearRgba::BLACK || material.emissive_texture.is_some() {
+        warnings.push(WboitMaterialWarning::Emissive);
+    }
+    if material.alpha_mode == AlphaMode::Blend
+        && material.base_color.alpha() >= 1.0
+        && material.base_color_texture.is_none()
+    {
+        warnings.push(WboitMaterialWarning::OpaqueBlend);
+    }
+    warnings
+}
+
+/// Log [`WboitMaterialWarning`]s for materials used while a WBOIT camera is active.
+///
+/// Each material is reported once, and again after it is modified.
+pub fn diagnose_wboit_materials(
+    wboit_cameras: Query<(), Or<(With<WboitSettings>, With<HEWboitSettings>)>>,
+    mesh_materials: Query<(Entity, &MeshMaterial3d<StandardMaterial>)>,
+    materials: Res<Assets<StandardMaterial>>,
+    mut material_events: EventReader<AssetEvent<StandardMaterial>>,
+    mut reported: Local<HashSet<AssetId<StandardMaterial>>>,
+) {
+    for event in material_events.read() {
+        if let AssetEvent::Modified { id } | AssetEvent::Removed { id } = event {
+            reported.remove(id);
+        }
+    }
+    if wboit_cameras.is_empty() {
+        return;
+    }
+
+    for (entity, mesh_material) in &mesh_materials {
+        let id = mesh_material.id();
+        if reported.contains(&id) {
+            continue;
+        }
+        let Some(material) = materials.get(id) else {
+            continue;
+        };
+        reported.insert(id);
+        for warning in wboit_material_warnings(material) {
+            warn!("WBOIT: material {id} (used by {entity}) {warning}");
+        }
+    }
+}
+
+/// Warn once per camera that has both `WboitSettings` (below quality 3) and user-added
+/// `HEWboitSettings`. HE-WBOIT takes precedence and the naive path skips such cameras; remove
+/// one of the two to make the choice explicit.
+pub fn check_conflicting_wboit_settings(
+    cameras: Query<
+        (Entity, &WboitSettings),
+        (With<HEWboitSettings>, Without<WboitQualityManagedHE>),
+    >,
+    mut warned: Local<HashSet<Entity>>,
+) {
+    for (entity, settings) in &cameras {
+        if settings.uses_naive_path() && warned.insert(entity) {
+            warn!(
+                "Camera {entity} has both WboitSettings and HEWboitSettings; rendering it with \
+                 HE-WBOIT only. Remove one of them to pick a mode."
+            );
+        }
+    }
+}
+
+/// Per-camera counts from the last rendered frame of how many `Transparent3d` items the WBOIT
+/// drain cleared and how many were queued into the WBOIT accum phase.
+///
+/// Inserted on WBOIT cameras in the main world (one frame behind). `cleared` noticeably larger
+/// than `queued` means transparents that WBOIT cannot draw (e.g. materials not registered with
+/// `register_wboit_material`, or any non-`StandardMaterial` on the HE path) are being dropped.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Component, Default)]
+pub struct WboitDrainStats {
+    /// `Transparent3d` items removed from the standard transparent pass.
+    pub cleared: usize,
+    /// Items queued into the WBOIT (or HE-WBOIT) accum phase.
+    pub queued: usize,
+}
+
+/// Channel from the render world drain systems back to the main world, keyed by main-world
+/// camera entity. Shared by both worlds.
+#[derive(Resource, Clone, Default)]
+pub struct WboitDrainStatsSink(pub Arc<Mutex<HashMap<Entity, WboitDrainStats>>>);
+
+impl WboitDrainStatsSink {
+    /// Record the stats of one view for the current frame.
+    pub fn record(&self, camera: Entity, stats: WboitDrainStats) {
+        if let Ok(mut map) = self.0.lock() {
+            map.insert(camera, stats);
+        }
+    }
+}
+
+/// Copy the stats recorded by the render world onto the main-world cameras.
+pub fn sync_wboit_drain_stats(mut commands: Commands, sink: Res<WboitDrainStatsSink>) {
+    let Ok(mut map) = sink.0.lock() else {
+        return;
+    };
+    for (camera, stats) in map.drain() {
+        if let Ok(mut entity) = commands.get_entity(camera) {
+            entity.try_insert(stats);
+        }
+    }
+}
+
+/// Which WBOIT path renders a camera.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Reflect)]
+pub enum WboitCameraMode {
+    /// Naive WBOIT (`WboitSettings` below quality 3).
+    Naive,
+    /// Histogram-equalized WBOIT (`HEWboitSettings`, or `WboitSettings` at quality 3).
+    HistogramEqualized,
+}
+
+/// Where a WBOIT camera stands as of the last frame it was rendered. Inserted on WBOIT
+/// cameras in the main world (one frame behind), like [`WboitDrainStats`].
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Component, Default)]
+pub enum WboitStatus {
+    /// No frame has been rendered for the camera yet, e.g. it is inactive.
+    #[default]
+    NotRendered,
+    /// The view has no `ViewDepthTexture`, so the accum passes (which depth test against the
+    /// opaque scene) never run and no transparents are drawn. Warned once per camera.
+    MissingDepthTexture,
+    /// The camera's WBOIT textures were not allocated, e.g. the viewport is empty.
+    TexturesNotReady,
+    /// The composite pipeline is still compiling; transparents are not composited yet.
+    PipelineNotReady,
+    /// HE-WBOIT is composited but still weights by plain depth
+    /// (`HEWboitSettings::warmup_frames`).
+    WarmingUp,
+    /// Rendered and composited.
+    Active,
+}
+
+/// Channel from the render world [`WboitStatus`] system back to the main world, keyed by
+/// main-world camera entity. Shared by both worlds.
+#[derive(Resource, Clone, Default)]
+pub struct WboitStatusSink(pub Arc<Mutex<HashMap<Entity, WboitStatus>>>);
+
+/// Work out the [`WboitStatus`] of every WBOIT view in the render world, and warn once per
+/// camera that lacks a depth texture: the accum nodes require one, so their view query would
+/// otherwise skip it silently.
+pub fn record_wboit_status(
+    sink: Res<WboitStatusSink>,
+    pipeline_cache: Res<PipelineCache>,
+    views: Query<
+        (
+            &ExtractedView,
+            Option<&HEWboitSettings>,
+            Has<ViewDepthTexture>,
+            Has<WboitTextures>,
+            Option<&WboitCompositePipelineId>,
+            Option<&HistoCompositePipelineId>,
+            Option<&HistoWboitWarmup>,
+        ),
+        Or<(With<WboitSettings>, With<HEWboitSettings>)>,
+    >,
+    mut warned: Local<HashSet<Entity>>,
+) {
+    // Forget cameras that are gone or no longer WBOIT cameras, like `check_msaa_wboit`.
+    warned.retain(|camera| {
+        views
+            .iter()
+            .any(|(view, ..)| view.retained_view_entity.main_entity.id() == *camera)
+    });
+    let Ok(mut map) = sink.0.lock() else {
+        return;
+    };
+    for (view, he_settings, has_depth, has_textures, naive_pipeline, he_pipeline, warmup) in &views
+    {
+        let camera = view.retained_view_entity.main_entity.id();
+        let pipeline = match he_settings {
+            Some(_) => he_pipeline.map(|id| id.0),
+            None => naive_pipeline.map(|id| id.0),
+        };
+        let status = if !has_depth {
+            if warned.insert(camera) {
+                warn!(
+                    "WBOIT camera {camera} has no depth texture; WBOIT needs the view's depth \
+                     buffer to test transparents against the opaque scene, so none are drawn. \
+                     Use a Camera3d with a depth texture."
+                );
+            }
+            WboitStatus::MissingDepthTexture
+        } else if !has_textures {
+            WboitStatus::TexturesNotReady
+        } else if pipeline
+            .and_then(|id| pipeline_cache.get_render_pipeline(id))
+            .is_none()
+        {
+            WboitStatus::PipelineNotReady
+        } else if let Some(settings) = he_settings
+            && !warmup.is_some_and(|warmup| warmup.is_warm(settings))
+        {
+            WboitStatus::WarmingUp
+        } else {
+            WboitStatus::Active
+        };
+        map.insert(camera, status);
+    }
+}
+
+/// Copy the statuses recorded by the render world onto the main-world cameras.
+pub fn sync_wboit_status(mut commands: Commands, sink: Res<WboitStatusSink>) {
+    let Ok(mut map) = sink.0.lock() else {
+        return;
+    };
+    for (camera, status) in map.drain() {
+        if let Ok(mut entity) = commands.get_entity(camera) {
+            entity.try_insert(status);
+        }
+    }
+}
+
+/// Every camera with `WboitSettings` or `HEWboitSettings`, with the path that renders it and
+/// its [`WboitStatus`], for debug UIs:
+///
+/// ```ignore
+/// fn overlay(cameras: WboitCameras, mut text: Single<&mut Text>) {
+///     text.0 = cameras
+///         .iter()
+///         .map(|(camera, mode, status)| format!("{camera}: {mode:?} ({status:?})\n"))
+///         .collect();
+/// }
+/// ```
+#[derive(SystemParam)]
+pub struct WboitCameras<'w, 's> {
+    cameras: Query<
+        'w,
+        's,
+        (
+            Entity,
+            Option<&'static WboitSettings>,
+            Has<HEWboitSettings>,
+            Option<&'static WboitStatus>,
+        ),
+        Or<(With<WboitSettings>, With<HEWboitSettings>)>,
+    >,
+}
+
+impl WboitCameras<'_, '_> {
+    /// `(camera, mode, status)` per WBOIT camera. HE-WBOIT takes precedence when a camera has
+    /// both settings; cameras not rendered yet report [`WboitStatus::NotRendered`].
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, WboitCameraMode, WboitStatus)> + '_ {
+        self.cameras
+            .iter()
+            .map(|(entity, settings, has_he, status)| {
+                let mode = if has_he || settings.is_some_and(|settings| !settings.uses_naive_path())
+                {
+                    WboitCameraMode::HistogramEqualized
+                } else {
+                    WboitCameraMode::Naive
+                };
+                (entity, mode, status.copied().unwrap_or_default())
+            })
+    }
+}
+
+/// PCI vendor ids of tile-based GPU makers: Arm (Mali), Qualcomm (Adreno), Imagination
+/// (PowerVR), Apple and Broadcom (VideoCore).
+const TILE_BASED_GPU_VENDORS: [u32; 5] = [0x13b5, 0x5143, 0x1010, 0x106b, 0x14e4];
+
+/// Whether `info` looks like a tile-based GPU, judged by its vendor. wgpu reports no vendor
+/// id on Metal, where Apple GPUs are recognized by name.
+pub fn is_tile_based_gpu(info: &WgpuAdapterInfo) -> bool {
+    TILE_BASED_GPU_VENDORS.contains(&info.vendor)
+        || (info.vendor == 0 && info.name.starts_with("Apple"))
+}
+
+/// Warn (once) when the render device is a tile-based GPU. WBOIT writes its accum targets
+/// out of tile memory and reads them back in a separate composite pass, which costs
+/// bandwidth such GPUs are short of. Keeping the composite on-tile would take input
+/// attachments or framebuffer fetch, which wgpu (as used by Bevy 0.16) does not expose, so
+/// this only points at the settings that reduce the traffic.
+pub fn warn_if_tile_based_gpu(world: &World) {
+    let Some(info) = world.get_resource::<RenderAdapterInfo>() else {
+        return;
+    };
+    if is_tile_based_gpu(info) {
+        warn_once!(
+            "WBOIT: {} looks like a tile-based GPU, where the accum targets and the separate \
+             composite pass cost memory bandwidth. Consider WboitSettings::accum_scale 0.5, \
+             quality 0 or 1 instead of HE-WBOIT, and max_distance to skip distant layers.",
+            info.name
+        );
+    }
+}
+
+/// Insert to write the WGSL sources of the built-in WBOIT shaders into `directory` once they
+/// are loaded, e.g. to attach the exact shaders to a bug report or to start a fork from them.
+///
+/// Files are named after the shaders in `src/shaders` and written once per run; shaders of
+/// plugins that are not added are skipped. These are the sources as embedded, before
+/// shader-def processing: which defs a pipeline uses is decided in its `specialize`.
+#[derive(Resource, Clone, Debug)]
+pub struct WboitShaderDump {
+    pub directory: PathBuf,
+}
+
+/// Write the built-in shaders for [`WboitShaderDump`].
+pub fn dump_wboit_shaders(
+    dump: Option<Res<WboitShaderDump>>,
+    shaders: Res<Assets<Shader>>,
+    mut written: Local<HashSet<&'static str>>,
+) {
+    let Some(dump) = dump else {
+        return;
+    };
+    let builtin = [
+        ("wboit_fragment.wgsl", &WBOIT_FRAGMENT_SHADER_HANDLE),
+        ("wboit_vertex.wgsl", &WBOIT_VERTEX_SHADER_HANDLE),
+        ("wboit_composite.wgsl", &WBOIT_COMPOSITE_SHADER_HANDLE),
+        ("wboit_minimal.wgsl", &WBOIT_MINIMAL_SHADER_HANDLE),
+        ("wboit_shadow.wgsl", &WBOIT_SHADOW_SHADER_HANDLE),
+        ("histo_fragment.wgsl", &HISTO_FRAGMENT_SHADER_HANDLE),
+        ("histo_cdf_build.wgsl", &HISTO_CDF_BUILD_SHADER_HANDLE),
+    ];
+    for (name, handle) in builtin {
+        if written.contains(name) {
+            continue;
+        }
+        let Some(shader) = shaders.get(handle) else {
+            continue;
+        };
+        written.insert(name);
+        let path = dump.directory.join(name);
+        let result = fs::create_dir_all(&dump.directory)
+            .and_then(|()| fs::write(&path, shader.source.as_str()));
+        match result {
+            Ok(()) => info!("WBOIT: wrote {name} to {}", path.display()),
+            Err(err) => warn!("WBOIT: could not write {}: {err}", path.display()),
+        }
+    }
+}
+
+/// Registers [`diagnose_wboit_materials`], [`check_conflicting_wboit_settings`], the
+/// [`WboitDrainStats`] and [`WboitStatus`] syncs and [`dump_wboit_shaders`]. Added by both
+/// `NaiveWboitPlugin` and `HEWboitPlugin`, whichever comes first.
+pub struct WboitDiagnosticsPlugin;
+
+impl Plugin for WboitDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        let sink = WboitDrainStatsSink::default();
+        let status_sink = WboitStatusSink::default();
+        app.register_type::<WboitDrainStats>()
+            .register_type::<WboitStatus>()
+            .insert_resource(sink.clone())
+            .insert_resource(status_sink.clone())
+            .add_systems(
+                Update,
+                (
+                    diagnose_wboit_materials,
+                    check_conflicting_wboit_settings,
+                    dump_wboit_shaders,
+                ),
+            )
+            .add_systems(First, (sync_wboit_drain_stats, sync_wboit_status));
+
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app
+                .insert_resource(sink)
+                .insert_resource(status_sink)
+                .add_systems(
+                    Render,
+                    record_wboit_status.in_set(RenderSet::PrepareBindGroups),
+                );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+
+    use crate::test_utils::{extracted_view, gpu_app};
+
+    #[test]
+    fn camera_without_depth_texture_reports_missing_depth_texture() {
+        let Some(mut app) = gpu_app() else {
+            return;
+        };
+        app.add_plugins(crate::WboitPlugin);
+        app.finish();
+        app.cleanup();
+        let camera = app.world_mut().spawn_empty().id();
+
+        let render_world = app.get_sub_app_mut(RenderApp).unwrap().world_mut();
+        render_world.spawn((
+            extracted_view(camera, Transform::IDENTITY),
+            WboitSettings::default(),
+        ));
+        render_world.run_system_once(record_wboit_status).unwrap();
+        app.world_mut().run_system_once(sync_wboit_status).unwrap();
+
+        assert_eq!(
+            app.world().get::<WboitStatus>(camera),
+            Some(&WboitStatus::MissingDepthTexture)
+        );
+    }
+}
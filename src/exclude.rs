@@ -0,0 +1,268 @@
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+use bevy::core_pipeline::core_3d::Transparent3d;
+use bevy::core_pipeline::core_3d::graph::{Core3d, Node3d};
+use bevy::ecs::query::QueryItem;
+use bevy::pbr::{Material, MeshPipeline, queue_material_meshes};
+use bevy::prelude::*;
+use bevy::render::camera::ExtractedCamera;
+use bevy::render::render_graph::{
+    NodeRunError, RenderGraph, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode,
+    ViewNodeRunner,
+};
+use bevy::render::render_phase::{
+    AddRenderCommand, DrawFunctions, SortedRenderPhasePlugin, ViewSortedRenderPhases,
+    sort_phase_system,
+};
+use bevy::render::render_resource::{RenderPassDescriptor, StoreOp};
+use bevy::render::renderer::RenderContext;
+use bevy::render::view::{ExtractedView, RetainedViewEntity, ViewDepthTexture, ViewTarget};
+use bevy::render::{Extract, ExtractSchedule, Render, RenderApp, RenderDebugFlags, RenderSet};
+
+use crate::histogram::composite::HistoWboitCompositePass;
+use crate::naive::composite::WboitCompositePass;
+use crate::phase::{WboitLate3d, render_phase_range};
+use crate::queue::{QueueWboitMeshes, TransparentDrawMaterial};
+use crate::settings::{HEWboitSettings, WboitSettings};
+
+/// Render graph label for the pass drawing materials excluded from WBOIT, after the composite.
+#[derive(RenderLabel, Debug, Clone, Hash, PartialEq, Eq)]
+pub struct WboitLateTransparentPass;
+
+/// System set moving transparent items of excluded materials out of `Transparent3d`. Runs
+/// before the WBOIT queue and drain systems, so those items are neither accumulated nor
+/// counted as cleared.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QueueWboitLateMeshes;
+
+/// Keeps transparent meshes with material `M` out of WBOIT on WBOIT and HE-WBOIT cameras.
+///
+/// Their items are taken out of the standard transparent pass before it is drained and drawn
+/// with sorted alpha blending after the WBOIT composite, so they stay crisp on top of the
+/// WBOIT layer (e.g. a glass pane in front of WBOIT smoke). They are depth tested against the
+/// opaque scene only, so a pane behind WBOIT transparents is still drawn over them.
+///
+/// Exclusion is per material type; wrap a material (e.g. in an `ExtendedMaterial` with an
+/// empty extension) to exclude only some of its uses. With `WboitTaaMode::AfterTaa` the
+/// composite runs after this pass, so excluded materials end up under the WBOIT layer.
+///
+/// Use [`WboitAppExt::exclude_material_from_wboit`](crate::WboitAppExt::exclude_material_from_wboit).
+pub struct WboitExcludeMaterialPlugin<M: Material>(PhantomData<M>);
+
+impl<M: Material> Default for WboitExcludeMaterialPlugin<M> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<M: Material> Plugin for WboitExcludeMaterialPlugin<M>
+where
+    M::Data: PartialEq + Eq + Hash + Clone,
+{
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<WboitLateTransparentPlugin>() {
+            app.add_plugins(WboitLateTransparentPlugin);
+        }
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .add_render_command::<WboitLate3d, TransparentDrawMaterial<M>>()
+            .add_systems(
+                Render,
+                queue_wboit_late_meshes::<M>
+                    .in_set(QueueWboitLateMeshes)
+                    .after(queue_material_meshes::<M>),
+            );
+    }
+}
+
+/// Shared infrastructure of every [`WboitExcludeMaterialPlugin`]: the `WboitLate3d` phase and
+/// the render graph node drawing it. Added by the first one.
+pub struct WboitLateTransparentPlugin;
+
+impl Plugin for WboitLateTransparentPlugin {
+    fn build(&self, app: &mut App) {
+        // Batching and per-phase GPU buffers for WboitLate3d, as for the accum phases.
+        app.add_plugins(SortedRenderPhasePlugin::<WboitLate3d, MeshPipeline>::new(
+            RenderDebugFlags::default(),
+        ));
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .init_resource::<DrawFunctions<WboitLate3d>>()
+            .configure_sets(
+                Render,
+                QueueWboitLateMeshes
+                    .in_set(RenderSet::QueueMeshes)
+                    .before(QueueWboitMeshes),
+            )
+            .add_systems(ExtractSchedule, extract_wboit_late_camera_phases)
+            .add_systems(
+                Render,
+                sort_phase_system::<WboitLate3d>.in_set(RenderSet::PhaseSort),
+            )
+            .add_render_graph_node::<ViewNodeRunner<WboitLateTransparentNode>>(
+                Core3d,
+                WboitLateTransparentPass,
+            )
+            .add_render_graph_edge(Core3d, WboitLateTransparentPass, Node3d::EndMainPass);
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        // Order after whichever composites exist (all plugins are built by now).
+        let graph = render_app.world().resource::<RenderGraph>();
+        let Some(core_3d) = graph.get_sub_graph(Core3d) else {
+            return;
+        };
+        let has_naive = core_3d.get_node_state(WboitCompositePass).is_ok();
+        let has_he = core_3d.get_node_state(HistoWboitCompositePass).is_ok();
+        if has_naive {
+            render_app.add_render_graph_edge(Core3d, WboitCompositePass, WboitLateTransparentPass);
+        }
+        if has_he {
+            render_app.add_render_graph_edge(
+                Core3d,
+                HistoWboitCompositePass,
+                WboitLateTransparentPass,
+            );
+        }
+        if !has_naive && !has_he {
+            render_app.add_render_graph_edge(
+                Core3d,
+                Node3d::MainTransparentPass,
+                WboitLateTransparentPass,
+            );
+        }
+    }
+}
+
+/// Populate `ViewSortedRenderPhases<WboitLate3d>` with an entry for each WBOIT and HE-WBOIT
+/// camera.
+fn extract_wboit_late_camera_phases(
+    mut late_phases: ResMut<ViewSortedRenderPhases<WboitLate3d>>,
+    cameras: Extract<
+        Query<
+            Entity,
+            (
+                With<Camera3d>,
+                Or<(With<WboitSettings>, With<HEWboitSettings>)>,
+            ),
+        >,
+    >,
+    mut live_entities: Local<HashSet<RetainedViewEntity>>,
+) {
+    live_entities.clear();
+    for entity in &cameras {
+        let retained = RetainedViewEntity::new(entity.into(), None, 0);
+        late_phases.insert_or_clear(retained);
+        live_entities.insert(retained);
+    }
+    late_phases.retain(|view_entity, _| live_entities.contains(view_entity));
+}
+
+/// Move `Transparent3d` items with material `M` into the `WboitLate3d` phase of their view.
+pub fn queue_wboit_late_meshes<M: Material>(
+    transparent_draw_functions: Res<DrawFunctions<Transparent3d>>,
+    late_draw_functions: Res<DrawFunctions<WboitLate3d>>,
+    mut transparent_phases: ResMut<ViewSortedRenderPhases<Transparent3d>>,
+    mut late_phases: ResMut<ViewSortedRenderPhases<WboitLate3d>>,
+    views: Query<&ExtractedView>,
+) {
+    let Some(draw_material) = transparent_draw_functions
+        .read()
+        .get_id::<TransparentDrawMaterial<M>>()
+    else {
+        return;
+    };
+    let draw_late = late_draw_functions
+        .read()
+        .id::<TransparentDrawMaterial<M>>();
+
+    for view in &views {
+        let (Some(transparent_phase), Some(late_phase)) = (
+            transparent_phases.get_mut(&view.retained_view_entity),
+            late_phases.get_mut(&view.retained_view_entity),
+        ) else {
+            continue;
+        };
+        transparent_phase.items.retain(|item| {
+            if item.draw_function != draw_material {
+                return true;
+            }
+            // Same pipeline: the late pass renders into the same view target and depth.
+            late_phase.add(WboitLate3d {
+                distance: item.distance,
+                pipeline: item.pipeline,
+                entity: item.entity,
+                draw_function: draw_late,
+                batch_range: item.batch_range.clone(),
+                extra_index: item.extra_index.clone(),
+                indexed: item.indexed,
+            });
+            false
+        });
+    }
+}
+
+/// Render graph node drawing the `WboitLate3d` phase onto the view target, after the WBOIT
+/// composite and before `Node3d::EndMainPass`.
+#[derive(Default)]
+pub struct WboitLateTransparentNode;
+
+impl ViewNode for WboitLateTransparentNode {
+    type ViewQuery = (
+        &'static ExtractedCamera,
+        &'static ExtractedView,
+        &'static ViewTarget,
+        &'static ViewDepthTexture,
+    );
+
+    fn run<'w>(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext<'w>,
+        (camera, extracted_view, view_target, depth): QueryItem<Self::ViewQuery>,
+        world: &'w World,
+    ) -> Result<(), NodeRunError> {
+        let late_phases = world.resource::<ViewSortedRenderPhases<WboitLate3d>>();
+        let Some(late_phase) = late_phases.get(&extracted_view.retained_view_entity) else {
+            return Ok(());
+        };
+        if late_phase.items.is_empty() {
+            return Ok(());
+        }
+
+        // Same attachments as the main transparent pass.
+        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("wboit_late_transparent_pass"),
+            color_attachments: &[Some(view_target.get_color_attachment())],
+            depth_stencil_attachment: Some(depth.get_attachment(StoreOp::Store)),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        if let Some(viewport) = camera.viewport.as_ref() {
+            render_pass.set_camera_viewport(viewport);
+        }
+
+        render_phase_range(
+            late_phase,
+            &mut render_pass,
+            world,
+            graph.view_entity(),
+            0..late_phase.items.len(),
+            "WBOIT late transparent phase",
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,37 @@
+use bevy::core_pipeline::core_3d::graph::{Core3d, Node3d};
+use bevy::prelude::*;
+use bevy::render::RenderApp;
+use bevy::render::render_graph::{EmptyNode, RenderGraphApp, RenderLabel};
+
+/// Render graph label marking the end of WBOIT compositing: an empty node placed after every
+/// composite of the main pass (`WboitGroupsPass` for naive WBOIT, `HistoWboitCompositePass` for
+/// HE-WBOIT) and before `Node3d::EndMainPass`.
+///
+/// Nodes needing the composited transparency (e.g. a distortion applied on top of it) go after
+/// this label and before `Node3d::EndMainPass`:
+///
+/// ```ignore
+/// render_app
+///     .add_render_graph_node::<ViewNodeRunner<MyNode>>(Core3d, MyPass)
+///     .add_render_graph_edges(Core3d, (WboitPostComposite, MyPass, Node3d::EndMainPass));
+/// ```
+///
+/// The composites of `WboitTaaMode::AfterTaa` and `WboitTaaMode::BeforeOpaque` run outside the
+/// main pass, so they are not ordered before it.
+#[derive(RenderLabel, Debug, Clone, Hash, PartialEq, Eq)]
+pub struct WboitPostComposite;
+
+/// Registers the [`WboitPostComposite`] node. Added by whichever of `NaiveWboitPlugin` and
+/// `HEWboitPlugin` comes first; each then orders its composite before the node.
+pub struct WboitPostCompositePlugin;
+
+impl Plugin for WboitPostCompositePlugin {
+    fn build(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .add_render_graph_node::<EmptyNode>(Core3d, WboitPostComposite)
+            .add_render_graph_edge(Core3d, WboitPostComposite, Node3d::EndMainPass);
+    }
+}
@@ -1,8 +1,10 @@
 use bevy::color::LinearRgba;
+use bevy::core_pipeline::core_3d::Transparent3d;
+use bevy::ecs::entity::Entities;
 use bevy::ecs::query::QueryItem;
 use bevy::pbr::{
-    DrawMesh, MeshPipelineKey, RenderMeshInstances, SetMaterialBindGroup, SetMeshBindGroup,
-    SetMeshViewBindGroup, ViewKeyCache,
+    DrawMesh, RenderMeshInstances, SetMaterialBindGroup, SetMeshBindGroup, SetMeshViewBindGroup,
+    ViewKeyCache,
 };
 use bevy::prelude::*;
 use bevy::render::camera::ExtractedCamera;
@@ -10,23 +12,26 @@ use bevy::render::mesh::RenderMesh;
 use bevy::render::render_asset::RenderAssets;
 use bevy::render::render_graph::{NodeRunError, RenderGraphContext, RenderLabel, ViewNode};
 use bevy::render::render_phase::{
-    DrawFunctions, PhaseItem, PhaseItemExtraIndex, RenderCommand,
-    RenderCommandResult, SetItemPipeline, TrackedRenderPass, ViewSortedRenderPhases,
+    DrawFunctions, PhaseItem, PhaseItemExtraIndex, RenderCommand, RenderCommandResult,
+    SetItemPipeline, TrackedRenderPass, ViewSortedRenderPhases,
 };
-use bevy::render::render_resource::{PipelineCache, SpecializedMeshPipelines};
-use bevy::render::renderer::RenderContext;
-use bevy::render::view::{ExtractedView, ViewDepthTexture};
 use bevy::render::render_resource::{
     LoadOp, Operations, RenderPassColorAttachment, RenderPassDepthStencilAttachment,
     RenderPassDescriptor, StoreOp,
 };
-use bevy::core_pipeline::core_3d::Transparent3d;
+use bevy::render::render_resource::{PipelineCache, SpecializedMeshPipelines};
+use bevy::render::renderer::RenderContext;
+use bevy::render::sync_world::MainEntityHashSet;
+use bevy::render::view::{ExtractedView, ViewDepthTexture};
 
-use crate::phase::HistoAccum3d;
+use super::composite::{HistoAccumBindGroups, HistoCompositePipelineId};
+use super::pipeline::HistogramWboitPipeline;
+use crate::diagnostics::{WboitDrainStats, WboitDrainStatsSink};
+use crate::phase::{HistoAccum3d, render_phase_range};
+use crate::pipeline::{view_depth_matches, wboit_mesh_key};
+use crate::queue::{WboitSortFn, is_beyond_max_distance, resolve_queued_mesh};
 use crate::settings::HEWboitSettings;
 use crate::textures::WboitTextures;
-use super::composite::HistoAccumBindGroups;
-use super::pipeline::HistogramWboitPipeline;
 
 /// RenderCommand that sets the histogram data bind group (group 3) from `HistoAccumBindGroups`.
 /// Selects the bind group matching the current `frame_index` from `WboitTextures`.
@@ -39,10 +44,7 @@ impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetHistoAccumBindGroup<I
 
     fn render<'w>(
         _item: &P,
-        (bind_groups, wboit_textures): (
-            &'w HistoAccumBindGroups,
-            &'w WboitTextures,
-        ),
+        (bind_groups, wboit_textures): (&'w HistoAccumBindGroups, &'w WboitTextures),
         _entity: Option<()>,
         _param: (),
         pass: &mut TrackedRenderPass<'w>,
@@ -68,6 +70,11 @@ pub type DrawHistoWboit = (
 ///
 /// Runs after `queue_material_meshes`, reads from `Transparent3d` to get the
 /// already-filtered transparent entities, then re-specializes with the histo WBOIT pipeline.
+///
+/// `NoFrustumCulling` entities are included the same way as in `queue_wboit_meshes`.
+///
+/// As in `queue_wboit_meshes`, the material's `depth_bias` only affects the sort distance;
+/// histogram binning and CDF weighting use the unbiased rasterized depth.
 pub fn queue_histo_wboit_meshes(
     render_meshes: Res<RenderAssets<RenderMesh>>,
     render_mesh_instances: Res<RenderMeshInstances>,
@@ -77,15 +84,18 @@ pub fn queue_histo_wboit_meshes(
     draw_functions: Res<DrawFunctions<HistoAccum3d>>,
     mut histo_phases: ResMut<ViewSortedRenderPhases<HistoAccum3d>>,
     transparent_phases: Res<ViewSortedRenderPhases<Transparent3d>>,
-    views: Query<&ExtractedView, With<HEWboitSettings>>,
+    views: Query<(&ExtractedView, &HEWboitSettings)>,
     view_key_cache: Res<ViewKeyCache>,
+    sort_fn: Option<Res<WboitSortFn>>,
+    entities: &Entities,
 ) {
     let Some(histo_pipeline) = histo_pipeline else {
         return;
     };
     let draw_histo = draw_functions.read().id::<DrawHistoWboit>();
+    let sort_fn = sort_fn.as_deref().cloned().unwrap_or_default();
 
-    for view in &views {
+    for (view, settings) in &views {
         let Some(histo_phase) = histo_phases.get_mut(&view.retained_view_entity) else {
             continue;
         };
@@ -102,24 +112,21 @@ pub fn queue_histo_wboit_meshes(
             let (render_entity, main_entity) = item.entity;
 
             let Some(mesh_instance) =
-                render_mesh_instances.render_mesh_queue_data(main_entity)
+                resolve_queued_mesh(entities, &render_mesh_instances, item.entity)
             else {
                 continue;
             };
+            if is_beyond_max_distance(settings.max_distance, view, mesh_instance.translation) {
+                continue;
+            }
             let Some(mesh) = render_meshes.get(mesh_instance.mesh_asset_id) else {
                 continue;
             };
 
-            let mesh_key = *view_key
-                | MeshPipelineKey::from_bits_retain(mesh.key_bits.bits())
-                | MeshPipelineKey::BLEND_ALPHA;
+            let mesh_key = wboit_mesh_key(*view_key, mesh);
 
-            let pipeline_id = pipelines.specialize(
-                &pipeline_cache,
-                &histo_pipeline,
-                mesh_key,
-                &mesh.layout,
-            );
+            let pipeline_id =
+                pipelines.specialize(&pipeline_cache, &histo_pipeline, mesh_key, &mesh.layout);
             let pipeline_id = match pipeline_id {
                 Ok(id) => id,
                 Err(err) => {
@@ -129,7 +136,7 @@ pub fn queue_histo_wboit_meshes(
             };
 
             histo_phase.add(HistoAccum3d {
-                distance: item.distance,
+                distance: sort_fn.distance(item.distance, mesh_instance.translation, view),
                 pipeline: pipeline_id,
                 entity: (render_entity, main_entity),
                 draw_function: draw_histo,
@@ -142,13 +149,51 @@ pub fn queue_histo_wboit_meshes(
 }
 
 /// Drain `Transparent3d` phase items for HE-WBOIT cameras so the standard pass is a no-op.
+///
+/// Until the HE pipelines are compiled, transparents fall back to the standard sorted pass
+/// instead of disappearing: nothing is drained while the camera's composite pipeline is not
+/// ready, and afterwards items whose accum pipeline is still compiling stay in `Transparent3d`
+/// (and are taken out of `HistoAccum3d`) for as long as it is.
+///
+/// Records `WboitDrainStats` like `drain_transparent_for_wboit`.
 pub fn drain_transparent_for_he_wboit(
     mut transparent_phases: ResMut<ViewSortedRenderPhases<Transparent3d>>,
-    views: Query<&ExtractedView, With<HEWboitSettings>>,
+    mut histo_phases: ResMut<ViewSortedRenderPhases<HistoAccum3d>>,
+    pipeline_cache: Res<PipelineCache>,
+    stats_sink: Option<Res<WboitDrainStatsSink>>,
+    views: Query<(&ExtractedView, Option<&HistoCompositePipelineId>), With<HEWboitSettings>>,
 ) {
-    for view in &views {
-        if let Some(phase) = transparent_phases.get_mut(&view.retained_view_entity) {
-            phase.items.clear();
+    for (view, composite_pipeline) in &views {
+        let Some(phase) = transparent_phases.get_mut(&view.retained_view_entity) else {
+            continue;
+        };
+        let composite_ready =
+            composite_pipeline.is_some_and(|id| pipeline_cache.get_render_pipeline(id.0).is_some());
+        let mut pending = MainEntityHashSet::default();
+        if let Some(histo_phase) = histo_phases.get_mut(&view.retained_view_entity) {
+            histo_phase.items.retain(|item| {
+                let ready =
+                    composite_ready && pipeline_cache.get_render_pipeline(item.pipeline).is_some();
+                if !ready {
+                    pending.insert(item.entity.1);
+                }
+                ready
+            });
+        }
+        let before = phase.items.len();
+        if composite_ready {
+            phase.items.retain(|item| pending.contains(&item.entity.1));
+        }
+        let cleared = before - phase.items.len();
+
+        if let Some(sink) = stats_sink.as_ref() {
+            let queued = histo_phases
+                .get(&view.retained_view_entity)
+                .map_or(0, |phase| phase.items.len());
+            sink.record(
+                view.retained_view_entity.main_entity.id(),
+                WboitDrainStats { cleared, queued },
+            );
         }
     }
 }
@@ -181,13 +226,16 @@ impl ViewNode for HistoWboitAccumNode {
             return Ok(());
         };
 
-        if histo_phase.items.is_empty() {
+        if histo_phase.items.is_empty() || !view_depth_matches(depth) {
             return Ok(());
         }
 
         let view_entity = graph.view_entity();
         let fi = wboit_textures.frame_index;
 
+        // The targets are cleared by the load ops below, whether or not any item draws: items
+        // whose pipeline is still compiling are skipped by `SetItemPipeline`, and with all of
+        // them skipped the composite sees neutral targets and leaves the view target unchanged.
         let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
             label: Some("histo_wboit_accum_pass"),
             color_attachments: &[
@@ -208,12 +256,11 @@ impl ViewNode for HistoWboitAccumNode {
                     },
                 }),
             ],
+            // Read-only depth: the pipeline never writes depth, and binding the shared opaque
+            // depth read-only guarantees later passes see it unchanged.
             depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
                 view: depth.view(),
-                depth_ops: Some(Operations {
-                    load: LoadOp::Load,
-                    store: StoreOp::Store,
-                }),
+                depth_ops: None,
                 stencil_ops: None,
             }),
             timestamp_writes: None,
@@ -224,10 +271,148 @@ impl ViewNode for HistoWboitAccumNode {
             render_pass.set_camera_viewport(viewport);
         }
 
-        if let Err(err) = histo_phase.render(&mut render_pass, world, view_entity) {
-            error!("Error rendering HE-WBOIT accum phase: {err:?}");
-        }
+        render_phase_range(
+            histo_phase,
+            &mut render_pass,
+            world,
+            view_entity,
+            0..histo_phase.items.len(),
+            "HE-WBOIT accum phase",
+        );
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::render::RenderApp;
+    use bevy::render::render_graph::{EmptyNode, NodeState, RenderGraph};
+    use bevy::render::render_phase::RenderCommandState;
+    use bevy::render::render_resource::CachedRenderPipelineId;
+    use bevy::render::renderer::{RenderAdapterInfo, RenderDevice, RenderQueue};
+
+    use crate::histogram::textures::prepare_histogram_wboit_textures;
+    use crate::pipeline::WBOIT_DEPTH_FORMAT;
+    use crate::test_utils::{
+        assert_queue_keeps_depth_bias, assert_queue_skips_meshes_past_max_distance,
+        assert_queues_no_frustum_culling_meshes, extracted_camera, extracted_view, gpu_app,
+        item_entity, read_depth, view_depth_texture,
+    };
+
+    #[test]
+    fn queued_items_keep_the_transparent_distance_with_depth_bias() {
+        assert_queue_keeps_depth_bias::<HistoAccum3d, _>(
+            HEWboitSettings::default(),
+            queue_histo_wboit_meshes,
+        );
+    }
+
+    #[test]
+    fn queues_no_frustum_culling_meshes_outside_the_frustum() {
+        assert_queues_no_frustum_culling_meshes::<HistoAccum3d, _, _>(
+            HEWboitSettings::default(),
+            queue_histo_wboit_meshes,
+            drain_transparent_for_he_wboit,
+        );
+    }
+
+    #[test]
+    fn queue_skips_meshes_past_max_distance() {
+        let settings = HEWboitSettings {
+            max_distance: Some(10.0),
+            ..default()
+        };
+        assert_queue_skips_meshes_past_max_distance::<HistoAccum3d, _>(
+            settings,
+            queue_histo_wboit_meshes,
+        );
+    }
+
+    #[test]
+    fn accum_pass_leaves_the_shared_depth_unchanged() {
+        let Some(mut app) = gpu_app() else {
+            return;
+        };
+        app.add_plugins((crate::WboitPlugin, crate::HEWboitPlugin));
+        app.finish();
+        app.cleanup();
+        let world = app.get_sub_app_mut(RenderApp).unwrap().world_mut();
+
+        let size = UVec2::new(64, 64);
+        let camera = world.spawn_empty().id();
+        let depth = view_depth_texture(world, size, WBOIT_DEPTH_FORMAT, 0.25);
+        world.entity_mut(camera).insert((
+            extracted_camera(size),
+            extracted_view(camera, Transform::IDENTITY),
+            HEWboitSettings::default(),
+            depth,
+        ));
+        world
+            .run_system_once(prepare_histogram_wboit_textures)
+            .unwrap();
+
+        // Items whose pipeline is not ready, so the node runs its pass and draws nothing.
+        let draw = RenderCommandState::<HistoAccum3d, SetItemPipeline>::new(world);
+        let draw_function = world
+            .resource::<DrawFunctions<HistoAccum3d>>()
+            .write()
+            .add(draw);
+        let retained_view = world
+            .get::<ExtractedView>(camera)
+            .unwrap()
+            .retained_view_entity;
+        let mut phases = world.resource_mut::<ViewSortedRenderPhases<HistoAccum3d>>();
+        phases.insert_or_clear(retained_view);
+        let phase = phases.get_mut(&retained_view).unwrap();
+        for index in 0..3 {
+            phase.add(HistoAccum3d {
+                distance: index as f32,
+                pipeline: CachedRenderPipelineId::INVALID,
+                entity: item_entity(index),
+                draw_function,
+                batch_range: 0..1,
+                extra_index: PhaseItemExtraIndex::None,
+                indexed: false,
+            });
+        }
+
+        let world = &*world;
+        let entity = world.entity(camera);
+        let depth = entity.get::<ViewDepthTexture>().unwrap();
+        assert!(
+            read_depth(world, depth, size)
+                .iter()
+                .all(|&value| value == 0.25)
+        );
+
+        let graph = RenderGraph::default();
+        let node = NodeState::new(HistoWboitAccumPass.intern(), EmptyNode);
+        let mut graph_context = RenderGraphContext::new(&graph, &node, &[], &mut []);
+        graph_context.set_view_entity(camera);
+        let mut render_context = RenderContext::new(
+            world.resource::<RenderDevice>().clone(),
+            world.resource::<RenderAdapterInfo>().0.clone().into_inner(),
+            None,
+        );
+        let view_query = (
+            entity.get::<ExtractedCamera>().unwrap(),
+            entity.get::<ExtractedView>().unwrap(),
+            depth,
+            entity.get::<WboitTextures>().unwrap(),
+        );
+        HistoWboitAccumNode
+            .run(&mut graph_context, &mut render_context, view_query, world)
+            .unwrap();
+        let (command_buffers, ..) = render_context.finish();
+        world.resource::<RenderQueue>().submit(command_buffers);
+
+        assert!(
+            read_depth(world, depth, size)
+                .iter()
+                .all(|&value| value == 0.25)
+        );
+    }
+}
@@ -6,6 +6,7 @@ use bevy::render::renderer::RenderContext;
 use bevy::render::view::ExtractedView;
 
 use super::pipeline::CdfBuildPipeline;
+use super::readback::{HEWboitDebug, HistogramReadbackBuffer};
 use super::textures::HistogramWboitTextures;
 
 /// Per-camera bind group for the CDF build compute pass.
@@ -19,7 +20,8 @@ pub struct HistoCdfBuildPass;
 /// Render graph node that runs the CDF build compute pass.
 ///
 /// Dispatches (tile_count_x, tile_count_y, 1) workgroups, each with 64 threads (= num_bins).
-/// The compute shader also clears the histogram buffer for the next frame.
+/// The histogram is only read here; `HistoClearNode` zeroes it before the next accum pass.
+/// With `HEWboitDebug`, it is also copied into the camera's readback buffer.
 #[derive(Default)]
 pub struct HistoCdfBuildNode;
 
@@ -28,17 +30,20 @@ impl ViewNode for HistoCdfBuildNode {
         &'static ExtractedView,
         Option<&'static HistogramWboitTextures>,
         Option<&'static CdfBuildBindGroup>,
+        Option<&'static HistogramReadbackBuffer>,
+        Has<HEWboitDebug>,
     );
 
     fn run<'w>(
         &self,
         _graph: &mut RenderGraphContext,
         render_context: &mut RenderContext<'w>,
-        (_extracted_view, histo_textures_opt, cdf_bind_group_opt): QueryItem<Self::ViewQuery>,
+        (_extracted_view, histo_textures_opt, cdf_bind_group_opt, readback_opt, debug): QueryItem<
+            Self::ViewQuery,
+        >,
         world: &'w World,
     ) -> Result<(), NodeRunError> {
-        let (Some(histo_textures), Some(cdf_bind_group)) =
-            (histo_textures_opt, cdf_bind_group_opt)
+        let (Some(histo_textures), Some(cdf_bind_group)) = (histo_textures_opt, cdf_bind_group_opt)
         else {
             return Ok(());
         };
@@ -49,8 +54,7 @@ impl ViewNode for HistoCdfBuildNode {
         };
 
         let pipeline_cache = world.resource::<PipelineCache>();
-        let Some(pipeline) =
-            pipeline_cache.get_compute_pipeline(cdf_build_pipeline.pipeline_id)
+        let Some(pipeline) = pipeline_cache.get_compute_pipeline(cdf_build_pipeline.pipeline_id)
         else {
             return Ok(());
         };
@@ -70,6 +74,20 @@ impl ViewNode for HistoCdfBuildNode {
             histo_textures.tile_count_y,
             1,
         );
+        drop(compute_pass);
+
+        if debug && let Some(readback) = readback_opt {
+            let size = histo_textures.histogram_buffer.size();
+            if readback.buffer.size() == size && readback.try_begin_copy() {
+                render_context.command_encoder().copy_buffer_to_buffer(
+                    &histo_textures.histogram_buffer,
+                    0,
+                    &readback.buffer,
+                    0,
+                    size,
+                );
+            }
+        }
 
         Ok(())
     }
@@ -0,0 +1,35 @@
+use bevy::ecs::query::QueryItem;
+use bevy::prelude::*;
+use bevy::render::render_graph::{NodeRunError, RenderGraphContext, RenderLabel, ViewNode};
+use bevy::render::renderer::RenderContext;
+
+use super::textures::HistogramWboitTextures;
+
+/// Render graph label for the HE-WBOIT histogram clear pass.
+#[derive(RenderLabel, Debug, Clone, Hash, PartialEq, Eq)]
+pub struct HistoClearPass;
+
+/// Render graph node that zeroes the histogram buffer before the accum pass, with a
+/// `clear_buffer` command (the buffer has `COPY_DST`), so the CDF build can stay read-only.
+#[derive(Default)]
+pub struct HistoClearNode;
+
+impl ViewNode for HistoClearNode {
+    type ViewQuery = Option<&'static HistogramWboitTextures>;
+
+    fn run<'w>(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext<'w>,
+        histo_textures_opt: QueryItem<Self::ViewQuery>,
+        _world: &'w World,
+    ) -> Result<(), NodeRunError> {
+        let Some(histo_textures) = histo_textures_opt else {
+            return Ok(());
+        };
+        render_context
+            .command_encoder()
+            .clear_buffer(&histo_textures.histogram_buffer, 0, None);
+        Ok(())
+    }
+}
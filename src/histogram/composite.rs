@@ -1,26 +1,24 @@
-use bevy::asset::{weak_handle, Handle};
+use bevy::asset::Handle;
 use bevy::core_pipeline::fullscreen_vertex_shader::fullscreen_shader_vertex_state;
 use bevy::ecs::query::QueryItem;
 use bevy::prelude::*;
 use bevy::render::camera::ExtractedCamera;
 use bevy::render::render_graph::{NodeRunError, RenderGraphContext, RenderLabel, ViewNode};
 use bevy::render::render_resource::{
-    BindGroup, BindGroupEntry, BindGroupLayout, BindGroupLayoutEntry, BindingResource,
-    BindingType, BlendState, CachedRenderPipelineId, ColorTargetState, ColorWrites, FragmentState,
-    PipelineCache, RenderPassDescriptor, RenderPipelineDescriptor, Shader, ShaderStages,
-    TextureFormat, TextureSampleType, TextureViewDimension,
+    BindGroup, BindGroupEntry, BindGroupLayout, BindGroupLayoutEntry, BindingResource, BindingType,
+    BlendState, BufferBindingType, CachedRenderPipelineId, ColorTargetState, ColorWrites,
+    FragmentState, PipelineCache, RenderPassDescriptor, RenderPipelineDescriptor, Shader,
+    ShaderDefVal, ShaderStages, TextureFormat, TextureSampleType, TextureViewDimension,
 };
 use bevy::render::renderer::{RenderContext, RenderDevice};
 use bevy::render::view::ViewTarget;
 
-use crate::settings::HEWboitSettings;
-use crate::textures::WboitTextures;
 use super::cdf_build::CdfBuildBindGroup;
 use super::pipeline::{CdfBuildPipeline, HistogramWboitPipeline};
 use super::textures::HistogramWboitTextures;
-
-pub const HISTO_COMPOSITE_SHADER_HANDLE: Handle<Shader> =
-    weak_handle!("c3d4e5f6-a7b8-9012-cdef-123456789012");
+use crate::naive::composite::WBOIT_COMPOSITE_SHADER_HANDLE;
+use crate::settings::HEWboitSettings;
+use crate::textures::{WboitTextures, accum_format_max};
 
 /// Render graph label for the HE-WBOIT composite pass.
 #[derive(RenderLabel, Debug, Clone, Hash, PartialEq, Eq)]
@@ -33,9 +31,10 @@ pub struct HistoWboitCompositePass;
 #[derive(Component)]
 pub struct HistoAccumBindGroups(pub [BindGroup; 2]);
 
-/// Per-camera component storing the composite pipeline ID (queued once).
+/// Per-camera component storing the composite pipeline ID and the target format it was queued
+/// for.
 #[derive(Component)]
-pub struct HistoCompositePipelineId(pub CachedRenderPipelineId);
+pub struct HistoCompositePipelineId(pub CachedRenderPipelineId, pub TextureFormat);
 
 /// Per-camera component storing the composite bind group.
 #[derive(Component)]
@@ -72,39 +71,56 @@ impl FromWorld for HistoCompositePipeline {
                 },
                 count: None,
             },
+            // This frame's CDF and the params, for `HEWboitSettings::absorption_color`.
+            BindGroupLayoutEntry {
+                binding: 2,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Float { filterable: false },
+                    view_dimension: TextureViewDimension::D3,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                binding: 3,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
         ];
 
-        let bind_group_layout = render_device.create_bind_group_layout(
-            "histo_composite_bind_group_layout",
-            &entries,
-        );
+        let bind_group_layout =
+            render_device.create_bind_group_layout("histo_composite_bind_group_layout", &entries);
 
         HistoCompositePipeline {
             bind_group_layout,
-            fragment_shader: HISTO_COMPOSITE_SHADER_HANDLE,
+            fragment_shader: WBOIT_COMPOSITE_SHADER_HANDLE,
         }
     }
 }
 
-/// Queue the composite pipeline once per HE-WBOIT camera.
+/// Queue the composite pipeline for each HE-WBOIT camera, again whenever its main texture
+/// format changes (e.g. `Camera::hdr` toggled). The pipeline targets the view's exact main
+/// texture format, whichever it is.
 pub fn queue_histo_composite_pipeline(
     mut commands: Commands,
     pipeline_cache: Res<PipelineCache>,
     composite_pipeline: Option<Res<HistoCompositePipeline>>,
-    views: Query<
-        (Entity, &ViewTarget),
-        (With<HEWboitSettings>, Without<HistoCompositePipelineId>),
-    >,
+    views: Query<(Entity, &ViewTarget, Option<&HistoCompositePipelineId>), With<HEWboitSettings>>,
 ) {
     let Some(composite_pipeline) = composite_pipeline else {
         return;
     };
-    for (entity, view_target) in &views {
-        let format = if view_target.main_texture_format() == ViewTarget::TEXTURE_FORMAT_HDR {
-            ViewTarget::TEXTURE_FORMAT_HDR
-        } else {
-            TextureFormat::bevy_default()
-        };
+    for (entity, view_target, queued) in &views {
+        let format = view_target.main_texture_format();
+        if queued.is_some_and(|queued| queued.1 == format) {
+            continue;
+        }
 
         let pipeline_id = pipeline_cache.queue_render_pipeline(RenderPipelineDescriptor {
             label: Some("histo_composite_pipeline".into()),
@@ -112,7 +128,14 @@ pub fn queue_histo_composite_pipeline(
             vertex: fullscreen_shader_vertex_state(),
             fragment: Some(FragmentState {
                 shader: composite_pipeline.fragment_shader.clone(),
-                shader_defs: vec![],
+                shader_defs: vec![
+                    "WBOIT_HISTOGRAM".into(),
+                    // The HE accum target is always Rgba16Float.
+                    ShaderDefVal::UInt(
+                        "ACCUM_FORMAT_MAX".into(),
+                        accum_format_max(TextureFormat::Rgba16Float),
+                    ),
+                ],
                 entry_point: "fragment".into(),
                 targets: vec![Some(ColorTargetState {
                     format,
@@ -129,11 +152,15 @@ pub fn queue_histo_composite_pipeline(
 
         commands
             .entity(entity)
-            .insert(HistoCompositePipelineId(pipeline_id));
+            .insert(HistoCompositePipelineId(pipeline_id, format));
     }
 }
 
 /// Prepare bind groups for HE-WBOIT cameras every frame.
+///
+/// Nothing is cached: each camera costs four `create_bind_group` calls per frame (two accum,
+/// CDF build, composite; the histogram is cleared with `clear_buffer`, without a bind group).
+/// `benches/prepare_bind_groups.rs` measures this path.
 pub fn prepare_histo_wboit_bind_groups(
     mut commands: Commands,
     render_device: Res<RenderDevice>,
@@ -215,6 +242,14 @@ pub fn prepare_histo_wboit_bind_groups(
                         &wboit_textures.revealage[fi].default_view,
                     ),
                 },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::TextureView(&histo_textures.cdf_view),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: histo_textures.histo_params_buffer.as_entire_binding(),
+                },
             ],
         );
 
@@ -227,6 +262,9 @@ pub fn prepare_histo_wboit_bind_groups(
 }
 
 /// Render graph node that renders the HE-WBOIT composite pass (fullscreen triangle).
+///
+/// Also runs while the camera warms up (`HEWboitSettings::warmup_frames`); the accum pass
+/// uses plain depth weighting until then.
 #[derive(Default)]
 pub struct HistoWboitCompositeNode;
 
@@ -0,0 +1,188 @@
+use std::any::TypeId;
+
+use bevy::prelude::*;
+use bevy::render::extract_component::ExtractComponent;
+use bevy::render::primitives::Aabb;
+use bevy::render::view::VisibleEntities;
+
+use crate::settings::{HEWboitDepthMode, HEWboitSettings};
+
+/// Far end of the histogram depth range estimated for a camera with
+/// [`HEWboitDepthMode::Auto`]: the largest view-space depth (in world units) of the bounds of
+/// the transparent meshes it saw this frame. Used in place of `HEWboitSettings::max_depth`.
+///
+/// Maintained by [`estimate_he_wboit_depth_range`]; absent while the camera sees no
+/// transparents.
+#[derive(Component, Clone, Copy, Debug, PartialEq, ExtractComponent, Reflect)]
+#[reflect(Component)]
+pub struct HEWboitAutoDepth(pub f32);
+
+/// Recompute [`HEWboitAutoDepth`] for each `Auto` HE-WBOIT camera from its visible meshes.
+///
+/// Only `StandardMaterial` meshes with a transparent alpha mode count, as those are the ones
+/// HE-WBOIT draws. Each mesh contributes the deepest corner of its world-space bounding box,
+/// so large meshes are covered entirely rather than up to their origin.
+pub fn estimate_he_wboit_depth_range(
+    mut commands: Commands,
+    cameras: Query<(
+        Entity,
+        &HEWboitSettings,
+        &GlobalTransform,
+        &VisibleEntities,
+        Option<&HEWboitAutoDepth>,
+    )>,
+    meshes: Query<(&GlobalTransform, &Aabb, &MeshMaterial3d<StandardMaterial>)>,
+    materials: Res<Assets<StandardMaterial>>,
+) {
+    for (entity, he_settings, camera_transform, visible, auto_depth) in &cameras {
+        if he_settings.depth_mode != HEWboitDepthMode::Auto {
+            if auto_depth.is_some() {
+                commands.entity(entity).remove::<HEWboitAutoDepth>();
+            }
+            continue;
+        }
+
+        let view_from_world = camera_transform.affine().inverse();
+        let mut max_depth = None::<f32>;
+        for (transform, aabb, material) in meshes.iter_many(visible.iter(TypeId::of::<Mesh3d>())) {
+            let transparent = materials.get(material.id()).is_some_and(|material| {
+                matches!(
+                    material.alpha_mode,
+                    AlphaMode::Blend
+                        | AlphaMode::Premultiplied
+                        | AlphaMode::Add
+                        | AlphaMode::Multiply
+                )
+            });
+            if !transparent {
+                continue;
+            }
+            // View space looks down -Z. The box's deepest point along the view direction is its
+            // center plus the half extents projected onto the view Z row.
+            let view_from_local = view_from_world * transform.affine();
+            let center = view_from_local.transform_point3a(aabb.center);
+            let z_row = Vec3A::new(
+                view_from_local.matrix3.x_axis.z,
+                view_from_local.matrix3.y_axis.z,
+                view_from_local.matrix3.z_axis.z,
+            );
+            let depth = -center.z + z_row.abs().dot(aabb.half_extents);
+            max_depth = Some(max_depth.map_or(depth, |max| max.max(depth)));
+        }
+
+        match max_depth.filter(|depth| *depth > 0.0) {
+            Some(depth) if auto_depth.map(|auto| auto.0) != Some(depth) => {
+                commands.entity(entity).insert(HEWboitAutoDepth(depth));
+            }
+            Some(_) => {}
+            None if auto_depth.is_some() => {
+                commands.entity(entity).remove::<HEWboitAutoDepth>();
+            }
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::{FRAC_PI_4, SQRT_2};
+
+    use bevy::ecs::system::RunSystemOnce;
+
+    /// Spawn a unit cube mesh at `transform` with an `alpha_mode` material, visible to `camera`.
+    fn spawn_cube(world: &mut World, camera: Entity, transform: Transform, alpha_mode: AlphaMode) {
+        let material = world
+            .resource_mut::<Assets<StandardMaterial>>()
+            .add(StandardMaterial {
+                alpha_mode,
+                ..default()
+            });
+        let mesh = world
+            .spawn((
+                GlobalTransform::from(transform),
+                Aabb::from_min_max(Vec3::splat(-1.0), Vec3::ONE),
+                MeshMaterial3d(material),
+            ))
+            .id();
+        world
+            .get_mut::<VisibleEntities>(camera)
+            .unwrap()
+            .push(mesh, TypeId::of::<Mesh3d>());
+    }
+
+    fn auto_depth(world: &mut World, camera: Entity) -> Option<f32> {
+        world
+            .run_system_once(estimate_he_wboit_depth_range)
+            .unwrap();
+        world.get::<HEWboitAutoDepth>(camera).map(|depth| depth.0)
+    }
+
+    #[test]
+    fn auto_depth_reaches_the_far_side_of_the_deepest_transparent_bounds() {
+        let mut world = World::new();
+        world.init_resource::<Assets<StandardMaterial>>();
+        let camera = world
+            .spawn((
+                HEWboitSettings {
+                    depth_mode: HEWboitDepthMode::Auto,
+                    ..default()
+                },
+                GlobalTransform::from_xyz(0.0, 0.0, 5.0),
+                VisibleEntities::default(),
+            ))
+            .id();
+        assert_eq!(auto_depth(&mut world, camera), None);
+
+        spawn_cube(
+            &mut world,
+            camera,
+            Transform::from_xyz(0.0, 0.0, -5.0),
+            AlphaMode::Blend,
+        );
+        assert_eq!(auto_depth(&mut world, camera), Some(11.0));
+
+        // Rotated a quarter turn about Y, the cube's corner points away from the camera.
+        let rotated =
+            Transform::from_xyz(0.0, 0.0, -15.0).with_rotation(Quat::from_rotation_y(FRAC_PI_4));
+        spawn_cube(&mut world, camera, rotated, AlphaMode::Add);
+        let depth = auto_depth(&mut world, camera).unwrap();
+        assert!((depth - (20.0 + SQRT_2)).abs() < 1e-4, "{depth}");
+
+        // Opaque meshes are not drawn by HE-WBOIT and leave the range alone.
+        spawn_cube(
+            &mut world,
+            camera,
+            Transform::from_xyz(0.0, 0.0, -95.0),
+            AlphaMode::Opaque,
+        );
+        assert_eq!(auto_depth(&mut world, camera), Some(depth));
+
+        world.get_mut::<HEWboitSettings>(camera).unwrap().depth_mode = HEWboitDepthMode::Fixed;
+        assert_eq!(auto_depth(&mut world, camera), None);
+    }
+
+    #[test]
+    fn auto_depth_is_removed_when_only_transparents_behind_the_camera_remain() {
+        let mut world = World::new();
+        world.init_resource::<Assets<StandardMaterial>>();
+        let camera = world
+            .spawn((
+                HEWboitSettings {
+                    depth_mode: HEWboitDepthMode::Auto,
+                    ..default()
+                },
+                GlobalTransform::IDENTITY,
+                VisibleEntities::default(),
+            ))
+            .id();
+        world.entity_mut(camera).insert(HEWboitAutoDepth(42.0));
+        spawn_cube(
+            &mut world,
+            camera,
+            Transform::from_xyz(0.0, 0.0, 10.0),
+            AlphaMode::Blend,
+        );
+        assert_eq!(auto_depth(&mut world, camera), None);
+    }
+}
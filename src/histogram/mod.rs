@@ -1,42 +1,61 @@
 pub mod accum_pass;
 pub mod cdf_build;
+pub mod clear;
 pub mod composite;
+pub mod depth_range;
 pub mod pipeline;
+pub mod readback;
 pub mod textures;
 
 use bevy::asset::load_internal_asset;
-use bevy::prelude::*;
 use bevy::core_pipeline::core_3d::graph::{Core3d, Node3d};
+use bevy::pbr::MeshPipeline;
 use bevy::pbr::queue_material_meshes;
+use bevy::prelude::*;
 use bevy::render::extract_component::ExtractComponentPlugin;
-use bevy::pbr::MeshPipeline;
+use bevy::render::extract_resource::ExtractResourcePlugin;
 use bevy::render::render_graph::{RenderGraphApp, ViewNodeRunner};
 use bevy::render::render_phase::{
     AddRenderCommand, DrawFunctions, SortedRenderPhasePlugin, ViewSortedRenderPhases,
     sort_phase_system,
 };
-use bevy::render::render_resource::{Shader, SpecializedMeshPipelines};
-use bevy::render::view::RetainedViewEntity;
+use bevy::render::render_resource::{DownlevelFlags, Shader, SpecializedMeshPipelines};
+use bevy::render::renderer::{RenderAdapter, RenderDevice};
+use bevy::render::view::{RetainedViewEntity, VisibilitySystems};
 use bevy::render::{Extract, ExtractSchedule, Render, RenderApp, RenderDebugFlags, RenderSet};
 use std::collections::HashSet;
 
+use crate::diagnostics::WboitDiagnosticsPlugin;
+use crate::exclude::QueueWboitLateMeshes;
+use crate::graph::{WboitPostComposite, WboitPostCompositePlugin};
 use crate::phase::HistoAccum3d;
-use crate::settings::HEWboitSettings;
+use crate::queue::WboitSortFn;
+use crate::settings::{
+    HEWboitSettings, WboitAdaptiveQuality, WboitSettings, apply_wboit_adaptive_quality,
+};
+use crate::textures::WboitTextures;
 
 use self::accum_pass::{
-    DrawHistoWboit, HistoWboitAccumNode, HistoWboitAccumPass,
-    drain_transparent_for_he_wboit, queue_histo_wboit_meshes,
+    DrawHistoWboit, HistoWboitAccumNode, HistoWboitAccumPass, drain_transparent_for_he_wboit,
+    queue_histo_wboit_meshes,
 };
-use self::cdf_build::{HistoCdfBuildNode, HistoCdfBuildPass};
+use self::cdf_build::{CdfBuildBindGroup, HistoCdfBuildNode, HistoCdfBuildPass};
+use self::clear::{HistoClearNode, HistoClearPass};
 use self::composite::{
-    HistoCompositePipeline, HistoWboitCompositeNode, HistoWboitCompositePass,
+    HistoAccumBindGroups, HistoCompositeBindGroup, HistoCompositePipeline,
+    HistoCompositePipelineId, HistoWboitCompositeNode, HistoWboitCompositePass,
     prepare_histo_wboit_bind_groups, queue_histo_composite_pipeline,
 };
+use self::depth_range::{HEWboitAutoDepth, estimate_he_wboit_depth_range};
 use self::pipeline::{
     CdfBuildPipeline, HistogramWboitPipeline, check_msaa_he_wboit,
     configure_depth_texture_usages_he_wboit,
 };
-use self::textures::prepare_histogram_wboit_textures;
+use self::readback::{
+    HEWboitDebug, HistogramReadback, HistogramReadbackBuffer, HistogramReadbackSink,
+    map_histogram_readback_buffers, prepare_histogram_readback_buffers, sync_histogram_readback,
+};
+use self::textures::{HistoWboitWarmup, HistogramWboitTextures, prepare_histogram_wboit_textures};
 
 /// Populate `ViewSortedRenderPhases<HistoAccum3d>` for each active HE-WBOIT camera.
 fn extract_histo_wboit_camera_phases(
@@ -53,13 +72,80 @@ fn extract_histo_wboit_camera_phases(
     histo_phases.retain(|view_entity, _| live_entities.contains(view_entity));
 }
 
+/// Rebuild device-owned HE-WBOIT state when `RenderDevice` is replaced.
+///
+/// See `reset_wboit_on_device_change` in the naive plugin.
+fn reset_histo_wboit_on_device_change(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    views: Query<Entity, With<HEWboitSettings>>,
+) {
+    if !render_device.is_changed() || render_device.is_added() {
+        return;
+    }
+    warn!("RenderDevice was replaced, rebuilding HE-WBOIT pipelines and resources");
+    commands.queue(|world: &mut World| {
+        let pipeline = HistogramWboitPipeline::from_world(world);
+        world.insert_resource(pipeline);
+        let cdf_build_pipeline = CdfBuildPipeline::from_world(world);
+        world.insert_resource(cdf_build_pipeline);
+        let composite_pipeline = HistoCompositePipeline::from_world(world);
+        world.insert_resource(composite_pipeline);
+        world.insert_resource(SpecializedMeshPipelines::<HistogramWboitPipeline>::default());
+    });
+    for entity in &views {
+        commands.entity(entity).remove::<(
+            WboitTextures,
+            HistogramWboitTextures,
+            HistoAccumBindGroups,
+            CdfBuildBindGroup,
+            HistoCompositePipelineId,
+            HistoCompositeBindGroup,
+            HistogramReadbackBuffer,
+        )>();
+    }
+}
+
+/// Drop the HE-WBOIT state of views whose `HEWboitSettings` was removed. Without this the
+/// composite pipeline and bind group of a camera switched back to naive WBOIT stay on its
+/// render entity, and the HE composite would run on top of the naive one. `WboitTextures` is
+/// kept for views now on the naive path, whose prepare system rewrites it.
+fn remove_inactive_histo_wboit_views(
+    mut commands: Commands,
+    views: Query<
+        (Entity, Has<WboitSettings>),
+        (With<HistogramWboitTextures>, Without<HEWboitSettings>),
+    >,
+) {
+    for (entity, has_naive) in &views {
+        let mut entity = commands.entity(entity);
+        entity.remove::<(
+            HistogramWboitTextures,
+            HistoWboitWarmup,
+            HistoAccumBindGroups,
+            CdfBuildBindGroup,
+            HistoCompositePipelineId,
+            HistoCompositeBindGroup,
+            HistogramReadbackBuffer,
+        )>();
+        if !has_naive {
+            entity.remove::<WboitTextures>();
+        }
+    }
+}
+
 /// Plugin implementing histogram-equalized WBOIT (Phase 2).
 ///
-/// Add `HEWboitSettings` to a camera entity to opt in.
+/// Add `HEWboitSettings` to a camera entity to opt in. Its render graph nodes are separate
+/// from the naive ones, so it combines with the naive plugins in any order. Only the first
+/// instance is built; adding it again does nothing.
 pub struct HEWboitPlugin;
 
 impl Plugin for HEWboitPlugin {
     fn build(&self, app: &mut App) {
+        if app.is_plugin_added::<Self>() {
+            return;
+        }
         load_internal_asset!(
             app,
             pipeline::HISTO_FRAGMENT_SHADER_HANDLE,
@@ -72,28 +158,53 @@ impl Plugin for HEWboitPlugin {
             "../shaders/histo_cdf_build.wgsl",
             Shader::from_wgsl
         );
+        // The composite shader is shared with the naive path (compiled with WBOIT_HISTOGRAM).
         load_internal_asset!(
             app,
-            composite::HISTO_COMPOSITE_SHADER_HANDLE,
-            "../shaders/histo_composite.wgsl",
+            crate::naive::composite::WBOIT_COMPOSITE_SHADER_HANDLE,
+            "../shaders/wboit_composite.wgsl",
             Shader::from_wgsl
         );
 
+        if !app.is_plugin_added::<WboitDiagnosticsPlugin>() {
+            app.add_plugins(WboitDiagnosticsPlugin);
+        }
+        if !app.is_plugin_added::<WboitPostCompositePlugin>() {
+            app.add_plugins(WboitPostCompositePlugin);
+        }
+        if !app.is_plugin_added::<ExtractResourcePlugin<WboitSortFn>>() {
+            app.add_plugins(ExtractResourcePlugin::<WboitSortFn>::default());
+        }
+
+        let readback_sink = HistogramReadbackSink::default();
         app.add_plugins((
             ExtractComponentPlugin::<HEWboitSettings>::default(),
-            SortedRenderPhasePlugin::<HistoAccum3d, MeshPipeline>::new(
-                RenderDebugFlags::default(),
-            ),
+            ExtractComponentPlugin::<HEWboitDebug>::default(),
+            ExtractComponentPlugin::<HEWboitAutoDepth>::default(),
+            SortedRenderPhasePlugin::<HistoAccum3d, MeshPipeline>::new(RenderDebugFlags::default()),
         ))
         .register_type::<HEWboitSettings>()
-        .add_systems(Update, check_msaa_he_wboit)
+        .register_type::<HEWboitDebug>()
+        .register_type::<HistogramReadback>()
+        .register_type::<crate::settings::HEWboitDepthMode>()
+        .register_type::<HEWboitAutoDepth>()
+        .register_type::<WboitAdaptiveQuality>()
+        .insert_resource(readback_sink.clone())
+        .add_systems(First, sync_histogram_readback)
+        .add_systems(Update, (check_msaa_he_wboit, apply_wboit_adaptive_quality))
+        .add_systems(
+            PostUpdate,
+            estimate_he_wboit_depth_range.after(VisibilitySystems::CheckVisibility),
+        )
         .add_systems(Last, configure_depth_texture_usages_he_wboit);
 
         let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
             return;
         };
 
+        crate::textures::add_wboit_textures_recreated_event(render_app);
         render_app
+            .insert_resource(readback_sink)
             .init_resource::<DrawFunctions<HistoAccum3d>>()
             .init_resource::<SpecializedMeshPipelines<HistogramWboitPipeline>>()
             .add_render_command::<HistoAccum3d, DrawHistoWboit>()
@@ -101,42 +212,87 @@ impl Plugin for HEWboitPlugin {
             .add_systems(
                 Render,
                 (
-                    prepare_histogram_wboit_textures
-                        .in_set(RenderSet::PrepareResources),
+                    reset_histo_wboit_on_device_change.in_set(RenderSet::ManageViews),
+                    remove_inactive_histo_wboit_views.in_set(RenderSet::ManageViews),
+                    prepare_histogram_wboit_textures.in_set(RenderSet::PrepareResources),
                     queue_histo_wboit_meshes
                         .in_set(RenderSet::QueueMeshes)
-                        .after(queue_material_meshes::<StandardMaterial>),
+                        .after(queue_material_meshes::<StandardMaterial>)
+                        .after(QueueWboitLateMeshes),
                     drain_transparent_for_he_wboit
                         .in_set(RenderSet::QueueMeshes)
                         .after(queue_histo_wboit_meshes),
                     sort_phase_system::<HistoAccum3d>.in_set(RenderSet::PhaseSort),
                     queue_histo_composite_pipeline.in_set(RenderSet::Queue),
                     prepare_histo_wboit_bind_groups.in_set(RenderSet::PrepareBindGroups),
+                    prepare_histogram_readback_buffers
+                        .in_set(RenderSet::PrepareResources)
+                        .after(prepare_histogram_wboit_textures),
+                    map_histogram_readback_buffers.in_set(RenderSet::Cleanup),
                 ),
             )
-            // Register render graph nodes: accum → cdf_build → composite
-            .add_render_graph_node::<ViewNodeRunner<HistoWboitAccumNode>>(Core3d, HistoWboitAccumPass)
+            // Register render graph nodes: clear → accum → cdf_build → composite, before
+            // WboitPostComposite
+            .add_render_graph_node::<ViewNodeRunner<HistoClearNode>>(Core3d, HistoClearPass)
+            .add_render_graph_node::<ViewNodeRunner<HistoWboitAccumNode>>(
+                Core3d,
+                HistoWboitAccumPass,
+            )
             .add_render_graph_node::<ViewNodeRunner<HistoCdfBuildNode>>(Core3d, HistoCdfBuildPass)
-            .add_render_graph_node::<ViewNodeRunner<HistoWboitCompositeNode>>(Core3d, HistoWboitCompositePass)
+            .add_render_graph_node::<ViewNodeRunner<HistoWboitCompositeNode>>(
+                Core3d,
+                HistoWboitCompositePass,
+            )
             .add_render_graph_edges(
                 Core3d,
                 (
                     Node3d::MainTransparentPass,
+                    HistoClearPass,
                     HistoWboitAccumPass,
                     HistoCdfBuildPass,
                     HistoWboitCompositePass,
-                    Node3d::EndMainPass,
+                    WboitPostComposite,
                 ),
             );
     }
 
+    fn is_unique(&self) -> bool {
+        false
+    }
+
     fn finish(&self, app: &mut App) {
         let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
             return;
         };
+        // Already finished by the first instance.
+        if render_app
+            .world()
+            .contains_resource::<HistogramWboitPipeline>()
+        {
+            return;
+        }
         render_app
             .init_resource::<HistogramWboitPipeline>()
             .init_resource::<CdfBuildPipeline>()
             .init_resource::<HistoCompositePipeline>();
+
+        // One-time summary of the resolved setup, for bug reports. The CDF is a 3D texture of
+        // (tiles x, tiles y, bins), so its size is bounded by `max_texture_dimension_3d`.
+        let world = render_app.world();
+        let limits = world.resource::<RenderDevice>().limits();
+        let compute_shaders = world
+            .resource::<RenderAdapter>()
+            .get_downlevel_capabilities()
+            .flags
+            .contains(DownlevelFlags::COMPUTE_SHADERS);
+        info!(
+            bindless = world.resource::<HistogramWboitPipeline>().bindless,
+            compute_shaders,
+            max_texture_dimension_3d = limits.max_texture_dimension_3d,
+            max_compute_workgroup_size_x = limits.max_compute_workgroup_size_x,
+            max_storage_buffer_binding_size = limits.max_storage_buffer_binding_size,
+            "HE-WBOIT initialized"
+        );
+        crate::diagnostics::warn_if_tile_based_gpu(world);
     }
 }
@@ -1,16 +1,20 @@
-use bevy::asset::{weak_handle, Handle};
-use bevy::pbr::{material_uses_bindless_resources, MeshPipeline, StandardMaterial};
+use bevy::asset::{Handle, weak_handle};
+use bevy::pbr::{MeshPipeline, StandardMaterial, material_uses_bindless_resources};
 use bevy::render::mesh::MeshVertexBufferLayoutRef;
 use bevy::render::render_resource::{
-    AsBindGroup, BindGroupLayout, BindGroupLayoutEntry, BindingType,
-    BlendComponent, BlendFactor, BlendOperation, BlendState, BufferBindingType,
-    CachedComputePipelineId, ColorTargetState, ColorWrites, ComputePipelineDescriptor,
-    PipelineCache, RenderPipelineDescriptor, SamplerBindingType, Shader, ShaderDefVal,
-    ShaderStages, SpecializedMeshPipeline, SpecializedMeshPipelineError, StorageTextureAccess,
-    TextureFormat, TextureSampleType, TextureViewDimension,
+    AsBindGroup, BindGroupLayout, BindGroupLayoutEntry, BindingType, BlendComponent, BlendFactor,
+    BlendOperation, BlendState, BufferBindingType, CachedComputePipelineId, ColorTargetState,
+    ColorWrites, ComputePipelineDescriptor, PipelineCache, RenderPipelineDescriptor,
+    SamplerBindingType, Shader, ShaderDefVal, ShaderStages, SpecializedMeshPipeline,
+    SpecializedMeshPipelineError, StorageTextureAccess, TextureFormat, TextureSampleType,
+    TextureViewDimension,
 };
 use bevy::render::renderer::RenderDevice;
 use bevy::{pbr::MeshPipelineKey, prelude::*};
+use std::collections::HashSet;
+
+use crate::pipeline::WBOIT_DEPTH_FORMAT;
+use crate::textures::WBOIT_REVEALAGE_FORMAT;
 
 pub const HISTO_FRAGMENT_SHADER_HANDLE: Handle<Shader> =
     weak_handle!("a1b2c3d4-e5f6-7890-abcd-ef1234567890");
@@ -18,15 +22,72 @@ pub const HISTO_FRAGMENT_SHADER_HANDLE: Handle<Shader> =
 pub const HISTO_CDF_BUILD_SHADER_HANDLE: Handle<Shader> =
     weak_handle!("b2c3d4e5-f6a7-8901-bcde-f12345678901");
 
+/// Histogram data bind group layout entries (group 3 in `histo_fragment.wgsl`), bindings 0-4
+/// in declaration order.
+fn histo_data_layout_entries() -> Vec<BindGroupLayoutEntry> {
+    vec![
+        BindGroupLayoutEntry {
+            binding: 0,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        },
+        BindGroupLayoutEntry {
+            binding: 1,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Texture {
+                sample_type: TextureSampleType::Float { filterable: true },
+                view_dimension: TextureViewDimension::D3,
+                multisampled: false,
+            },
+            count: None,
+        },
+        BindGroupLayoutEntry {
+            binding: 2,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Sampler(SamplerBindingType::Filtering),
+            count: None,
+        },
+        BindGroupLayoutEntry {
+            binding: 3,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        },
+        BindGroupLayoutEntry {
+            binding: 4,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Texture {
+                sample_type: TextureSampleType::Float { filterable: false },
+                view_dimension: TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        },
+    ]
+}
+
 /// The histogram-equalized WBOIT accumulation pipeline.
 ///
-/// Group layout: 0=View, 1=Mesh, 2=HistogramData, 3=StandardMaterial
+/// Group layout: 0=View, 1=Mesh, 2=StandardMaterial, 3=HistogramData
+///
+/// The group 3 entries must match the `@group(3)` declarations in `histo_fragment.wgsl`; the
+/// `layouts_match_shader_declarations` test compares the two.
 #[derive(Resource, Clone)]
 pub struct HistogramWboitPipeline {
     pub mesh_pipeline: MeshPipeline,
-    /// StandardMaterial bind group layout, inserted at group 3.
+    /// StandardMaterial bind group layout, inserted at group 2.
     pub material_layout: BindGroupLayout,
-    /// Histogram data bind group layout (histogram buf, cdf tex, sampler, params, prev_revealage), group 2.
+    /// Histogram data bind group layout (histogram buf, cdf tex, sampler, params,
+    /// prev_revealage), group 3.
     pub histo_data_layout_obj: BindGroupLayout,
     pub fragment_shader: Handle<Shader>,
     /// Whether the device supports bindless resources for StandardMaterial.
@@ -40,60 +101,8 @@ impl FromWorld for HistogramWboitPipeline {
         let bindless = material_uses_bindless_resources::<StandardMaterial>(render_device);
         let mesh_pipeline = world.resource::<MeshPipeline>().clone();
 
-        // Histogram data bind group layout (group 2 in fragment shader).
-        let histo_data_entries = vec![
-            BindGroupLayoutEntry {
-                binding: 0,
-                visibility: ShaderStages::FRAGMENT,
-                ty: BindingType::Buffer {
-                    ty: BufferBindingType::Storage { read_only: false },
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            },
-            BindGroupLayoutEntry {
-                binding: 1,
-                visibility: ShaderStages::FRAGMENT,
-                ty: BindingType::Texture {
-                    sample_type: TextureSampleType::Float { filterable: true },
-                    view_dimension: TextureViewDimension::D3,
-                    multisampled: false,
-                },
-                count: None,
-            },
-            BindGroupLayoutEntry {
-                binding: 2,
-                visibility: ShaderStages::FRAGMENT,
-                ty: BindingType::Sampler(SamplerBindingType::Filtering),
-                count: None,
-            },
-            BindGroupLayoutEntry {
-                binding: 3,
-                visibility: ShaderStages::FRAGMENT,
-                ty: BindingType::Buffer {
-                    ty: BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            },
-            BindGroupLayoutEntry {
-                binding: 4,
-                visibility: ShaderStages::FRAGMENT,
-                ty: BindingType::Texture {
-                    sample_type: TextureSampleType::Float { filterable: false },
-                    view_dimension: TextureViewDimension::D2,
-                    multisampled: false,
-                },
-                count: None,
-            },
-        ];
-
-        let histo_data_layout_obj = render_device.create_bind_group_layout(
-            "histo_data_bind_group_layout",
-            &histo_data_entries,
-        );
+        let histo_data_layout_obj = render_device
+            .create_bind_group_layout("histo_data_bind_group_layout", &histo_data_layout_entries());
 
         HistogramWboitPipeline {
             mesh_pipeline,
@@ -140,7 +149,8 @@ impl SpecializedMeshPipeline for HistogramWboitPipeline {
         desc.layout.insert(2, self.material_layout.clone());
         desc.layout.push(self.histo_data_layout_obj.clone());
 
-        // Override fragment shader.
+        // Override fragment shader, keeping the mesh-derived shader defs (`VERTEX_COLORS`, ...)
+        // as the naive pipeline does.
         if let Some(ref mut fragment) = desc.fragment {
             fragment.shader = self.fragment_shader.clone();
         }
@@ -165,7 +175,7 @@ impl SpecializedMeshPipeline for HistogramWboitPipeline {
                     write_mask: ColorWrites::ALL,
                 }),
                 Some(ColorTargetState {
-                    format: TextureFormat::R8Unorm,
+                    format: WBOIT_REVEALAGE_FORMAT,
                     blend: Some(BlendState {
                         color: BlendComponent {
                             src_factor: BlendFactor::Zero,
@@ -184,6 +194,7 @@ impl SpecializedMeshPipeline for HistogramWboitPipeline {
         }
 
         if let Some(ref mut ds) = desc.depth_stencil {
+            ds.format = WBOIT_DEPTH_FORMAT;
             ds.depth_write_enabled = false;
         }
 
@@ -191,6 +202,43 @@ impl SpecializedMeshPipeline for HistogramWboitPipeline {
     }
 }
 
+/// CDF build bind group layout entries (group 0 in `histo_cdf_build.wgsl`). The CDF build only
+/// reads the histogram; clearing is done by `HistoClearNode`.
+fn cdf_build_layout_entries() -> Vec<BindGroupLayoutEntry> {
+    vec![
+        BindGroupLayoutEntry {
+            binding: 0,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        },
+        BindGroupLayoutEntry {
+            binding: 1,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::StorageTexture {
+                access: StorageTextureAccess::WriteOnly,
+                format: TextureFormat::Rgba16Float,
+                view_dimension: TextureViewDimension::D3,
+            },
+            count: None,
+        },
+        BindGroupLayoutEntry {
+            binding: 2,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        },
+    ]
+}
+
 /// Resource holding the CDF build compute pipeline.
 #[derive(Resource)]
 pub struct CdfBuildPipeline {
@@ -203,43 +251,8 @@ impl FromWorld for CdfBuildPipeline {
         let render_device = world.resource::<RenderDevice>();
         let pipeline_cache = world.resource::<PipelineCache>();
 
-        let cdf_build_entries = vec![
-            BindGroupLayoutEntry {
-                binding: 0,
-                visibility: ShaderStages::COMPUTE,
-                ty: BindingType::Buffer {
-                    ty: BufferBindingType::Storage { read_only: false },
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            },
-            BindGroupLayoutEntry {
-                binding: 1,
-                visibility: ShaderStages::COMPUTE,
-                ty: BindingType::StorageTexture {
-                    access: StorageTextureAccess::WriteOnly,
-                    format: TextureFormat::Rgba16Float,
-                    view_dimension: TextureViewDimension::D3,
-                },
-                count: None,
-            },
-            BindGroupLayoutEntry {
-                binding: 2,
-                visibility: ShaderStages::COMPUTE,
-                ty: BindingType::Buffer {
-                    ty: BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            },
-        ];
-
-        let cdf_build_layout = render_device.create_bind_group_layout(
-            "cdf_build_bind_group_layout",
-            &cdf_build_entries,
-        );
+        let cdf_build_layout = render_device
+            .create_bind_group_layout("cdf_build_bind_group_layout", &cdf_build_layout_entries());
 
         let pipeline_id = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
             label: Some("histo_cdf_build_pipeline".into()),
@@ -258,11 +271,27 @@ impl FromWorld for CdfBuildPipeline {
     }
 }
 
-/// Check that MSAA is off for cameras with HEWboitSettings.
-pub fn check_msaa_he_wboit(cameras: Query<&Msaa, With<crate::settings::HEWboitSettings>>) {
-    for msaa in &cameras {
+/// Force MSAA off for cameras with HEWboitSettings, warning once per camera.
+///
+/// See `check_msaa_wboit`; cameras that lose their `HEWboitSettings` are forgotten the same way.
+pub fn check_msaa_he_wboit(
+    mut cameras: Query<(Entity, &mut Msaa), With<crate::settings::HEWboitSettings>>,
+    mut removed: RemovedComponents<crate::settings::HEWboitSettings>,
+    mut warned: Local<HashSet<Entity>>,
+) {
+    for entity in removed.read() {
+        warned.remove(&entity);
+    }
+    for (entity, mut msaa) in &mut cameras {
         if *msaa != Msaa::Off {
-            panic!("HE-WBOIT requires Msaa::Off. Set Msaa::Off on cameras with HEWboitSettings.");
+            if warned.insert(entity) {
+                warn!(
+                    "HE-WBOIT requires Msaa::Off, but camera {entity} has {:?}; disabling MSAA. \
+                     Set Msaa::Off on cameras with HEWboitSettings to silence this warning.",
+                    *msaa
+                );
+            }
+            *msaa = Msaa::Off;
         }
     }
 }
@@ -280,3 +309,220 @@ pub fn configure_depth_texture_usages_he_wboit(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::render::RenderApp;
+    use bevy::render::camera::CameraProjection;
+    use naga::{AddressSpace, ImageClass, ImageDimension, StorageAccess, TypeInner};
+
+    use crate::test_utils::{gpu_app, run_compute};
+
+    /// `@group(group)` declarations of `source` as layout binding types, by binding.
+    ///
+    /// Only the declarations before the first function are parsed, without the `#import`
+    /// directives: the functions use Bevy imports that naga cannot resolve on its own, while
+    /// the bindings and the types they use are self-contained.
+    fn declared_bindings(source: &str, group: u32) -> Vec<(u32, BindingType)> {
+        let mut declarations = String::new();
+        let mut import_depth = None;
+        for line in source.lines() {
+            if line.starts_with("fn ")
+                || line.starts_with("@fragment")
+                || line.starts_with("@compute")
+            {
+                break;
+            }
+            if line.starts_with("#import") {
+                import_depth = Some(0);
+            }
+            if let Some(depth) = import_depth.as_mut() {
+                *depth += line.matches('{').count() as i32 - line.matches('}').count() as i32;
+                if *depth == 0 {
+                    import_depth = None;
+                }
+                continue;
+            }
+            declarations.push_str(line);
+            declarations.push('\n');
+        }
+        let module = naga::front::wgsl::parse_str(&declarations).unwrap();
+
+        let mut bindings: Vec<_> = module
+            .global_variables
+            .iter()
+            .filter_map(|(_, var)| {
+                let binding = var
+                    .binding
+                    .as_ref()
+                    .filter(|binding| binding.group == group)?;
+                let ty = match (var.space, &module.types[var.ty].inner) {
+                    (AddressSpace::Uniform, _) => buffer(BufferBindingType::Uniform),
+                    (AddressSpace::Storage { access }, _) => buffer(BufferBindingType::Storage {
+                        read_only: !access.contains(StorageAccess::STORE),
+                    }),
+                    (_, TypeInner::Sampler { comparison: false }) => {
+                        BindingType::Sampler(SamplerBindingType::Filtering)
+                    }
+                    (
+                        _,
+                        TypeInner::Image {
+                            dim,
+                            arrayed: false,
+                            class,
+                        },
+                    ) => {
+                        let view_dimension = match dim {
+                            ImageDimension::D2 => TextureViewDimension::D2,
+                            ImageDimension::D3 => TextureViewDimension::D3,
+                            other => panic!("unexpected image dimension {other:?}"),
+                        };
+                        match class {
+                            ImageClass::Sampled { multi, .. } => BindingType::Texture {
+                                // Filterability is not declared in WGSL; see `same_binding`.
+                                sample_type: TextureSampleType::Float { filterable: true },
+                                view_dimension,
+                                multisampled: *multi,
+                            },
+                            ImageClass::Storage { format, .. } => BindingType::StorageTexture {
+                                access: StorageTextureAccess::WriteOnly,
+                                format: storage_format(*format),
+                                view_dimension,
+                            },
+                            other => panic!("unexpected image class {other:?}"),
+                        }
+                    }
+                    (space, other) => panic!("unexpected binding {space:?} {other:?}"),
+                };
+                Some((binding.binding, ty))
+            })
+            .collect();
+        bindings.sort_by_key(|(binding, _)| *binding);
+        bindings
+    }
+
+    fn buffer(ty: BufferBindingType) -> BindingType {
+        BindingType::Buffer {
+            ty,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        }
+    }
+
+    fn storage_format(format: naga::StorageFormat) -> TextureFormat {
+        match format {
+            naga::StorageFormat::Rgba16Float => TextureFormat::Rgba16Float,
+            naga::StorageFormat::R32Float => TextureFormat::R32Float,
+            naga::StorageFormat::R32Uint => TextureFormat::R32Uint,
+            other => panic!("unexpected storage format {other:?}"),
+        }
+    }
+
+    /// Whether the layout binding type `layout` can bind what the shader declares as `shader`.
+    fn same_binding(layout: &BindingType, shader: &BindingType) -> bool {
+        match (layout, shader) {
+            (
+                BindingType::Texture {
+                    sample_type: TextureSampleType::Float { .. },
+                    view_dimension,
+                    multisampled,
+                },
+                BindingType::Texture {
+                    sample_type: TextureSampleType::Float { .. },
+                    view_dimension: shader_dimension,
+                    multisampled: shader_multisampled,
+                },
+            ) => view_dimension == shader_dimension && multisampled == shader_multisampled,
+            _ => layout == shader,
+        }
+    }
+
+    fn assert_layout_matches(entries: &[BindGroupLayoutEntry], source: &str, group: u32) {
+        let declared = declared_bindings(source, group);
+        let layout: Vec<_> = entries
+            .iter()
+            .map(|entry| (entry.binding, entry.ty))
+            .collect();
+        assert_eq!(
+            layout
+                .iter()
+                .map(|(binding, _)| *binding)
+                .collect::<Vec<_>>(),
+            declared
+                .iter()
+                .map(|(binding, _)| *binding)
+                .collect::<Vec<_>>(),
+            "bindings of group {group}"
+        );
+        for ((binding, layout), (_, shader)) in layout.iter().zip(&declared) {
+            assert!(
+                same_binding(layout, shader),
+                "group {group} binding {binding}: layout has {layout:?}, shader declares {shader:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn layouts_match_shader_declarations() {
+        assert_layout_matches(
+            &histo_data_layout_entries(),
+            include_str!("../shaders/histo_fragment.wgsl"),
+            3,
+        );
+        assert_layout_matches(
+            &cdf_build_layout_entries(),
+            include_str!("../shaders/histo_cdf_build.wgsl"),
+            0,
+        );
+    }
+
+    /// Histogram bin `histo_fragment.wgsl` records a fragment at each of the view depths
+    /// `depths` in, for a default perspective camera, `max_depth` and `num_bins`.
+    fn depth_bins(world: &World, depths: &[f32], max_depth: f32, num_bins: u32) -> Vec<u32> {
+        let source = include_str!("../shaders/histo_fragment.wgsl");
+        let start = source.find("fn normalized_depth").unwrap();
+        let end = source.find("@fragment").unwrap();
+        // Bevy's projection-agnostic `depth_ndc_to_view_z`, through the inverse projection.
+        let projection = PerspectiveProjection::default();
+        let view_from_clip = projection.get_clip_from_view().inverse().to_cols_array();
+        let view_from_clip = view_from_clip.map(|x| format!("{x:?}")).join(", ");
+        let shader = format!(
+            "const VIEW_FROM_CLIP = mat4x4<f32>({view_from_clip});\n\
+             fn depth_ndc_to_view_z(ndc_depth: f32) -> f32 {{\n\
+             let view_pos = VIEW_FROM_CLIP * vec4(0.0, 0.0, ndc_depth, 1.0);\n\
+             return view_pos.z / view_pos.w;\n}}\n\
+             {}\n\
+             fn compute(v: vec4<f32>) -> vec4<f32> {{\n\
+             return vec4(f32(depth_bin(normalized_depth(v.x, v.y), u32(v.z))), 0.0, 0.0, 0.0);\n}}",
+            &source[start..end]
+        );
+        // Bevy's infinite reversed-Z projection: NDC depth is near / view depth.
+        let inputs: Vec<_> = depths
+            .iter()
+            .map(|depth| [projection.near / depth, max_depth, num_bins as f32, 0.0])
+            .collect();
+        run_compute(world, &shader, &inputs)
+            .into_iter()
+            .map(|out| out[0] as u32)
+            .collect()
+    }
+
+    #[test]
+    fn fragments_land_in_the_bin_of_their_view_depth() {
+        let Some(mut app) = gpu_app() else {
+            return;
+        };
+        app.finish();
+        app.cleanup();
+        let world = app.get_sub_app(RenderApp).unwrap().world();
+
+        // Four bins of 2.5 world units over a max_depth of 10; the near plane (NDC depth 1)
+        // lands in bin 0 and anything past max_depth in the last bin.
+        let near = PerspectiveProjection::default().near;
+        assert_eq!(
+            depth_bins(world, &[near, 2.4, 2.6, 5.1, 9.9, 100.0], 10.0, 4),
+            [0, 0, 1, 2, 3, 3]
+        );
+    }
+}
@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+
+use bevy::prelude::*;
+use bevy::render::extract_component::ExtractComponent;
+use bevy::render::render_resource::{Buffer, BufferDescriptor, BufferUsages, MapMode};
+use bevy::render::renderer::RenderDevice;
+use bevy::render::view::ExtractedView;
+
+use super::textures::HistogramWboitTextures;
+
+/// Opt-in HE-WBOIT debugging for a camera. Reads the optical-depth histogram back to the CPU
+/// every frame a previous read has finished, into [`HistogramReadback`] on the same camera.
+///
+/// Costs a buffer copy and a map per read; cameras without it pay nothing.
+#[derive(Component, Clone, Copy, Default, ExtractComponent, Reflect)]
+#[reflect(Component, Default)]
+pub struct HEWboitDebug;
+
+/// The last histogram read back for a camera with [`HEWboitDebug`], a few frames behind.
+///
+/// `bins` holds one `u32` per histogram and bin, histogram-major: `bins[(y * tile_count_x +
+/// x) * num_bins + bin]`. Each value is the summed optical depth `-ln(1 - alpha)` of the
+/// fragments that landed in that histogram's tiles and depth bin, quantized by 4096 (see
+/// `histo_fragment.wgsl`). The "tiles" here are histograms, which each cover
+/// `HEWboitSettings::histogram_downscale` screen tiles per axis. Histogram `(0, 0)` ends at
+/// the first cell edge past the target's corner, on the grid anchored at
+/// `HEWboitSettings::tile_origin`.
+#[derive(Component, Clone, Debug, Default, Reflect)]
+#[reflect(Component, Default)]
+pub struct HistogramReadback {
+    pub tile_count_x: u32,
+    pub tile_count_y: u32,
+    pub num_bins: u32,
+    pub bins: Vec<u32>,
+}
+
+impl HistogramReadback {
+    /// Value of one histogram and depth bin.
+    pub fn get(&self, tile_x: u32, tile_y: u32, bin: u32) -> u32 {
+        let index = (tile_y * self.tile_count_x + tile_x) * self.num_bins + bin;
+        self.bins[index as usize]
+    }
+}
+
+/// Channel from the render world readback callbacks back to the main world, keyed by
+/// main-world camera entity. Shared by both worlds.
+#[derive(Resource, Clone, Default)]
+pub struct HistogramReadbackSink(pub Arc<Mutex<HashMap<Entity, HistogramReadback>>>);
+
+/// `HistogramReadbackBuffer::state`: free for the next copy.
+const READBACK_IDLE: u8 = 0;
+/// `HistogramReadbackBuffer::state`: copy recorded this frame, waiting to be mapped.
+const READBACK_COPIED: u8 = 1;
+/// `HistogramReadbackBuffer::state`: map requested, waiting for the callback.
+const READBACK_MAPPING: u8 = 2;
+
+/// Per-camera staging buffer the histogram is copied into for [`HEWboitDebug`].
+#[derive(Component)]
+pub struct HistogramReadbackBuffer {
+    pub buffer: Buffer,
+    pub tile_count_x: u32,
+    pub tile_count_y: u32,
+    pub num_bins: u32,
+    state: Arc<AtomicU8>,
+}
+
+impl HistogramReadbackBuffer {
+    /// Whether the node may record a copy into the buffer this frame, i.e. it is not mapped
+    /// or waiting to be.
+    pub fn try_begin_copy(&self) -> bool {
+        self.state
+            .compare_exchange(
+                READBACK_IDLE,
+                READBACK_COPIED,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            )
+            .is_ok()
+    }
+}
+
+/// Create (or resize) the staging buffer of each camera with `HEWboitDebug`.
+pub fn prepare_histogram_readback_buffers(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    views: Query<
+        (
+            Entity,
+            &HistogramWboitTextures,
+            Option<&HistogramReadbackBuffer>,
+        ),
+        With<HEWboitDebug>,
+    >,
+) {
+    for (entity, histo, existing) in &views {
+        let up_to_date = existing.is_some_and(|readback| {
+            readback.tile_count_x == histo.histogram_count_x
+                && readback.tile_count_y == histo.histogram_count_y
+                && readback.num_bins == histo.num_bins
+        });
+        if up_to_date {
+            continue;
+        }
+        let buffer = render_device.create_buffer(&BufferDescriptor {
+            label: Some("histo_readback_buffer"),
+            size: histo.histogram_buffer.size(),
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        commands.entity(entity).insert(HistogramReadbackBuffer {
+            buffer,
+            tile_count_x: histo.histogram_count_x,
+            tile_count_y: histo.histogram_count_y,
+            num_bins: histo.num_bins,
+            state: Arc::new(AtomicU8::new(READBACK_IDLE)),
+        });
+    }
+}
+
+/// Map the staging buffers copied this frame. Runs after the render graph was submitted; the
+/// callback publishes the histogram to [`HistogramReadbackSink`] and frees the buffer.
+pub fn map_histogram_readback_buffers(
+    sink: Res<HistogramReadbackSink>,
+    views: Query<(&ExtractedView, &HistogramReadbackBuffer)>,
+) {
+    for (view, readback) in &views {
+        if readback
+            .state
+            .compare_exchange(
+                READBACK_COPIED,
+                READBACK_MAPPING,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            )
+            .is_err()
+        {
+            continue;
+        }
+        let camera = view.retained_view_entity.main_entity.id();
+        let buffer = readback.buffer.clone();
+        let state = readback.state.clone();
+        let sink = sink.0.clone();
+        let (tile_count_x, tile_count_y, num_bins) = (
+            readback.tile_count_x,
+            readback.tile_count_y,
+            readback.num_bins,
+        );
+        readback
+            .buffer
+            .slice(..)
+            .map_async(MapMode::Read, move |result| {
+                if result.is_ok() {
+                    let bins = {
+                        let data = buffer.slice(..).get_mapped_range();
+                        data.chunks_exact(4)
+                            .map(|bytes| {
+                                u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+                            })
+                            .collect()
+                    };
+                    buffer.unmap();
+                    if let Ok(mut map) = sink.lock() {
+                        map.insert(
+                            camera,
+                            HistogramReadback {
+                                tile_count_x,
+                                tile_count_y,
+                                num_bins,
+                                bins,
+                            },
+                        );
+                    }
+                }
+                state.store(READBACK_IDLE, Ordering::Release);
+            });
+    }
+}
+
+/// Copy the histograms read back by the render world onto the main-world cameras.
+pub fn sync_histogram_readback(mut commands: Commands, sink: Res<HistogramReadbackSink>) {
+    let Ok(mut map) = sink.0.lock() else {
+        return;
+    };
+    for (camera, readback) in map.drain() {
+        if let Ok(mut entity) = commands.get_entity(camera) {
+            entity.try_insert(readback);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::render::RenderApp;
+    use bevy::render::renderer::RenderQueue;
+
+    use crate::test_utils::{extracted_view, gpu_app};
+
+    /// Record `fragments` fragments of coverage `alpha` into the `num_bins` bins of a
+    /// histogram buffer, round-robin and 64 at a time, through `histo_fragment.wgsl`'s
+    /// `record_optical_depth`.
+    fn overdraw_histogram(world: &World, num_bins: u32, fragments: u32, alpha: f32) -> Buffer {
+        let source = include_str!("../shaders/histo_fragment.wgsl");
+        let start = source.find("fn record_optical_depth").unwrap();
+        let end = source.find("fn normalized_depth").unwrap();
+        let shader = format!(
+            "const OD_SCALE: f32 = 4096.0;\n\
+             @group(0) @binding(0) var<storage, read_write> histogram: array<atomic<u32>>;\n\
+             {}\n\
+             @compute @workgroup_size(64)\n\
+             fn main(@builtin(global_invocation_id) id: vec3<u32>) {{\n\
+             record_optical_depth(id.x % {num_bins}u, {alpha:?});\n}}",
+            &source[start..end]
+        );
+
+        let render_device = world.resource::<RenderDevice>();
+        let device = render_device.wgpu_device();
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(shader.into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: None,
+            layout: None,
+            module: &module,
+            entry_point: Some("main"),
+            compilation_options: default(),
+            cache: None,
+        });
+        let histogram = render_device.create_buffer(&BufferDescriptor {
+            label: None,
+            size: num_bins as u64 * 4,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: histogram.as_entire_binding(),
+            }],
+        });
+        let mut encoder = device.create_command_encoder(&default());
+        {
+            let mut pass = encoder.begin_compute_pass(&default());
+            pass.set_pipeline(&pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(fragments / 64, 1, 1);
+        }
+        world.resource::<RenderQueue>().submit([encoder.finish()]);
+        histogram
+    }
+
+    #[test]
+    fn histogram_total_matches_the_fragments_under_heavy_overdraw() {
+        let Some(mut app) = gpu_app() else {
+            return;
+        };
+        app.finish();
+        app.cleanup();
+        let camera = app.world_mut().spawn_empty().id();
+        let sink = HistogramReadbackSink::default();
+        app.insert_resource(sink.clone());
+
+        // 2x2 histograms of 4 bins, each bin hit by 4096 fragments in flight together.
+        let (tile_count_x, tile_count_y, num_bins) = (2, 2, 4);
+        let bins = tile_count_x * tile_count_y * num_bins;
+        let fragments = bins * 4096;
+        let alpha = 0.5;
+
+        let render_world = app.get_sub_app_mut(RenderApp).unwrap().world_mut();
+        render_world.insert_resource(sink);
+        let histogram = overdraw_histogram(render_world, bins, fragments, alpha);
+        let readback = HistogramReadbackBuffer {
+            buffer: render_world
+                .resource::<RenderDevice>()
+                .create_buffer(&BufferDescriptor {
+                    label: None,
+                    size: histogram.size(),
+                    usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                }),
+            tile_count_x,
+            tile_count_y,
+            num_bins,
+            state: Arc::new(AtomicU8::new(READBACK_IDLE)),
+        };
+
+        // What the CDF build node and the render world do with a `HEWboitDebug` camera.
+        assert!(readback.try_begin_copy());
+        let mut encoder = render_world
+            .resource::<RenderDevice>()
+            .create_command_encoder(&default());
+        encoder.copy_buffer_to_buffer(&histogram, 0, &readback.buffer, 0, histogram.size());
+        render_world
+            .resource::<RenderQueue>()
+            .submit([encoder.finish()]);
+        render_world.spawn((extracted_view(camera, Transform::IDENTITY), readback));
+        render_world
+            .run_system_once(map_histogram_readback_buffers)
+            .unwrap();
+        render_world
+            .resource::<RenderDevice>()
+            .wgpu_device()
+            .poll(wgpu::Maintain::Wait);
+        app.world_mut()
+            .run_system_once(sync_histogram_readback)
+            .unwrap();
+
+        let readback = app.world().get::<HistogramReadback>(camera).unwrap();
+        assert_eq!(readback.bins.len(), bins as usize);
+        let quantized_od = (-(1.0 - alpha).ln() * 4096.0) as u64;
+        let total: u64 = readback.bins.iter().map(|&bin| bin as u64).sum();
+        assert_eq!(total, fragments as u64 * quantized_od);
+        assert_eq!(
+            readback.get(1, 1, num_bins - 1),
+            (fragments / bins) * quantized_od as u32
+        );
+    }
+}
@@ -1,16 +1,20 @@
 use bevy::prelude::*;
 use bevy::render::camera::ExtractedCamera;
+use bevy::render::render_resource::FilterMode;
 use bevy::render::render_resource::{
     Buffer, BufferDescriptor, BufferInitDescriptor, BufferUsages, Extent3d, Sampler,
     SamplerDescriptor, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
     TextureView, TextureViewDescriptor,
 };
-use bevy::render::render_resource::FilterMode;
 use bevy::render::renderer::{RenderDevice, RenderQueue};
+use bevy::render::settings::WgpuLimits;
 use bevy::render::texture::TextureCache;
 
-use crate::settings::HEWboitSettings;
-use crate::textures::WboitTextures;
+use super::depth_range::HEWboitAutoDepth;
+use crate::settings::{HEWboitDepthMode, HEWboitSettings};
+use crate::textures::{
+    WBOIT_REVEALAGE_FORMAT, WboitTextures, WboitTexturesRecreated, texture_bytes,
+};
 
 /// GPU-side histogram parameters (must match HistogramParams in WGSL shaders).
 #[repr(C)]
@@ -21,17 +25,39 @@ pub struct HistogramParams {
     pub num_bins: u32,
     pub tile_size: u32,
     pub max_depth: f32,
-    pub _padding: [u32; 3],
+    pub equalization_strength: f32,
+    /// Non-zero while the camera is warming up (see `HEWboitSettings::warmup_frames`): the
+    /// accum pass weights by plain depth instead of the previous frame's CDF and revealage.
+    pub fallback_weighting: u32,
+    /// `HEWboitSettings::histogram_downscale`: tiles per histogram along each axis.
+    pub histogram_downscale: u32,
+    pub histogram_count_x: u32,
+    pub histogram_count_y: u32,
+    /// `HEWboitSettings::tile_origin`, reduced modulo the histogram cell size
+    /// (`tile_size * histogram_downscale`).
+    pub tile_origin: [u32; 2],
+    /// `HEWboitSettings::absorption_color`, read by the composite.
+    pub absorption_color: [f32; 4],
 }
 
 impl HistogramParams {
-    fn as_bytes(&self) -> [u8; 32] {
-        let mut bytes = [0u8; 32];
+    fn as_bytes(&self) -> [u8; 64] {
+        let mut bytes = [0u8; 64];
         bytes[0..4].copy_from_slice(&self.tile_count_x.to_le_bytes());
         bytes[4..8].copy_from_slice(&self.tile_count_y.to_le_bytes());
         bytes[8..12].copy_from_slice(&self.num_bins.to_le_bytes());
         bytes[12..16].copy_from_slice(&self.tile_size.to_le_bytes());
         bytes[16..20].copy_from_slice(&self.max_depth.to_le_bytes());
+        bytes[20..24].copy_from_slice(&self.equalization_strength.to_le_bytes());
+        bytes[24..28].copy_from_slice(&self.fallback_weighting.to_le_bytes());
+        bytes[28..32].copy_from_slice(&self.histogram_downscale.to_le_bytes());
+        bytes[32..36].copy_from_slice(&self.histogram_count_x.to_le_bytes());
+        bytes[36..40].copy_from_slice(&self.histogram_count_y.to_le_bytes());
+        bytes[40..44].copy_from_slice(&self.tile_origin[0].to_le_bytes());
+        bytes[44..48].copy_from_slice(&self.tile_origin[1].to_le_bytes());
+        for (i, channel) in self.absorption_color.iter().enumerate() {
+            bytes[48 + i * 4..52 + i * 4].copy_from_slice(&channel.to_le_bytes());
+        }
         bytes
     }
 }
@@ -39,7 +65,8 @@ impl HistogramParams {
 /// Per-camera HE-WBOIT textures and buffers in the render world.
 #[derive(Component)]
 pub struct HistogramWboitTextures {
-    /// Storage buffer for histogram data: tile_count_x * tile_count_y * num_bins u32 values.
+    /// Storage buffer for histogram data: histogram_count_x * histogram_count_y * num_bins u32
+    /// values.
     pub histogram_buffer: Buffer,
     /// 3D CDF texture (tile_count_x, tile_count_y, num_bins), Rgba16Float.
     pub cdf_texture: bevy::render::render_resource::Texture,
@@ -51,9 +78,69 @@ pub struct HistogramWboitTextures {
     pub histo_params_buffer: Buffer,
     pub tile_count_x: u32,
     pub tile_count_y: u32,
+    /// Histograms per axis, one per `HEWboitSettings::histogram_downscale` tiles.
+    pub histogram_count_x: u32,
+    pub histogram_count_y: u32,
     pub num_bins: u32,
 }
 
+/// Pixels the tile grid anchored at `origin` extends before the render target's corner: the
+/// first histogram cell starts that far left of (and above) it, so its edges fall on `origin`.
+fn grid_shift(origin: UVec2, cell_size: u32) -> UVec2 {
+    (UVec2::splat(cell_size) - origin % cell_size) % cell_size
+}
+
+/// Smallest tile size from `tile_size` up (doubling) whose resources fit the device at
+/// `target` pixels: the CDF is a 3D storage texture of (tiles x, tiles y, bins), bounded by
+/// `max_texture_dimension_3d` (wgpu has no separate dimension limit for storage textures), and
+/// built with one workgroup per tile; the histogram is bound as a storage buffer. A single
+/// tile always fits.
+fn fit_tile_size(
+    mut tile_size: u32,
+    target: UVec2,
+    origin: UVec2,
+    num_bins: u32,
+    histogram_downscale: u32,
+    limits: &WgpuLimits,
+) -> u32 {
+    let max_tiles = limits
+        .max_texture_dimension_3d
+        .min(limits.max_compute_workgroups_per_dimension);
+    loop {
+        let grid = target + grid_shift(origin, tile_size.saturating_mul(histogram_downscale));
+        let tiles = UVec2::new(grid.x.div_ceil(tile_size), grid.y.div_ceil(tile_size));
+        let histograms = UVec2::new(
+            tiles.x.div_ceil(histogram_downscale),
+            tiles.y.div_ceil(histogram_downscale),
+        );
+        let histogram_bytes =
+            u64::from(histograms.x) * u64::from(histograms.y) * u64::from(num_bins) * 4;
+        let fits = tiles.max_element() <= max_tiles
+            && histogram_bytes <= u64::from(limits.max_storage_buffer_binding_size);
+        if fits || tiles == UVec2::ONE {
+            return tile_size;
+        }
+        tile_size = tile_size.saturating_mul(2);
+    }
+}
+
+/// Frames rendered since the HE textures of a camera were (re)created, for
+/// `HEWboitSettings::warmup_frames`.
+#[derive(Component)]
+pub struct HistoWboitWarmup {
+    /// Viewport size the textures were created for; a resize restarts the warmup.
+    pub viewport_size: UVec2,
+    /// Frames since (re)creation, saturating.
+    pub frames: u32,
+}
+
+impl HistoWboitWarmup {
+    /// Whether the previous-frame data read by the accum pass is valid yet.
+    pub fn is_warm(&self, settings: &HEWboitSettings) -> bool {
+        self.frames >= settings.warmup_frames
+    }
+}
+
 /// Prepare (create/resize) HE-WBOIT textures for cameras with `HEWboitSettings`.
 ///
 /// Creates both `WboitTextures` (accum + revealage MRT) and `HistogramWboitTextures`
@@ -63,14 +150,28 @@ pub fn prepare_histogram_wboit_textures(
     render_device: Res<RenderDevice>,
     render_queue: Res<RenderQueue>,
     mut texture_cache: ResMut<TextureCache>,
-    cameras: Query<(Entity, &ExtractedCamera, &HEWboitSettings)>,
+    cameras: Query<(
+        Entity,
+        &ExtractedCamera,
+        &HEWboitSettings,
+        Option<&HEWboitAutoDepth>,
+    )>,
     mut existing_wboit: Query<&mut WboitTextures>,
     mut existing_histo: Query<&mut HistogramWboitTextures>,
+    mut warmups: Query<&mut HistoWboitWarmup>,
+    mut recreated: EventWriter<WboitTexturesRecreated>,
 ) {
-    for (entity, camera, he_settings) in &cameras {
-        let Some(size) = camera.physical_viewport_size else {
+    let limits = render_device.limits();
+    for (entity, camera, he_settings, auto_depth) in &cameras {
+        // Whole render target, so the accum pass can share the opaque depth attachment and
+        // draw at a sub-viewport's offset.
+        let Some(size) = camera.physical_target_size else {
             continue;
         };
+        // Zero-sized textures are invalid; nothing is drawn into such a target anyway.
+        if size.x == 0 || size.y == 0 {
+            continue;
+        }
         let width = size.x;
         let height = size.y;
 
@@ -105,7 +206,7 @@ pub fn prepare_histogram_wboit_textures(
                 mip_level_count: 1,
                 sample_count: 1,
                 dimension: TextureDimension::D2,
-                format: TextureFormat::R8Unorm,
+                format: WBOIT_REVEALAGE_FORMAT,
                 usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
                 view_formats: &[],
             },
@@ -123,60 +224,131 @@ pub fn prepare_histogram_wboit_textures(
                 mip_level_count: 1,
                 sample_count: 1,
                 dimension: TextureDimension::D2,
-                format: TextureFormat::R8Unorm,
+                format: WBOIT_REVEALAGE_FORMAT,
                 usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
                 view_formats: &[],
             },
         );
 
+        let wboit_bytes =
+            texture_bytes(&accum) + texture_bytes(&revealage_a) + texture_bytes(&revealage_b);
+
         // Toggle frame_index or initialize
+        let mut wboit_recreated = true;
         let new_frame_index = if let Ok(mut tex) = existing_wboit.get_mut(entity) {
+            wboit_recreated = tex.accum.texture.size() != accum.texture.size();
             let fi = 1 - tex.frame_index;
             tex.accum = accum;
             tex.revealage = [revealage_a, revealage_b];
             tex.frame_index = fi;
+            tex.persistent = false;
             fi
         } else {
             commands.entity(entity).insert(WboitTextures {
                 accum,
                 revealage: [revealage_a, revealage_b],
                 frame_index: 0,
+                glow: None,
+                overdraw: None,
+                normal: None,
+                nearest_depth: None,
+                shared_with: None,
+                persistent: false,
             });
             0
         };
 
         // --- HistogramWboitTextures ---
-        let tile_size = he_settings.tile_size;
-        let num_bins = he_settings.num_bins;
-        let tile_count_x = width.div_ceil(tile_size);
-        let tile_count_y = height.div_ceil(tile_size);
+        // A target smaller than one tile still gets a single 1x1 tile grid. The CDF build
+        // kernel runs one 64-thread workgroup per tile, so there are at most 64 bins.
+        let num_bins = he_settings.num_bins.clamp(1, 64);
+        let histogram_downscale = he_settings.histogram_downscale.max(1);
+        let requested_tile_size = he_settings.tile_size.max(1);
+        let tile_origin = he_settings.tile_origin.unwrap_or_else(|| {
+            camera
+                .viewport
+                .as_ref()
+                .map(|viewport| viewport.physical_position)
+                .unwrap_or_default()
+        });
+        let tile_size = fit_tile_size(
+            requested_tile_size,
+            size,
+            tile_origin,
+            num_bins,
+            histogram_downscale,
+            &limits,
+        );
+        if tile_size != requested_tile_size {
+            warn_once!(
+                "HEWboitSettings::tile_size {} needs more tiles than this device supports at \
+                 {width}x{height}; using {tile_size}",
+                he_settings.tile_size
+            );
+        }
+        // The grid starts up to one histogram cell before the target's corner, so that its
+        // edges fall on the tile origin.
+        let cell_size = tile_size.saturating_mul(histogram_downscale);
+        let grid_size = size + grid_shift(tile_origin, cell_size);
+        let tile_count_x = grid_size.x.div_ceil(tile_size);
+        let tile_count_y = grid_size.y.div_ceil(tile_size);
+        let histogram_count_x = tile_count_x.div_ceil(histogram_downscale);
+        let histogram_count_y = tile_count_y.div_ceil(histogram_downscale);
 
-        let params = HistogramParams {
+        let mut params = HistogramParams {
             tile_count_x,
             tile_count_y,
             num_bins,
             tile_size,
-            max_depth: he_settings.max_depth,
-            _padding: [0; 3],
+            max_depth: match (he_settings.depth_mode, auto_depth) {
+                (HEWboitDepthMode::Auto, Some(auto_depth)) => auto_depth.0,
+                _ => he_settings.max_depth,
+            },
+            equalization_strength: he_settings.equalization_strength.clamp(0.0, 1.0),
+            fallback_weighting: 0,
+            histogram_downscale,
+            histogram_count_x,
+            histogram_count_y,
+            tile_origin: (tile_origin % cell_size).to_array(),
+            absorption_color: he_settings.absorption_color.to_f32_array(),
         };
 
         // Check if we need to recreate (size or params changed)
         let needs_recreate = if let Ok(histo) = existing_histo.get(entity) {
             histo.tile_count_x != tile_count_x
                 || histo.tile_count_y != tile_count_y
+                || histo.histogram_count_x != histogram_count_x
+                || histo.histogram_count_y != histogram_count_y
                 || histo.num_bins != num_bins
         } else {
             true
         };
 
+        let warm = match warmups.get_mut(entity) {
+            Ok(mut warmup) if !needs_recreate && warmup.viewport_size == size => {
+                warmup.frames = warmup.frames.saturating_add(1);
+                warmup.is_warm(he_settings)
+            }
+            _ => {
+                commands.entity(entity).insert(HistoWboitWarmup {
+                    viewport_size: size,
+                    frames: 0,
+                });
+                he_settings.warmup_frames == 0
+            }
+        };
+        params.fallback_weighting = u32::from(!warm);
+
         if needs_recreate {
-            // Histogram storage buffer: tile_count_x * tile_count_y * num_bins * 4 bytes (u32 per bin).
-            // Initialized to zero; the CDF build shader clears it after each frame.
-            let histogram_size = (tile_count_x * tile_count_y * num_bins * 4) as u64;
+            // Histogram storage buffer: histogram_count_x * histogram_count_y * num_bins * 4
+            // bytes (u32 per bin). The histogram clear pass zeroes it before each accum pass.
+            let histogram_size = (histogram_count_x * histogram_count_y * num_bins * 4) as u64;
             let histogram_buffer = render_device.create_buffer(&BufferDescriptor {
                 label: Some("histo_histogram_buffer"),
                 size: histogram_size,
-                usage: BufferUsages::STORAGE,
+                // COPY_DST for the `clear_buffer` in `HistoClearNode`, COPY_SRC for the
+                // `HEWboitDebug` readback.
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
                 mapped_at_creation: false,
             });
 
@@ -215,6 +387,12 @@ pub fn prepare_histogram_wboit_textures(
                 ..default()
             });
 
+            let cdf_bytes = u64::from(tile_count_x * tile_count_y * num_bins) * 8;
+            debug!(
+                "HE-WBOIT resources for {entity} created at {width}x{height}: {} bytes",
+                wboit_bytes + histogram_size + cdf_bytes + params.as_bytes().len() as u64
+            );
+
             let new_histo = HistogramWboitTextures {
                 histogram_buffer,
                 cdf_texture,
@@ -223,6 +401,8 @@ pub fn prepare_histogram_wboit_textures(
                 histo_params_buffer,
                 tile_count_x,
                 tile_count_y,
+                histogram_count_x,
+                histogram_count_y,
                 num_bins,
             };
 
@@ -234,12 +414,199 @@ pub fn prepare_histogram_wboit_textures(
                 commands.entity(entity).insert(new_histo);
             }
         } else {
-            // Same dimensions — just update the params buffer in case tile_size changed.
+            // Same dimensions — just update the params buffer in case max_depth, the
+            // equalization strength or the warmup fallback changed.
             if let Ok(histo) = existing_histo.get(entity) {
                 render_queue.write_buffer(&histo.histo_params_buffer, 0, &params.as_bytes());
             }
         }
 
         let _ = new_frame_index; // used above
+
+        if wboit_recreated || needs_recreate {
+            recreated.write(WboitTexturesRecreated {
+                camera: entity,
+                size,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::render::RenderApp;
+    use bevy::render::camera::Viewport;
+
+    use crate::test_utils::{extracted_camera, gpu_app};
+
+    /// Run `prepare_histogram_wboit_textures` for one camera with `camera` and `settings`, then
+    /// check `f` against the textures it got. Skipped without a GPU adapter.
+    fn with_prepared(
+        camera: ExtractedCamera,
+        settings: HEWboitSettings,
+        f: impl FnOnce(&WboitTextures, &HistogramWboitTextures),
+    ) {
+        let Some(mut app) = gpu_app() else {
+            return;
+        };
+        app.add_plugins(crate::HEWboitPlugin);
+        app.finish();
+        app.cleanup();
+        let world = app.get_sub_app_mut(RenderApp).unwrap().world_mut();
+
+        let camera = world.spawn((camera, settings)).id();
+        world
+            .run_system_once(prepare_histogram_wboit_textures)
+            .unwrap();
+        let camera = world.entity(camera);
+        f(
+            camera.get::<WboitTextures>().unwrap(),
+            camera.get::<HistogramWboitTextures>().unwrap(),
+        );
+    }
+
+    #[test]
+    fn histogram_downscale_sizes_agree_from_settings_to_shaders() {
+        // 10x7 tiles of 32 pixels, in 4x3 histograms of up to 3x3 tiles.
+        let size = UVec2::new(300, 200);
+        let settings = HEWboitSettings {
+            histogram_downscale: 3,
+            num_bins: 16,
+            ..default()
+        };
+        with_prepared(extracted_camera(size), settings, |wboit, histo| {
+            assert_eq!((histo.tile_count_x, histo.tile_count_y), (10, 7));
+            assert_eq!((histo.histogram_count_x, histo.histogram_count_y), (4, 3));
+            assert_eq!(histo.histogram_buffer.size(), 4 * 3 * 16 * 4);
+            assert_eq!(
+                histo.cdf_texture.size(),
+                Extent3d {
+                    width: 10,
+                    height: 7,
+                    depth_or_array_layers: 16,
+                }
+            );
+            // The last pixel lands in the last histogram, as `histo_fragment.wgsl` finds it.
+            let cell_size = 32 * 3;
+            let last_cell = (size - 1 + grid_shift(UVec2::ZERO, cell_size)) / cell_size;
+            assert_eq!(last_cell, UVec2::new(3, 2));
+
+            let cdf_bytes = 10 * 7 * 16 * 8;
+            assert_eq!(
+                settings.estimated_memory(size),
+                wboit.allocated_bytes() + histo.histogram_buffer.size() + cdf_bytes + 64
+            );
+        });
+    }
+
+    #[test]
+    fn grid_shift_puts_a_cell_edge_on_the_tile_origin() {
+        for origin in [
+            UVec2::ZERO,
+            UVec2::new(50, 30),
+            UVec2::new(96, 97),
+            UVec2::new(1000, 5),
+        ] {
+            let shift = grid_shift(origin, 96);
+            assert!(shift.max_element() < 96, "{origin}");
+            assert_eq!((origin + shift) % 96, UVec2::ZERO, "{origin}");
+        }
+    }
+
+    #[test]
+    fn tile_grid_is_anchored_at_the_viewport_origin() {
+        // 8x4 tiles of 32 pixels when anchored at the target's corner.
+        let size = UVec2::new(256, 128);
+        let offset = |tile_origin| {
+            let mut camera = extracted_camera(size);
+            camera.viewport = Some(Viewport {
+                physical_position: UVec2::new(16, 8),
+                physical_size: UVec2::new(128, 64),
+                ..default()
+            });
+            (
+                camera,
+                HEWboitSettings {
+                    tile_origin,
+                    ..default()
+                },
+            )
+        };
+
+        // Shifted by 16x24 pixels, the grid needs another column and row of tiles.
+        let (camera, settings) = offset(None);
+        with_prepared(camera, settings, |_, histo| {
+            assert_eq!((histo.tile_count_x, histo.tile_count_y), (9, 5));
+        });
+        let (camera, settings) = offset(Some(UVec2::ZERO));
+        with_prepared(camera, settings, |_, histo| {
+            assert_eq!((histo.tile_count_x, histo.tile_count_y), (8, 4));
+        });
+    }
+
+    /// Limits generous everywhere but in `max_texture_dimension_3d` and
+    /// `max_storage_buffer_binding_size`.
+    fn small_limits(
+        max_texture_dimension_3d: u32,
+        max_storage_buffer_binding_size: u32,
+    ) -> WgpuLimits {
+        WgpuLimits {
+            max_texture_dimension_3d,
+            max_storage_buffer_binding_size,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn fit_tile_size_keeps_a_tile_size_that_fits() {
+        // 4x4 tiles of 16 pixels, 16 bins: 1 KiB of histograms.
+        let limits = small_limits(8, 1024);
+        assert_eq!(
+            fit_tile_size(16, UVec2::splat(64), UVec2::ZERO, 16, 1, &limits),
+            16
+        );
+    }
+
+    #[test]
+    fn fit_tile_size_doubles_until_the_cdf_fits_the_3d_texture_limit() {
+        // 16 tiles a side at 16 pixels, 8 at 32.
+        let limits = small_limits(8, u32::MAX);
+        assert_eq!(
+            fit_tile_size(16, UVec2::splat(256), UVec2::ZERO, 16, 1, &limits),
+            32
+        );
+        // Anchored at x = 1, the grid starts 31 pixels early and needs a ninth column at 32
+        // pixels; at 64 it starts 63 pixels early and needs 5.
+        assert_eq!(
+            fit_tile_size(32, UVec2::splat(256), UVec2::new(1, 0), 16, 1, &limits),
+            64
+        );
+    }
+
+    #[test]
+    fn fit_tile_size_doubles_until_the_histograms_fit_the_storage_buffer_limit() {
+        // 16 bins of 4 bytes: 32x32 tiles at 8 pixels take 64 KiB, 16x16 at 16 take 16 KiB and
+        // 8x8 at 32 take the 4 KiB allowed.
+        let limits = small_limits(u32::MAX, 4096);
+        assert_eq!(
+            fit_tile_size(8, UVec2::splat(256), UVec2::ZERO, 16, 1, &limits),
+            32
+        );
+        // Histograms at a quarter of the tile resolution (downscale 2) fit at 16 pixels.
+        assert_eq!(
+            fit_tile_size(8, UVec2::splat(256), UVec2::ZERO, 16, 2, &limits),
+            16
+        );
+    }
+
+    #[test]
+    fn fit_tile_size_falls_back_to_a_single_tile_when_nothing_fits() {
+        let limits = small_limits(0, 0);
+        assert_eq!(
+            fit_tile_size(16, UVec2::new(256, 100), UVec2::ZERO, 16, 1, &limits),
+            256
+        );
     }
 }
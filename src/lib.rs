@@ -1,25 +1,160 @@
 #![allow(clippy::too_many_arguments, clippy::type_complexity)]
 
+pub mod diagnostics;
+pub mod exclude;
+pub mod graph;
 pub mod histogram;
+pub mod material;
+pub mod minimal;
 pub mod naive;
 pub mod phase;
 pub mod pipeline;
 pub mod queue;
 pub mod settings;
+pub mod shadow;
 pub mod textures;
 
+#[cfg(test)]
+pub(crate) mod test_utils;
+
 use bevy::prelude::*;
 
+pub use diagnostics::{
+    WboitCameraMode, WboitCameras, WboitDrainStats, WboitMaterialWarning, WboitShaderDump,
+    WboitStatus, wboit_material_warnings,
+};
+pub use exclude::WboitExcludeMaterialPlugin;
+pub use graph::WboitPostComposite;
 pub use histogram::HEWboitPlugin;
-pub use naive::NaiveWboitPlugin;
-pub use settings::{HEWboitSettings, WboitSettings};
+pub use histogram::depth_range::HEWboitAutoDepth;
+pub use histogram::readback::{HEWboitDebug, HistogramReadback};
+pub use material::{WboitAppExt, WboitMaterial, WboitMaterialPlugin};
+pub use minimal::{WboitMinimal, WboitMinimalPlugin};
+pub use naive::composite::WboitCompositeShader;
+pub use naive::probe::{WboitPixelProbe, WboitPixelProbed};
+pub use naive::{NaiveWboitPlugin, WboitInvalidatePipelines};
+pub use queue::{WboitPrewarmMeshes, WboitSortFn};
+pub use settings::{
+    HEWboitDepthMode, HEWboitSettings, InheritWboitDefaults, WboitAdaptiveDegraded,
+    WboitAdaptiveQuality, WboitAlwaysVisible, WboitCompositeMask, WboitCompositeTonemap,
+    WboitDebug, WboitDefaults, WboitDepthOffset, WboitGroup, WboitGroups, WboitInstanceOpacity,
+    WboitLayerConfig, WboitMode, WboitSettings, WboitSharedTarget, WboitTaaMode,
+    WboitWeightOverride,
+};
+pub use shadow::{WboitShadowExtension, WboitShadowMaterial, WboitShadowMaterialPlugin};
+pub use textures::WboitTexturesRecreated;
 
 /// Convenience plugin that enables naive WBOIT.
 /// Add `WboitSettings` to a camera entity to opt in.
+///
+/// Adds [`NaiveWboitPlugin`] unless it is already added. Like every WBOIT plugin, it can be
+/// added more than once and in any order with the others; only the first instance does
+/// anything.
 pub struct WboitPlugin;
 
 impl Plugin for WboitPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(NaiveWboitPlugin);
+        if !app.is_plugin_added::<NaiveWboitPlugin>() {
+            app.add_plugins(NaiveWboitPlugin);
+        }
+    }
+
+    fn is_unique(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::core_pipeline::core_3d::graph::{Core3d, Node3d};
+    use bevy::render::RenderApp;
+    use bevy::render::render_graph::{EmptyNode, RenderGraph};
+
+    /// An app with a render sub-app holding just the `Core3d` nodes WBOIT orders itself
+    /// against, so plugins can be built without a GPU.
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Shader>();
+        let mut core_3d = RenderGraph::default();
+        for label in [
+            Node3d::StartMainPass,
+            Node3d::MainOpaquePass,
+            Node3d::MainTransparentPass,
+            Node3d::EndMainPass,
+            Node3d::Bloom,
+        ] {
+            core_3d.add_node(label, EmptyNode);
+        }
+        let mut graph = RenderGraph::default();
+        graph.add_sub_graph(Core3d, core_3d);
+        let mut render_app = SubApp::new();
+        render_app.insert_resource(graph);
+        app.insert_sub_app(RenderApp, render_app);
+        app
+    }
+
+    /// Every edge of the `Core3d` graph, as `(from, to)` node names.
+    fn core_3d_edges(app: &mut App) -> Vec<(String, String)> {
+        let render_app = app.get_sub_app(RenderApp).unwrap();
+        let graph = render_app.world().resource::<RenderGraph>();
+        let mut edges: Vec<_> = graph
+            .get_sub_graph(Core3d)
+            .unwrap()
+            .iter_nodes()
+            .flat_map(|node| node.edges.output_edges())
+            .map(|edge| {
+                let from = format!("{:?}", edge.get_output_node());
+                let to = format!("{:?}", edge.get_input_node());
+                (from, to)
+            })
+            .collect();
+        edges.sort();
+        edges
+    }
+
+    fn assert_registered_once(edges: &[(String, String)], reference: &[(String, String)]) {
+        for window in edges.windows(2) {
+            assert_ne!(window[0], window[1], "edge registered twice");
+        }
+        assert_eq!(edges, reference);
+    }
+
+    #[test]
+    fn plugins_register_their_graph_once_in_any_combination() {
+        let naive = core_3d_edges(app().add_plugins(NaiveWboitPlugin));
+        let both = core_3d_edges(app().add_plugins((NaiveWboitPlugin, HEWboitPlugin)));
+        assert!(
+            naive
+                .iter()
+                .any(|(from, _)| from.contains("WboitAccumPass"))
+        );
+        assert!(
+            both.iter()
+                .any(|(from, _)| from.contains("HistoWboitAccumPass"))
+        );
+
+        assert_registered_once(&core_3d_edges(app().add_plugins(WboitPlugin)), &naive);
+        assert_registered_once(
+            &core_3d_edges(app().add_plugins((NaiveWboitPlugin, WboitPlugin))),
+            &naive,
+        );
+        assert_registered_once(
+            &core_3d_edges(app().add_plugins((WboitPlugin, NaiveWboitPlugin))),
+            &naive,
+        );
+        assert_registered_once(
+            &core_3d_edges(app().add_plugins((WboitPlugin, WboitPlugin, NaiveWboitPlugin))),
+            &naive,
+        );
+        assert_registered_once(
+            &core_3d_edges(app().add_plugins((WboitMinimalPlugin, WboitPlugin))),
+            &naive,
+        );
+        assert_registered_once(
+            &core_3d_edges(app().add_plugins((HEWboitPlugin, WboitPlugin, HEWboitPlugin))),
+            &both,
+        );
     }
 }
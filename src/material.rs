@@ -0,0 +1,178 @@
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+use bevy::pbr::{Material, queue_material_meshes};
+use bevy::prelude::*;
+use bevy::render::mesh::MeshVertexBufferLayoutRef;
+use bevy::render::render_phase::AddRenderCommand;
+use bevy::render::render_resource::{
+    RenderPipelineDescriptor, ShaderRef, SpecializedMeshPipelineError, SpecializedMeshPipelines,
+};
+use bevy::render::renderer::RenderDevice;
+use bevy::render::{Render, RenderApp, RenderSet};
+
+use crate::exclude::WboitExcludeMaterialPlugin;
+use crate::naive::{
+    WboitPipelinesInvalidated, invalidate_wboit_pipelines, reset_wboit_on_device_change,
+};
+use crate::phase::{WboitAccum3d, WboitNearestDepth3d, add_odd_group_render_command};
+use crate::pipeline::{WboitPipeline, WboitPipelineKey};
+use crate::queue::{
+    DrawWboit, DrawWboitNearestDepth, QueueWboitMeshes, prewarm_wboit_pipelines, queue_wboit_meshes,
+};
+
+/// A `Material` that can be drawn by the naive WBOIT accum pass.
+///
+/// The WBOIT pipeline binds the material at group 2 exactly like `MaterialPipeline` does, but
+/// replaces the fragment shader with one that writes the accum and revealage targets, and does
+/// not call `Material::specialize`; see [`WboitMaterial::specialize_wboit`].
+///
+/// Bevy's `Wireframe` overlay has its own render phase and never goes through WBOIT. For
+/// transparent wireframes, use a `LineList` mesh (its topology is kept by the accum pipeline)
+/// or set the polygon mode in `specialize_wboit`.
+pub trait WboitMaterial: Material {
+    /// Fragment shader used in the WBOIT accum pass. [`ShaderRef::Default`] uses the built-in
+    /// `wboit_fragment.wgsl`, which evaluates `pbr_input_from_standard_material` and therefore
+    /// only suits materials with `StandardMaterial`'s bind group layout. Other materials should
+    /// return a shader with the same outputs and group 3 bindings as `wboit_fragment.wgsl`.
+    fn wboit_fragment_shader() -> ShaderRef {
+        ShaderRef::Default
+    }
+
+    /// Adjust the WBOIT accum pipeline after it has been built, in place of
+    /// `Material::specialize` (which needs a `MaterialPipeline`). For example, set
+    /// `descriptor.primitive.polygon_mode = PolygonMode::Line` for wireframe transparents
+    /// (requires `WgpuFeatures::POLYGON_MODE_LINE`). Keep the color targets as they are.
+    fn specialize_wboit(
+        descriptor: &mut RenderPipelineDescriptor,
+        layout: &MeshVertexBufferLayoutRef,
+        key: WboitPipelineKey,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        let _ = (descriptor, layout, key);
+        Ok(())
+    }
+}
+
+impl WboitMaterial for StandardMaterial {}
+
+/// Feeds transparent meshes with material `M` into the naive WBOIT accum phase.
+///
+/// `NaiveWboitPlugin` adds this for `StandardMaterial`. Use
+/// [`WboitAppExt::register_wboit_material`] for other materials; all registered materials
+/// share one accum phase and one composite per camera.
+pub struct WboitMaterialPlugin<M: WboitMaterial>(PhantomData<M>);
+
+impl<M: WboitMaterial> Default for WboitMaterialPlugin<M> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<M: WboitMaterial> Plugin for WboitMaterialPlugin<M>
+where
+    M::Data: PartialEq + Eq + Hash + Clone,
+{
+    fn build(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        add_odd_group_render_command::<DrawWboit<M>>(render_app);
+        render_app
+            .init_resource::<SpecializedMeshPipelines<WboitPipeline<M>>>()
+            .add_render_command::<WboitAccum3d, DrawWboit<M>>()
+            .add_render_command::<WboitNearestDepth3d, DrawWboitNearestDepth<M>>()
+            .add_systems(
+                Render,
+                (
+                    reset_wboit_material_on_device_change::<M>
+                        .in_set(RenderSet::ManageViews)
+                        .after(reset_wboit_on_device_change),
+                    invalidate_wboit_material_pipelines::<M>
+                        .in_set(RenderSet::ManageViews)
+                        .after(invalidate_wboit_pipelines),
+                    queue_wboit_meshes::<M>
+                        .in_set(RenderSet::QueueMeshes)
+                        .in_set(QueueWboitMeshes)
+                        .after(queue_material_meshes::<M>),
+                    prewarm_wboit_pipelines::<M>.in_set(RenderSet::QueueMeshes),
+                ),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.init_resource::<WboitPipeline<M>>();
+    }
+}
+
+/// Re-create `WboitPipeline<M>` after `RenderDevice` is replaced. Ordered after
+/// `reset_wboit_on_device_change`, which rebuilds the shared accum data layout first.
+fn reset_wboit_material_on_device_change<M: WboitMaterial>(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+) {
+    if !render_device.is_changed() || render_device.is_added() {
+        return;
+    }
+    commands.queue(|world: &mut World| {
+        let pipeline = WboitPipeline::<M>::from_world(world);
+        world.insert_resource(pipeline);
+        world.insert_resource(SpecializedMeshPipelines::<WboitPipeline<M>>::default());
+    });
+}
+
+/// Drop the specialized `WboitPipeline<M>` variants on `WboitInvalidatePipelines`, so the
+/// queue specializes (and compiles) them again.
+fn invalidate_wboit_material_pipelines<M: WboitMaterial>(
+    invalidated: Res<WboitPipelinesInvalidated>,
+    mut pipelines: ResMut<SpecializedMeshPipelines<WboitPipeline<M>>>,
+) {
+    if invalidated.0 {
+        *pipelines = SpecializedMeshPipelines::default();
+    }
+}
+
+/// `App` extension for registering additional WBOIT materials.
+pub trait WboitAppExt {
+    /// Draw transparent meshes with material `M` through naive WBOIT on `WboitSettings`
+    /// cameras, alongside `StandardMaterial`.
+    ///
+    /// Without this, transparent items of other materials are still drained from the
+    /// transparent pass on WBOIT cameras, and show up as `WboitDrainStats::cleared` without a
+    /// matching `queued`.
+    fn register_wboit_material<M: WboitMaterial>(&mut self) -> &mut Self
+    where
+        M::Data: PartialEq + Eq + Hash + Clone;
+
+    /// Keep transparent meshes with material `M` out of WBOIT entirely: they are neither
+    /// drained nor accumulated, and are drawn sorted on top of the WBOIT composite. See
+    /// [`WboitExcludeMaterialPlugin`].
+    fn exclude_material_from_wboit<M: Material>(&mut self) -> &mut Self
+    where
+        M::Data: PartialEq + Eq + Hash + Clone;
+}
+
+impl WboitAppExt for App {
+    fn register_wboit_material<M: WboitMaterial>(&mut self) -> &mut Self
+    where
+        M::Data: PartialEq + Eq + Hash + Clone,
+    {
+        if !self.is_plugin_added::<WboitMaterialPlugin<M>>() {
+            self.add_plugins(WboitMaterialPlugin::<M>::default());
+        }
+        self
+    }
+
+    fn exclude_material_from_wboit<M: Material>(&mut self) -> &mut Self
+    where
+        M::Data: PartialEq + Eq + Hash + Clone,
+    {
+        if !self.is_plugin_added::<WboitExcludeMaterialPlugin<M>>() {
+            self.add_plugins(WboitExcludeMaterialPlugin::<M>::default());
+        }
+        self
+    }
+}
@@ -0,0 +1,312 @@
+use bevy::asset::{load_internal_asset, weak_handle};
+use bevy::ecs::entity::Entities;
+use bevy::pbr::{
+    DrawMesh, MeshPipeline, RenderMeshInstances, SetMeshBindGroup, SetMeshViewBindGroup,
+    ViewKeyCache,
+};
+use bevy::prelude::*;
+use bevy::render::Extract;
+use bevy::render::mesh::{MeshTag, MeshVertexBufferLayoutRef, RenderMesh};
+use bevy::render::render_asset::RenderAssets;
+use bevy::render::render_phase::{
+    AddRenderCommand, DrawFunctions, PhaseItemExtraIndex, SetItemPipeline, ViewSortedRenderPhases,
+};
+use bevy::render::render_resource::{
+    BindGroupLayout, PipelineCache, RenderPipelineDescriptor, Shader, SpecializedMeshPipeline,
+    SpecializedMeshPipelineError, SpecializedMeshPipelines,
+};
+use bevy::render::renderer::RenderDevice;
+use bevy::render::sync_world::{MainEntity, MainEntityHashSet};
+use bevy::render::view::{ExtractedView, RenderVisibleEntities};
+use bevy::render::{ExtractSchedule, Render, RenderApp, RenderSet};
+
+use crate::naive::{
+    NaiveWboitPlugin, WboitPipelinesInvalidated, invalidate_wboit_pipelines,
+    reset_wboit_on_device_change,
+};
+use crate::phase::{WboitAccum3d, WboitOddGroup, add_odd_group_render_command};
+use crate::pipeline::{WboitAccumDataLayout, WboitPipelineKey, specialize_wboit_accum_targets};
+use crate::queue::{
+    QueueWboitMeshes, SetWboitAccumBindGroup, WboitAlwaysVisibleEntities, WboitGroupEntities,
+    WboitSortFn, is_beyond_max_distance, resolve_queued_mesh,
+};
+use crate::settings::{WboitGroups, WboitSettings, WboitWeightOverride};
+
+pub const WBOIT_MINIMAL_SHADER_HANDLE: Handle<Shader> =
+    weak_handle!("8c1d2e3f-4a5b-4c6d-9e7f-0a1b2c3d4e5f");
+
+/// Draws this mesh through naive WBOIT without any material: its color is the mesh's vertex
+/// color (`Mesh::ATTRIBUTE_COLOR`, white without one) times `tint`, unlit. Requires
+/// [`WboitMinimalPlugin`]; the entity needs a `Mesh3d` but no `MeshMaterial3d`.
+///
+/// The tint is passed per instance through the entity's `MeshTag`, as linear RGBA with 8 bits
+/// per channel, so a `MeshTag` set by hand is overwritten. Thickness absorption, soft
+/// particles, grazing correction and the animated weight do not apply.
+#[derive(Component, Clone, Copy, Debug, Reflect)]
+#[reflect(Component, Default)]
+#[require(MeshTag)]
+pub struct WboitMinimal {
+    pub tint: Color,
+}
+
+impl Default for WboitMinimal {
+    fn default() -> Self {
+        Self { tint: Color::WHITE }
+    }
+}
+
+/// Material-less naive WBOIT for [`WboitMinimal`] meshes: vertex colored, unlit transparents
+/// (debug visualization, gizmo-like overlays) that never touch the material system, so no
+/// material bind group layout, bindless support or group index juggling is involved.
+///
+/// Shares the accum phase, targets and composite of `NaiveWboitPlugin` (added if missing) and
+/// draws on every `WboitSettings` camera. Only the first instance is built; adding it again
+/// does nothing.
+pub struct WboitMinimalPlugin;
+
+impl Plugin for WboitMinimalPlugin {
+    fn build(&self, app: &mut App) {
+        if app.is_plugin_added::<Self>() {
+            return;
+        }
+        load_internal_asset!(
+            app,
+            WBOIT_MINIMAL_SHADER_HANDLE,
+            "shaders/wboit_minimal.wgsl",
+            Shader::from_wgsl
+        );
+        if !app.is_plugin_added::<NaiveWboitPlugin>() {
+            app.add_plugins(NaiveWboitPlugin);
+        }
+        app.register_type::<WboitMinimal>()
+            .add_systems(PostUpdate, sync_wboit_minimal_tint);
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        add_odd_group_render_command::<DrawWboitMinimal>(render_app);
+        render_app
+            .init_resource::<WboitMinimalEntities>()
+            .init_resource::<SpecializedMeshPipelines<WboitMinimalPipeline>>()
+            .add_render_command::<WboitAccum3d, DrawWboitMinimal>()
+            .add_systems(ExtractSchedule, extract_wboit_minimal_entities)
+            .add_systems(
+                Render,
+                (
+                    reset_wboit_minimal_on_device_change
+                        .in_set(RenderSet::ManageViews)
+                        .after(reset_wboit_on_device_change),
+                    invalidate_wboit_minimal_pipelines
+                        .in_set(RenderSet::ManageViews)
+                        .after(invalidate_wboit_pipelines),
+                    queue_wboit_minimal_meshes
+                        .in_set(RenderSet::QueueMeshes)
+                        .in_set(QueueWboitMeshes),
+                ),
+            );
+    }
+
+    fn is_unique(&self) -> bool {
+        false
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.init_resource::<WboitMinimalPipeline>();
+    }
+}
+
+/// Re-create `WboitMinimalPipeline` after `RenderDevice` is replaced, like
+/// `WboitPipeline<M>` in `WboitMaterialPlugin`.
+fn reset_wboit_minimal_on_device_change(mut commands: Commands, render_device: Res<RenderDevice>) {
+    if !render_device.is_changed() || render_device.is_added() {
+        return;
+    }
+    commands.queue(|world: &mut World| {
+        let pipeline = WboitMinimalPipeline::from_world(world);
+        world.insert_resource(pipeline);
+        world.insert_resource(SpecializedMeshPipelines::<WboitMinimalPipeline>::default());
+    });
+}
+
+/// Drop the specialized `WboitMinimalPipeline` variants on `WboitInvalidatePipelines`, like
+/// `WboitPipeline<M>` in `WboitMaterialPlugin`.
+fn invalidate_wboit_minimal_pipelines(
+    invalidated: Res<WboitPipelinesInvalidated>,
+    mut pipelines: ResMut<SpecializedMeshPipelines<WboitMinimalPipeline>>,
+) {
+    if invalidated.0 {
+        *pipelines = SpecializedMeshPipelines::default();
+    }
+}
+
+/// Pack the tint of changed [`WboitMinimal`] entities into their `MeshTag`.
+fn sync_wboit_minimal_tint(
+    mut minimal: Query<(&WboitMinimal, &mut MeshTag), Changed<WboitMinimal>>,
+) {
+    for (minimal, mut tag) in &mut minimal {
+        tag.0 = u32::from_le_bytes(LinearRgba::from(minimal.tint).to_u8_array());
+    }
+}
+
+/// Main-world entities with `WboitMinimal`, extracted each frame.
+#[derive(Resource, Default)]
+pub struct WboitMinimalEntities(pub MainEntityHashSet);
+
+fn extract_wboit_minimal_entities(
+    mut minimal: ResMut<WboitMinimalEntities>,
+    entities: Extract<Query<Entity, (With<WboitMinimal>, With<Mesh3d>)>>,
+) {
+    minimal.0.clear();
+    minimal.0.extend(entities.iter().map(MainEntity::from));
+}
+
+/// Draw command for [`WboitMinimal`] meshes: no material group, accum data at group 2.
+pub type DrawWboitMinimal = (
+    SetItemPipeline,
+    SetMeshViewBindGroup<0>,
+    SetMeshBindGroup<1>,
+    SetWboitAccumBindGroup<2>,
+    DrawMesh,
+);
+
+/// The accum pipeline for [`WboitMinimal`] meshes.
+///
+/// Group layout: 0=View, 1=Mesh, 2=WboitAccumData
+#[derive(Resource, Clone)]
+pub struct WboitMinimalPipeline {
+    pub mesh_pipeline: MeshPipeline,
+    pub accum_data_layout: BindGroupLayout,
+}
+
+impl FromWorld for WboitMinimalPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let accum_data_layout = world
+            .get_resource_or_init::<WboitAccumDataLayout>()
+            .0
+            .clone();
+        WboitMinimalPipeline {
+            mesh_pipeline: world.resource::<MeshPipeline>().clone(),
+            accum_data_layout,
+        }
+    }
+}
+
+impl SpecializedMeshPipeline for WboitMinimalPipeline {
+    type Key = WboitPipelineKey;
+
+    fn specialize(
+        &self,
+        key: Self::Key,
+        layout: &MeshVertexBufferLayoutRef,
+    ) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError> {
+        let mut desc = self.mesh_pipeline.specialize(key.mesh_key, layout)?;
+        desc.label = Some("wboit_minimal_accum_pipeline".into());
+        desc.layout.push(self.accum_data_layout.clone());
+        if let Some(ref mut fragment) = desc.fragment {
+            fragment.shader = WBOIT_MINIMAL_SHADER_HANDLE;
+        }
+        specialize_wboit_accum_targets(&mut desc, key);
+        Ok(desc)
+    }
+}
+
+/// Queue the visible [`WboitMinimal`] meshes of each naive WBOIT view into its accum phase.
+///
+/// They have no `Transparent3d` item, so they are taken from the view's visible meshes and
+/// show up in `WboitDrainStats::queued` without a matching `cleared`.
+pub fn queue_wboit_minimal_meshes(
+    render_meshes: Res<RenderAssets<RenderMesh>>,
+    render_mesh_instances: Res<RenderMeshInstances>,
+    minimal_pipeline: Option<Res<WboitMinimalPipeline>>,
+    mut pipelines: ResMut<SpecializedMeshPipelines<WboitMinimalPipeline>>,
+    pipeline_cache: Res<PipelineCache>,
+    draw_functions: Res<DrawFunctions<WboitAccum3d>>,
+    sort_fn: Option<Res<WboitSortFn>>,
+    minimal: Res<WboitMinimalEntities>,
+    always_visible: Res<WboitAlwaysVisibleEntities>,
+    group_entities: Res<WboitGroupEntities>,
+    mut wboit_phases: ResMut<ViewSortedRenderPhases<WboitAccum3d>>,
+    views: Query<(
+        &ExtractedView,
+        &RenderVisibleEntities,
+        &WboitSettings,
+        Option<&WboitWeightOverride>,
+        Option<&WboitGroups>,
+    )>,
+    view_key_cache: Res<ViewKeyCache>,
+    entities: &Entities,
+) {
+    let Some(minimal_pipeline) = minimal_pipeline else {
+        return;
+    };
+    if minimal.0.is_empty() {
+        return;
+    }
+    let draw_functions = draw_functions.read();
+    let draw_minimal = [
+        draw_functions.id::<DrawWboitMinimal>(),
+        draw_functions.id::<WboitOddGroup<DrawWboitMinimal>>(),
+    ];
+    let sort_fn = sort_fn.as_deref().cloned().unwrap_or_default();
+
+    for (view, visible_entities, settings, weight_override, groups) in &views {
+        let (Some(wboit_phase), Some(view_key)) = (
+            wboit_phases.get_mut(&view.retained_view_entity),
+            view_key_cache.get(&view.retained_view_entity),
+        ) else {
+            continue;
+        };
+        let rangefinder = view.rangefinder3d();
+
+        for &(render_entity, main_entity) in visible_entities.iter::<Mesh3d>() {
+            if !minimal.0.contains(&main_entity) {
+                continue;
+            }
+            let Some(group_rank) = group_entities.rank(groups, main_entity) else {
+                continue;
+            };
+            let Some(mesh_instance) = resolve_queued_mesh(
+                entities,
+                &render_mesh_instances,
+                (render_entity, main_entity),
+            ) else {
+                continue;
+            };
+            if is_beyond_max_distance(settings.max_distance, view, mesh_instance.translation) {
+                continue;
+            }
+            let Some(mesh) = render_meshes.get(mesh_instance.mesh_asset_id) else {
+                continue;
+            };
+
+            let key = WboitPipelineKey::new(*view_key, mesh, settings);
+            let key = WboitPipelineKey {
+                always_visible: key.always_visible || always_visible.0.contains(&main_entity),
+                weight_override: weight_override.copied(),
+                ..key
+            };
+            let pipeline_id =
+                match pipelines.specialize(&pipeline_cache, &minimal_pipeline, key, &mesh.layout) {
+                    Ok(id) => id,
+                    Err(err) => {
+                        error!("WBOIT minimal pipeline specialization error: {err}");
+                        continue;
+                    }
+                };
+
+            let distance = rangefinder.distance_translation(&mesh_instance.translation);
+            wboit_phase.add(WboitAccum3d {
+                distance: sort_fn.distance(distance, mesh_instance.translation, view),
+                pipeline: pipeline_id,
+                entity: (render_entity, main_entity),
+                draw_function: draw_minimal[group_rank % 2],
+                batch_range: 0..1,
+                extra_index: PhaseItemExtraIndex::None,
+                indexed: mesh.indexed(),
+            });
+        }
+    }
+}
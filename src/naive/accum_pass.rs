@@ -1,3 +1,5 @@
+use core::ops::Range;
+
 use bevy::color::LinearRgba;
 use bevy::ecs::query::QueryItem;
 use bevy::prelude::*;
@@ -5,20 +7,114 @@ use bevy::render::camera::ExtractedCamera;
 use bevy::render::render_graph::{NodeRunError, RenderGraphContext, RenderLabel, ViewNode};
 use bevy::render::render_phase::ViewSortedRenderPhases;
 use bevy::render::render_resource::{
-    LoadOp, Operations, RenderPassColorAttachment, RenderPassDepthStencilAttachment,
-    RenderPassDescriptor, StoreOp,
+    BindGroup, BindGroupEntry, BindingResource, LoadOp, Operations, RenderPassColorAttachment,
+    RenderPassDepthStencilAttachment, RenderPassDescriptor, StoreOp,
 };
-use bevy::render::renderer::RenderContext;
+use bevy::render::renderer::{RenderContext, RenderDevice};
 use bevy::render::view::{ExtractedView, ViewDepthTexture};
 
-use crate::phase::WboitAccum3d;
-use crate::textures::WboitTextures;
+use crate::phase::{WboitAccum3d, WboitNearestDepth3d, render_phase_range};
+use crate::pipeline::{WboitAccumDataLayout, view_depth_matches};
+use crate::settings::{WboitSettings, WboitTaaMode};
+use crate::textures::{WboitParamsBuffer, WboitTextures};
+
+use super::groups::WboitGroupRanges;
+use super::probe::WboitPixelProbeBuffer;
+use super::shared::WboitSharedViews;
 
 /// Render graph label for the WBOIT accumulation pass.
 #[derive(RenderLabel, Debug, Clone, Hash, PartialEq, Eq)]
 pub struct WboitAccumPass;
 
+/// Render graph label for the WBOIT accumulation pass used with `WboitTaaMode::BeforeOpaque`,
+/// placed between `Node3d::StartMainPass` and `Node3d::MainOpaquePass`.
+#[derive(RenderLabel, Debug, Clone, Hash, PartialEq, Eq)]
+pub struct WboitBackgroundAccumPass;
+
+/// Per-camera accum data bind group (group 3): params uniform, opaque depth and nearest
+/// transparent depth.
+#[derive(Component)]
+pub struct WboitAccumBindGroup(pub BindGroup);
+
+/// Per-camera group 3 bind group of the nearest-depth prepass, which renders into the
+/// nearest-depth target and so can't sample it; the opaque depth takes its place.
+#[derive(Component)]
+pub struct WboitNearestDepthBindGroup(pub BindGroup);
+
+/// Prepare the accum data bind group for each WBOIT camera.
+pub fn prepare_wboit_accum_bind_group(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    accum_data_layout: Option<Res<WboitAccumDataLayout>>,
+    views: Query<
+        (
+            Entity,
+            &WboitParamsBuffer,
+            &ViewDepthTexture,
+            Option<&WboitTextures>,
+        ),
+        With<WboitSettings>,
+    >,
+) {
+    let Some(accum_data_layout) = accum_data_layout else {
+        return;
+    };
+    for (entity, params_buffer, depth, wboit_textures) in &views {
+        let nearest_depth = wboit_textures.and_then(|textures| textures.nearest_depth.as_ref());
+        // Without the nearest-depth target, binding 2 is only a placeholder.
+        let nearest_depth_view = nearest_depth.map_or(depth.view(), |tex| &tex.default_view);
+        let bind_group = render_device.create_bind_group(
+            "wboit_accum_bind_group",
+            &accum_data_layout.0,
+            &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: params_buffer.0.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(depth.view()),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::TextureView(nearest_depth_view),
+                },
+            ],
+        );
+
+        let mut entity_commands = commands.entity(entity);
+        entity_commands.insert(WboitAccumBindGroup(bind_group));
+        if nearest_depth.is_none() {
+            entity_commands.remove::<WboitNearestDepthBindGroup>();
+            continue;
+        }
+        let prepass_bind_group = render_device.create_bind_group(
+            "wboit_nearest_depth_bind_group",
+            &accum_data_layout.0,
+            &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: params_buffer.0.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(depth.view()),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::TextureView(depth.view()),
+                },
+            ],
+        );
+        entity_commands.insert(WboitNearestDepthBindGroup(prepass_bind_group));
+    }
+}
+
 /// Render graph node that renders the WBOIT accumulation pass into MRT textures.
+///
+/// Skips cameras with `WboitTaaMode::BeforeOpaque`; see `WboitBackgroundAccumNode`. Views
+/// without a `ViewDepthTexture` do not match the view query at all; `record_wboit_status`
+/// warns about those.
 #[derive(Default)]
 pub struct WboitAccumNode;
 
@@ -28,70 +124,490 @@ impl ViewNode for WboitAccumNode {
         &'static ExtractedView,
         &'static ViewDepthTexture,
         &'static WboitTextures,
+        &'static WboitSettings,
     );
 
     fn run<'w>(
         &self,
         graph: &mut RenderGraphContext,
         render_context: &mut RenderContext<'w>,
-        (camera, extracted_view, depth, wboit_textures): QueryItem<Self::ViewQuery>,
+        view_query: QueryItem<Self::ViewQuery>,
         world: &'w World,
     ) -> Result<(), NodeRunError> {
-        let wboit_phases = world.resource::<ViewSortedRenderPhases<WboitAccum3d>>();
-        let Some(wboit_phase) = wboit_phases.get(&extracted_view.retained_view_entity) else {
+        if view_query.4.taa_mode == WboitTaaMode::BeforeOpaque {
             return Ok(());
-        };
+        }
+        run_accum(graph, render_context, view_query, world);
+        Ok(())
+    }
+}
+
+/// Render graph node that renders the WBOIT accumulation pass before the opaque pass, for
+/// cameras with `WboitTaaMode::BeforeOpaque`.
+#[derive(Default)]
+pub struct WboitBackgroundAccumNode;
 
-        if wboit_phase.items.is_empty() {
+impl ViewNode for WboitBackgroundAccumNode {
+    type ViewQuery = <WboitAccumNode as ViewNode>::ViewQuery;
+
+    fn run<'w>(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext<'w>,
+        view_query: QueryItem<Self::ViewQuery>,
+        world: &'w World,
+    ) -> Result<(), NodeRunError> {
+        if view_query.4.taa_mode != WboitTaaMode::BeforeOpaque {
             return Ok(());
         }
+        run_accum(graph, render_context, view_query, world);
+        Ok(())
+    }
+}
+
+/// Draw the `WboitAccum3d` phase of the view into its accum targets. With `WboitGroups` (and
+/// `WboitTaaMode::BeforeTaa`) only the first group is drawn; `WboitGroupsNode` draws the rest.
+fn run_accum<'w>(
+    graph: &mut RenderGraphContext,
+    render_context: &mut RenderContext<'w>,
+    view_query: QueryItem<<WboitAccumNode as ViewNode>::ViewQuery>,
+    world: &'w World,
+) {
+    let (_, extracted_view, depth, wboit_textures, settings) = view_query;
+    let wboit_phases = world.resource::<ViewSortedRenderPhases<WboitAccum3d>>();
+    let Some(wboit_phase) = wboit_phases.get(&extracted_view.retained_view_entity) else {
+        return;
+    };
+
+    // Scaled targets never attach the view depth. The owner of a `WboitSharedTarget` clears
+    // the shared targets even with nothing to draw.
+    let view_entity = graph.view_entity();
+    let attaches_depth = !settings.is_accum_scaled();
+    let clears_shared = world.resource::<WboitSharedViews>().is_owner(view_entity);
+    if (wboit_phase.items.is_empty() && !clears_shared)
+        || (attaches_depth && !view_depth_matches(depth))
+    {
+        return;
+    }
+
+    let range = match world.get::<WboitGroupRanges>(view_entity) {
+        Some(ranges) if settings.taa_mode == WboitTaaMode::BeforeTaa => {
+            ranges.0.first().cloned().unwrap_or_default()
+        }
+        _ => 0..wboit_phase.items.len(),
+    };
+    render_nearest_depth(render_context, view_entity, view_query, world);
+    render_accum(render_context, view_entity, view_query, range, world);
+
+    if let Some(probe) = world.get::<WboitPixelProbeBuffer>(view_entity) {
+        probe.copy_texels(render_context, wboit_textures);
+    }
+}
 
-        let view_entity = graph.view_entity();
-        let fi = wboit_textures.frame_index;
+/// Draw the `WboitNearestDepth3d` phase of the view into its nearest-depth target, cleared to
+/// the far plane first (unless the target is shared with an earlier camera). Does nothing
+/// without `WboitSettings::nearest_depth_falloff`.
+fn render_nearest_depth<'w>(
+    render_context: &mut RenderContext<'w>,
+    view_entity: Entity,
+    (camera, extracted_view, _, wboit_textures, settings): QueryItem<
+        <WboitAccumNode as ViewNode>::ViewQuery,
+    >,
+    world: &'w World,
+) {
+    let Some(nearest_depth) = wboit_textures.nearest_depth.as_ref() else {
+        return;
+    };
+    let nearest_phases = world.resource::<ViewSortedRenderPhases<WboitNearestDepth3d>>();
+    let Some(nearest_phase) = nearest_phases.get(&extracted_view.retained_view_entity) else {
+        return;
+    };
 
-        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
-            label: Some("wboit_accum_pass"),
+    // Reverse-Z: 0 is the far plane, which the falloff treats as "no transparent surface".
+    let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+        label: Some("wboit_nearest_depth_pass"),
+        color_attachments: &[],
+        depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+            view: &nearest_depth.default_view,
+            depth_ops: Some(Operations {
+                load: if wboit_textures.shared_with.is_some() {
+                    LoadOp::Load
+                } else {
+                    LoadOp::Clear(0.0)
+                },
+                store: StoreOp::Store,
+            }),
+            stencil_ops: None,
+        }),
+        timestamp_writes: None,
+        occlusion_query_set: None,
+    });
+
+    // Same viewport handling as `render_accum`: the target has the accum targets' size.
+    if let (false, Some(viewport)) = (settings.is_accum_scaled(), camera.viewport.as_ref()) {
+        render_pass.set_camera_viewport(viewport);
+    }
+
+    render_phase_range(
+        nearest_phase,
+        &mut render_pass,
+        world,
+        view_entity,
+        0..nearest_phase.items.len(),
+        "WBOIT nearest depth phase",
+    );
+}
+
+/// Clear the accum targets of the view and draw `range` of its `WboitAccum3d` phase into
+/// them. The targets are cleared even when `range` is empty. Targets shared with an earlier
+/// camera through a `WboitSharedTarget` are loaded instead, to add to them.
+///
+/// The clear is the load op of the pass, so it does not depend on any item drawing: items whose
+/// pipeline is still compiling are skipped by `SetItemPipeline`, and if all of them are, the
+/// targets keep their neutral values (no accumulation, revealage 1) and the composite leaves the
+/// view target unchanged.
+pub(super) fn render_accum<'w>(
+    render_context: &mut RenderContext<'w>,
+    view_entity: Entity,
+    (camera, extracted_view, depth, wboit_textures, settings): QueryItem<
+        <WboitAccumNode as ViewNode>::ViewQuery,
+    >,
+    range: Range<usize>,
+    world: &'w World,
+) {
+    let wboit_phases = world.resource::<ViewSortedRenderPhases<WboitAccum3d>>();
+    let Some(wboit_phase) = wboit_phases.get(&extracted_view.retained_view_entity) else {
+        return;
+    };
+
+    let fi = wboit_textures.frame_index;
+    let scaled = settings.is_accum_scaled();
+    let load = |clear_value: LinearRgba| match wboit_textures.shared_with {
+        Some(_) => LoadOp::Load,
+        None => LoadOp::Clear(clear_value.into()),
+    };
+
+    let mut color_attachments = vec![
+        // Target 0: accumulation (Rgba16Float), clear to transparent
+        Some(RenderPassColorAttachment {
+            view: &wboit_textures.accum.default_view,
+            resolve_target: None,
+            ops: Operations {
+                load: load(LinearRgba::new(0.0, 0.0, 0.0, 0.0)),
+                store: StoreOp::Store,
+            },
+        }),
+        // Target 1: revealage (R8Unorm), clear to 1.0
+        Some(RenderPassColorAttachment {
+            view: &wboit_textures.revealage[fi].default_view,
+            resolve_target: None,
+            ops: Operations {
+                load: load(LinearRgba::new(1.0, 0.0, 0.0, 0.0)),
+                store: StoreOp::Store,
+            },
+        }),
+    ];
+    // Target 2: glow (Rgba16Float), clear to black
+    if let Some(glow) = wboit_textures.glow.as_ref() {
+        color_attachments.push(Some(RenderPassColorAttachment {
+            view: &glow.default_view,
+            resolve_target: None,
+            ops: Operations {
+                load: load(LinearRgba::new(0.0, 0.0, 0.0, 0.0)),
+                store: StoreOp::Store,
+            },
+        }));
+    }
+    // Target 3 (overdraw debug only): fragment count (R16Float), clear to 0
+    if let Some(overdraw) = wboit_textures.overdraw.as_ref() {
+        color_attachments.push(Some(RenderPassColorAttachment {
+            view: &overdraw.default_view,
+            resolve_target: None,
+            ops: Operations {
+                load: load(LinearRgba::new(0.0, 0.0, 0.0, 0.0)),
+                store: StoreOp::Store,
+            },
+        }));
+    }
+    // Next target (accumulate_normals only): weighted view-space normal xy (Rg16Float)
+    if let Some(normal) = wboit_textures.normal.as_ref() {
+        color_attachments.push(Some(RenderPassColorAttachment {
+            view: &normal.default_view,
+            resolve_target: None,
+            ops: Operations {
+                load: load(LinearRgba::new(0.0, 0.0, 0.0, 0.0)),
+                store: StoreOp::Store,
+            },
+        }));
+    }
+
+    let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+        label: Some("wboit_accum_pass"),
+        color_attachments: &color_attachments,
+        // Use existing depth from opaque pass as a read-only attachment, so the same
+        // texture can also be sampled by the accum fragment shader (group 3). Read-only
+        // also means the shared opaque depth used by later passes can never be modified
+        // here; a feature that writes transparent depth would need a private depth copy.
+        // Scaled accum targets can't share the full-res depth; the shader tests instead. Before
+        // the opaque pass (`WboitTaaMode::BeforeOpaque`) there is no opaque depth yet, and those
+        // pipelines pass every fragment.
+        depth_stencil_attachment: (!scaled).then(|| RenderPassDepthStencilAttachment {
+            view: depth.view(),
+            depth_ops: None,
+            stencil_ops: None,
+        }),
+        timestamp_writes: None,
+        occlusion_query_set: None,
+    });
+
+    // Scaled targets cover exactly the (scaled) viewport, so the default full-target
+    // viewport is already correct for them.
+    if let (false, Some(viewport)) = (scaled, camera.viewport.as_ref()) {
+        render_pass.set_camera_viewport(viewport);
+    }
+
+    render_phase_range(
+        wboit_phase,
+        &mut render_pass,
+        world,
+        view_entity,
+        range,
+        "WBOIT accum phase",
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::render::RenderApp;
+    use bevy::render::render_graph::{EmptyNode, NodeState, RenderGraph};
+    use bevy::render::render_phase::{
+        DrawFunctions, PhaseItemExtraIndex, RenderCommandState, SetItemPipeline,
+    };
+    use bevy::render::render_resource::{CachedRenderPipelineId, TextureFormat, TextureView};
+    use bevy::render::renderer::{RenderAdapterInfo, RenderQueue};
+
+    use crate::pipeline::WBOIT_DEPTH_FORMAT;
+    use crate::test_utils::{
+        extracted_camera, extracted_view, gpu_app, item_entity, read_depth, read_texture,
+        view_depth_texture,
+    };
+    use crate::textures::prepare_wboit_textures;
+
+    /// Target size, wide enough that the rows of both targets need no padding in buffer copies.
+    const SIZE: UVec2 = UVec2::new(256, 2);
+
+    /// Spawn a `SIZE` WBOIT camera into the render world of a `gpu_app` with `WboitPlugin`,
+    /// with a depth texture of `depth_format` cleared to 0.25, leftovers of an earlier frame in
+    /// its accum targets, and three items in its phase whose pipeline is not ready.
+    fn accum_view(world: &mut World, depth_format: TextureFormat) -> Entity {
+        let camera = world.spawn_empty().id();
+        world.entity_mut(camera).insert((
+            extracted_camera(SIZE),
+            extracted_view(camera, Transform::IDENTITY),
+            WboitSettings::default(),
+        ));
+        world.run_system_once(prepare_wboit_textures).unwrap();
+
+        let depth = view_depth_texture(world, SIZE, depth_format, 0.25);
+        world.entity_mut(camera).insert(depth);
+
+        let textures = world.get::<WboitTextures>(camera).unwrap();
+        let fi = textures.frame_index;
+        let render_device = world.resource::<RenderDevice>();
+        let mut encoder = render_device.create_command_encoder(&default());
+        fn leftover(view: &TextureView) -> Option<RenderPassColorAttachment<'_>> {
+            Some(RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(LinearRgba::new(0.3, 0.6, 0.9, 0.5).into()),
+                    store: StoreOp::Store,
+                },
+            })
+        }
+        encoder.begin_render_pass(&RenderPassDescriptor {
+            label: None,
             color_attachments: &[
-                // Target 0: accumulation (Rgba16Float), clear to transparent
-                Some(RenderPassColorAttachment {
-                    view: &wboit_textures.accum.default_view,
-                    resolve_target: None,
-                    ops: Operations {
-                        load: LoadOp::Clear(LinearRgba::new(0.0, 0.0, 0.0, 0.0).into()),
-                        store: StoreOp::Store,
-                    },
-                }),
-                // Target 1: revealage (R8Unorm), clear to 1.0
-                Some(RenderPassColorAttachment {
-                    view: &wboit_textures.revealage[fi].default_view,
-                    resolve_target: None,
-                    ops: Operations {
-                        load: LoadOp::Clear(LinearRgba::new(1.0, 0.0, 0.0, 0.0).into()),
-                        store: StoreOp::Store,
-                    },
-                }),
+                leftover(&textures.accum.default_view),
+                leftover(&textures.revealage[fi].default_view),
             ],
-            // Use existing depth from opaque pass (load, don't clear)
-            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
-                view: depth.view(),
-                depth_ops: Some(Operations {
-                    load: LoadOp::Load,
-                    store: StoreOp::Store,
-                }),
-                stencil_ops: None,
-            }),
+            depth_stencil_attachment: None,
             timestamp_writes: None,
             occlusion_query_set: None,
         });
+        world.resource::<RenderQueue>().submit([encoder.finish()]);
 
-        if let Some(viewport) = camera.viewport.as_ref() {
-            render_pass.set_camera_viewport(viewport);
+        // `SetItemPipeline` skips every one of these items.
+        let draw = RenderCommandState::<WboitAccum3d, SetItemPipeline>::new(world);
+        let draw_function = world
+            .resource::<DrawFunctions<WboitAccum3d>>()
+            .write()
+            .add(draw);
+        let retained_view = world
+            .get::<ExtractedView>(camera)
+            .unwrap()
+            .retained_view_entity;
+        let mut phases = world.resource_mut::<ViewSortedRenderPhases<WboitAccum3d>>();
+        phases.insert_or_clear(retained_view);
+        let phase = phases.get_mut(&retained_view).unwrap();
+        for index in 0..3 {
+            phase.add(WboitAccum3d {
+                distance: index as f32,
+                pipeline: CachedRenderPipelineId::INVALID,
+                entity: item_entity(index),
+                draw_function,
+                batch_range: 0..1,
+                extra_index: PhaseItemExtraIndex::None,
+                indexed: false,
+            });
         }
+        camera
+    }
 
-        if let Err(err) = wboit_phase.render(&mut render_pass, world, view_entity) {
-            error!("Error rendering WBOIT accum phase: {err:?}");
-        }
+    fn render_context(world: &World) -> RenderContext<'_> {
+        RenderContext::new(
+            world.resource::<RenderDevice>().clone(),
+            world.resource::<RenderAdapterInfo>().0.clone().into_inner(),
+            None,
+        )
+    }
 
-        Ok(())
+    fn view_query(
+        world: &World,
+        camera: Entity,
+    ) -> QueryItem<'_, <WboitAccumNode as ViewNode>::ViewQuery> {
+        let entity = world.entity(camera);
+        (
+            entity.get::<ExtractedCamera>().unwrap(),
+            entity.get::<ExtractedView>().unwrap(),
+            entity.get::<ViewDepthTexture>().unwrap(),
+            entity.get::<WboitTextures>().unwrap(),
+            entity.get::<WboitSettings>().unwrap(),
+        )
+    }
+
+    #[test]
+    fn accum_targets_are_cleared_when_no_item_pipeline_is_ready() {
+        let Some(mut app) = gpu_app() else {
+            return;
+        };
+        app.add_plugins(crate::WboitPlugin);
+        app.finish();
+        app.cleanup();
+        let world = app.get_sub_app_mut(RenderApp).unwrap().world_mut();
+        let camera = accum_view(world, WBOIT_DEPTH_FORMAT);
+
+        let world = &*world;
+        let mut render_context = render_context(world);
+        render_accum(
+            &mut render_context,
+            camera,
+            view_query(world, camera),
+            0..3,
+            world,
+        );
+        let (command_buffers, ..) = render_context.finish();
+        world.resource::<RenderQueue>().submit(command_buffers);
+
+        // Nothing accumulated and full revealage: the composite leaves the background as is.
+        let textures = world.get::<WboitTextures>(camera).unwrap();
+        let fi = textures.frame_index;
+        assert!(
+            read_texture(world, &textures.accum.texture, SIZE, 8)
+                .iter()
+                .all(|&byte| byte == 0)
+        );
+        assert!(
+            read_texture(world, &textures.revealage[fi].texture, SIZE, 1)
+                .iter()
+                .all(|&byte| byte == u8::MAX)
+        );
+    }
+
+    #[test]
+    fn accum_pass_leaves_the_shared_depth_unchanged() {
+        let Some(mut app) = gpu_app() else {
+            return;
+        };
+        app.add_plugins(crate::WboitPlugin);
+        app.finish();
+        app.cleanup();
+        let world = app.get_sub_app_mut(RenderApp).unwrap().world_mut();
+        let camera = accum_view(world, WBOIT_DEPTH_FORMAT);
+
+        let world = &*world;
+        let depth = world.get::<ViewDepthTexture>(camera).unwrap();
+        assert!(
+            read_depth(world, depth, SIZE)
+                .iter()
+                .all(|&value| value == 0.25)
+        );
+
+        let mut render_context = render_context(world);
+        render_accum(
+            &mut render_context,
+            camera,
+            view_query(world, camera),
+            0..3,
+            world,
+        );
+        let (command_buffers, ..) = render_context.finish();
+        world.resource::<RenderQueue>().submit(command_buffers);
+
+        assert!(
+            read_depth(world, depth, SIZE)
+                .iter()
+                .all(|&value| value == 0.25)
+        );
+    }
+
+    #[test]
+    fn accum_node_skips_a_view_whose_depth_format_does_not_match() {
+        let Some(mut app) = gpu_app() else {
+            return;
+        };
+        app.add_plugins(crate::WboitPlugin);
+        app.finish();
+        app.cleanup();
+        let world = app.get_sub_app_mut(RenderApp).unwrap().world_mut();
+        let depth_format = TextureFormat::Depth24PlusStencil8;
+        assert_ne!(depth_format, WBOIT_DEPTH_FORMAT);
+        let camera = accum_view(world, depth_format);
+
+        let world = &*world;
+        let textures = world.get::<WboitTextures>(camera).unwrap();
+        let fi = textures.frame_index;
+        let leftovers = (
+            read_texture(world, &textures.accum.texture, SIZE, 8),
+            read_texture(world, &textures.revealage[fi].texture, SIZE, 1),
+        );
+
+        // The accum pipelines cannot attach this depth; the node skips the view without an
+        // error and leaves its targets as they were.
+        let graph = RenderGraph::default();
+        let node = NodeState::new(WboitAccumPass.intern(), EmptyNode);
+        let mut graph_context = RenderGraphContext::new(&graph, &node, &[], &mut []);
+        graph_context.set_view_entity(camera);
+        let mut render_context = render_context(world);
+        WboitAccumNode
+            .run(
+                &mut graph_context,
+                &mut render_context,
+                view_query(world, camera),
+                world,
+            )
+            .unwrap();
+        let (command_buffers, ..) = render_context.finish();
+        world.resource::<RenderQueue>().submit(command_buffers);
+
+        assert_eq!(
+            (
+                read_texture(world, &textures.accum.texture, SIZE, 8),
+                read_texture(world, &textures.revealage[fi].texture, SIZE, 1),
+            ),
+            leftovers
+        );
     }
 }
@@ -1,20 +1,30 @@
-use bevy::asset::{weak_handle, Handle};
+use bevy::asset::{Handle, weak_handle};
 use bevy::core_pipeline::fullscreen_vertex_shader::fullscreen_shader_vertex_state;
 use bevy::ecs::query::QueryItem;
 use bevy::prelude::*;
 use bevy::render::camera::ExtractedCamera;
+use bevy::render::extract_resource::ExtractResource;
+use bevy::render::render_asset::RenderAssets;
 use bevy::render::render_graph::{NodeRunError, RenderGraphContext, RenderLabel, ViewNode};
+use bevy::render::render_phase::ViewSortedRenderPhases;
 use bevy::render::render_resource::{
-    BindGroup, BindGroupEntry, BindGroupLayout, BindGroupLayoutEntry, BindingType,
-    BlendState, CachedRenderPipelineId, ColorTargetState, ColorWrites, FragmentState,
-    PipelineCache, RenderPassDescriptor, RenderPipelineDescriptor,
-    Shader, ShaderStages, TextureFormat, TextureSampleType, TextureViewDimension,
+    BindGroup, BindGroupEntry, BindGroupLayout, BindGroupLayoutEntry, BindingType, BlendState,
+    BufferBindingType, CachedRenderPipelineId, ColorTargetState, ColorWrites, FilterMode,
+    FragmentState, PipelineCache, RenderPassDescriptor, RenderPipelineDescriptor, Sampler,
+    SamplerBindingType, SamplerDescriptor, Shader, ShaderDefVal, ShaderStages, TextureFormat,
+    TextureSampleType, TextureViewDimension,
 };
 use bevy::render::renderer::{RenderContext, RenderDevice};
-use bevy::render::view::ViewTarget;
+use bevy::render::texture::{FallbackImage, GpuImage};
+use bevy::render::view::{ExtractedView, ViewTarget};
 
-use crate::settings::WboitSettings;
-use crate::textures::WboitTextures;
+use crate::phase::WboitAccum3d;
+use crate::settings::{
+    WboitCompositeMask, WboitCompositeTonemap, WboitDebug, WboitSettings, WboitTaaMode,
+};
+use crate::textures::{WBOIT_ACCUM_FORMAT, WboitParamsBuffer, WboitTextures, accum_format_max};
+
+use super::shared::WboitSharedViews;
 
 pub const WBOIT_COMPOSITE_SHADER_HANDLE: Handle<Shader> =
     weak_handle!("5f2a9d1b-3c4e-4f7a-8b6c-1e2f3a4b5c6d");
@@ -23,19 +33,105 @@ pub const WBOIT_COMPOSITE_SHADER_HANDLE: Handle<Shader> =
 #[derive(RenderLabel, Debug, Clone, Hash, PartialEq, Eq)]
 pub struct WboitCompositePass;
 
+/// Render graph label for the WBOIT composite pass used with `WboitTaaMode::AfterTaa`,
+/// placed after `Node3d::Taa` (when present) and before `Node3d::Bloom`.
+#[derive(RenderLabel, Debug, Clone, Hash, PartialEq, Eq)]
+pub struct WboitPostTaaCompositePass;
+
+/// Render graph label for the WBOIT composite pass used with `WboitTaaMode::BeforeOpaque`,
+/// placed after `WboitBackgroundAccumPass` and before `Node3d::MainOpaquePass`.
+#[derive(RenderLabel, Debug, Clone, Hash, PartialEq, Eq)]
+pub struct WboitBackgroundCompositePass;
+
 /// Per-camera component storing the composite pipeline ID.
 #[derive(Component)]
 pub struct WboitCompositePipelineId(pub CachedRenderPipelineId);
 
+/// Per-camera component storing the variant the queued composite pipeline was built for, so
+/// the pipeline is re-queued when it changes.
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub struct WboitCompositeKey {
+    /// `WboitSettings::debug`.
+    pub debug: WboitDebug,
+    /// The camera has a `WboitCompositeMask`.
+    pub masked: bool,
+    /// `WboitSettings::output_alpha`.
+    pub output_alpha: bool,
+    /// `WboitSettings::composite_blend_state`.
+    pub blend: Option<BlendState>,
+    /// `WboitSettings::composite_tonemap`, `None` on HDR targets (see [`is_hdr_format`]).
+    pub tonemap: Option<WboitCompositeTonemap>,
+    /// The view's exact main texture format, which changes when `Camera::hdr` is toggled.
+    /// The pipeline targets it as is, whichever format the view uses.
+    pub format: TextureFormat,
+}
+
+impl WboitCompositeKey {
+    /// Key of a camera with `settings` whose main texture has `format`; `masked` when it has a
+    /// `WboitCompositeMask`.
+    pub fn new(settings: &WboitSettings, masked: bool, format: TextureFormat) -> Self {
+        Self {
+            debug: settings.debug,
+            masked,
+            output_alpha: settings.output_alpha,
+            blend: settings.composite_blend_state,
+            tonemap: settings
+                .composite_tonemap
+                .filter(|_| !is_hdr_format(format)),
+            format,
+        }
+    }
+}
+
 /// Per-camera component storing the composite bind group.
 #[derive(Component)]
 pub struct WboitCompositeBindGroup(pub BindGroup);
 
+/// Fragment shader used by the naive WBOIT composite pass. Insert it in the main world to
+/// replace the built-in composite (e.g. to fold WBOIT resolve into custom tonemapping) while
+/// keeping the accum pass and textures.
+///
+/// The shader must have a `fragment` entry point taking
+/// `bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput` and returning one
+/// `vec4<f32>` blended with premultiplied alpha (or `WboitSettings::composite_blend_state`)
+/// onto the view target. It may declare any of the group 0 bindings of
+/// `WboitCompositePipeline::bind_group_layout`:
+///
+/// - `@binding(0)`: accum, `texture_2d<f32>` (premultiplied color times weight, weight in alpha)
+/// - `@binding(1)`: revealage, `texture_2d<f32>` (product of `1 - alpha` in `r`)
+/// - `@binding(2)`: `sampler` for reduced-resolution accum targets and the mask, bilinear or
+///   nearest per `WboitSettings::composite_filter`
+/// - `@binding(3)`: `WboitParams` uniform (see `wboit_composite.wgsl`)
+/// - `@binding(4)`: overdraw count, `texture_2d<f32>` (only meaningful for `WboitDebug::Overdraw`
+///   and, holding the largest weight, `WboitDebug::Weight`)
+/// - `@binding(5)`: glow, `texture_2d<f32>` (sum of `AlphaMode::Add` color, added on top)
+/// - `@binding(6)`: mask, `texture_2d<f32>` (`WboitCompositeMask`, white when there is none;
+///   the `WBOIT_COMPOSITE_MASK` shader def is set when the camera has a mask)
+///
+/// `WBOIT_COMPOSITE_TONEMAP` and `WBOIT_COMPOSITE_TONEMAP_REINHARD` or
+/// `WBOIT_COMPOSITE_TONEMAP_ACES` are set per `WboitSettings::composite_tonemap` on LDR
+/// targets.
+#[derive(Resource, Clone, ExtractResource)]
+pub struct WboitCompositeShader(pub Handle<Shader>);
+
+impl Default for WboitCompositeShader {
+    fn default() -> Self {
+        Self(WBOIT_COMPOSITE_SHADER_HANDLE)
+    }
+}
+
 /// Resource holding the composite pipeline layout.
 #[derive(Resource)]
 pub struct WboitCompositePipeline {
     pub bind_group_layout: BindGroupLayout,
+    /// Shader the composite pipelines are currently built with, synced from the extracted
+    /// `WboitCompositeShader`.
     pub fragment_shader: Handle<Shader>,
+    /// Bilinear sampler used to upsample reduced-resolution accum targets.
+    pub upsample_sampler: Sampler,
+    /// Nearest-neighbor counterpart of `upsample_sampler`, for
+    /// `WboitSettings::composite_filter` set to `Nearest`.
+    pub point_sampler: Sampler,
 }
 
 impl FromWorld for WboitCompositePipeline {
@@ -47,18 +143,69 @@ impl FromWorld for WboitCompositePipeline {
                 binding: 0,
                 visibility: ShaderStages::FRAGMENT,
                 ty: BindingType::Texture {
-                    sample_type: TextureSampleType::Float { filterable: false },
+                    sample_type: TextureSampleType::Float { filterable: true },
                     view_dimension: TextureViewDimension::D2,
                     multisampled: false,
                 },
                 count: None,
             },
-            // Binding 1: revealage texture
+            // Binding 1: revealage texture, always linear (`WBOIT_REVEALAGE_FORMAT`)
             BindGroupLayoutEntry {
                 binding: 1,
                 visibility: ShaderStages::FRAGMENT,
                 ty: BindingType::Texture {
-                    sample_type: TextureSampleType::Float { filterable: false },
+                    sample_type: TextureSampleType::Float { filterable: true },
+                    view_dimension: TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            // Binding 2: upsample sampler (used when accum_scale < 1)
+            BindGroupLayoutEntry {
+                binding: 2,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                count: None,
+            },
+            // Binding 3: WboitParams uniform
+            BindGroupLayoutEntry {
+                binding: 3,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            // Binding 4: overdraw count (revealage stands in when the debug mode is off)
+            BindGroupLayoutEntry {
+                binding: 4,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Float { filterable: true },
+                    view_dimension: TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            // Binding 5: glow texture (additive light from AlphaMode::Add fragments)
+            BindGroupLayoutEntry {
+                binding: 5,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Float { filterable: true },
+                    view_dimension: TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            // Binding 6: WboitCompositeMask image (white fallback when the camera has none)
+            BindGroupLayoutEntry {
+                binding: 6,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Float { filterable: true },
                     view_dimension: TextureViewDimension::D2,
                     multisampled: false,
                 },
@@ -66,47 +213,82 @@ impl FromWorld for WboitCompositePipeline {
             },
         ];
 
-        let bind_group_layout = render_device.create_bind_group_layout(
-            "wboit_composite_bind_group_layout",
-            &entries,
-        );
+        let bind_group_layout =
+            render_device.create_bind_group_layout("wboit_composite_bind_group_layout", &entries);
+
+        let upsample_sampler = render_device.create_sampler(&SamplerDescriptor {
+            label: Some("wboit_composite_upsample_sampler"),
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            mipmap_filter: FilterMode::Nearest,
+            ..default()
+        });
+        let point_sampler = render_device.create_sampler(&SamplerDescriptor {
+            label: Some("wboit_composite_point_sampler"),
+            mag_filter: FilterMode::Nearest,
+            min_filter: FilterMode::Nearest,
+            mipmap_filter: FilterMode::Nearest,
+            ..default()
+        });
 
         WboitCompositePipeline {
             bind_group_layout,
             fragment_shader: WBOIT_COMPOSITE_SHADER_HANDLE,
+            upsample_sampler,
+            point_sampler,
         }
     }
 }
 
-/// Queue the composite pipeline for each WBOIT camera.
-pub fn queue_wboit_composite_pipeline(
-    mut commands: Commands,
-    pipeline_cache: Res<PipelineCache>,
-    composite_pipeline: Option<Res<WboitCompositePipeline>>,
-    views: Query<(Entity, &ViewTarget), (With<WboitSettings>, Without<WboitCompositePipelineId>)>,
-) {
-    let Some(composite_pipeline) = composite_pipeline else {
-        return;
-    };
-    for (entity, view_target) in &views {
-        let format = if view_target.main_texture_format() == ViewTarget::TEXTURE_FORMAT_HDR {
-            ViewTarget::TEXTURE_FORMAT_HDR
-        } else {
-            TextureFormat::bevy_default()
-        };
-
-        let pipeline_id = pipeline_cache.queue_render_pipeline(RenderPipelineDescriptor {
+impl WboitCompositePipeline {
+    /// Descriptor of the composite pipeline for a camera with `key`.
+    pub fn descriptor(&self, key: &WboitCompositeKey) -> RenderPipelineDescriptor {
+        let mut shader_defs = vec![ShaderDefVal::UInt(
+            "ACCUM_FORMAT_MAX".into(),
+            accum_format_max(WBOIT_ACCUM_FORMAT),
+        )];
+        match key.debug {
+            WboitDebug::None => {}
+            WboitDebug::Overdraw => shader_defs.push("WBOIT_DEBUG_OVERDRAW".into()),
+            WboitDebug::Weight => {
+                shader_defs.push("WBOIT_DEBUG_OVERDRAW".into());
+                shader_defs.push("WBOIT_DEBUG_WEIGHT".into());
+            }
+            WboitDebug::TransparencyOnly => {
+                shader_defs.push("WBOIT_DEBUG_TRANSPARENCY_ONLY".into());
+            }
+        }
+        if key.masked {
+            shader_defs.push("WBOIT_COMPOSITE_MASK".into());
+        }
+        if let Some(tonemap) = key.tonemap {
+            shader_defs.push("WBOIT_COMPOSITE_TONEMAP".into());
+            shader_defs.push(match tonemap {
+                WboitCompositeTonemap::Reinhard => "WBOIT_COMPOSITE_TONEMAP_REINHARD".into(),
+                WboitCompositeTonemap::Aces => "WBOIT_COMPOSITE_TONEMAP_ACES".into(),
+            });
+        }
+        RenderPipelineDescriptor {
             label: Some("wboit_composite_pipeline".into()),
-            layout: vec![composite_pipeline.bind_group_layout.clone()],
+            layout: vec![self.bind_group_layout.clone()],
             vertex: fullscreen_shader_vertex_state(),
             fragment: Some(FragmentState {
-                shader: composite_pipeline.fragment_shader.clone(),
-                shader_defs: vec![],
+                shader: self.fragment_shader.clone(),
+                shader_defs,
                 entry_point: "fragment".into(),
+                // The output is premultiplied with coverage in alpha, so blending it "over" the
+                // target alpha leaves the combined coverage there for stacking.
                 targets: vec![Some(ColorTargetState {
-                    format,
-                    blend: Some(BlendState::PREMULTIPLIED_ALPHA_BLENDING),
-                    write_mask: ColorWrites::ALL,
+                    format: key.format,
+                    blend: Some(
+                        key.blend
+                            .unwrap_or(BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    ),
+                    write_mask: if key.output_alpha {
+                        ColorWrites::ALL
+                    } else {
+                        ColorWrites::COLOR
+                    },
                 })],
             }),
             primitive: default(),
@@ -114,26 +296,102 @@ pub fn queue_wboit_composite_pipeline(
             multisample: default(),
             zero_initialize_workgroup_memory: false,
             push_constant_ranges: vec![],
-        });
+        }
+    }
+}
+
+/// Whether a view target format stores values above `1.0` (floating point formats), so the
+/// composite leaves tonemapping to Bevy. Decided from the format itself rather than by
+/// comparing with `ViewTarget::TEXTURE_FORMAT_HDR`, so other HDR formats are recognized too.
+pub(crate) fn is_hdr_format(format: TextureFormat) -> bool {
+    matches!(
+        format,
+        TextureFormat::R16Float
+            | TextureFormat::Rg16Float
+            | TextureFormat::Rgba16Float
+            | TextureFormat::R32Float
+            | TextureFormat::Rg32Float
+            | TextureFormat::Rgba32Float
+            | TextureFormat::Rg11b10Ufloat
+            | TextureFormat::Rgb9e5Ufloat
+    )
+}
+
+/// Queue the composite pipeline for each WBOIT camera.
+///
+/// Pipelines are re-queued for every camera when `WboitCompositeShader` changes, and per
+/// camera when its [`WboitCompositeKey`] changes.
+pub fn queue_wboit_composite_pipeline(
+    mut commands: Commands,
+    pipeline_cache: Res<PipelineCache>,
+    composite_pipeline: Option<ResMut<WboitCompositePipeline>>,
+    composite_shader: Option<Res<WboitCompositeShader>>,
+    views: Query<(
+        Entity,
+        &ViewTarget,
+        &WboitSettings,
+        Has<WboitCompositePipelineId>,
+        Has<WboitCompositeMask>,
+        Option<&WboitCompositeKey>,
+    )>,
+) {
+    let Some(mut composite_pipeline) = composite_pipeline else {
+        return;
+    };
+    let shader_changed = match composite_shader {
+        Some(shader) if shader.0 != composite_pipeline.fragment_shader => {
+            composite_pipeline.fragment_shader = shader.0.clone();
+            true
+        }
+        _ => false,
+    };
+    for (entity, view_target, settings, queued, masked, queued_key) in &views {
+        let key = WboitCompositeKey::new(settings, masked, view_target.main_texture_format());
+        if queued && !shader_changed && queued_key == Some(&key) {
+            continue;
+        }
+        let pipeline_id = pipeline_cache.queue_render_pipeline(composite_pipeline.descriptor(&key));
 
         commands
             .entity(entity)
-            .insert(WboitCompositePipelineId(pipeline_id));
+            .insert((WboitCompositePipelineId(pipeline_id), key));
     }
 }
 
 /// Prepare the composite bind group for each WBOIT camera.
+///
+/// Rebuilt every frame (one `create_bind_group` per camera, plus one in
+/// `prepare_wboit_accum_bind_group`), since the revealage texture alternates with
+/// `frame_index`. `benches/prepare_bind_groups.rs` measures this path.
 pub fn prepare_wboit_composite_bind_group(
     mut commands: Commands,
     render_device: Res<RenderDevice>,
     composite_pipeline: Option<Res<WboitCompositePipeline>>,
-    views: Query<(Entity, &WboitTextures), With<WboitSettings>>,
+    gpu_images: Res<RenderAssets<GpuImage>>,
+    fallback_image: Res<FallbackImage>,
+    views: Query<(
+        Entity,
+        &WboitSettings,
+        &WboitTextures,
+        &WboitParamsBuffer,
+        Option<&WboitCompositeMask>,
+    )>,
 ) {
     let Some(composite_pipeline) = composite_pipeline else {
         return;
     };
-    for (entity, wboit_textures) in &views {
+    for (entity, settings, wboit_textures, params_buffer, mask) in &views {
         let fi = wboit_textures.frame_index;
+        let Some(glow) = wboit_textures.glow.as_ref() else {
+            continue;
+        };
+        let mask_view = mask
+            .and_then(|mask| gpu_images.get(&mask.0))
+            .map_or(&fallback_image.d2.texture_view, |image| &image.texture_view);
+        let sampler = match settings.composite_filter {
+            FilterMode::Nearest => &composite_pipeline.point_sampler,
+            FilterMode::Linear => &composite_pipeline.upsample_sampler,
+        };
         let bind_group = render_device.create_bind_group(
             "wboit_composite_bind_group",
             &composite_pipeline.bind_group_layout,
@@ -150,6 +408,36 @@ pub fn prepare_wboit_composite_bind_group(
                         &wboit_textures.revealage[fi].default_view,
                     ),
                 },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: bevy::render::render_resource::BindingResource::Sampler(sampler),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: params_buffer.0.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 4,
+                    resource: bevy::render::render_resource::BindingResource::TextureView(
+                        &wboit_textures
+                            .overdraw
+                            .as_ref()
+                            .unwrap_or(&wboit_textures.revealage[fi])
+                            .default_view,
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 5,
+                    resource: bevy::render::render_resource::BindingResource::TextureView(
+                        &glow.default_view,
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 6,
+                    resource: bevy::render::render_resource::BindingResource::TextureView(
+                        mask_view,
+                    ),
+                },
             ],
         );
 
@@ -160,49 +448,476 @@ pub fn prepare_wboit_composite_bind_group(
 }
 
 /// Render graph node that runs the WBOIT composite pass (fullscreen triangle).
+///
+/// Only composites cameras with `WboitTaaMode::BeforeTaa`; see `WboitPostTaaCompositeNode`.
 #[derive(Default)]
 pub struct WboitCompositeNode;
 
 impl ViewNode for WboitCompositeNode {
+    // Requiring `WboitSettings` keeps the node from compositing stale bind groups after a
+    // camera leaves the naive path (e.g. quality raised to 3).
     type ViewQuery = (
         &'static ExtractedCamera,
+        &'static ExtractedView,
         &'static ViewTarget,
+        &'static WboitSettings,
         Option<&'static WboitCompositePipelineId>,
         Option<&'static WboitCompositeBindGroup>,
     );
 
     fn run<'w>(
         &self,
-        _graph: &mut RenderGraphContext,
+        graph: &mut RenderGraphContext,
         render_context: &mut RenderContext<'w>,
-        (camera, view_target, pipeline_id_opt, bind_group_opt): QueryItem<Self::ViewQuery>,
+        (camera, view, view_target, settings, pipeline_id_opt, bind_group_opt): QueryItem<
+            Self::ViewQuery,
+        >,
         world: &'w World,
     ) -> Result<(), NodeRunError> {
-        let (Some(pipeline_id), Some(bind_group)) = (pipeline_id_opt, bind_group_opt) else {
+        if settings.taa_mode != WboitTaaMode::BeforeTaa
+            || skips_composite(graph.view_entity(), view, world)
+        {
             return Ok(());
-        };
+        }
+        run_composite(
+            render_context,
+            camera,
+            view_target,
+            pipeline_id_opt,
+            bind_group_opt,
+            world,
+        );
+        Ok(())
+    }
+}
+
+/// Render graph node that runs the WBOIT composite after TAA, for cameras with
+/// `WboitTaaMode::AfterTaa`.
+#[derive(Default)]
+pub struct WboitPostTaaCompositeNode;
+
+impl ViewNode for WboitPostTaaCompositeNode {
+    type ViewQuery = <WboitCompositeNode as ViewNode>::ViewQuery;
 
-        let pipeline_cache = world.resource::<PipelineCache>();
-        let Some(pipeline) = pipeline_cache.get_render_pipeline(pipeline_id.0) else {
+    fn run<'w>(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext<'w>,
+        (camera, view, view_target, settings, pipeline_id_opt, bind_group_opt): QueryItem<
+            Self::ViewQuery,
+        >,
+        world: &'w World,
+    ) -> Result<(), NodeRunError> {
+        if settings.taa_mode != WboitTaaMode::AfterTaa
+            || skips_composite(graph.view_entity(), view, world)
+        {
             return Ok(());
-        };
+        }
+        run_composite(
+            render_context,
+            camera,
+            view_target,
+            pipeline_id_opt,
+            bind_group_opt,
+            world,
+        );
+        Ok(())
+    }
+}
+
+/// Render graph node that runs the WBOIT composite before the opaque pass, for cameras with
+/// `WboitTaaMode::BeforeOpaque`. Being the first pass to use the view target, it composites
+/// over the camera's clear color, and the opaque pass then draws on top.
+#[derive(Default)]
+pub struct WboitBackgroundCompositeNode;
+
+impl ViewNode for WboitBackgroundCompositeNode {
+    type ViewQuery = <WboitCompositeNode as ViewNode>::ViewQuery;
+
+    fn run<'w>(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext<'w>,
+        (camera, view, view_target, settings, pipeline_id_opt, bind_group_opt): QueryItem<
+            Self::ViewQuery,
+        >,
+        world: &'w World,
+    ) -> Result<(), NodeRunError> {
+        if settings.taa_mode != WboitTaaMode::BeforeOpaque
+            || skips_composite(graph.view_entity(), view, world)
+        {
+            return Ok(());
+        }
+        run_composite(
+            render_context,
+            camera,
+            view_target,
+            pipeline_id_opt,
+            bind_group_opt,
+            world,
+        );
+        Ok(())
+    }
+}
+
+/// Whether the composite skips the view: it queued nothing into `WboitAccum3d` this frame, so
+/// the accum pass skipped it without clearing its targets and compositing them would blend in
+/// the last frame's transparents; or a later camera of its `WboitSharedTarget` composites the
+/// shared targets. That last camera always composites, since earlier ones may have drawn.
+fn skips_composite(view_entity: Entity, view: &ExtractedView, world: &World) -> bool {
+    let shared_views = world.resource::<WboitSharedViews>();
+    if shared_views.resolves(view_entity) {
+        return false;
+    }
+    shared_views.defers_composite(view_entity)
+        || world
+            .resource::<ViewSortedRenderPhases<WboitAccum3d>>()
+            .get(&view.retained_view_entity)
+            .is_none_or(|phase| phase.items.is_empty())
+}
+
+/// Draw the fullscreen composite onto the view target, if the pipeline and bind group are ready.
+///
+/// The composite always covers exactly the camera viewport, so a sub-viewport camera (e.g.
+/// split screen) never touches the rest of the shared target. There is no option to cover the
+/// whole target instead: no transparents accumulate outside the viewport, so every extra pixel
+/// would be discarded. Full-resolution accum targets are sized to the whole render target and
+/// loaded at the fragment's target coordinates; reduced-resolution ones cover only the
+/// viewport and are sampled by the fullscreen triangle's viewport UV.
+pub(super) fn run_composite<'w>(
+    render_context: &mut RenderContext<'w>,
+    camera: &ExtractedCamera,
+    view_target: &ViewTarget,
+    pipeline_id_opt: Option<&WboitCompositePipelineId>,
+    bind_group_opt: Option<&WboitCompositeBindGroup>,
+    world: &'w World,
+) {
+    let (Some(pipeline_id), Some(bind_group)) = (pipeline_id_opt, bind_group_opt) else {
+        return;
+    };
+
+    let pipeline_cache = world.resource::<PipelineCache>();
+    let Some(pipeline) = pipeline_cache.get_render_pipeline(pipeline_id.0) else {
+        return;
+    };
+
+    let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+        label: Some("wboit_composite_pass"),
+        color_attachments: &[Some(view_target.get_color_attachment())],
+        depth_stencil_attachment: None,
+        timestamp_writes: None,
+        occlusion_query_set: None,
+    });
+
+    if let Some(viewport) = camera.viewport.as_ref() {
+        render_pass.set_camera_viewport(viewport);
+    }
+
+    render_pass.set_render_pipeline(pipeline);
+    render_pass.set_bind_group(0, &bind_group.0, &[]);
+    render_pass.draw(0..3, 0..1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::render::RenderApp;
+    use bevy::render::render_resource::{
+        BindingResource, BufferDescriptor, BufferInitDescriptor, BufferUsages, Extent3d, LoadOp,
+        Maintain, MapMode, Operations, Origin3d, RenderPassColorAttachment, StoreOp,
+        TexelCopyBufferInfo, TexelCopyBufferLayout, TexelCopyTextureInfo, Texture, TextureAspect,
+        TextureDescriptor, TextureDimension, TextureUsages,
+    };
+    use bevy::render::renderer::RenderQueue;
+
+    use crate::test_utils::{compile_render_pipeline, extract_shaders, gpu_app};
+    use crate::textures::{WBOIT_REVEALAGE_FORMAT, WboitParams};
+
+    use super::super::probe::f16_to_f32;
+
+    /// Format of the target the composite tests render onto: HDR, so nothing is clamped.
+    const TARGET_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
 
-        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
-            label: Some("wboit_composite_pass"),
-            color_attachments: &[Some(view_target.get_color_attachment())],
+    /// One texel of the composite inputs.
+    struct Texel {
+        accum: LinearRgba,
+        revealage: f32,
+        glow: LinearRgba,
+    }
+
+    /// 1x1 texture of `format`, cleared to `color`.
+    fn cleared_texture(world: &World, format: TextureFormat, color: LinearRgba) -> Texture {
+        let render_device = world.resource::<RenderDevice>();
+        let texture = render_device.create_texture(&TextureDescriptor {
+            label: None,
+            size: Extent3d::default(),
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsages::RENDER_ATTACHMENT
+                | TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&default());
+        let mut encoder = render_device.create_command_encoder(&default());
+        encoder.begin_render_pass(&RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(color.into()),
+                    store: StoreOp::Store,
+                },
+            })],
             depth_stencil_attachment: None,
             timestamp_writes: None,
             occlusion_query_set: None,
         });
+        world.resource::<RenderQueue>().submit([encoder.finish()]);
+        texture
+    }
+
+    /// Target color after the composite pipeline for `settings` ran over one `texel`, on a
+    /// target cleared to `background`. `None` without a GPU adapter.
+    fn composite(settings: &WboitSettings, texel: &Texel, background: LinearRgba) -> Option<Vec4> {
+        let mut app = gpu_app()?;
+        app.add_plugins(crate::WboitPlugin);
+        app.finish();
+        app.cleanup();
+        extract_shaders(&mut app);
+        let world = app.get_sub_app_mut(RenderApp).unwrap().world_mut();
+
+        let composite_pipeline = world.resource::<WboitCompositePipeline>();
+        let key = WboitCompositeKey::new(settings, false, TARGET_FORMAT);
+        let descriptor = composite_pipeline.descriptor(&key);
+        let pipeline = compile_render_pipeline(world, descriptor);
+
+        let accum = cleared_texture(world, WBOIT_ACCUM_FORMAT, texel.accum);
+        let revealage = cleared_texture(
+            world,
+            WBOIT_REVEALAGE_FORMAT,
+            LinearRgba::new(texel.revealage, 0.0, 0.0, 0.0),
+        );
+        let glow = cleared_texture(world, TextureFormat::Rgba16Float, texel.glow);
+        let mask = cleared_texture(world, TextureFormat::Rgba8Unorm, LinearRgba::WHITE);
+        let target = cleared_texture(world, TARGET_FORMAT, background);
 
-        if let Some(viewport) = camera.viewport.as_ref() {
-            render_pass.set_camera_viewport(viewport);
+        let composite_pipeline = world.resource::<WboitCompositePipeline>();
+        let render_device = world.resource::<RenderDevice>();
+        let params = WboitParams::from_settings(settings, UVec2::ONE, UVec2::ONE);
+        let params_buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
+            label: None,
+            contents: &params.as_bytes(),
+            usage: BufferUsages::UNIFORM,
+        });
+        let views = [&accum, &revealage, &revealage, &glow, &mask]
+            .map(|texture| texture.create_view(&default()));
+        let bind_group = render_device.create_bind_group(
+            None,
+            &composite_pipeline.bind_group_layout,
+            &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&views[0]),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(&views[1]),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::Sampler(&composite_pipeline.upsample_sampler),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: params_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 4,
+                    resource: BindingResource::TextureView(&views[2]),
+                },
+                BindGroupEntry {
+                    binding: 5,
+                    resource: BindingResource::TextureView(&views[3]),
+                },
+                BindGroupEntry {
+                    binding: 6,
+                    resource: BindingResource::TextureView(&views[4]),
+                },
+            ],
+        );
+
+        let readback = render_device.create_buffer(&BufferDescriptor {
+            label: None,
+            size: 8,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let target_view = target.create_view(&default());
+        let mut encoder = render_device.create_command_encoder(&default());
+        {
+            let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &target_view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Load,
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_pipeline(&pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.draw(0..3, 0..1);
         }
+        encoder.copy_texture_to_buffer(
+            TexelCopyTextureInfo {
+                texture: &target,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            TexelCopyBufferInfo {
+                buffer: &readback,
+                layout: TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: None,
+                    rows_per_image: None,
+                },
+            },
+            Extent3d::default(),
+        );
+        world.resource::<RenderQueue>().submit([encoder.finish()]);
+        render_device.map_buffer(&readback.slice(..), MapMode::Read, |result| result.unwrap());
+        render_device.poll(Maintain::Wait);
+        let bytes = readback.slice(..).get_mapped_range().to_vec();
+        Some(Vec4::from_array(core::array::from_fn(|channel| {
+            f16_to_f32(u16::from_le_bytes([
+                bytes[2 * channel],
+                bytes[2 * channel + 1],
+            ]))
+        })))
+    }
 
-        render_pass.set_render_pipeline(pipeline);
-        render_pass.set_bind_group(0, &bind_group.0, &[]);
-        render_pass.draw(0..3, 0..1);
+    /// Blend state of the composite pipeline's color target for a camera with `settings`.
+    fn target_blend(pipeline: &WboitCompositePipeline, settings: &WboitSettings) -> BlendState {
+        let key = WboitCompositeKey::new(settings, false, TextureFormat::Rgba8UnormSrgb);
+        let descriptor = pipeline.descriptor(&key);
+        descriptor.fragment.unwrap().targets[0]
+            .as_ref()
+            .unwrap()
+            .blend
+            .unwrap()
+    }
 
-        Ok(())
+    #[test]
+    fn composite_blend_state_sets_the_color_target_blend() {
+        let Some(mut app) = gpu_app() else {
+            return;
+        };
+        app.add_plugins(crate::WboitPlugin);
+        app.finish();
+        app.cleanup();
+        let world = app.get_sub_app_mut(RenderApp).unwrap().world_mut();
+        let pipeline = world.resource::<WboitCompositePipeline>();
+
+        assert_eq!(
+            target_blend(pipeline, &WboitSettings::default()),
+            BlendState::PREMULTIPLIED_ALPHA_BLENDING
+        );
+        let settings = WboitSettings {
+            composite_blend_state: Some(BlendState::ALPHA_BLENDING),
+            ..default()
+        };
+        assert_eq!(
+            target_blend(pipeline, &settings),
+            BlendState::ALPHA_BLENDING
+        );
+    }
+
+    #[test]
+    fn revealage_gamma_of_one_keeps_revealage_and_two_squares_it() {
+        // A white layer over black: the output color is the layer's coverage,
+        // 1 - revealage^gamma.
+        let texel = Texel {
+            accum: LinearRgba::WHITE,
+            revealage: 0.5,
+            glow: LinearRgba::NONE,
+        };
+        // Revealage is stored as unorm8.
+        let r = (0.5f32 * 255.0).round() / 255.0;
+        for (gamma, coverage) in [(1.0, 1.0 - r), (2.0, 1.0 - r * r)] {
+            let settings = WboitSettings {
+                revealage_gamma: gamma,
+                ..default()
+            };
+            let Some(out) = composite(&settings, &texel, LinearRgba::NONE) else {
+                return;
+            };
+            assert!(
+                out.truncate().abs_diff_eq(Vec3::splat(coverage), 2e-3),
+                "gamma {gamma}: {out} is not {coverage}"
+            );
+        }
+    }
+
+    #[test]
+    fn composite_exposure_scales_only_the_transparent_layer() {
+        let texel = Texel {
+            accum: LinearRgba::new(0.25, 0.5, 0.75, 1.0),
+            revealage: 0.5,
+            glow: LinearRgba::NONE,
+        };
+        let background = LinearRgba::new(0.2, 0.3, 0.4, 1.0);
+        // The layer alone, over black, and what the background adds under it.
+        let layer_and_background = |composite_exposure| {
+            let settings = WboitSettings {
+                composite_exposure,
+                ..default()
+            };
+            let layer = composite(&settings, &texel, LinearRgba::NONE)?.truncate();
+            let over = composite(&settings, &texel, background)?.truncate();
+            Some((layer, over - layer))
+        };
+        let Some((layer, background_term)) = layer_and_background(1.0) else {
+            return;
+        };
+        let (exposed_layer, exposed_background_term) = layer_and_background(2.0).unwrap();
+
+        assert!(layer.cmpgt(Vec3::ZERO).all());
+        assert!(
+            exposed_layer.abs_diff_eq(layer * 2.0, 2e-3),
+            "{exposed_layer} is not twice {layer}"
+        );
+        assert!(
+            exposed_background_term.abs_diff_eq(background_term, 2e-3),
+            "{exposed_background_term} is not {background_term}"
+        );
+    }
+
+    #[test]
+    fn accum_overflowed_by_deep_stacks_composites_to_a_finite_color() {
+        // The accum shaders cap each fragment at ACCUM_FORMAT_MAX / ACCUM_LAYER_HEADROOM, so
+        // nine opaque white layers at the largest weight sum to 9 / 8 of the half-float
+        // maximum, which the additive blend rounds to Inf.
+        let texel = Texel {
+            accum: LinearRgba::new(f32::INFINITY, f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            revealage: 0.0,
+            glow: LinearRgba::NONE,
+        };
+        let Some(out) = composite(&WboitSettings::default(), &texel, LinearRgba::NONE) else {
+            return;
+        };
+        assert!(
+            out.truncate().abs_diff_eq(Vec3::ONE, 2e-3),
+            "{out} is not opaque white"
+        );
     }
 }
@@ -0,0 +1,112 @@
+use core::ops::Range;
+
+use bevy::ecs::query::QueryItem;
+use bevy::prelude::*;
+use bevy::render::render_graph::{NodeRunError, RenderGraphContext, RenderLabel, ViewNode};
+use bevy::render::render_phase::ViewSortedRenderPhases;
+use bevy::render::renderer::RenderContext;
+use bevy::render::view::{ExtractedView, ViewTarget};
+
+use crate::phase::WboitAccum3d;
+use crate::queue::WboitGroupEntities;
+use crate::settings::{WboitGroups, WboitTaaMode};
+
+use super::accum_pass::{WboitAccumNode, render_accum};
+use super::composite::{WboitCompositeBindGroup, WboitCompositePipelineId, run_composite};
+
+/// Render graph label for the pass drawing the groups after the first of a `WboitGroups`
+/// camera, placed after `WboitCompositePass` and before `Node3d::EndMainPass`.
+#[derive(RenderLabel, Debug, Clone, Hash, PartialEq, Eq)]
+pub struct WboitGroupsPass;
+
+/// Per-camera component with the range of `WboitAccum3d` items of each group in
+/// `WboitGroups`, in the same order.
+#[derive(Component, Clone, Default)]
+pub struct WboitGroupRanges(pub Vec<Range<usize>>);
+
+/// Order the sorted `WboitAccum3d` items of each `WboitGroups` view by group, keeping the
+/// distance order within each group, and record where each group starts and ends.
+pub fn sort_wboit_groups(
+    mut commands: Commands,
+    group_entities: Res<WboitGroupEntities>,
+    mut wboit_phases: ResMut<ViewSortedRenderPhases<WboitAccum3d>>,
+    views: Query<(Entity, &ExtractedView, &WboitGroups)>,
+    stale: Query<Entity, (With<WboitGroupRanges>, Without<WboitGroups>)>,
+) {
+    for entity in &stale {
+        commands.entity(entity).remove::<WboitGroupRanges>();
+    }
+    for (entity, view, groups) in &views {
+        let Some(wboit_phase) = wboit_phases.get_mut(&view.retained_view_entity) else {
+            continue;
+        };
+        // Unlisted groups were never queued, so every item has a rank. Stable, so the
+        // distance order survives within each group.
+        let rank = |item: &WboitAccum3d| group_entities.rank(Some(groups), item.entity.1);
+        wboit_phase.items.sort_by_key(rank);
+
+        let mut ranges = Vec::with_capacity(groups.0.len());
+        let mut start = 0;
+        for group_rank in 0..groups.0.len() {
+            let len = wboit_phase.items[start..]
+                .iter()
+                .take_while(|item| rank(item) == Some(group_rank))
+                .count();
+            ranges.push(start..start + len);
+            start += len;
+        }
+        commands.entity(entity).insert(WboitGroupRanges(ranges));
+    }
+}
+
+/// Render graph node that accumulates and composites every group after the first of a
+/// `WboitGroups` camera, one after the other, reusing the camera's accum targets.
+#[derive(Default)]
+pub struct WboitGroupsNode;
+
+impl ViewNode for WboitGroupsNode {
+    type ViewQuery = (
+        <WboitAccumNode as ViewNode>::ViewQuery,
+        &'static ViewTarget,
+        &'static WboitGroupRanges,
+        Option<&'static WboitCompositePipelineId>,
+        Option<&'static WboitCompositeBindGroup>,
+    );
+
+    fn run<'w>(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext<'w>,
+        (accum_query, view_target, ranges, pipeline_id_opt, bind_group_opt): QueryItem<
+            Self::ViewQuery,
+        >,
+        world: &'w World,
+    ) -> Result<(), NodeRunError> {
+        let (camera, _, _, _, settings) = accum_query;
+        if settings.taa_mode != WboitTaaMode::BeforeTaa {
+            return Ok(());
+        }
+        let view_entity = graph.view_entity();
+        for range in ranges.0.iter().skip(1) {
+            if range.is_empty() {
+                continue;
+            }
+            render_accum(
+                render_context,
+                view_entity,
+                accum_query,
+                range.clone(),
+                world,
+            );
+            run_composite(
+                render_context,
+                camera,
+                view_target,
+                pipeline_id_opt,
+                bind_group_opt,
+                world,
+            );
+        }
+        Ok(())
+    }
+}
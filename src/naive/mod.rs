@@ -1,64 +1,218 @@
 pub mod accum_pass;
 pub mod composite;
+pub mod groups;
+pub mod probe;
+pub mod shared;
 
 use bevy::asset::load_internal_asset;
-use bevy::prelude::*;
 use bevy::core_pipeline::core_3d::graph::{Core3d, Node3d};
-use bevy::pbr::queue_material_meshes;
+use bevy::pbr::{MeshPipeline, material_uses_bindless_resources};
+use bevy::prelude::*;
 use bevy::render::extract_component::ExtractComponentPlugin;
-use bevy::pbr::MeshPipeline;
-use bevy::render::render_graph::{RenderGraphApp, ViewNodeRunner};
+use bevy::render::extract_resource::ExtractResourcePlugin;
+use bevy::render::render_graph::{RenderGraph, RenderGraphApp, ViewNodeRunner};
 use bevy::render::render_phase::{
-    AddRenderCommand, DrawFunctions, SortedRenderPhasePlugin, ViewSortedRenderPhases,
-    sort_phase_system,
+    DrawFunctions, SortedRenderPhasePlugin, ViewSortedRenderPhases, sort_phase_system,
 };
-use bevy::render::render_resource::{Shader, SpecializedMeshPipelines};
+use bevy::render::render_resource::{Shader, TextureFormat};
+use bevy::render::renderer::RenderDevice;
 use bevy::render::view::RetainedViewEntity;
 use bevy::render::{Extract, ExtractSchedule, Render, RenderApp, RenderDebugFlags, RenderSet};
 use std::collections::HashSet;
 
-use crate::phase::WboitAccum3d;
-use crate::pipeline::WboitPipeline;
-use crate::queue::{DrawWboit, drain_transparent_for_wboit, queue_wboit_meshes};
-use crate::textures::prepare_wboit_textures;
+use crate::diagnostics::WboitDiagnosticsPlugin;
+use crate::graph::{WboitPostComposite, WboitPostCompositePlugin};
+use crate::material::WboitMaterialPlugin;
+use crate::phase::{WboitAccum3d, WboitNearestDepth3d};
+use crate::pipeline::WboitAccumDataLayout;
+use crate::queue::{
+    QueueWboitMeshes, WboitAlwaysVisibleEntities, WboitGroupEntities, WboitInstanceDataEntities,
+    WboitMeshLayers, WboitPrewarmMeshes, WboitSortFn, cap_wboit_accum_phases,
+    drain_transparent_for_wboit, extract_wboit_always_visible, extract_wboit_groups,
+    extract_wboit_instance_data, extract_wboit_mesh_layers, sort_wboit_accum_phases,
+};
+use crate::settings::{HEWboitSettings, WboitSettings};
+use crate::textures::{
+    WBOIT_REVEALAGE_FORMAT, WboitParamsBuffer, WboitTextures, prepare_wboit_textures,
+};
 
-use self::accum_pass::{WboitAccumNode, WboitAccumPass};
+use self::accum_pass::{
+    WboitAccumBindGroup, WboitAccumNode, WboitAccumPass, WboitBackgroundAccumNode,
+    WboitBackgroundAccumPass, WboitNearestDepthBindGroup, prepare_wboit_accum_bind_group,
+};
 use self::composite::{
-    WboitCompositeNode, WboitCompositePass,
-    WboitCompositePipeline, prepare_wboit_composite_bind_group,
-    queue_wboit_composite_pipeline,
+    WboitBackgroundCompositeNode, WboitBackgroundCompositePass, WboitCompositeBindGroup,
+    WboitCompositeKey, WboitCompositeNode, WboitCompositePass, WboitCompositePipeline,
+    WboitCompositePipelineId, WboitCompositeShader, WboitPostTaaCompositeNode,
+    WboitPostTaaCompositePass, prepare_wboit_composite_bind_group, queue_wboit_composite_pipeline,
+};
+use self::groups::{WboitGroupRanges, WboitGroupsNode, WboitGroupsPass, sort_wboit_groups};
+use self::probe::{
+    WboitPixelProbe, WboitPixelProbeBuffer, WboitPixelProbeSink, WboitPixelProbed,
+    map_wboit_pixel_probes, prepare_wboit_pixel_probes, sync_wboit_pixel_probes,
 };
+use self::shared::{WboitSharedViews, extract_wboit_shared_target};
 
-/// Populate `ViewSortedRenderPhases<WboitAccum3d>` with an entry for each active WBOIT camera.
+/// Populate `ViewSortedRenderPhases<WboitAccum3d>` with an entry for each active WBOIT camera,
+/// and `ViewSortedRenderPhases<WboitNearestDepth3d>` for those using
+/// `WboitSettings::nearest_depth_falloff`.
 ///
 /// Mirrors how `extract_core_3d_camera_phases` manages `Transparent3d`.
 fn extract_wboit_camera_phases(
     mut wboit_phases: ResMut<ViewSortedRenderPhases<WboitAccum3d>>,
-    cameras: Extract<Query<Entity, (With<Camera3d>, With<crate::settings::WboitSettings>)>>,
+    mut nearest_phases: ResMut<ViewSortedRenderPhases<WboitNearestDepth3d>>,
+    cameras: Extract<Query<(Entity, &WboitSettings), (With<Camera3d>, Without<HEWboitSettings>)>>,
     mut live_entities: Local<HashSet<RetainedViewEntity>>,
 ) {
     live_entities.clear();
-    for entity in &cameras {
+    for (entity, settings) in &cameras {
+        if !settings.uses_naive_path() {
+            continue;
+        }
         let retained = RetainedViewEntity::new(entity.into(), None, 0);
         wboit_phases.insert_or_clear(retained);
+        if settings.uses_nearest_depth() {
+            nearest_phases.insert_or_clear(retained);
+        } else {
+            nearest_phases.remove(&retained);
+        }
         live_entities.insert(retained);
     }
     wboit_phases.retain(|view_entity, _| live_entities.contains(view_entity));
+    nearest_phases.retain(|view_entity, _| live_entities.contains(view_entity));
+}
+
+/// Rebuild device-owned naive WBOIT state when `RenderDevice` is replaced.
+///
+/// Bevy 0.16 does not recreate the device on loss by itself, but integrations that do (e.g.
+/// after a GPU reset on the web) replace the `RenderDevice` resource. The `finish`-stage
+/// pipelines are then re-created from the new device (each `WboitMaterialPlugin` rebuilds its
+/// own `WboitPipeline<M>` right after this), and the per-camera buffers, textures,
+/// bind groups and pipeline ids are dropped so the prepare and queue systems rebuild them.
+pub(crate) fn reset_wboit_on_device_change(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    views: Query<Entity, With<WboitSettings>>,
+) {
+    if !render_device.is_changed() || render_device.is_added() {
+        return;
+    }
+    warn!("RenderDevice was replaced, rebuilding WBOIT pipelines and resources");
+    commands.queue(|world: &mut World| {
+        let accum_data_layout = WboitAccumDataLayout::from_world(world);
+        world.insert_resource(accum_data_layout);
+        let composite_pipeline = WboitCompositePipeline::from_world(world);
+        world.insert_resource(composite_pipeline);
+    });
+    for entity in &views {
+        commands.entity(entity).remove::<(
+            WboitTextures,
+            WboitParamsBuffer,
+            WboitAccumBindGroup,
+            WboitNearestDepthBindGroup,
+            WboitCompositePipelineId,
+            WboitCompositeBindGroup,
+            WboitCompositeKey,
+            WboitPixelProbeBuffer,
+            WboitGroupRanges,
+        )>();
+    }
+}
+
+/// Send this event to drop every specialized naive WBOIT pipeline (the accum pipelines of each
+/// `WboitMaterialPlugin` and `WboitMinimalPlugin`, and each camera's composite), so they are
+/// specialized and compiled again from the current shader sources on the next frame. For
+/// shader-editing workflows where an edit is not picked up by the cached pipelines.
+#[derive(Event, Clone, Copy, Debug, Default)]
+pub struct WboitInvalidatePipelines;
+
+/// Render-world flag: whether a [`WboitInvalidatePipelines`] event was sent since the last
+/// extraction.
+#[derive(Resource, Default)]
+pub struct WboitPipelinesInvalidated(pub bool);
+
+fn extract_wboit_pipeline_invalidation(
+    mut invalidated: ResMut<WboitPipelinesInvalidated>,
+    mut events: Extract<EventReader<WboitInvalidatePipelines>>,
+) {
+    invalidated.0 = events.read().count() > 0;
+}
+
+/// Drop the composite pipeline ids on [`WboitInvalidatePipelines`], so
+/// `queue_wboit_composite_pipeline` queues new ones. Each `WboitMaterialPlugin` clears its
+/// specialized accum pipelines right after this.
+pub(crate) fn invalidate_wboit_pipelines(
+    mut commands: Commands,
+    invalidated: Res<WboitPipelinesInvalidated>,
+    views: Query<Entity, With<WboitCompositePipelineId>>,
+) {
+    if !invalidated.0 {
+        return;
+    }
+    info!("Invalidating WBOIT pipelines");
+    for entity in &views {
+        commands
+            .entity(entity)
+            .remove::<(WboitCompositePipelineId, WboitCompositeKey)>();
+    }
+}
+
+/// Drop the naive WBOIT state of views that stopped using the naive path (`WboitSettings`
+/// removed, quality raised to 3, or `HEWboitSettings` added), so a camera switched to HE-WBOIT
+/// or to no OIT keeps no naive targets alive. `WboitTextures` is shared with the HE path and
+/// only dropped when the view has no `HEWboitSettings` either.
+pub(crate) fn remove_inactive_wboit_views(
+    mut commands: Commands,
+    views: Query<(Entity, Has<HEWboitSettings>), (With<WboitParamsBuffer>, Without<WboitSettings>)>,
+) {
+    for (entity, has_he) in &views {
+        let mut entity = commands.entity(entity);
+        entity.remove::<(
+            WboitParamsBuffer,
+            WboitAccumBindGroup,
+            WboitNearestDepthBindGroup,
+            WboitCompositePipelineId,
+            WboitCompositeBindGroup,
+            WboitCompositeKey,
+            WboitPixelProbeBuffer,
+            WboitGroupRanges,
+        )>();
+        if !has_he {
+            entity.remove::<WboitTextures>();
+        }
+    }
 }
 
 /// Plugin that enables naive WBOIT (McGuire & Bavoil 2013) rendering.
 ///
 /// Add `WboitSettings` to a camera entity to opt in.
+///
+/// Registers the naive WBOIT render graph nodes, so only the first instance is built: adding
+/// it again, directly or through [`WboitPlugin`], [`WboitMinimalPlugin`] or
+/// [`WboitShadowMaterialPlugin`] (which add it when it is missing), does nothing.
+///
+/// [`WboitPlugin`]: crate::WboitPlugin
+/// [`WboitMinimalPlugin`]: crate::WboitMinimalPlugin
+/// [`WboitShadowMaterialPlugin`]: crate::WboitShadowMaterialPlugin
 pub struct NaiveWboitPlugin;
 
 impl Plugin for NaiveWboitPlugin {
     fn build(&self, app: &mut App) {
+        if app.is_plugin_added::<Self>() {
+            return;
+        }
         load_internal_asset!(
             app,
             crate::pipeline::WBOIT_FRAGMENT_SHADER_HANDLE,
             "../shaders/wboit_fragment.wgsl",
             Shader::from_wgsl
         );
+        load_internal_asset!(
+            app,
+            crate::pipeline::WBOIT_VERTEX_SHADER_HANDLE,
+            "../shaders/wboit_vertex.wgsl",
+            Shader::from_wgsl
+        );
         load_internal_asset!(
             app,
             composite::WBOIT_COMPOSITE_SHADER_HANDLE,
@@ -66,64 +220,221 @@ impl Plugin for NaiveWboitPlugin {
             Shader::from_wgsl
         );
 
+        if !app.is_plugin_added::<WboitDiagnosticsPlugin>() {
+            app.add_plugins(WboitDiagnosticsPlugin);
+        }
+        if !app.is_plugin_added::<WboitPostCompositePlugin>() {
+            app.add_plugins(WboitPostCompositePlugin);
+        }
+        if !app.is_plugin_added::<ExtractResourcePlugin<WboitSortFn>>() {
+            app.add_plugins(ExtractResourcePlugin::<WboitSortFn>::default());
+        }
+
+        let probe_sink = WboitPixelProbeSink::default();
         app.add_plugins((
             ExtractComponentPlugin::<crate::settings::WboitSettings>::default(),
+            ExtractComponentPlugin::<crate::settings::WboitLayerConfig>::default(),
+            ExtractComponentPlugin::<crate::settings::WboitCompositeMask>::default(),
+            ExtractComponentPlugin::<crate::settings::WboitWeightOverride>::default(),
+            ExtractComponentPlugin::<crate::settings::WboitGroups>::default(),
+            ExtractComponentPlugin::<WboitPixelProbe>::default(),
+            ExtractResourcePlugin::<WboitCompositeShader>::default(),
+            ExtractResourcePlugin::<WboitPrewarmMeshes>::default(),
             // Registers batch_and_prepare_sorted_render_phase + collect_buffers_for_phase for
             // WboitAccum3d, which populates phase_instance_buffers so SetMeshBindGroup<1>
             // can find the per-phase GPU buffer in GPU-preprocessing mode.
-            SortedRenderPhasePlugin::<WboitAccum3d, MeshPipeline>::new(
+            SortedRenderPhasePlugin::<WboitAccum3d, MeshPipeline>::new(RenderDebugFlags::default()),
+            SortedRenderPhasePlugin::<WboitNearestDepth3d, MeshPipeline>::new(
                 RenderDebugFlags::default(),
             ),
         ))
         .register_type::<crate::settings::WboitSettings>()
-        .add_systems(Update, crate::pipeline::check_msaa_wboit)
-        .add_systems(Last, crate::pipeline::configure_depth_texture_usages_wboit);
+        .register_type::<crate::settings::WboitDefaults>()
+        .register_type::<crate::settings::InheritWboitDefaults>()
+        .register_type::<crate::settings::WboitLayerConfig>()
+        .register_type::<crate::settings::WboitAlwaysVisible>()
+        .register_type::<crate::settings::WboitInstanceOpacity>()
+        .register_type::<crate::settings::WboitDepthOffset>()
+        .register_type::<crate::settings::WboitCompositeMask>()
+        .register_type::<crate::settings::WboitWeightOverride>()
+        .register_type::<crate::settings::WboitGroup>()
+        .register_type::<crate::settings::WboitGroups>()
+        .register_type::<crate::settings::WboitSharedTarget>()
+        .register_type::<WboitPixelProbe>()
+        .add_event::<WboitPixelProbed>()
+        .add_event::<WboitInvalidatePipelines>()
+        .insert_resource(probe_sink.clone())
+        .add_systems(First, sync_wboit_pixel_probes)
+        .init_resource::<crate::settings::WboitDefaults>()
+        .init_resource::<WboitCompositeShader>()
+        .register_type::<crate::settings::WboitMode>()
+        .add_systems(
+            Update,
+            (
+                crate::pipeline::check_msaa_wboit,
+                crate::pipeline::check_wboit_mode,
+                crate::pipeline::check_transparent_window_wboit,
+            ),
+        )
+        .add_systems(
+            Last,
+            (
+                crate::settings::apply_wboit_defaults,
+                crate::settings::apply_wboit_quality,
+                crate::pipeline::configure_depth_texture_usages_wboit,
+                crate::queue::sync_wboit_instance_data,
+            )
+                .chain(),
+        );
 
         let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
             return;
         };
 
+        crate::textures::add_wboit_textures_recreated_event(render_app);
         render_app
+            .insert_resource(probe_sink)
             .init_resource::<DrawFunctions<WboitAccum3d>>()
-            .init_resource::<SpecializedMeshPipelines<WboitPipeline>>()
-            .add_render_command::<WboitAccum3d, DrawWboit>()
-            .add_systems(ExtractSchedule, extract_wboit_camera_phases)
+            .init_resource::<DrawFunctions<WboitNearestDepth3d>>()
+            .init_resource::<WboitMeshLayers>()
+            .init_resource::<WboitAlwaysVisibleEntities>()
+            .init_resource::<WboitInstanceDataEntities>()
+            .init_resource::<WboitGroupEntities>()
+            .init_resource::<WboitPipelinesInvalidated>()
+            .init_resource::<WboitSharedViews>()
+            .add_systems(
+                ExtractSchedule,
+                (
+                    extract_wboit_camera_phases,
+                    extract_wboit_mesh_layers,
+                    extract_wboit_always_visible,
+                    extract_wboit_instance_data,
+                    extract_wboit_groups,
+                    extract_wboit_pipeline_invalidation,
+                    extract_wboit_shared_target,
+                ),
+            )
             .add_systems(
                 Render,
                 (
+                    reset_wboit_on_device_change.in_set(RenderSet::ManageViews),
+                    invalidate_wboit_pipelines.in_set(RenderSet::ManageViews),
+                    remove_inactive_wboit_views.in_set(RenderSet::ManageViews),
                     prepare_wboit_textures.in_set(RenderSet::PrepareResources),
-                    queue_wboit_meshes
+                    prepare_wboit_pixel_probes
+                        .in_set(RenderSet::PrepareResources)
+                        .after(prepare_wboit_textures),
+                    map_wboit_pixel_probes.in_set(RenderSet::Cleanup),
+                    cap_wboit_accum_phases
                         .in_set(RenderSet::QueueMeshes)
-                        .after(queue_material_meshes::<StandardMaterial>),
+                        .after(QueueWboitMeshes),
                     drain_transparent_for_wboit
                         .in_set(RenderSet::QueueMeshes)
-                        .after(queue_wboit_meshes),
-                    sort_phase_system::<WboitAccum3d>.in_set(RenderSet::PhaseSort),
+                        .after(cap_wboit_accum_phases),
+                    sort_wboit_accum_phases.in_set(RenderSet::PhaseSort),
+                    sort_phase_system::<WboitNearestDepth3d>.in_set(RenderSet::PhaseSort),
+                    sort_wboit_groups
+                        .in_set(RenderSet::PhaseSort)
+                        .after(sort_wboit_accum_phases),
                     queue_wboit_composite_pipeline.in_set(RenderSet::Queue),
-                    prepare_wboit_composite_bind_group
-                        .in_set(RenderSet::PrepareBindGroups),
+                    prepare_wboit_accum_bind_group.in_set(RenderSet::PrepareBindGroups),
+                    prepare_wboit_composite_bind_group.in_set(RenderSet::PrepareBindGroups),
                 ),
             )
-            // Register render graph nodes: accum → composite, placed after MainTransparentPass
+            // Register render graph nodes: accum → composite → the other WboitGroups, placed
+            // between MainTransparentPass and WboitPostComposite, plus the alternative post-TAA
+            // composite (WboitTaaMode::AfterTaa) and the background accum → composite before
+            // MainOpaquePass (WboitTaaMode::BeforeOpaque).
+            // Anything that draws into the view target before transparents (skybox, atmosphere
+            // sky and aerial perspective) is ordered before MainTransparentPass, so it is
+            // always under the composite.
             .add_render_graph_node::<ViewNodeRunner<WboitAccumNode>>(Core3d, WboitAccumPass)
             .add_render_graph_node::<ViewNodeRunner<WboitCompositeNode>>(Core3d, WboitCompositePass)
+            .add_render_graph_node::<ViewNodeRunner<WboitGroupsNode>>(Core3d, WboitGroupsPass)
+            .add_render_graph_node::<ViewNodeRunner<WboitPostTaaCompositeNode>>(
+                Core3d,
+                WboitPostTaaCompositePass,
+            )
             .add_render_graph_edges(
                 Core3d,
                 (
                     Node3d::MainTransparentPass,
                     WboitAccumPass,
                     WboitCompositePass,
+                    WboitGroupsPass,
+                    WboitPostComposite,
+                ),
+            )
+            .add_render_graph_node::<ViewNodeRunner<WboitBackgroundAccumNode>>(
+                Core3d,
+                WboitBackgroundAccumPass,
+            )
+            .add_render_graph_node::<ViewNodeRunner<WboitBackgroundCompositeNode>>(
+                Core3d,
+                WboitBackgroundCompositePass,
+            )
+            .add_render_graph_edges(
+                Core3d,
+                (
                     Node3d::EndMainPass,
+                    WboitPostTaaCompositePass,
+                    Node3d::Bloom,
+                ),
+            )
+            .add_render_graph_edges(
+                Core3d,
+                (
+                    Node3d::StartMainPass,
+                    WboitBackgroundAccumPass,
+                    WboitBackgroundCompositePass,
+                    Node3d::MainOpaquePass,
                 ),
             );
+
+        // After `DrawFunctions<WboitAccum3d>` exists, which it registers its draw commands in.
+        app.add_plugins(WboitMaterialPlugin::<StandardMaterial>::default());
+    }
+
+    fn is_unique(&self) -> bool {
+        false
     }
 
     fn finish(&self, app: &mut App) {
         let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
             return;
         };
+        // Already finished by the first instance.
+        if render_app
+            .world()
+            .contains_resource::<WboitCompositePipeline>()
+        {
+            return;
+        }
         render_app
-            .init_resource::<WboitPipeline>()
+            .init_resource::<WboitAccumDataLayout>()
             .init_resource::<WboitCompositePipeline>();
+
+        // TAA is an optional plugin, so only order the post-TAA composite after it when its
+        // node exists (all plugins are built by now).
+        let has_taa = render_app
+            .world()
+            .resource::<RenderGraph>()
+            .get_sub_graph(Core3d)
+            .is_some_and(|graph| graph.get_node_state(Node3d::Taa).is_ok());
+        if has_taa {
+            render_app.add_render_graph_edge(Core3d, Node3d::Taa, WboitPostTaaCompositePass);
+        }
+
+        // One-time summary of the resolved setup, for bug reports.
+        let render_device = render_app.world().resource::<RenderDevice>();
+        info!(
+            bindless = material_uses_bindless_resources::<StandardMaterial>(render_device),
+            accum_format = ?TextureFormat::Rgba16Float,
+            revealage_format = ?WBOIT_REVEALAGE_FORMAT,
+            post_taa_composite_after_taa = has_taa,
+            max_texture_dimension_2d = render_device.limits().max_texture_dimension_2d,
+            "Naive WBOIT initialized"
+        );
+        crate::diagnostics::warn_if_tile_based_gpu(render_app.world());
     }
 }
@@ -0,0 +1,279 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+
+use bevy::prelude::*;
+use bevy::render::camera::ExtractedCamera;
+use bevy::render::extract_component::ExtractComponent;
+use bevy::render::render_phase::ViewSortedRenderPhases;
+use bevy::render::render_resource::{
+    Buffer, BufferDescriptor, BufferUsages, Extent3d, MapMode, Origin3d, TexelCopyBufferInfo,
+    TexelCopyBufferLayout, TexelCopyTextureInfo, TextureAspect,
+};
+use bevy::render::renderer::{RenderContext, RenderDevice};
+use bevy::render::view::ExtractedView;
+
+use crate::phase::WboitAccum3d;
+use crate::settings::WboitSettings;
+use crate::textures::{WBOIT_ACCUM_FORMAT, WboitTextures, accum_format_max};
+
+/// Reads back the composited transparent color at one pixel of a naive WBOIT camera, e.g. the
+/// color of the glass under a crosshair. While present, each finished read sends a
+/// [`WboitPixelProbed`] event, a few frames behind; remove it to stop probing.
+///
+/// Only the accum and revealage texels at `pos` are copied, so the cost is independent of the
+/// resolution and nothing waits on the GPU.
+#[derive(Component, Clone, Copy, Debug, Default, ExtractComponent, Reflect)]
+#[reflect(Component, Default)]
+pub struct WboitPixelProbe {
+    /// Physical pixel, relative to the camera viewport's top-left corner. Clamped to the
+    /// viewport.
+    pub pos: UVec2,
+}
+
+/// Result of a [`WboitPixelProbe`].
+#[derive(Event, Clone, Copy, Debug)]
+pub struct WboitPixelProbed {
+    /// Main-world camera entity.
+    pub camera: Entity,
+    /// The probed `WboitPixelProbe::pos`.
+    pub pos: UVec2,
+    /// Premultiplied transparent layer at the pixel, as the composite blends it over the
+    /// opaque scene: the alpha is its coverage, and `LinearRgba::NONE` means no transparents.
+    /// `AlphaMode::Add` glow, the composite mask and debug views are not included.
+    pub color: LinearRgba,
+}
+
+/// Channel from the render world readback callbacks back to the main world. Shared by both
+/// worlds.
+#[derive(Resource, Clone, Default)]
+pub struct WboitPixelProbeSink(pub Arc<Mutex<Vec<WboitPixelProbed>>>);
+
+/// Byte offset of the revealage texel in `WboitPixelProbeBuffer::buffer`; the accum texel is
+/// at 0. Texture-to-buffer copies need 256-byte aligned rows.
+const REVEALAGE_OFFSET: u64 = 256;
+
+/// `WboitPixelProbeBuffer::state`: free for the next copy.
+const PROBE_IDLE: u8 = 0;
+/// `WboitPixelProbeBuffer::state`: copy recorded this frame, waiting to be mapped.
+const PROBE_COPIED: u8 = 1;
+/// `WboitPixelProbeBuffer::state`: map requested, waiting for the callback.
+const PROBE_MAPPING: u8 = 2;
+
+/// Per-camera staging buffer the probed texels are copied into for [`WboitPixelProbe`].
+#[derive(Component)]
+pub struct WboitPixelProbeBuffer {
+    pub buffer: Buffer,
+    /// Probed texel in the accum targets.
+    pub texel: UVec2,
+    state: Arc<AtomicU8>,
+}
+
+impl WboitPixelProbeBuffer {
+    /// Copy the probed accum and revealage texels into the buffer, unless it is still mapped
+    /// or waiting to be. Called by the accum pass right after it drew.
+    pub fn copy_texels(&self, render_context: &mut RenderContext, textures: &WboitTextures) {
+        if self
+            .state
+            .compare_exchange(
+                PROBE_IDLE,
+                PROBE_COPIED,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            )
+            .is_err()
+        {
+            return;
+        }
+        let sources = [
+            (&textures.accum, 0),
+            (&textures.revealage[textures.frame_index], REVEALAGE_OFFSET),
+        ];
+        for (texture, offset) in sources {
+            render_context.command_encoder().copy_texture_to_buffer(
+                TexelCopyTextureInfo {
+                    texture: &texture.texture,
+                    mip_level: 0,
+                    origin: Origin3d {
+                        x: self.texel.x,
+                        y: self.texel.y,
+                        z: 0,
+                    },
+                    aspect: TextureAspect::All,
+                },
+                TexelCopyBufferInfo {
+                    buffer: &self.buffer,
+                    layout: TexelCopyBufferLayout {
+                        offset,
+                        bytes_per_row: None,
+                        rows_per_image: None,
+                    },
+                },
+                Extent3d {
+                    width: 1,
+                    height: 1,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+    }
+}
+
+/// Create the staging buffer of each camera with `WboitPixelProbe` and update its texel.
+pub fn prepare_wboit_pixel_probes(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    mut views: Query<(
+        Entity,
+        &ExtractedCamera,
+        &WboitSettings,
+        &WboitPixelProbe,
+        &WboitTextures,
+        Option<&mut WboitPixelProbeBuffer>,
+    )>,
+) {
+    for (entity, camera, settings, probe, textures, existing) in &mut views {
+        let Some(viewport_size) = camera.physical_viewport_size else {
+            continue;
+        };
+        let size = textures.accum.texture.size();
+        let pos = probe.pos.min(viewport_size.saturating_sub(UVec2::ONE));
+        // Full-resolution targets cover the render target, scaled ones only the viewport.
+        let texel = if settings.is_accum_scaled() {
+            let target_size = camera.physical_target_size.unwrap_or(viewport_size);
+            (pos.as_vec2() * settings.accum_ratio(viewport_size, target_size)).as_uvec2()
+        } else {
+            let origin = camera
+                .viewport
+                .as_ref()
+                .map_or(UVec2::ZERO, |viewport| viewport.physical_position);
+            origin + pos
+        };
+        let texel = texel.min(UVec2::new(size.width, size.height) - UVec2::ONE);
+
+        if let Some(mut existing) = existing {
+            existing.texel = texel;
+            continue;
+        }
+        let buffer = render_device.create_buffer(&BufferDescriptor {
+            label: Some("wboit_pixel_probe_buffer"),
+            size: REVEALAGE_OFFSET * 2,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        commands.entity(entity).insert(WboitPixelProbeBuffer {
+            buffer,
+            texel,
+            state: Arc::new(AtomicU8::new(PROBE_IDLE)),
+        });
+    }
+}
+
+/// Map the probe buffers copied this frame and resolve them in the callback. Views that drew
+/// no transparents this frame (so nothing was copied) report `LinearRgba::NONE` right away.
+pub fn map_wboit_pixel_probes(
+    sink: Res<WboitPixelProbeSink>,
+    wboit_phases: Res<ViewSortedRenderPhases<WboitAccum3d>>,
+    views: Query<(
+        &ExtractedView,
+        &WboitSettings,
+        &WboitPixelProbe,
+        &WboitPixelProbeBuffer,
+    )>,
+) {
+    for (view, settings, probe, probe_buffer) in &views {
+        let camera = view.retained_view_entity.main_entity.id();
+        let pos = probe.pos;
+        let copied = probe_buffer
+            .state
+            .compare_exchange(
+                PROBE_COPIED,
+                PROBE_MAPPING,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            )
+            .is_ok();
+        if !copied {
+            let drew = wboit_phases
+                .get(&view.retained_view_entity)
+                .is_some_and(|phase| !phase.items.is_empty());
+            if let (false, Ok(mut probed)) = (drew, sink.0.lock()) {
+                probed.push(WboitPixelProbed {
+                    camera,
+                    pos,
+                    color: LinearRgba::NONE,
+                });
+            }
+            continue;
+        }
+
+        let buffer = probe_buffer.buffer.clone();
+        let state = probe_buffer.state.clone();
+        let sink = sink.0.clone();
+        let settings = *settings;
+        probe_buffer
+            .buffer
+            .slice(..)
+            .map_async(MapMode::Read, move |result| {
+                if result.is_ok() {
+                    let (accum, revealage) = {
+                        let data = buffer.slice(..).get_mapped_range();
+                        let accum: [f32; 4] = std::array::from_fn(|i| {
+                            f16_to_f32(u16::from_le_bytes([data[i * 2], data[i * 2 + 1]]))
+                        });
+                        (accum, data[REVEALAGE_OFFSET as usize] as f32 / 255.0)
+                    };
+                    buffer.unmap();
+                    if let Ok(mut probed) = sink.lock() {
+                        probed.push(WboitPixelProbed {
+                            camera,
+                            pos,
+                            color: resolve_probe(accum, revealage, &settings),
+                        });
+                    }
+                }
+                state.store(PROBE_IDLE, Ordering::Release);
+            });
+    }
+}
+
+/// CPU version of `resolve` in `wboit_composite.wgsl`, with the accum clamp, revealage gamma
+/// and exposure the composite applies.
+fn resolve_probe(accum: [f32; 4], revealage: f32, settings: &WboitSettings) -> LinearRgba {
+    let accum_max = accum_format_max(WBOIT_ACCUM_FORMAT) as f32;
+    let accum = accum.map(|component| component.min(accum_max));
+    if accum[3] < 1e-5 {
+        return LinearRgba::NONE;
+    }
+    let revealage = if settings.revealage_gamma != 1.0 {
+        revealage.powf(settings.revealage_gamma)
+    } else {
+        revealage
+    };
+    let alpha = (1.0 - revealage).min(settings.max_opacity);
+    let scale = alpha / accum[3].max(1e-5) * settings.composite_exposure;
+    LinearRgba::new(accum[0] * scale, accum[1] * scale, accum[2] * scale, alpha)
+}
+
+/// Decode an IEEE 754 half-precision float (the accum target's `Rgba16Float` texels).
+pub(super) fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = i32::from((bits >> 10) & 0x1f);
+    let mantissa = f32::from(bits & 0x3ff);
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+/// Send the probe results read back by the render world as [`WboitPixelProbed`] events.
+pub fn sync_wboit_pixel_probes(
+    sink: Res<WboitPixelProbeSink>,
+    mut events: EventWriter<WboitPixelProbed>,
+) {
+    let Ok(mut probed) = sink.0.lock() else {
+        return;
+    };
+    events.write_batch(probed.drain(..));
+}
@@ -0,0 +1,63 @@
+use bevy::prelude::*;
+use bevy::render::Extract;
+use bevy::render::sync_world::RenderEntity;
+
+use crate::settings::{HEWboitSettings, WboitSettings, WboitSharedTarget};
+
+/// Render world counterpart of [`WboitSharedTarget`]: the render entities of its active naive
+/// WBOIT cameras, in list order. Empty without the resource; a single camera shares nothing.
+#[derive(Resource, Default)]
+pub struct WboitSharedViews(pub Vec<Entity>);
+
+impl WboitSharedViews {
+    /// The camera owning the shared targets, if `view` draws into them without owning them.
+    pub fn owner_of(&self, view: Entity) -> Option<Entity> {
+        let owner = *self.0.first()?;
+        (owner != view && self.0.contains(&view)).then_some(owner)
+    }
+
+    /// Whether `view` owns the shared targets, clearing them for the other cameras.
+    pub fn is_owner(&self, view: Entity) -> bool {
+        self.0.len() > 1 && self.0.first() == Some(&view)
+    }
+
+    /// Whether `view` composites the shared targets, as the last camera sharing them.
+    pub fn resolves(&self, view: Entity) -> bool {
+        self.0.len() > 1 && self.0.last() == Some(&view)
+    }
+
+    /// Whether `view` shares its targets but leaves the composite to a later camera.
+    pub fn defers_composite(&self, view: Entity) -> bool {
+        self.0.len() > 1 && self.0.contains(&view) && !self.resolves(view)
+    }
+}
+
+/// Extract [`WboitSharedTarget`] into [`WboitSharedViews`], skipping inactive cameras and
+/// cameras that are not on the naive path.
+pub fn extract_wboit_shared_target(
+    mut shared_views: ResMut<WboitSharedViews>,
+    shared_target: Extract<Option<Res<WboitSharedTarget>>>,
+    cameras: Extract<Query<(&RenderEntity, &Camera, &WboitSettings), Without<HEWboitSettings>>>,
+) {
+    shared_views.0.clear();
+    let Some(shared_target) = shared_target.as_ref() else {
+        return;
+    };
+    let mut last_order = None;
+    for &entity in &shared_target.cameras {
+        let Ok((render_entity, camera, settings)) = cameras.get(entity) else {
+            continue;
+        };
+        if !camera.is_active || !settings.uses_naive_path() {
+            continue;
+        }
+        if last_order.is_some_and(|order| camera.order <= order) {
+            warn_once!(
+                "WboitSharedTarget cameras must be listed in rendering order (increasing \
+                 Camera::order); {entity} renders before the camera listed ahead of it"
+            );
+        }
+        last_order = Some(camera.order);
+        shared_views.0.push(render_entity.id());
+    }
+}
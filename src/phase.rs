@@ -1,11 +1,21 @@
+use bevy::ecs::system::ReadOnlySystemParam;
 use bevy::math::FloatOrd;
 use bevy::prelude::*;
-use bevy::render::render_phase::{CachedRenderPipelinePhaseItem, DrawFunctionId, PhaseItem, PhaseItemExtraIndex, SortedPhaseItem};
+use bevy::render::render_phase::{
+    CachedRenderPipelinePhaseItem, DrawFunctionId, PhaseItem, PhaseItemExtraIndex, SortedPhaseItem,
+};
+use bevy::render::render_phase::{
+    DrawFunctions, RenderCommand, RenderCommandState, SortedRenderPhase, TrackedRenderPass,
+};
 use bevy::render::render_resource::CachedRenderPipelineId;
 use bevy::render::sync_world::MainEntity;
+use core::marker::PhantomData;
 use core::ops::Range;
 
 pub struct HistoAccum3d {
+    /// View-space sort distance, copied from `Transparent3d` and therefore including the
+    /// material's `depth_bias`. Only affects batching order; the HE depth binning uses the
+    /// unbiased rasterized depth.
     pub distance: f32,
     pub pipeline: CachedRenderPipelineId,
     pub entity: (Entity, MainEntity),
@@ -76,6 +86,9 @@ impl SortedPhaseItem for HistoAccum3d {
 }
 
 pub struct WboitAccum3d {
+    /// View-space sort distance, copied from `Transparent3d` and therefore including the
+    /// material's `depth_bias`. Only affects batching order; the WBOIT weight uses the
+    /// unbiased rasterized depth.
     pub distance: f32,
     pub pipeline: CachedRenderPipelineId,
     pub entity: (Entity, MainEntity),
@@ -144,3 +157,235 @@ impl SortedPhaseItem for WboitAccum3d {
         self.indexed
     }
 }
+
+/// Transparent item drawn depth-only into the nearest-transparent-depth target before the
+/// accum pass, for `WboitSettings::nearest_depth_falloff`.
+pub struct WboitNearestDepth3d {
+    /// Same sort distance as the matching `WboitAccum3d` item.
+    pub distance: f32,
+    pub pipeline: CachedRenderPipelineId,
+    pub entity: (Entity, MainEntity),
+    pub draw_function: DrawFunctionId,
+    pub batch_range: Range<u32>,
+    pub extra_index: PhaseItemExtraIndex,
+    pub indexed: bool,
+}
+
+impl PhaseItem for WboitNearestDepth3d {
+    const AUTOMATIC_BATCHING: bool = true;
+
+    #[inline]
+    fn entity(&self) -> Entity {
+        self.entity.0
+    }
+
+    #[inline]
+    fn main_entity(&self) -> MainEntity {
+        self.entity.1
+    }
+
+    #[inline]
+    fn draw_function(&self) -> DrawFunctionId {
+        self.draw_function
+    }
+
+    #[inline]
+    fn batch_range(&self) -> &Range<u32> {
+        &self.batch_range
+    }
+
+    #[inline]
+    fn batch_range_mut(&mut self) -> &mut Range<u32> {
+        &mut self.batch_range
+    }
+
+    #[inline]
+    fn extra_index(&self) -> PhaseItemExtraIndex {
+        self.extra_index.clone()
+    }
+
+    #[inline]
+    fn batch_range_and_extra_index_mut(&mut self) -> (&mut Range<u32>, &mut PhaseItemExtraIndex) {
+        (&mut self.batch_range, &mut self.extra_index)
+    }
+}
+
+impl CachedRenderPipelinePhaseItem for WboitNearestDepth3d {
+    #[inline]
+    fn cached_pipeline(&self) -> CachedRenderPipelineId {
+        self.pipeline
+    }
+}
+
+impl SortedPhaseItem for WboitNearestDepth3d {
+    type SortKey = FloatOrd;
+
+    #[inline]
+    fn sort_key(&self) -> Self::SortKey {
+        FloatOrd(self.distance)
+    }
+
+    #[inline]
+    fn indexed(&self) -> bool {
+        self.indexed
+    }
+}
+
+/// Transparent item of a material excluded from WBOIT, drawn sorted after the WBOIT
+/// composite by `WboitLateTransparentNode`.
+pub struct WboitLate3d {
+    /// Sort distance, copied from `Transparent3d`, so excluded materials are drawn in the same
+    /// back-to-front order as in the standard transparent pass.
+    pub distance: f32,
+    pub pipeline: CachedRenderPipelineId,
+    pub entity: (Entity, MainEntity),
+    pub draw_function: DrawFunctionId,
+    pub batch_range: Range<u32>,
+    pub extra_index: PhaseItemExtraIndex,
+    pub indexed: bool,
+}
+
+impl PhaseItem for WboitLate3d {
+    const AUTOMATIC_BATCHING: bool = true;
+
+    #[inline]
+    fn entity(&self) -> Entity {
+        self.entity.0
+    }
+
+    #[inline]
+    fn main_entity(&self) -> MainEntity {
+        self.entity.1
+    }
+
+    #[inline]
+    fn draw_function(&self) -> DrawFunctionId {
+        self.draw_function
+    }
+
+    #[inline]
+    fn batch_range(&self) -> &Range<u32> {
+        &self.batch_range
+    }
+
+    #[inline]
+    fn batch_range_mut(&mut self) -> &mut Range<u32> {
+        &mut self.batch_range
+    }
+
+    #[inline]
+    fn extra_index(&self) -> PhaseItemExtraIndex {
+        self.extra_index.clone()
+    }
+
+    #[inline]
+    fn batch_range_and_extra_index_mut(&mut self) -> (&mut Range<u32>, &mut PhaseItemExtraIndex) {
+        (&mut self.batch_range, &mut self.extra_index)
+    }
+}
+
+impl CachedRenderPipelinePhaseItem for WboitLate3d {
+    #[inline]
+    fn cached_pipeline(&self) -> CachedRenderPipelineId {
+        self.pipeline
+    }
+}
+
+impl SortedPhaseItem for WboitLate3d {
+    type SortKey = FloatOrd;
+
+    #[inline]
+    fn sort_key(&self) -> Self::SortKey {
+        FloatOrd(self.distance)
+    }
+
+    #[inline]
+    fn indexed(&self) -> bool {
+        self.indexed
+    }
+}
+
+/// Marker under which a `WboitAccum3d` render command `C` is registered a second time, for
+/// the items of odd-ranked `WboitGroups` groups. Batching only merges neighboring items with
+/// the same draw function, so switching ids at every group boundary keeps a batch from
+/// spanning two groups, which are drawn by separate passes.
+pub struct WboitOddGroup<C>(PhantomData<C>);
+
+/// Register the `WboitAccum3d` render command `C` under `WboitOddGroup<C>` as well.
+pub(crate) fn add_odd_group_render_command<C>(render_app: &mut SubApp)
+where
+    C: RenderCommand<WboitAccum3d> + Send + Sync + 'static,
+    C::Param: ReadOnlySystemParam,
+{
+    let draw_function = RenderCommandState::<WboitAccum3d, C>::new(render_app.world_mut());
+    render_app
+        .world()
+        .resource::<DrawFunctions<WboitAccum3d>>()
+        .write()
+        .add_with::<WboitOddGroup<C>, _>(draw_function);
+}
+
+/// Render `range` of a sorted WBOIT phase like `SortedRenderPhase::render_range`, except that
+/// an item whose draw fails (e.g. its entity was despawned after the phase was queued) is
+/// skipped instead of ending the pass, so the other items still draw. Failures are logged once
+/// per call, with the first error and the number of skipped items.
+pub(crate) fn render_phase_range<'w, I: SortedPhaseItem>(
+    phase: &SortedRenderPhase<I>,
+    render_pass: &mut TrackedRenderPass<'w>,
+    world: &'w World,
+    view: Entity,
+    range: Range<usize>,
+    label: &str,
+) {
+    let Some(items) = phase.items.get(range) else {
+        error!("Error rendering {label}: range out of bounds");
+        return;
+    };
+
+    let draw_functions = world.resource::<DrawFunctions<I>>();
+    let mut draw_functions = draw_functions.write();
+    draw_functions.prepare(world);
+
+    let mut first_error = None;
+    let mut skipped = 0;
+    let mut index = 0;
+    while index < items.len() {
+        let item = &items[index];
+        let batch_len = item.batch_range().len();
+        if batch_len == 0 {
+            index += 1;
+            continue;
+        }
+        let draw_function = draw_functions.get_mut(item.draw_function()).unwrap();
+        if let Err(err) = draw_function.draw(world, render_pass, view, item) {
+            first_error.get_or_insert(err);
+            skipped += 1;
+        }
+        index += batch_len;
+    }
+    if let Some(err) = first_error {
+        error!("Error rendering {label}: {err:?} ({skipped} draws skipped)");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::render::render_phase::{AddRenderCommand, SetItemPipeline};
+
+    #[test]
+    fn odd_groups_never_batch_with_even_ones() {
+        let mut render_app = SubApp::new();
+        render_app
+            .init_resource::<DrawFunctions<WboitAccum3d>>()
+            .add_render_command::<WboitAccum3d, SetItemPipeline>();
+        add_odd_group_render_command::<SetItemPipeline>(&mut render_app);
+
+        let draw_functions = render_app.world().resource::<DrawFunctions<WboitAccum3d>>();
+        let draw_functions = draw_functions.read();
+        assert_ne!(
+            draw_functions.id::<SetItemPipeline>(),
+            draw_functions.id::<WboitOddGroup<SetItemPipeline>>(),
+        );
+    }
+}
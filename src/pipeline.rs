@@ -1,65 +1,315 @@
-use bevy::asset::{weak_handle, Handle};
-use bevy::pbr::{material_uses_bindless_resources, MeshPipeline, StandardMaterial};
-use bevy::render::mesh::MeshVertexBufferLayoutRef;
+use bevy::asset::{Handle, weak_handle};
+use bevy::core_pipeline::core_3d::CORE_3D_DEPTH_FORMAT;
+use bevy::pbr::{MeshPipeline, StandardMaterial, material_uses_bindless_resources};
+use bevy::render::camera::NormalizedRenderTarget;
+use bevy::render::mesh::{MeshVertexBufferLayoutRef, RenderMesh};
 use bevy::render::render_resource::{
-    AsBindGroup, BindGroupLayout, BlendComponent, BlendFactor, BlendOperation,
-    BlendState, ColorTargetState, ColorWrites, RenderPipelineDescriptor,
-    SpecializedMeshPipeline, SpecializedMeshPipelineError, TextureFormat,
+    BindGroupLayout, BindGroupLayoutEntry, BindingType, BlendComponent, BlendFactor,
+    BlendOperation, BlendState, BufferBindingType, CachedRenderPipelineId, ColorTargetState,
+    ColorWrites, CompareFunction, DepthBiasState, DepthStencilState, PipelineCache,
+    RenderPipelineDescriptor, ShaderStages, SpecializedMeshPipeline, SpecializedMeshPipelineError,
+    SpecializedMeshPipelines, StencilState, TextureFormat, TextureSampleType, TextureViewDimension,
 };
-use bevy::render::render_resource::{Shader, ShaderDefVal};
+use bevy::render::render_resource::{Shader, ShaderDefVal, ShaderRef};
 use bevy::render::renderer::RenderDevice;
+use bevy::render::view::ViewDepthTexture;
+use bevy::window::{CompositeAlphaMode, PrimaryWindow};
 use bevy::{pbr::MeshPipelineKey, prelude::*};
+use std::collections::HashSet;
+use std::marker::PhantomData;
+
+use crate::material::WboitMaterial;
+use crate::settings::{WboitDebug, WboitSettings, WboitTaaMode, WboitWeightOverride};
+use crate::textures::{
+    WBOIT_ACCUM_FORMAT, WBOIT_NEAREST_DEPTH_FORMAT, WBOIT_REVEALAGE_FORMAT, accum_format_max,
+};
 
 pub const WBOIT_FRAGMENT_SHADER_HANDLE: Handle<Shader> =
     weak_handle!("3e4b7c2a-1f0d-4e8a-9b5c-2d6f7e8a9b0c");
 
-/// The WBOIT accumulation pipeline.
+/// Accum vertex shader used with `WboitTaaMode::AfterTaa` and `WboitDepthOffset`; see
+/// [`WboitPipelineKey::unjittered`] and [`WboitPipelineKey::instance_data`].
+pub const WBOIT_VERTEX_SHADER_HANDLE: Handle<Shader> =
+    weak_handle!("9a7e3d51-c2b8-4f06-8e1d-6b4a0c7f2e93");
+
+/// Depth format the naive and HE accum pipelines are built for, set explicitly on their
+/// depth-stencil state instead of relying on what `MeshPipeline::specialize` picks.
 ///
-/// Wraps `MeshPipeline` but adds the StandardMaterial bind group layout at index 2,
-/// overrides the fragment shader for WBOIT MRT output.
+/// Bevy 0.16 has no per-camera depth format: `prepare_core_3d_depth_textures` creates every
+/// `Camera3d` depth texture as `CORE_3D_DEPTH_FORMAT`, so this always matches. Should a view's
+/// depth texture be replaced by one with another format, the accum passes skip that view (see
+/// [`view_depth_matches`]) rather than hit a validation error.
+pub const WBOIT_DEPTH_FORMAT: TextureFormat = CORE_3D_DEPTH_FORMAT;
+
+/// Whether `depth` can be attached to the accum pipelines, i.e. has [`WBOIT_DEPTH_FORMAT`].
+/// Warns (once) when it cannot.
+pub fn view_depth_matches(depth: &ViewDepthTexture) -> bool {
+    let format = depth.texture.format();
+    if format != WBOIT_DEPTH_FORMAT {
+        warn_once!(
+            "View depth format {format:?} does not match the WBOIT accum pipelines \
+             ({WBOIT_DEPTH_FORMAT:?}); skipping the WBOIT accum pass for that view"
+        );
+        return false;
+    }
+    true
+}
+
+/// Accum data bind group layout (group 3 in `wboit_fragment.wgsl`): params uniform and
+/// opaque depth.
+///
+/// Shared by every `WboitPipeline<M>`, so the per-camera `WboitAccumBindGroup` is compatible
+/// with all registered materials.
 #[derive(Resource, Clone)]
-pub struct WboitPipeline {
+pub struct WboitAccumDataLayout(pub BindGroupLayout);
+
+impl FromWorld for WboitAccumDataLayout {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let accum_data_entries = vec![
+            // Binding 0: WboitParams uniform
+            BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            // Binding 1: opaque depth texture (read-only attachment during the accum pass)
+            BindGroupLayoutEntry {
+                binding: 1,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Depth,
+                    view_dimension: TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            // Binding 2: nearest transparent depth (`WboitSettings::nearest_depth_falloff`;
+            // the opaque depth stands in when it is off, and during the prepass writing it)
+            BindGroupLayoutEntry {
+                binding: 2,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Depth,
+                    view_dimension: TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+        ];
+        WboitAccumDataLayout(
+            render_device.create_bind_group_layout(
+                "wboit_accum_data_bind_group_layout",
+                &accum_data_entries,
+            ),
+        )
+    }
+}
+
+/// The WBOIT accumulation pipeline for material `M`.
+///
+/// Wraps `MeshPipeline` but adds the material bind group layout at index 2,
+/// overrides the fragment shader for WBOIT MRT output.
+///
+/// Group layout: 0=View, 1=Mesh, 2=Material, 3=WboitAccumData
+#[derive(Resource)]
+pub struct WboitPipeline<M: WboitMaterial = StandardMaterial> {
     pub mesh_pipeline: MeshPipeline,
-    /// StandardMaterial's bind group layout, inserted at index 2.
+    /// The material's bind group layout, inserted at index 2.
     pub material_layout: BindGroupLayout,
+    /// Accum data bind group layout (params uniform, opaque depth), group 3.
+    pub accum_data_layout: BindGroupLayout,
     pub fragment_shader: Handle<Shader>,
-    /// Whether the device supports (and will use) bindless resources for StandardMaterial.
+    /// Whether the device supports (and will use) bindless resources for the material.
     /// Mirrors the check in `MaterialPipelineSpecializer` so we add `BINDLESS` to shader defs.
     pub bindless: bool,
+    pub marker: PhantomData<M>,
 }
 
-impl FromWorld for WboitPipeline {
+impl<M: WboitMaterial> Clone for WboitPipeline<M> {
+    fn clone(&self) -> Self {
+        Self {
+            mesh_pipeline: self.mesh_pipeline.clone(),
+            material_layout: self.material_layout.clone(),
+            accum_data_layout: self.accum_data_layout.clone(),
+            fragment_shader: self.fragment_shader.clone(),
+            bindless: self.bindless,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<M: WboitMaterial> FromWorld for WboitPipeline<M> {
     fn from_world(world: &mut World) -> Self {
+        let accum_data_layout = world
+            .get_resource_or_init::<WboitAccumDataLayout>()
+            .0
+            .clone();
         let mesh_pipeline = world.resource::<MeshPipeline>().clone();
+        let fragment_shader = match M::wboit_fragment_shader() {
+            ShaderRef::Default => WBOIT_FRAGMENT_SHADER_HANDLE,
+            ShaderRef::Handle(handle) => handle,
+            ShaderRef::Path(path) => world.resource::<AssetServer>().load(path),
+        };
         let render_device = world.resource::<RenderDevice>();
-        let material_layout = StandardMaterial::bind_group_layout(render_device);
-        let bindless = material_uses_bindless_resources::<StandardMaterial>(render_device);
+        let material_layout = M::bind_group_layout(render_device);
+        let bindless = material_uses_bindless_resources::<M>(render_device);
+
         WboitPipeline {
             mesh_pipeline,
             material_layout,
-            fragment_shader: WBOIT_FRAGMENT_SHADER_HANDLE,
+            accum_data_layout,
+            fragment_shader,
             bindless,
+            marker: PhantomData,
         }
     }
 }
 
-impl SpecializedMeshPipeline for WboitPipeline {
-    type Key = MeshPipelineKey;
+/// Specialization key for `WboitPipeline`: the mesh key plus WBOIT-specific variant bits.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WboitPipelineKey {
+    pub mesh_key: MeshPipelineKey,
+    /// Accum targets are smaller than the depth buffer (`WboitSettings::accum_scale < 1`), so
+    /// the pipeline has no depth attachment and the shader tests against the sampled depth.
+    pub manual_depth_test: bool,
+    /// `WboitSettings::quality` (0..=2 on this path), selecting the weighting shader variant.
+    pub quality: u8,
+    /// The camera's `WboitWeightOverride`, replacing the weighting `quality` selects.
+    pub weight_override: Option<WboitWeightOverride>,
+    /// `WboitSettings::animated_weight`: enables the time-driven dissolve term.
+    pub animated_weight: bool,
+    /// `WboitDebug::Overdraw` or `WboitDebug::Weight`: adds an MRT target counting fragments
+    /// per pixel.
+    pub overdraw: bool,
+    /// `WboitDebug::Weight`: the overdraw target keeps the largest fragment weight instead.
+    pub debug_weight: bool,
+    /// `WboitSettings::accumulate_normals`: adds an MRT target summing weighted view-space
+    /// normals, after the overdraw target if there is one.
+    pub normals: bool,
+    /// The entity has `WboitAlwaysVisible`, or the camera accumulates before the opaque pass
+    /// (`WboitTaaMode::BeforeOpaque`): no opaque depth test, and no depth-based absorption or
+    /// soft-particle fade.
+    pub always_visible: bool,
+    /// The entity carries `WboitInstanceOpacity` and `WboitDepthOffset` in a WBOIT-owned
+    /// `MeshTag`: the vertex shader moves it by its offset and the fragment shader scales by
+    /// its opacity. The values themselves are per instance, so all such entities share one
+    /// variant.
+    pub instance_data: bool,
+    /// `WboitSettings::depth_test_bias` is non-zero: the fixed-function depth test is replaced
+    /// by a biased test in the shader.
+    pub depth_test_bias: bool,
+    /// `WboitTaaMode::AfterTaa`: vertices are projected without the view's TAA jitter, to
+    /// line up with the resolved image the composite draws onto. The other modes use the
+    /// jittered projection like the opaque passes, so TAA resolves both alike.
+    pub unjittered: bool,
+    /// `WboitSettings::nearest_depth_falloff`: the accum shader fades fragments behind the
+    /// nearest transparent depth.
+    pub nearest_depth_falloff: bool,
+    /// The depth-only variant drawing `WboitNearestDepth3d`: no color targets, depth write
+    /// into the nearest-depth target, occlusion by opaques tested in the shader.
+    pub nearest_depth_prepass: bool,
+}
+
+/// `MeshPipelineKey` of a transparent `mesh` drawn by a view with `view_key`, shared by the
+/// naive and HE accum pipelines.
+///
+/// Everything that changes the pipeline must come through here: the view bits (HDR, MSAA,
+/// tonemapping, ...), and the mesh bits (primitive topology, vertex attributes, morph targets,
+/// ...). Anything left out would let two different meshes share one cached pipeline.
+pub fn wboit_mesh_key(view_key: MeshPipelineKey, mesh: &RenderMesh) -> MeshPipelineKey {
+    // Use BLEND_ALPHA as the default alpha mode key; WBOIT overrides the
+    // fragment shader so this mainly affects vertex shader specialization.
+    view_key
+        | MeshPipelineKey::from_bits_retain(mesh.key_bits.bits())
+        | MeshPipelineKey::BLEND_ALPHA
+}
+
+impl WboitPipelineKey {
+    /// Key for a transparent `mesh` drawn by a view with `view_key` and `settings`.
+    pub fn new(view_key: MeshPipelineKey, mesh: &RenderMesh, settings: &WboitSettings) -> Self {
+        Self {
+            mesh_key: wboit_mesh_key(view_key, mesh),
+            manual_depth_test: settings.is_accum_scaled(),
+            quality: settings.quality,
+            weight_override: None,
+            animated_weight: settings.animated_weight,
+            overdraw: settings.debug.uses_debug_target(),
+            debug_weight: settings.debug == WboitDebug::Weight,
+            normals: settings.accumulate_normals,
+            always_visible: settings.taa_mode == WboitTaaMode::BeforeOpaque,
+            instance_data: false,
+            depth_test_bias: settings.depth_test_bias != 0.0,
+            unjittered: settings.taa_mode == WboitTaaMode::AfterTaa,
+            nearest_depth_falloff: settings.uses_nearest_depth(),
+            nearest_depth_prepass: false,
+        }
+    }
+
+    /// Key of the nearest-depth prepass variant of this accum key: same geometry and depth
+    /// test, without the extra targets or the falloff.
+    pub fn nearest_depth_prepass(self) -> Self {
+        Self {
+            overdraw: false,
+            debug_weight: false,
+            normals: false,
+            nearest_depth_falloff: false,
+            nearest_depth_prepass: true,
+            ..self
+        }
+    }
+}
+
+impl<M: WboitMaterial> WboitPipeline<M> {
+    /// Specialize (and start compiling) the accum pipeline for each key and mesh layout ahead
+    /// of time, so drawing them later hits the cache. Errors are logged and skipped.
+    pub fn prewarm<'a>(
+        &self,
+        pipelines: &mut SpecializedMeshPipelines<Self>,
+        pipeline_cache: &PipelineCache,
+        keys: impl IntoIterator<Item = (WboitPipelineKey, &'a MeshVertexBufferLayoutRef)>,
+    ) -> Vec<CachedRenderPipelineId> {
+        keys.into_iter()
+            .filter_map(|(key, layout)| {
+                pipelines
+                    .specialize(pipeline_cache, self, key, layout)
+                    .map_err(|err| error!("WBOIT pipeline prewarm error: {err}"))
+                    .ok()
+            })
+            .collect()
+    }
+}
+
+impl<M: WboitMaterial> SpecializedMeshPipeline for WboitPipeline<M> {
+    type Key = WboitPipelineKey;
 
     fn specialize(
         &self,
         key: Self::Key,
         layout: &MeshVertexBufferLayoutRef,
     ) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError> {
-        let mut desc = self.mesh_pipeline.specialize(key, layout)?;
+        // The primitive state (topology from the mesh, e.g. `LineList` edges, and culling) is
+        // kept as `MeshPipeline` builds it; only the fragment stage, targets, layouts and depth
+        // state are overridden below.
+        let mut desc = self.mesh_pipeline.specialize(key.mesh_key, layout)?;
 
         desc.label = Some("wboit_accum_pipeline".into());
 
         // Add MATERIAL_BIND_GROUP shader def (index 2) so PBR imports resolve correctly.
         // In Bevy 0.16 the view binding array is merged into group 0; mesh is group 1; material is group 2.
-        desc.vertex.shader_defs.push(ShaderDefVal::UInt("MATERIAL_BIND_GROUP".into(), 2));
+        desc.vertex
+            .shader_defs
+            .push(ShaderDefVal::UInt("MATERIAL_BIND_GROUP".into(), 2));
         if let Some(ref mut fragment) = desc.fragment {
-            fragment.shader_defs.push(ShaderDefVal::UInt("MATERIAL_BIND_GROUP".into(), 2));
+            fragment
+                .shader_defs
+                .push(ShaderDefVal::UInt("MATERIAL_BIND_GROUP".into(), 2));
         }
 
         // Mirror MaterialPipelineSpecializer: add BINDLESS when the device supports it.
@@ -70,73 +320,337 @@ impl SpecializedMeshPipeline for WboitPipeline {
             }
         }
 
-        // Insert StandardMaterial bind group layout at index 2.
+        // Insert the material bind group layout at index 2.
         // MeshPipeline::specialize() produces layouts for groups 0-1;
         // without this the fragment shader's material bindings have no pipeline layout entry.
         desc.layout.insert(2, self.material_layout.clone());
+        desc.layout.push(self.accum_data_layout.clone());
 
-        // Override fragment shader
+        // Override fragment shader. The shader defs `MeshPipeline` derived from the vertex
+        // layout (`VERTEX_COLORS`, `VERTEX_UVS_A`, `VERTEX_TANGENTS`, ...) are kept, so
+        // `pbr_input_from_standard_material` applies vertex colors exactly as in the forward pass.
         if let Some(ref mut fragment) = desc.fragment {
             fragment.shader = self.fragment_shader.clone();
         }
 
-        // Override color targets for MRT:
-        // Target 0: accum (Rgba16Float, additive blend)
-        // Target 1: revealage (R8Unorm, multiplicative blend)
-        if let Some(ref mut fragment) = desc.fragment {
-            fragment.targets = vec![
-                Some(ColorTargetState {
-                    format: TextureFormat::Rgba16Float,
-                    blend: Some(BlendState {
-                        color: BlendComponent {
-                            src_factor: BlendFactor::One,
-                            dst_factor: BlendFactor::One,
-                            operation: BlendOperation::Add,
-                        },
-                        alpha: BlendComponent {
-                            src_factor: BlendFactor::One,
-                            dst_factor: BlendFactor::One,
-                            operation: BlendOperation::Add,
-                        },
-                    }),
-                    write_mask: ColorWrites::ALL,
+        specialize_wboit_accum_targets(&mut desc, key);
+
+        M::specialize_wboit(&mut desc, layout, key)?;
+
+        Ok(desc)
+    }
+}
+
+/// Color targets, depth state and variant shader defs of a naive accum pipeline for `key`,
+/// applied on top of a `MeshPipeline` descriptor. Shared by `WboitPipeline<M>` and the
+/// material-less `WboitMinimalPipeline`, so both match the targets of the accum pass.
+pub(crate) fn specialize_wboit_accum_targets(
+    desc: &mut RenderPipelineDescriptor,
+    key: WboitPipelineKey,
+) {
+    // Override color targets for MRT:
+    // Target 0: accum (WBOIT_ACCUM_FORMAT, additive blend)
+    // Target 1: revealage (WBOIT_REVEALAGE_FORMAT, multiplicative blend)
+    // Target 2: glow (Rgba16Float, additive blend), AlphaMode::Add light
+    if let Some(ref mut fragment) = desc.fragment {
+        fragment.shader_defs.push(ShaderDefVal::UInt(
+            "ACCUM_FORMAT_MAX".into(),
+            accum_format_max(WBOIT_ACCUM_FORMAT),
+        ));
+        fragment.targets = vec![
+            Some(ColorTargetState {
+                format: WBOIT_ACCUM_FORMAT,
+                blend: Some(BlendState {
+                    color: BlendComponent {
+                        src_factor: BlendFactor::One,
+                        dst_factor: BlendFactor::One,
+                        operation: BlendOperation::Add,
+                    },
+                    alpha: BlendComponent {
+                        src_factor: BlendFactor::One,
+                        dst_factor: BlendFactor::One,
+                        operation: BlendOperation::Add,
+                    },
+                }),
+                write_mask: ColorWrites::ALL,
+            }),
+            Some(ColorTargetState {
+                format: WBOIT_REVEALAGE_FORMAT,
+                blend: Some(BlendState {
+                    color: BlendComponent {
+                        src_factor: BlendFactor::Zero,
+                        dst_factor: BlendFactor::OneMinusSrc,
+                        operation: BlendOperation::Add,
+                    },
+                    alpha: BlendComponent {
+                        src_factor: BlendFactor::Zero,
+                        dst_factor: BlendFactor::OneMinusSrc,
+                        operation: BlendOperation::Add,
+                    },
                 }),
-                Some(ColorTargetState {
-                    format: TextureFormat::R8Unorm,
-                    blend: Some(BlendState {
-                        color: BlendComponent {
-                            src_factor: BlendFactor::Zero,
-                            dst_factor: BlendFactor::OneMinusSrc,
-                            operation: BlendOperation::Add,
-                        },
-                        alpha: BlendComponent {
-                            src_factor: BlendFactor::Zero,
-                            dst_factor: BlendFactor::OneMinusSrc,
-                            operation: BlendOperation::Add,
-                        },
-                    }),
-                    write_mask: ColorWrites::ALL,
+                write_mask: ColorWrites::ALL,
+            }),
+            Some(ColorTargetState {
+                format: TextureFormat::Rgba16Float,
+                blend: Some(BlendState {
+                    color: BlendComponent {
+                        src_factor: BlendFactor::One,
+                        dst_factor: BlendFactor::One,
+                        operation: BlendOperation::Add,
+                    },
+                    alpha: BlendComponent::REPLACE,
                 }),
-            ];
+                write_mask: ColorWrites::ALL,
+            }),
+        ];
+    }
+
+    if key.unjittered || key.instance_data {
+        desc.vertex.shader = WBOIT_VERTEX_SHADER_HANDLE;
+    }
+    if key.unjittered {
+        desc.vertex.shader_defs.push("WBOIT_UNJITTERED".into());
+    }
+    if key.instance_data {
+        desc.vertex.shader_defs.push("WBOIT_INSTANCE_DATA".into());
+    }
+
+    // Depth: test enabled, write disabled (preserve opaque depth)
+    if let Some(ref mut ds) = desc.depth_stencil {
+        ds.format = WBOIT_DEPTH_FORMAT;
+        ds.depth_write_enabled = false;
+    }
+
+    let unweighted = match key.weight_override {
+        Some(WboitWeightOverride::Unweighted) => true,
+        Some(WboitWeightOverride::Depth) => false,
+        None => key.quality == 0,
+    };
+    if let Some(fragment) = desc.fragment.as_mut() {
+        if unweighted {
+            fragment.shader_defs.push("WBOIT_UNWEIGHTED".into());
+        }
+        if key.quality == 2 {
+            fragment.shader_defs.push("WBOIT_GRAZING_CORRECTION".into());
+        }
+    }
+
+    // Scaled accum: the full-res depth buffer can't be attached to the smaller targets,
+    // so drop the depth-stencil state and let the fragment shader discard occluded fragments.
+    if key.manual_depth_test {
+        desc.depth_stencil = None;
+        if !key.always_visible {
+            let fragment = desc.fragment.as_mut().unwrap();
+            fragment.shader_defs.push("WBOIT_MANUAL_DEPTH_TEST".into());
         }
+    }
 
-        // Depth: test enabled, write disabled (preserve opaque depth)
+    // Depth test bias: keep the read-only depth attachment (if any) but pass every fragment,
+    // and test against the biased sampled depth in the shader instead.
+    if key.depth_test_bias && !key.always_visible {
         if let Some(ref mut ds) = desc.depth_stencil {
-            ds.depth_write_enabled = false;
+            ds.depth_compare = CompareFunction::Always;
+        }
+        if let Some(ref mut fragment) = desc.fragment {
+            if !key.manual_depth_test {
+                fragment.shader_defs.push("WBOIT_MANUAL_DEPTH_TEST".into());
+            }
+            fragment.shader_defs.push("WBOIT_DEPTH_TEST_BIAS".into());
         }
+    }
 
-        Ok(desc)
+    // Always visible: accumulate over opaque geometry instead of being occluded by it.
+    if key.always_visible {
+        if let Some(ref mut ds) = desc.depth_stencil {
+            ds.depth_compare = CompareFunction::Always;
+        }
+        if let Some(ref mut fragment) = desc.fragment {
+            fragment.shader_defs.push("WBOIT_ALWAYS_VISIBLE".into());
+        }
+    }
+
+    if key.animated_weight {
+        let fragment = desc.fragment.as_mut().unwrap();
+        fragment.shader_defs.push("WBOIT_ANIMATED_WEIGHT".into());
+    }
+
+    if key.instance_data {
+        let fragment = desc.fragment.as_mut().unwrap();
+        fragment.shader_defs.push("WBOIT_INSTANCE_DATA".into());
+    }
+
+    // Overdraw debug: Target 3 (R16Float, additive) counts fragments per pixel. The weight
+    // debug keeps the largest fragment weight in it instead.
+    if key.overdraw {
+        let fragment = desc.fragment.as_mut().unwrap();
+        fragment.shader_defs.push("WBOIT_DEBUG_OVERDRAW".into());
+        if key.debug_weight {
+            fragment.shader_defs.push("WBOIT_DEBUG_WEIGHT".into());
+        }
+        fragment.targets.push(Some(ColorTargetState {
+            format: TextureFormat::R16Float,
+            blend: Some(BlendState {
+                color: BlendComponent {
+                    src_factor: BlendFactor::One,
+                    dst_factor: BlendFactor::One,
+                    operation: if key.debug_weight {
+                        BlendOperation::Max
+                    } else {
+                        BlendOperation::Add
+                    },
+                },
+                alpha: BlendComponent::REPLACE,
+            }),
+            write_mask: ColorWrites::ALL,
+        }));
+    }
+
+    // Normals: next target (Rg16Float, additive) sums the weighted view-space normal xy.
+    if key.normals {
+        let fragment = desc.fragment.as_mut().unwrap();
+        fragment.shader_defs.push("WBOIT_ACCUM_NORMALS".into());
+        fragment.targets.push(Some(ColorTargetState {
+            format: TextureFormat::Rg16Float,
+            blend: Some(BlendState {
+                color: BlendComponent {
+                    src_factor: BlendFactor::One,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+                alpha: BlendComponent::REPLACE,
+            }),
+            write_mask: ColorWrites::ALL,
+        }));
+    }
+    if key.nearest_depth_falloff {
+        let fragment = desc.fragment.as_mut().unwrap();
+        fragment
+            .shader_defs
+            .push("WBOIT_NEAREST_DEPTH_FALLOFF".into());
+    }
+
+    // Nearest-depth prepass: depth only, writing the nearest transparent depth into the
+    // private target. Opaque occlusion moves into the shader since the opaque depth is no
+    // longer the attachment.
+    if key.nearest_depth_prepass {
+        if let Some(ref mut fragment) = desc.fragment {
+            fragment.targets.clear();
+            fragment
+                .shader_defs
+                .push("WBOIT_NEAREST_DEPTH_PREPASS".into());
+            if !key.always_visible && !key.manual_depth_test && !key.depth_test_bias {
+                fragment.shader_defs.push("WBOIT_MANUAL_DEPTH_TEST".into());
+            }
+        }
+        desc.depth_stencil = Some(DepthStencilState {
+            format: WBOIT_NEAREST_DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: CompareFunction::GreaterEqual,
+            stencil: StencilState::default(),
+            bias: DepthBiasState::default(),
+        });
     }
 }
 
-/// Check that MSAA is off for cameras with WboitSettings.
+/// Force MSAA off for cameras with WboitSettings.
+///
+/// WBOIT requires `Msaa::Off`. Rather than panicking (MSAA is often still on for a frame or
+/// two during startup), this warns once per camera and switches MSAA off. Cameras that lose
+/// their `WboitSettings` (or are despawned) are forgotten, so they warn again if WBOIT is
+/// turned back on.
 pub fn check_msaa_wboit(
-    cameras: Query<&Msaa, With<crate::settings::WboitSettings>>,
+    mut cameras: Query<(Entity, &mut Msaa), With<WboitSettings>>,
+    mut removed: RemovedComponents<WboitSettings>,
+    mut warned: Local<HashSet<Entity>>,
 ) {
-    for msaa in &cameras {
+    for entity in removed.read() {
+        warned.remove(&entity);
+    }
+    for (entity, mut msaa) in &mut cameras {
         if *msaa != Msaa::Off {
-            panic!("WBOIT requires Msaa::Off. Set Msaa::Off on cameras with WboitSettings.");
+            if warned.insert(entity) {
+                warn!(
+                    "WBOIT requires Msaa::Off, but camera {entity} has {:?}; disabling MSAA. \
+                     Set Msaa::Off on cameras with WboitSettings to silence this warning.",
+                    *msaa
+                );
+            }
+            *msaa = Msaa::Off;
+        }
+    }
+}
+
+/// Warn once when a camera's `WboitSettings::mode` is not supported by the device.
+///
+/// The render systems always draw the weighted path for such cameras (see
+/// `WboitMode::resolve`); the setting itself is left untouched.
+pub fn check_wboit_mode(
+    cameras: Query<(Entity, &crate::settings::WboitSettings)>,
+    render_device: Option<Res<RenderDevice>>,
+) {
+    let Some(render_device) = render_device else {
+        return;
+    };
+    for (entity, settings) in &cameras {
+        if settings.mode.resolve(&render_device) != settings.mode {
+            warn_once!(
+                "Camera {entity} requests {:?}, which this device does not support; falling \
+                 back to weighted blended OIT.",
+                settings.mode
+            );
+        }
+    }
+}
+
+/// Warn once per camera when a WBOIT camera renders into a transparent window without
+/// `WboitSettings::output_alpha`.
+///
+/// The composite then leaves the window's alpha as the opaque pass wrote it, so transparents
+/// over a cleared background never show up on the desktop. With `PostMultiplied` the compositor
+/// also multiplies the already premultiplied WBOIT color by alpha again, darkening soft edges.
+/// Like `check_msaa_wboit`, cameras that lose their `WboitSettings` are forgotten.
+pub fn check_transparent_window_wboit(
+    cameras: Query<(Entity, &Camera, &WboitSettings)>,
+    windows: Query<&Window>,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
+    mut removed: RemovedComponents<WboitSettings>,
+    mut warned: Local<HashSet<Entity>>,
+) {
+    for entity in removed.read() {
+        warned.remove(&entity);
+    }
+    for (entity, camera, settings) in &cameras {
+        let Some(NormalizedRenderTarget::Window(window_ref)) =
+            camera.target.normalize(primary_window.single().ok())
+        else {
+            continue;
+        };
+        let Ok(window) = windows.get(window_ref.entity()) else {
+            continue;
+        };
+        if !window.transparent || warned.contains(&entity) {
+            continue;
+        }
+        match window.composite_alpha_mode {
+            CompositeAlphaMode::PreMultiplied | CompositeAlphaMode::Inherit
+                if !settings.output_alpha =>
+            {
+                warn!(
+                    "Camera {entity} renders WBOIT into a transparent window without \
+                     WboitSettings::output_alpha; transparents will not show through to the \
+                     desktop."
+                );
+            }
+            CompositeAlphaMode::PostMultiplied => {
+                warn!(
+                    "Camera {entity} renders WBOIT into a window with \
+                     CompositeAlphaMode::PostMultiplied, but WBOIT outputs premultiplied color; \
+                     use CompositeAlphaMode::PreMultiplied if the platform supports it."
+                );
+            }
+            _ => continue,
         }
+        warned.insert(entity);
     }
 }
 
@@ -153,3 +667,221 @@ pub fn configure_depth_texture_usages_wboit(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::pbr::alpha_mode_pipeline_key;
+    use bevy::render::RenderApp;
+    use bevy::render::mesh::{MeshVertexBufferLayouts, PrimitiveTopology};
+    use bevy::render::render_resource::{FragmentState, VertexState};
+
+    use crate::settings::WboitMode;
+    use crate::test_utils::{gpu_app, render_mesh, run_compute};
+
+    #[test]
+    fn distinct_keys_specialize_to_distinct_pipelines() {
+        let Some(mut app) = gpu_app() else {
+            return;
+        };
+        app.add_plugins(crate::WboitPlugin);
+        app.finish();
+        app.cleanup();
+        let world = app.get_sub_app_mut(RenderApp).unwrap().world_mut();
+
+        let mut layouts = world.resource_mut::<MeshVertexBufferLayouts>();
+        let meshes = [
+            PrimitiveTopology::TriangleList,
+            PrimitiveTopology::TriangleStrip,
+            PrimitiveTopology::LineList,
+        ]
+        .map(|topology| render_mesh(topology, &mut layouts));
+
+        let mut keys = Vec::new();
+        for hdr in [false, true] {
+            let view_key = MeshPipelineKey::from_msaa_samples(1) | MeshPipelineKey::from_hdr(hdr);
+            for mesh in &meshes {
+                for taa_mode in [WboitTaaMode::BeforeTaa, WboitTaaMode::AfterTaa] {
+                    let settings = WboitSettings {
+                        taa_mode,
+                        ..default()
+                    };
+                    let key = WboitPipelineKey::new(view_key, mesh, &settings);
+                    for (always_visible, instance_data) in
+                        [(false, false), (true, false), (false, true), (true, true)]
+                    {
+                        let key = WboitPipelineKey {
+                            always_visible,
+                            instance_data,
+                            ..key
+                        };
+                        keys.push((key, mesh.layout.clone()));
+                    }
+                }
+            }
+        }
+        assert_eq!(keys.len(), 2 * 3 * 2 * 4);
+
+        world.resource_scope(
+            |world, mut pipelines: Mut<SpecializedMeshPipelines<WboitPipeline>>| {
+                let pipeline = world.resource::<WboitPipeline>();
+                let pipeline_cache = world.resource::<PipelineCache>();
+                let mut specialize = |(key, layout): &(WboitPipelineKey, _)| {
+                    pipelines
+                        .specialize(pipeline_cache, pipeline, *key, layout)
+                        .unwrap()
+                };
+                let ids: Vec<_> = keys.iter().map(&mut specialize).collect();
+                for (i, a) in ids.iter().enumerate() {
+                    for (j, b) in ids.iter().enumerate().skip(i + 1) {
+                        assert_ne!(a, b, "keys {i} and {j} share a pipeline");
+                    }
+                }
+                // Equal keys hit the cache instead of queueing another pipeline.
+                let again: Vec<_> = keys.iter().map(&mut specialize).collect();
+                assert_eq!(again, ids);
+            },
+        );
+    }
+
+    #[test]
+    fn transparent_alpha_modes_share_the_accum_key() {
+        // The accum shader resolves the alpha mode from the material flags, so every
+        // transparent alpha mode draws with the same blend bits.
+        let mut layouts = MeshVertexBufferLayouts::default();
+        let mesh = render_mesh(PrimitiveTopology::TriangleList, &mut layouts);
+        let blend = |alpha_mode| {
+            let view_key = alpha_mode_pipeline_key(alpha_mode, &Msaa::Off);
+            wboit_mesh_key(view_key, &mesh) & MeshPipelineKey::BLEND_RESERVED_BITS
+        };
+        for alpha_mode in [AlphaMode::Blend, AlphaMode::Premultiplied, AlphaMode::Add] {
+            assert_eq!(blend(alpha_mode), MeshPipelineKey::BLEND_ALPHA);
+        }
+    }
+
+    /// `v` through `sanitize` of `wboit_fragment.wgsl`, run in a compute shader.
+    fn sanitized(world: &World, v: &[[f32; 4]]) -> Vec<[f32; 4]> {
+        let source = include_str!("shaders/wboit_fragment.wgsl");
+        let start = source.find("fn is_non_finite").unwrap();
+        let end = source
+            .find("// Replace non-finite components with zero. Unlike")
+            .unwrap();
+        let shader = format!(
+            "{}\nfn compute(v: vec4<f32>) -> vec4<f32> {{ return sanitize(v); }}",
+            &source[start..end]
+        );
+        run_compute(world, &shader, v)
+    }
+
+    #[test]
+    fn sanitize_zeroes_non_finite_and_negative_components() {
+        let Some(mut app) = gpu_app() else {
+            return;
+        };
+        app.finish();
+        app.cleanup();
+        let world = app.get_sub_app_mut(RenderApp).unwrap().world();
+
+        let out = sanitized(
+            world,
+            &[
+                [f32::NAN, f32::INFINITY, f32::NEG_INFINITY, -2.0],
+                [1.5, 0.0, 65504.0, f32::MAX],
+            ],
+        );
+        assert_eq!(out, [[0.0; 4], [1.5, 0.0, 65504.0, f32::MAX]]);
+    }
+
+    #[test]
+    fn unsupported_modes_resolve_to_the_weighted_path() {
+        let Some(mut app) = gpu_app() else {
+            return;
+        };
+        app.finish();
+        app.cleanup();
+        let world = app.get_sub_app(RenderApp).unwrap().world();
+        let render_device = world.resource::<RenderDevice>();
+
+        assert!(WboitMode::Weighted.is_supported(render_device));
+        assert_eq!(
+            WboitMode::Weighted.resolve(render_device),
+            WboitMode::Weighted
+        );
+        assert!(!WboitMode::RovOrdered.is_supported(render_device));
+        assert_eq!(
+            WboitMode::RovOrdered.resolve(render_device),
+            WboitMode::Weighted
+        );
+    }
+
+    #[test]
+    fn accum_format_max_is_passed_to_the_accum_shaders() {
+        let mut layouts = MeshVertexBufferLayouts::default();
+        let mesh = render_mesh(PrimitiveTopology::TriangleList, &mut layouts);
+        let key = WboitPipelineKey::new(
+            MeshPipelineKey::from_msaa_samples(1),
+            &mesh,
+            &WboitSettings::default(),
+        );
+        let mut desc = RenderPipelineDescriptor {
+            label: None,
+            layout: vec![],
+            push_constant_ranges: vec![],
+            vertex: VertexState {
+                shader: default(),
+                shader_defs: vec![],
+                entry_point: "vertex".into(),
+                buffers: vec![],
+            },
+            fragment: Some(FragmentState {
+                shader: default(),
+                shader_defs: vec![],
+                entry_point: "fragment".into(),
+                targets: vec![],
+            }),
+            primitive: default(),
+            depth_stencil: None,
+            multisample: default(),
+            zero_initialize_workgroup_memory: false,
+        };
+        specialize_wboit_accum_targets(&mut desc, key);
+        let fragment = desc.fragment.unwrap();
+        assert_eq!(
+            fragment.targets[0].as_ref().unwrap().format,
+            WBOIT_ACCUM_FORMAT
+        );
+        let Some(&ShaderDefVal::UInt(_, max)) = fragment
+            .shader_defs
+            .iter()
+            .find(|def| matches!(def, ShaderDefVal::UInt(name, _) if name == "ACCUM_FORMAT_MAX"))
+        else {
+            panic!("no ACCUM_FORMAT_MAX shader def");
+        };
+        assert_eq!(max, 65504);
+
+        // Both accum shaders turn the def into the half-float maximum.
+        for source in [
+            include_str!("shaders/wboit_fragment.wgsl"),
+            include_str!("shaders/wboit_minimal.wgsl"),
+        ] {
+            let declaration = source
+                .lines()
+                .find(|line| line.starts_with("const ACCUM_FORMAT_MAX"))
+                .unwrap()
+                .replace("#{ACCUM_FORMAT_MAX}", &max.to_string());
+            let module = naga::front::wgsl::parse_str(&declaration).unwrap();
+            let (_, constant) = module.constants.iter().next().unwrap();
+            assert!(matches!(
+                module.global_expressions[constant.init],
+                naga::Expression::Literal(naga::Literal::F32(65504.0))
+            ));
+        }
+    }
+
+    #[test]
+    fn accum_format_max_covers_the_accum_format_range() {
+        assert_eq!(accum_format_max(TextureFormat::Rgba16Float), 65504);
+        assert_eq!(accum_format_max(TextureFormat::Rgba32Float), u32::MAX);
+        assert_eq!(accum_format_max(TextureFormat::Rgba8Unorm), 1);
+    }
+}
@@ -1,52 +1,245 @@
-use bevy::prelude::*;
+use bevy::core_pipeline::core_3d::Transparent3d;
+use bevy::ecs::entity::{Entities, EntityHashSet};
+use bevy::math::FloatOrd;
 use bevy::pbr::{
-    DrawMesh, MeshPipelineKey, RenderMeshInstances, SetMeshBindGroup,
-    SetMeshViewBindGroup, SetMaterialBindGroup,
-    ViewKeyCache,
+    DrawMesh, RenderMeshInstances, RenderMeshQueueData, SetMaterialBindGroup, SetMeshBindGroup,
+    SetMeshViewBindGroup, ViewKeyCache,
 };
+use bevy::prelude::*;
+use bevy::render::Extract;
+use bevy::render::extract_resource::ExtractResource;
+use bevy::render::mesh::{MeshTag, RenderMesh};
 use bevy::render::render_asset::RenderAssets;
 use bevy::render::render_phase::{
-    DrawFunctions, PhaseItemExtraIndex, SetItemPipeline, ViewSortedRenderPhases,
+    DrawFunctions, PhaseItem, PhaseItemExtraIndex, RenderCommand, RenderCommandResult,
+    SetItemPipeline, TrackedRenderPass, ViewSortedRenderPhases,
 };
 use bevy::render::render_resource::{PipelineCache, SpecializedMeshPipelines};
-use bevy::render::view::ExtractedView;
-use bevy::render::mesh::RenderMesh;
-use bevy::core_pipeline::core_3d::Transparent3d;
+use bevy::render::renderer::RenderDevice;
+use bevy::render::sync_world::{MainEntity, MainEntityHashMap, MainEntityHashSet};
+use bevy::render::view::{ExtractedView, RenderLayers};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::diagnostics::{WboitDrainStats, WboitDrainStatsSink};
+use crate::material::WboitMaterial;
+use crate::naive::WboitPipelinesInvalidated;
+use crate::naive::accum_pass::{WboitAccumBindGroup, WboitNearestDepthBindGroup};
+use crate::phase::{WboitAccum3d, WboitNearestDepth3d, WboitOddGroup};
+use crate::pipeline::{WboitPipeline, WboitPipelineKey};
+use crate::settings::{
+    WboitAlwaysVisible, WboitDepthOffset, WboitGroup, WboitGroups, WboitInstanceOpacity,
+    WboitLayerConfig, WboitSettings, WboitWeightOverride,
+};
+
+/// RenderCommand that sets the naive accum data bind group (group 3) from `WboitAccumBindGroup`.
+pub struct SetWboitAccumBindGroup<const I: usize>;
+
+impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetWboitAccumBindGroup<I> {
+    type Param = ();
+    type ViewQuery = &'static WboitAccumBindGroup;
+    type ItemQuery = ();
+
+    fn render<'w>(
+        _item: &P,
+        bind_group: &'w WboitAccumBindGroup,
+        _entity: Option<()>,
+        _param: (),
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        pass.set_bind_group(I, &bind_group.0, &[]);
+        RenderCommandResult::Success
+    }
+}
+
+/// RenderCommand that sets the nearest-depth prepass data bind group (group 3) from
+/// `WboitNearestDepthBindGroup`.
+pub struct SetWboitNearestDepthBindGroup<const I: usize>;
+
+impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetWboitNearestDepthBindGroup<I> {
+    type Param = ();
+    type ViewQuery = &'static WboitNearestDepthBindGroup;
+    type ItemQuery = ();
 
-use crate::phase::WboitAccum3d;
-use crate::pipeline::WboitPipeline;
-use crate::settings::WboitSettings;
+    fn render<'w>(
+        _item: &P,
+        bind_group: &'w WboitNearestDepthBindGroup,
+        _entity: Option<()>,
+        _param: (),
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        pass.set_bind_group(I, &bind_group.0, &[]);
+        RenderCommandResult::Success
+    }
+}
 
-pub type DrawWboit = (
+/// Draw command type for naive WBOIT transparent meshes with material `M`.
+/// Accum data (params, opaque depth) at group 3 (wboit_fragment.wgsl declares @group(3)).
+pub type DrawWboit<M = StandardMaterial> = (
     SetItemPipeline,
     SetMeshViewBindGroup<0>,
     SetMeshBindGroup<1>,
-    SetMaterialBindGroup<StandardMaterial, 2>,
+    SetMaterialBindGroup<M, 2>,
+    SetWboitAccumBindGroup<3>,
     DrawMesh,
 );
 
-/// Specialize and queue transparent meshes into `WboitAccum3d` for WBOIT cameras.
+/// Draw command type for the nearest-depth prepass (`WboitNearestDepth3d`) of meshes with
+/// material `M`.
+pub type DrawWboitNearestDepth<M = StandardMaterial> = (
+    SetItemPipeline,
+    SetMeshViewBindGroup<0>,
+    SetMeshBindGroup<1>,
+    SetMaterialBindGroup<M, 2>,
+    SetWboitNearestDepthBindGroup<3>,
+    DrawMesh,
+);
+
+/// Same tuple as bevy_pbr's private `DrawMaterial<M>`, registered for `Transparent3d` by
+/// `MaterialPlugin<M>`. Its draw function id tells which material queued a transparent item.
+pub(crate) type TransparentDrawMaterial<M> = (
+    SetItemPipeline,
+    SetMeshViewBindGroup<0>,
+    SetMeshBindGroup<1>,
+    SetMaterialBindGroup<M, 2>,
+    DrawMesh,
+);
+
+/// Sort distance used for the WBOIT and HE-WBOIT accum phases.
 ///
-/// Runs after `queue_material_meshes`, reads from `Transparent3d` to get the
-/// already-filtered transparent entities, then re-specializes them with the WBOIT pipeline.
-pub fn queue_wboit_meshes(
+/// The accum result is order independent, so the sort only affects batching and, on the HE
+/// path, which fragments land in the histogram first. Insert this resource in the main world
+/// to replace the default metric (e.g. for logarithmic depth or custom LOD ordering).
+#[derive(Resource, Clone, Default, ExtractResource)]
+pub enum WboitSortFn {
+    /// The distance `Transparent3d` was sorted with: the view rangefinder distance of the
+    /// mesh translation plus the material's `depth_bias`.
+    #[default]
+    Rangefinder,
+    /// Computes the distance from the mesh translation and the view. Larger values are drawn
+    /// later, like rangefinder distances.
+    Custom(Arc<dyn Fn(Vec3, &ExtractedView) -> f32 + Send + Sync>),
+}
+
+impl WboitSortFn {
+    /// Sort distance of an item with `transparent_distance` (its `Transparent3d` distance) and
+    /// mesh `translation`.
+    pub fn distance(
+        &self,
+        transparent_distance: f32,
+        translation: Vec3,
+        view: &ExtractedView,
+    ) -> f32 {
+        match self {
+            Self::Rangefinder => transparent_distance,
+            Self::Custom(sort_fn) => sort_fn(translation, view),
+        }
+    }
+}
+
+/// Whether a mesh at `translation` is beyond `max_distance` (view-space depth) from `view`.
+pub fn is_beyond_max_distance(
+    max_distance: Option<f32>,
+    view: &ExtractedView,
+    translation: Vec3,
+) -> bool {
+    // View space looks down -Z, so the depth in front of the camera is the negated Z.
+    max_distance.is_some_and(|max| -view.rangefinder3d().distance_translation(&translation) > max)
+}
+
+/// System set containing `queue_wboit_meshes::<M>` for every registered WBOIT material.
+#[derive(SystemSet, Clone, PartialEq, Eq, Debug, Hash)]
+pub struct QueueWboitMeshes;
+
+/// Mesh instance of a visible mesh about to be queued into a WBOIT phase, if both of its
+/// entities still resolve: the render entity exists and the main entity still has a mesh
+/// instance. A mesh despawned since it was found visible is left out, rather than reaching the
+/// draw functions with a stale entity.
+///
+/// Meshes are not synced to the render world, so their items usually carry
+/// `Entity::PLACEHOLDER` as render entity; only the mesh instance is checked for those.
+pub(crate) fn resolve_queued_mesh<'a>(
+    entities: &Entities,
+    render_mesh_instances: &'a RenderMeshInstances,
+    (render_entity, main_entity): (Entity, MainEntity),
+) -> Option<RenderMeshQueueData<'a>> {
+    if render_entity != Entity::PLACEHOLDER && !entities.contains(render_entity) {
+        return None;
+    }
+    render_mesh_instances.render_mesh_queue_data(main_entity)
+}
+
+/// Specialize and queue transparent meshes with material `M` into `WboitAccum3d` for WBOIT
+/// cameras.
+///
+/// Runs after `queue_material_meshes::<M>`, reads from `Transparent3d` to get the
+/// already-filtered transparent entities, keeps the ones drawn with `M`'s draw function, then
+/// re-specializes them with the WBOIT pipeline.
+///
+/// `Transparent3d` is built from the view's `RenderVisibleEntities`, which includes entities with
+/// `NoFrustumCulling` (they skip the frustum test but are still marked visible). Parts of such
+/// meshes outside the frustum or behind the near plane are clipped by the rasterizer, so the
+/// depth used for weighting always stays within [0, 1].
+///
+/// `depth_bias`: the sort distance is taken from `Transparent3d`, which already includes the
+/// material's `depth_bias`. Weighting deliberately ignores the bias: it is a sorting hint in
+/// Bevy (it does not move the rasterized depth), and the accum result is order independent,
+/// so a biased decal weights exactly like an unbiased surface at the same depth.
+///
+/// With `WboitSettings::nearest_depth_falloff`, each item is also queued into
+/// `WboitNearestDepth3d` with the depth-only prepass variant of its key.
+pub fn queue_wboit_meshes<M: WboitMaterial>(
     render_meshes: Res<RenderAssets<RenderMesh>>,
-    render_mesh_instances: Res<RenderMeshInstances>,
-    wboit_pipeline: Option<Res<WboitPipeline>>,
-    mut pipelines: ResMut<SpecializedMeshPipelines<WboitPipeline>>,
+    // Grouped to stay within the system parameter limit.
+    (render_mesh_instances, entities): (Res<RenderMeshInstances>, &Entities),
+    wboit_pipeline: Option<Res<WboitPipeline<M>>>,
+    mut pipelines: ResMut<SpecializedMeshPipelines<WboitPipeline<M>>>,
     pipeline_cache: Res<PipelineCache>,
     draw_functions: Res<DrawFunctions<WboitAccum3d>>,
+    transparent_draw_functions: Res<DrawFunctions<Transparent3d>>,
+    sort_fn: Option<Res<WboitSortFn>>,
     mut wboit_phases: ResMut<ViewSortedRenderPhases<WboitAccum3d>>,
     transparent_phases: Res<ViewSortedRenderPhases<Transparent3d>>,
-    views: Query<&ExtractedView, With<WboitSettings>>,
+    mesh_layers: Res<WboitMeshLayers>,
+    // Grouped to stay within the system parameter limit.
+    (always_visible, instance_data): (
+        Res<WboitAlwaysVisibleEntities>,
+        Res<WboitInstanceDataEntities>,
+    ),
+    group_entities: Res<WboitGroupEntities>,
+    (nearest_draw_functions, mut nearest_phases): (
+        Res<DrawFunctions<WboitNearestDepth3d>>,
+        ResMut<ViewSortedRenderPhases<WboitNearestDepth3d>>,
+    ),
+    views: Query<(
+        &ExtractedView,
+        &WboitSettings,
+        Option<&WboitLayerConfig>,
+        Option<&WboitWeightOverride>,
+        Option<&WboitGroups>,
+    )>,
     view_key_cache: Res<ViewKeyCache>,
 ) {
     let Some(wboit_pipeline) = wboit_pipeline else {
         return;
     };
-    let draw_wboit = draw_functions.read().id::<DrawWboit>();
+    let Some(draw_material) = transparent_draw_functions
+        .read()
+        .get_id::<TransparentDrawMaterial<M>>()
+    else {
+        return;
+    };
+    let draw_functions = draw_functions.read();
+    let draw_wboit = [
+        draw_functions.id::<DrawWboit<M>>(),
+        draw_functions.id::<WboitOddGroup<DrawWboit<M>>>(),
+    ];
+    let draw_nearest_depth = nearest_draw_functions
+        .read()
+        .id::<DrawWboitNearestDepth<M>>();
+    let sort_fn = sort_fn.as_deref().cloned().unwrap_or_default();
 
-    for view in &views {
+    for (view, settings, layer_config, weight_override, groups) in &views {
         let Some(wboit_phase) = wboit_phases.get_mut(&view.retained_view_entity) else {
             continue;
         };
@@ -58,27 +251,43 @@ pub fn queue_wboit_meshes(
         let Some(transparent_phase) = transparent_phases.get(&view.retained_view_entity) else {
             continue;
         };
+        let mut nearest_phase = nearest_phases
+            .get_mut(&view.retained_view_entity)
+            .filter(|_| settings.uses_nearest_depth());
 
         for item in &transparent_phase.items {
             let (render_entity, main_entity) = item.entity;
+            if item.draw_function != draw_material
+                || mesh_layers.is_sorted(layer_config, main_entity)
+            {
+                continue;
+            }
+            let Some(group_rank) = group_entities.rank(groups, main_entity) else {
+                continue;
+            };
 
             let Some(mesh_instance) =
-                render_mesh_instances.render_mesh_queue_data(main_entity)
+                resolve_queued_mesh(entities, &render_mesh_instances, item.entity)
             else {
                 continue;
             };
+            if is_beyond_max_distance(settings.max_distance, view, mesh_instance.translation) {
+                continue;
+            }
             let Some(mesh) = render_meshes.get(mesh_instance.mesh_asset_id) else {
                 continue;
             };
 
-            // Use BLEND_ALPHA as the default alpha mode key; WBOIT overrides the
-            // fragment shader so this mainly affects vertex shader specialization.
-            let mesh_key = *view_key
-                | MeshPipelineKey::from_bits_retain(mesh.key_bits.bits())
-                | MeshPipelineKey::BLEND_ALPHA;
+            let key = WboitPipelineKey::new(*view_key, mesh, settings);
+            let key = WboitPipelineKey {
+                always_visible: key.always_visible || always_visible.0.contains(&main_entity),
+                instance_data: instance_data.0.contains(&main_entity),
+                weight_override: weight_override.copied(),
+                ..key
+            };
 
             let pipeline_id =
-                pipelines.specialize(&pipeline_cache, &wboit_pipeline, mesh_key, &mesh.layout);
+                pipelines.specialize(&pipeline_cache, &wboit_pipeline, key, &mesh.layout);
             let pipeline_id = match pipeline_id {
                 Ok(id) => id,
                 Err(err) => {
@@ -86,12 +295,34 @@ pub fn queue_wboit_meshes(
                     continue;
                 }
             };
+            let distance = sort_fn.distance(item.distance, mesh_instance.translation, view);
+
+            if let Some(nearest_phase) = nearest_phase.as_mut() {
+                let prepass_pipeline = pipelines.specialize(
+                    &pipeline_cache,
+                    &wboit_pipeline,
+                    key.nearest_depth_prepass(),
+                    &mesh.layout,
+                );
+                match prepass_pipeline {
+                    Ok(pipeline) => nearest_phase.add(WboitNearestDepth3d {
+                        distance,
+                        pipeline,
+                        entity: (render_entity, main_entity),
+                        draw_function: draw_nearest_depth,
+                        batch_range: 0..1,
+                        extra_index: PhaseItemExtraIndex::None,
+                        indexed: item.indexed,
+                    }),
+                    Err(err) => error!("WBOIT nearest depth pipeline specialization error: {err}"),
+                }
+            }
 
             wboit_phase.add(WboitAccum3d {
-                distance: item.distance,
+                distance,
                 pipeline: pipeline_id,
                 entity: (render_entity, main_entity),
-                draw_function: draw_wboit,
+                draw_function: draw_wboit[group_rank % 2],
                 batch_range: 0..1,
                 extra_index: PhaseItemExtraIndex::None,
                 indexed: item.indexed,
@@ -100,14 +331,634 @@ pub fn queue_wboit_meshes(
     }
 }
 
-/// Drain transparent phase items for WBOIT cameras so the standard transparent pass is a no-op.
+/// Cap the `WboitAccum3d` phase of each view at `WboitSettings::max_draws`, right after
+/// queueing, so `drain_transparent_for_wboit` and its `WboitDrainStats` see what is drawn.
+///
+/// With `WboitSettings::sort_accum` the nearest items are kept (the largest sort distances,
+/// drawn last); without it, the first items queued. The `WboitNearestDepth3d` phase keeps the
+/// same meshes, so dropped ones neither cost a prepass draw nor fade the ones that are drawn.
+pub fn cap_wboit_accum_phases(
+    mut wboit_phases: ResMut<ViewSortedRenderPhases<WboitAccum3d>>,
+    mut nearest_phases: ResMut<ViewSortedRenderPhases<WboitNearestDepth3d>>,
+    views: Query<(&ExtractedView, &WboitSettings)>,
+) {
+    for (view, settings) in &views {
+        let Some(max_draws) = settings.max_draws.map(|max| max as usize) else {
+            continue;
+        };
+        let Some(phase) = wboit_phases.get_mut(&view.retained_view_entity) else {
+            continue;
+        };
+        let excess = phase.items.len().saturating_sub(max_draws);
+        if excess == 0 {
+            continue;
+        }
+        warn_once!(
+            "WBOIT: {} transparent draws exceed WboitSettings::max_draws ({max_draws}); \
+             skipping the rest",
+            phase.items.len()
+        );
+        if settings.sort_accum {
+            // Move the `excess` farthest items to the front, then drop them.
+            phase
+                .items
+                .select_nth_unstable_by_key(excess - 1, |item| FloatOrd(item.distance));
+            phase.items.drain(..excess);
+        } else {
+            phase.items.truncate(max_draws);
+        }
+        if let Some(nearest_phase) = nearest_phases.get_mut(&view.retained_view_entity) {
+            let kept: MainEntityHashSet = phase.items.iter().map(|item| item.entity.1).collect();
+            nearest_phase
+                .items
+                .retain(|item| kept.contains(&item.entity.1));
+        }
+    }
+}
+
+/// Sort the `WboitAccum3d` phase of each view, like `sort_phase_system`, except for views
+/// whose `WboitSettings::sort_accum` is off.
+pub fn sort_wboit_accum_phases(
+    mut wboit_phases: ResMut<ViewSortedRenderPhases<WboitAccum3d>>,
+    views: Query<(&ExtractedView, &WboitSettings)>,
+) {
+    for (view, settings) in &views {
+        if !settings.sort_accum {
+            continue;
+        }
+        if let Some(phase) = wboit_phases.get_mut(&view.retained_view_entity) {
+            phase.sort();
+        }
+    }
+}
+
+/// Meshes whose WBOIT accum pipelines are compiled ahead of time, for every WBOIT camera and
+/// registered WBOIT material, so the first frame they are drawn transparent does not stall on
+/// shader compilation.
+///
+/// Insert it in the main world during loading, with the meshes that will later be drawn with
+/// transparent materials. Pipelines are keyed on the camera's view key and `WboitSettings`, so
+/// prewarming needs the final cameras to exist; changing those later compiles new variants.
+#[derive(Resource, Clone, Default, ExtractResource)]
+pub struct WboitPrewarmMeshes(pub Vec<Handle<Mesh>>);
+
+/// Specialize the WBOIT pipelines of material `M` for every mesh in `WboitPrewarmMeshes` on
+/// every WBOIT camera, without queuing anything.
+///
+/// Each mesh is prewarmed with and without the per-entity key bits the queue may add
+/// (`WboitAlwaysVisible`, and per-instance data from `WboitInstanceOpacity` or
+/// `WboitDepthOffset`), along with its nearest-depth prepass variant. Each key is specialized
+/// once: only again after the pipelines are dropped, on `WboitInvalidatePipelines` or a device
+/// change. Meshes whose `RenderMesh` is not ready yet are retried the next frame.
+pub fn prewarm_wboit_pipelines<M: WboitMaterial>(
+    prewarm_meshes: Option<Res<WboitPrewarmMeshes>>,
+    render_meshes: Res<RenderAssets<RenderMesh>>,
+    wboit_pipeline: Option<Res<WboitPipeline<M>>>,
+    mut pipelines: ResMut<SpecializedMeshPipelines<WboitPipeline<M>>>,
+    pipeline_cache: Res<PipelineCache>,
+    views: Query<(&ExtractedView, &WboitSettings, Option<&WboitWeightOverride>)>,
+    view_key_cache: Res<ViewKeyCache>,
+    render_device: Res<RenderDevice>,
+    invalidated: Res<WboitPipelinesInvalidated>,
+    mut prewarmed: Local<HashSet<(AssetId<Mesh>, WboitPipelineKey)>>,
+) {
+    if (render_device.is_changed() && !render_device.is_added()) || invalidated.0 {
+        prewarmed.clear();
+    }
+    let (Some(prewarm_meshes), Some(wboit_pipeline)) = (prewarm_meshes, wboit_pipeline) else {
+        return;
+    };
+    let mut keys = Vec::new();
+    for (view, settings, weight_override) in &views {
+        let Some(view_key) = view_key_cache.get(&view.retained_view_entity) else {
+            continue;
+        };
+        for handle in &prewarm_meshes.0 {
+            let Some(mesh) = render_meshes.get(handle) else {
+                continue;
+            };
+            let key = WboitPipelineKey {
+                weight_override: weight_override.copied(),
+                ..WboitPipelineKey::new(*view_key, mesh, settings)
+            };
+            for (always_visible, instance_data) in
+                [(false, false), (true, false), (false, true), (true, true)]
+            {
+                let key = WboitPipelineKey {
+                    always_visible: key.always_visible || always_visible,
+                    instance_data,
+                    ..key
+                };
+                let prepass_key = key
+                    .nearest_depth_falloff
+                    .then(|| key.nearest_depth_prepass());
+                for key in [Some(key), prepass_key].into_iter().flatten() {
+                    if prewarmed.insert((handle.id(), key)) {
+                        keys.push((key, &mesh.layout));
+                    }
+                }
+            }
+        }
+    }
+    wboit_pipeline.prewarm(&mut pipelines, &pipeline_cache, keys);
+}
+
+/// `RenderLayers` of mesh entities, extracted only while a camera has a `WboitLayerConfig`.
+#[derive(Resource, Default)]
+pub struct WboitMeshLayers(pub MainEntityHashMap<RenderLayers>);
+
+impl WboitMeshLayers {
+    /// Whether `config` routes `entity` to the sorted transparent pass.
+    pub fn is_sorted(&self, config: Option<&WboitLayerConfig>, entity: MainEntity) -> bool {
+        let Some(config) = config else {
+            return false;
+        };
+        match self.0.get(&entity) {
+            Some(layers) => config.sorted_layers.intersects(layers),
+            None => config.sorted_layers.intersects(&RenderLayers::layer(0)),
+        }
+    }
+}
+
+/// Main-world entities with `WboitAlwaysVisible`, extracted each frame.
+#[derive(Resource, Default)]
+pub struct WboitAlwaysVisibleEntities(pub MainEntityHashSet);
+
+/// Extract the set of `WboitAlwaysVisible` entities.
+pub fn extract_wboit_always_visible(
+    mut always_visible: ResMut<WboitAlwaysVisibleEntities>,
+    entities: Extract<Query<Entity, With<WboitAlwaysVisible>>>,
+) {
+    always_visible.0.clear();
+    always_visible
+        .0
+        .extend(entities.iter().map(MainEntity::from));
+}
+
+/// Main-world entities carrying `WboitInstanceOpacity` or `WboitDepthOffset` in a WBOIT-owned
+/// `MeshTag`, extracted each frame.
+#[derive(Resource, Default)]
+pub struct WboitInstanceDataEntities(pub MainEntityHashSet);
+
+/// Extract the set of entities with a WBOIT-owned `MeshTag`.
+pub fn extract_wboit_instance_data(
+    mut instance_data: ResMut<WboitInstanceDataEntities>,
+    entities: Extract<Query<Entity, With<WboitOwnedMeshTag>>>,
+) {
+    instance_data.0.clear();
+    instance_data
+        .0
+        .extend(entities.iter().map(MainEntity::from));
+}
+
+/// Marks a `MeshTag` that WBOIT inserted to carry per-instance data. Only tags with this
+/// marker are overwritten or removed by WBOIT.
+#[derive(Component, Clone, Copy, Default)]
+pub struct WboitOwnedMeshTag;
+
+/// Pack an instance's `WboitInstanceOpacity` and `WboitDepthOffset` into its `MeshTag`: the
+/// opacity as unorm16 in the low half, the offset as bfloat16 (the high half of its `f32`,
+/// rounded) in the high half. Missing values pack as opacity `1` and offset `0`.
+pub fn wboit_instance_tag(opacity: Option<f32>, depth_offset: Option<f32>) -> u32 {
+    let opacity = (opacity.unwrap_or(1.0).clamp(0.0, 1.0) * 65535.0).round() as u32;
+    let depth_offset = depth_offset
+        .filter(|offset| offset.is_finite())
+        .unwrap_or(0.0);
+    let depth_offset = depth_offset.to_bits().saturating_add(0x8000) & 0xffff_0000;
+    depth_offset | opacity
+}
+
+/// Write each entity's `WboitInstanceOpacity` and `WboitDepthOffset` into its `MeshTag`, where
+/// the accum shaders read them per instance, and drop the tag again when both are removed.
+///
+/// Entities with a `MeshTag` of their own keep it: their opacity and offset are ignored with a
+/// warning. Bevy's GPU mesh extraction does not watch `MeshTag` for changes, so `Mesh3d` is
+/// marked changed as well to get the new tag uploaded.
+pub fn sync_wboit_instance_data(
+    mut commands: Commands,
+    mut entities: Query<(
+        Option<&WboitInstanceOpacity>,
+        Option<&WboitDepthOffset>,
+        Option<&mut Mesh3d>,
+        Has<MeshTag>,
+        Has<WboitOwnedMeshTag>,
+    )>,
+    changed: Query<Entity, Or<(Changed<WboitInstanceOpacity>, Changed<WboitDepthOffset>)>>,
+    mut removed_opacity: RemovedComponents<WboitInstanceOpacity>,
+    mut removed_offset: RemovedComponents<WboitDepthOffset>,
+) {
+    let removed = removed_opacity.read().chain(removed_offset.read());
+    let dirty: EntityHashSet = changed.iter().chain(removed).collect();
+    for entity in dirty {
+        let Ok((opacity, depth_offset, mesh, has_tag, owns_tag)) = entities.get_mut(entity) else {
+            continue;
+        };
+        if has_tag && !owns_tag {
+            if opacity.is_some() || depth_offset.is_some() {
+                warn_once!(
+                    "WBOIT: {entity} has WboitInstanceOpacity or WboitDepthOffset and its own \
+                     MeshTag; they are ignored since they are carried in the MeshTag"
+                );
+            }
+            continue;
+        }
+        if opacity.is_none() && depth_offset.is_none() {
+            if owns_tag {
+                commands
+                    .entity(entity)
+                    .remove::<(MeshTag, WboitOwnedMeshTag)>();
+            }
+            continue;
+        }
+        let tag = wboit_instance_tag(opacity.map(|o| o.0), depth_offset.map(|o| o.0));
+        commands
+            .entity(entity)
+            .insert((MeshTag(tag), WboitOwnedMeshTag));
+        if let Some(mut mesh) = mesh {
+            mesh.set_changed();
+        }
+    }
+}
+
+/// Extract mesh `RenderLayers` for `WboitLayerConfig` routing.
+pub fn extract_wboit_mesh_layers(
+    mut mesh_layers: ResMut<WboitMeshLayers>,
+    configs: Extract<Query<(), With<WboitLayerConfig>>>,
+    meshes: Extract<Query<(Entity, &RenderLayers), With<Mesh3d>>>,
+) {
+    mesh_layers.0.clear();
+    if configs.is_empty() {
+        return;
+    }
+    for (entity, layers) in &meshes {
+        mesh_layers.0.insert(entity.into(), layers.clone());
+    }
+}
+
+/// `WboitGroup` of mesh entities, extracted only while a camera has `WboitGroups`. Meshes not
+/// in the map are in group `0`.
+#[derive(Resource, Default)]
+pub struct WboitGroupEntities(pub MainEntityHashMap<u8>);
+
+impl WboitGroupEntities {
+    /// Position of `entity`'s group in `groups`, `None` if the camera does not draw that
+    /// group. Every entity is at position `0` on a camera without `WboitGroups`.
+    pub fn rank(&self, groups: Option<&WboitGroups>, entity: MainEntity) -> Option<usize> {
+        let Some(groups) = groups else {
+            return Some(0);
+        };
+        groups.rank(self.0.get(&entity).copied().unwrap_or_default())
+    }
+}
+
+/// Extract mesh `WboitGroup`s for `WboitGroups` cameras.
+pub fn extract_wboit_groups(
+    mut group_entities: ResMut<WboitGroupEntities>,
+    cameras: Extract<Query<(), With<WboitGroups>>>,
+    meshes: Extract<Query<(Entity, &WboitGroup), With<Mesh3d>>>,
+) {
+    group_entities.0.clear();
+    if cameras.is_empty() {
+        return;
+    }
+    for (entity, group) in &meshes {
+        group_entities.0.insert(entity.into(), group.0);
+    }
+}
+
+/// Drain transparent phase items for WBOIT cameras so the standard transparent pass is a no-op,
+/// except for items a `WboitLayerConfig` routes to the sorted pass.
+///
+/// Views that queued fewer items into `WboitAccum3d` than
+/// `WboitSettings::min_transparents_for_wboit` are left to the sorted pass instead: their
+/// `WboitAccum3d` and `WboitNearestDepth3d` phases are emptied and nothing is drained.
+///
+/// Records how many items were cleared versus queued into `WboitAccum3d` as `WboitDrainStats`.
 pub fn drain_transparent_for_wboit(
     mut transparent_phases: ResMut<ViewSortedRenderPhases<Transparent3d>>,
-    views: Query<&ExtractedView, With<WboitSettings>>,
+    mut wboit_phases: ResMut<ViewSortedRenderPhases<WboitAccum3d>>,
+    mut nearest_phases: ResMut<ViewSortedRenderPhases<WboitNearestDepth3d>>,
+    stats_sink: Option<Res<WboitDrainStatsSink>>,
+    mesh_layers: Res<WboitMeshLayers>,
+    views: Query<(&ExtractedView, &WboitSettings, Option<&WboitLayerConfig>)>,
 ) {
-    for view in &views {
-        if let Some(phase) = transparent_phases.get_mut(&view.retained_view_entity) {
-            phase.items.clear();
+    for (view, settings, layer_config) in &views {
+        let Some(phase) = transparent_phases.get_mut(&view.retained_view_entity) else {
+            continue;
+        };
+        let total = phase.items.len();
+        let wboit_phase = wboit_phases.get_mut(&view.retained_view_entity);
+        let wboit_count = wboit_phase.as_ref().map_or(0, |phase| phase.items.len());
+        if !settings.uses_wboit_for(wboit_count) {
+            if let Some(wboit_phase) = wboit_phase {
+                wboit_phase.items.clear();
+            }
+            if let Some(nearest_phase) = nearest_phases.get_mut(&view.retained_view_entity) {
+                nearest_phase.items.clear();
+            }
+        } else {
+            phase
+                .items
+                .retain(|item| mesh_layers.is_sorted(layer_config, item.entity.1));
+        }
+        let cleared = total - phase.items.len();
+
+        if let Some(sink) = stats_sink.as_ref() {
+            let queued = wboit_phases
+                .get(&view.retained_view_entity)
+                .map_or(0, |phase| phase.items.len());
+            sink.record(
+                view.retained_view_entity.main_entity.id(),
+                WboitDrainStats { cleared, queued },
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{
+        assert_queue_keeps_depth_bias, assert_queue_skips_meshes_past_max_distance,
+        assert_queues_no_frustum_culling_meshes, extracted_view, item_entity,
+    };
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::render::render_phase::RenderCommandState;
+    use bevy::render::render_resource::CachedRenderPipelineId;
+    use bevy::render::view::RetainedViewEntity;
+
+    fn instance_data_app() -> App {
+        let mut app = App::new();
+        app.add_systems(Update, sync_wboit_instance_data);
+        app
+    }
+
+    fn mesh_tag(app: &App, entity: Entity) -> Option<u32> {
+        app.world().get::<MeshTag>(entity).map(|tag| tag.0)
+    }
+
+    /// A render world with one naive WBOIT view with `settings`, whose `WboitAccum3d` phase
+    /// holds an item per sort distance in `distances`, in that queue order.
+    fn accum_phase_world(settings: WboitSettings, distances: &[f32]) -> World {
+        let mut world = World::new();
+        world.init_resource::<DrawFunctions<WboitAccum3d>>();
+        let draw = RenderCommandState::<WboitAccum3d, SetItemPipeline>::new(&mut world);
+        let draw_function = world
+            .resource::<DrawFunctions<WboitAccum3d>>()
+            .write()
+            .add(draw);
+
+        let camera = world.spawn_empty().id();
+        let view = extracted_view(camera, Transform::default());
+        let mut phases = ViewSortedRenderPhases::<WboitAccum3d>::default();
+        phases.insert_or_clear(view.retained_view_entity);
+        let phase = phases.get_mut(&view.retained_view_entity).unwrap();
+        for (index, &distance) in distances.iter().enumerate() {
+            phase.add(WboitAccum3d {
+                distance,
+                pipeline: CachedRenderPipelineId::INVALID,
+                entity: item_entity(index as u32),
+                draw_function,
+                batch_range: 0..1,
+                extra_index: PhaseItemExtraIndex::None,
+                indexed: false,
+            });
         }
+        world.insert_resource(phases);
+        world.init_resource::<ViewSortedRenderPhases<WboitNearestDepth3d>>();
+        world.entity_mut(camera).insert((view, settings));
+        world
+    }
+
+    fn retained_view(world: &mut World) -> RetainedViewEntity {
+        let mut views = world.query::<&ExtractedView>();
+        views.single(world).unwrap().retained_view_entity
+    }
+
+    fn accum_distances(world: &mut World) -> Vec<f32> {
+        let view = retained_view(world);
+        let phases = world.resource::<ViewSortedRenderPhases<WboitAccum3d>>();
+        phases
+            .get(&view)
+            .unwrap()
+            .items
+            .iter()
+            .map(|item| item.distance)
+            .collect()
+    }
+
+    #[test]
+    fn max_draws_keeps_the_nearest_or_the_first_queued_items() {
+        // Rangefinder distances grow towards the camera: 4.0 is the nearest item.
+        let distances = [-3.0, 4.0, -9.0, 0.5, -1.0];
+        let kept = |sort_accum| {
+            let settings = WboitSettings {
+                max_draws: Some(3),
+                sort_accum,
+                ..default()
+            };
+            let mut world = accum_phase_world(settings, &distances);
+            world.run_system_once(cap_wboit_accum_phases).unwrap();
+            accum_distances(&mut world)
+        };
+
+        let mut nearest = kept(true);
+        nearest.sort_by(f32::total_cmp);
+        assert_eq!(nearest, [-1.0, 0.5, 4.0]);
+        assert_eq!(kept(false), [-3.0, 4.0, -9.0]);
+
+        let mut world = accum_phase_world(WboitSettings::default(), &distances);
+        world.run_system_once(cap_wboit_accum_phases).unwrap();
+        assert_eq!(accum_distances(&mut world).len(), distances.len());
+    }
+
+    #[test]
+    fn max_draws_caps_the_nearest_depth_prepass_to_the_same_meshes() {
+        let settings = WboitSettings {
+            max_draws: Some(2),
+            nearest_depth_falloff: Some(0.5),
+            ..default()
+        };
+        let distances = [-3.0, 4.0, -9.0, 0.5];
+        let mut world = accum_phase_world(settings, &distances);
+        world.init_resource::<DrawFunctions<WboitNearestDepth3d>>();
+        let draw = RenderCommandState::<WboitNearestDepth3d, SetItemPipeline>::new(&mut world);
+        let draw_function = world
+            .resource::<DrawFunctions<WboitNearestDepth3d>>()
+            .write()
+            .add(draw);
+        let view = retained_view(&mut world);
+        let mut nearest_phases =
+            world.resource_mut::<ViewSortedRenderPhases<WboitNearestDepth3d>>();
+        nearest_phases.insert_or_clear(view);
+        let nearest_phase = nearest_phases.get_mut(&view).unwrap();
+        for (index, &distance) in distances.iter().enumerate() {
+            nearest_phase.add(WboitNearestDepth3d {
+                distance,
+                pipeline: CachedRenderPipelineId::INVALID,
+                entity: item_entity(index as u32),
+                draw_function,
+                batch_range: 0..1,
+                extra_index: PhaseItemExtraIndex::None,
+                indexed: false,
+            });
+        }
+
+        world.run_system_once(cap_wboit_accum_phases).unwrap();
+        let accum: MainEntityHashSet = world
+            .resource::<ViewSortedRenderPhases<WboitAccum3d>>()
+            .get(&view)
+            .unwrap()
+            .items
+            .iter()
+            .map(|item| item.entity.1)
+            .collect();
+        let nearest: MainEntityHashSet = world
+            .resource::<ViewSortedRenderPhases<WboitNearestDepth3d>>()
+            .get(&view)
+            .unwrap()
+            .items
+            .iter()
+            .map(|item| item.entity.1)
+            .collect();
+        assert_eq!(
+            accum,
+            [item_entity(1).1, item_entity(3).1].into_iter().collect()
+        );
+        assert_eq!(nearest, accum);
+    }
+
+    #[test]
+    fn drain_stats_count_the_capped_phase() {
+        let settings = WboitSettings {
+            max_draws: Some(2),
+            ..default()
+        };
+        let mut world = accum_phase_world(settings, &[1.0, 2.0, 3.0, 4.0]);
+        let sink = WboitDrainStatsSink::default();
+        world.insert_resource(sink.clone());
+        world.init_resource::<ViewSortedRenderPhases<Transparent3d>>();
+        world.init_resource::<WboitMeshLayers>();
+        let view = retained_view(&mut world);
+        world
+            .resource_mut::<ViewSortedRenderPhases<Transparent3d>>()
+            .insert_or_clear(view);
+
+        world.run_system_once(cap_wboit_accum_phases).unwrap();
+        world.run_system_once(drain_transparent_for_wboit).unwrap();
+        let stats = sink.0.lock().unwrap()[&view.main_entity.id()];
+        assert_eq!(stats.queued, 2);
+    }
+
+    #[test]
+    fn instance_tag_packs_opacity_and_depth_offset() {
+        assert_eq!(wboit_instance_tag(None, None), 0xffff);
+        assert_eq!(wboit_instance_tag(Some(0.0), None), 0);
+        assert_eq!(wboit_instance_tag(Some(2.0), None), 0xffff);
+        assert_eq!(wboit_instance_tag(Some(0.5), None), 32768);
+
+        let tag = wboit_instance_tag(None, Some(0.01));
+        assert_eq!(tag & 0xffff, 0xffff);
+        let offset = f32::from_bits(tag & 0xffff_0000);
+        assert!((offset - 0.01).abs() < 0.01 / 128.0);
+        let tag = wboit_instance_tag(None, Some(-3.0));
+        assert_eq!(f32::from_bits(tag & 0xffff_0000), -3.0);
+    }
+
+    #[test]
+    fn instance_data_inserts_updates_and_removes_its_own_mesh_tag() {
+        let mut app = instance_data_app();
+        let entity = app
+            .world_mut()
+            .spawn((WboitInstanceOpacity(0.5), WboitDepthOffset(0.25)))
+            .id();
+        app.update();
+        assert_eq!(
+            mesh_tag(&app, entity),
+            Some(wboit_instance_tag(Some(0.5), Some(0.25)))
+        );
+        assert!(app.world().get::<WboitOwnedMeshTag>(entity).is_some());
+
+        app.world_mut()
+            .entity_mut(entity)
+            .remove::<WboitInstanceOpacity>();
+        app.update();
+        assert_eq!(
+            mesh_tag(&app, entity),
+            Some(wboit_instance_tag(None, Some(0.25)))
+        );
+
+        app.world_mut()
+            .entity_mut(entity)
+            .remove::<WboitDepthOffset>();
+        app.update();
+        assert_eq!(mesh_tag(&app, entity), None);
+        assert!(app.world().get::<WboitOwnedMeshTag>(entity).is_none());
+    }
+
+    #[test]
+    fn instance_data_leaves_a_foreign_mesh_tag_alone() {
+        let mut app = instance_data_app();
+        let entity = app
+            .world_mut()
+            .spawn((
+                MeshTag(7),
+                WboitInstanceOpacity(0.5),
+                WboitDepthOffset(0.25),
+            ))
+            .id();
+        app.update();
+        assert_eq!(mesh_tag(&app, entity), Some(7));
+
+        app.world_mut()
+            .entity_mut(entity)
+            .remove::<(WboitInstanceOpacity, WboitDepthOffset)>();
+        app.update();
+        assert_eq!(mesh_tag(&app, entity), Some(7));
+    }
+
+    #[test]
+    fn queued_items_keep_the_transparent_distance_with_depth_bias() {
+        assert_queue_keeps_depth_bias::<WboitAccum3d, _>(
+            WboitSettings::default(),
+            queue_wboit_meshes::<StandardMaterial>,
+        );
+    }
+
+    #[test]
+    fn queues_no_frustum_culling_meshes_outside_the_frustum() {
+        assert_queues_no_frustum_culling_meshes::<WboitAccum3d, _, _>(
+            WboitSettings::default(),
+            queue_wboit_meshes::<StandardMaterial>,
+            drain_transparent_for_wboit,
+        );
+    }
+
+    #[test]
+    fn max_distance_culls_only_what_is_past_it_in_front_of_the_camera() {
+        // Camera at z = 5 looking down -Z: view depth is 5 - z.
+        let view = extracted_view(Entity::PLACEHOLDER, Transform::from_xyz(0.0, 0.0, 5.0));
+        let beyond = |z| is_beyond_max_distance(Some(10.0), &view, Vec3::new(1.0, 2.0, z));
+        assert!(!beyond(0.0), "in front");
+        assert!(!beyond(-5.0), "at max_distance");
+        assert!(beyond(-5.5), "past max_distance");
+        assert!(!beyond(20.0), "behind the camera");
+        assert!(!is_beyond_max_distance(
+            None,
+            &view,
+            Vec3::new(0.0, 0.0, -1.0e6)
+        ));
+    }
+
+    #[test]
+    fn queue_skips_meshes_past_max_distance() {
+        let settings = WboitSettings {
+            max_distance: Some(10.0),
+            ..default()
+        };
+        assert_queue_skips_meshes_past_max_distance::<WboitAccum3d, _>(
+            settings,
+            queue_wboit_meshes::<StandardMaterial>,
+        );
     }
 }
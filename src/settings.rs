@@ -1,17 +1,758 @@
+use bevy::ecs::query::QueryItem;
 use bevy::prelude::*;
 use bevy::render::extract_component::ExtractComponent;
+use bevy::render::render_resource::{BlendState, FilterMode};
+use bevy::render::renderer::RenderDevice;
+use bevy::render::view::RenderLayers;
 
 /// Enables naive WBOIT on this camera. Requires `Msaa::Off`.
 ///
 /// Usage:
 /// ```ignore
-/// commands.spawn((Camera3d::default(), WboitSettings, Msaa::Off));
+/// commands.spawn((Camera3d::default(), WboitSettings::default(), Msaa::Off));
 /// ```
-#[derive(Component, Clone, Copy, Default, ExtractComponent, Reflect)]
+///
+/// Confining WBOIT to a stencil-marked region is not supported: Bevy's core 3D depth texture
+/// (`CORE_3D_DEPTH_FORMAT`, `Depth32Float`) has no stencil aspect, and nothing in the opaque
+/// pipeline writes stencil, so there is no mask for the accum or composite pass to test.
+#[derive(Component, Clone, Copy, Reflect)]
 #[reflect(Default)]
-pub struct WboitSettings;
+pub struct WboitSettings {
+    /// Single OIT quality dial, selecting which passes and shader variants are active:
+    ///
+    /// - `0`: unweighted additive average (no depth weight), cheapest.
+    /// - `1`: standard depth-weighted WBOIT (default).
+    /// - `2`: WBOIT plus grazing-angle coverage correction, for use with
+    ///   [`thickness_absorption`](Self::thickness_absorption) on volumetric glass.
+    /// - `3`: histogram-equalized WBOIT. The camera is handed over to `HEWboitPlugin` (which
+    ///   must be added) via a managed `HEWboitSettings`; see [`apply_wboit_quality`].
+    ///
+    /// Values above `3` are treated as `3`.
+    pub quality: u8,
+    /// Exponential absorption coefficient (per world unit) applied to transparent fragments
+    /// based on the distance to the opaque surface behind them. Thicker regions become more
+    /// opaque and darker, approximating volumetric glass. `0.0` disables absorption.
+    pub thickness_absorption: f32,
+    /// Replace NaN/Inf and negative values in the accum fragment output with zero, so a single
+    /// bad fragment (broken material, degenerate weight) cannot poison the whole pixel through
+    /// additive blending.
+    pub sanitize_output: bool,
+    /// Resolution scale of the accum/revealage targets relative to the camera viewport, in
+    /// `(0, 1]`. Values below `1.0` render transparents at reduced resolution (e.g. `0.5` for
+    /// half-res smoke/fog) and upsample them in the composite (see `composite_filter`). The
+    /// opaque depth test is then done in the fragment shader against the full-resolution depth
+    /// buffer.
+    pub accum_scale: f32,
+    /// Filter used when the composite upsamples reduced-resolution accum targets (and samples
+    /// the `WboitCompositeMask`). `Linear` (default) is smooth but can bleed transparent color
+    /// a few pixels past silhouettes; `Nearest` keeps hard, blocky edges. Full-resolution
+    /// targets are loaded texel for texel and are unaffected.
+    ///
+    /// Not reflected (`FilterMode` is a wgpu type).
+    #[reflect(ignore)]
+    pub composite_filter: FilterMode,
+    /// Upper bound on the coverage of the composited transparent layer, in `(0, 1]`. Under
+    /// pathological overdraw revealage approaches zero and the transparents turn into a solid
+    /// wall; capping the coverage keeps some of the opaque scene visible through dense
+    /// clusters. `1.0` disables the cap.
+    pub max_opacity: f32,
+    /// Multiplier on the opacity of every transparent fragment rendered by this camera, in
+    /// `[0, 1]`. Animate it to fade the whole transparent layer in or out without touching
+    /// materials.
+    pub global_opacity: f32,
+    /// Where the composite runs relative to temporal anti-aliasing (only matters when the
+    /// camera also has TAA), or [`WboitTaaMode::BeforeOpaque`] to draw the transparents as a
+    /// background layer behind all opaque geometry.
+    pub taa_mode: WboitTaaMode,
+    /// Modulate each transparent fragment's coverage (and therefore its weight) with a
+    /// noise-driven pulse over time and screen position, for stylized dissolve/flicker
+    /// effects. Uses the view's `globals.time`, so it animates without any extra uniform.
+    pub animated_weight: bool,
+    /// Debug visualization replacing the normal composite.
+    pub debug: WboitDebug,
+    /// Exponent applied to the revealage (transmittance) in the composite, for matching an
+    /// external compositor that expects a specific transmittance encoding. `1.0` (default)
+    /// leaves it linear.
+    pub revealage_gamma: f32,
+    /// Soft-particle fade distance in world units. Transparent fragments closer than this to
+    /// the opaque surface behind them fade out linearly, so particles and fog cards blend into
+    /// intersecting geometry instead of showing a hard edge. `0.0` disables the fade.
+    pub soft_particle_distance: f32,
+    /// Multiplier on the composited transparent color (not its coverage), for matching the
+    /// exposure of an HDR pipeline before tonemapping. `1.0` leaves the output unchanged.
+    pub composite_exposure: f32,
+    /// Tonemap applied to the composited transparent color when the view target is LDR (the
+    /// camera is not `Hdr`). The accum target is `Rgba16Float`, so bright (e.g. emissive)
+    /// transparents resolve to values above `1.0` that the LDR target clips harshly; a tonemap
+    /// rolls them off instead. Applied after `composite_exposure`, to the layer color and the
+    /// `AlphaMode::Add` glow separately. Ignored on HDR targets (any floating point main
+    /// texture format), which Bevy tonemaps later. `None` (default) writes the values as they
+    /// are.
+    pub composite_tonemap: Option<WboitCompositeTonemap>,
+    /// Transparents whose mesh origin is farther than this from the camera (view-space depth,
+    /// in world units) are not drawn at all, saving fill rate on distant, negligible layers.
+    /// `None` draws everything. Copied to the managed `HEWboitSettings` at quality 3.
+    pub max_distance: Option<f32>,
+    /// Shifts the occlusion boundary of the accum pass's opaque depth test, in world units
+    /// along the view direction. Positive values keep transparent fragments up to this far
+    /// behind an opaque surface (e.g. a water volume that should not be clipped by the terrain
+    /// it intersects); negative values also hide fragments just in front of it. `0.0` (default)
+    /// uses the fixed-function depth test; other values switch the pipelines to a shader-side
+    /// test against the sampled opaque depth.
+    pub depth_test_bias: f32,
+    /// Front emphasis from a single-layer depth peel, in world units. A depth-only prepass
+    /// records the nearest transparent surface of each pixel (fragments with alpha below
+    /// `0.01` do not count), and the accum pass scales the weight of every fragment by
+    /// `exp(-d / falloff)`, `d` being its distance behind that surface, so layers far behind
+    /// the front one fade out of the average. Costs a depth-only draw of every transparent and
+    /// a `Depth32Float` target. `WboitMinimal` meshes neither record nor fade. `None` (default)
+    /// or a non-positive falloff weights by depth alone.
+    pub nearest_depth_falloff: Option<f32>,
+    /// Write the transparent layer's coverage (`1 - revealage`, capped by `max_opacity`) into
+    /// the view target's alpha, blended premultiplied "over" what is there. Enable this on a
+    /// camera that renders into a target cleared to transparent (e.g. an overlay rendered to
+    /// an image) so the result can be composited over other content downstream. When `false`
+    /// (default) only color is written and the target alpha is left as the opaque pass wrote
+    /// it, which is what a camera with an opaque background wants.
+    ///
+    /// The same applies to a transparent window (`Window::transparent` with
+    /// `CompositeAlphaMode::PreMultiplied` and a clear color with zero alpha): with this
+    /// enabled, transparents over the cleared background show through to the desktop instead
+    /// of being dropped by the compositor. The target then holds premultiplied color, which is
+    /// what `PreMultiplied` expects.
+    pub output_alpha: bool,
+    /// Blend state of the composite pass, used instead of the default premultiplied "over"
+    /// (`BlendState::PREMULTIPLIED_ALPHA_BLENDING`) when `Some`, e.g. `BlendState` with a
+    /// `One`/`One` color component for an additive overlay. The composite still outputs
+    /// premultiplied color with coverage in alpha. `None` (default) keeps the built-in blend.
+    ///
+    /// Not reflected (`BlendState` is a wgpu type).
+    #[reflect(ignore)]
+    pub composite_blend_state: Option<BlendState>,
+    /// Also accumulate the view-space normal of the transparent surfaces into
+    /// `WboitTextures::normal`, for effects like screen-space refraction in a custom render
+    /// node. Not used by the built-in composite; costs one more `Rg16Float` accum target.
+    pub accumulate_normals: bool,
+    /// Give this camera WBOIT textures of its own instead of taking them from Bevy's
+    /// `TextureCache` each frame. Cached textures may be handed to another camera of the same
+    /// size, or swapped between the two revealage slots, from one frame to the next; these are
+    /// kept until the size or the allocated targets change (or the camera goes away), so a
+    /// render node can read what the previous frame left in them, e.g. the previous revealage
+    /// at `WboitTextures::revealage[1 - frame_index]`. Costs memory the cache could otherwise
+    /// share between cameras.
+    pub persistent_textures: bool,
+    /// Sort the camera's accum phase by distance each frame (default). The accumulated result
+    /// does not depend on draw order, so the sort only groups draws of nearby meshes for
+    /// batching; `false` skips it, saving CPU time with thousands of transparents, and draws
+    /// them in queue order (still grouped by `WboitGroups`). `benches/sort_accum.rs` compares
+    /// the two.
+    pub sort_accum: bool,
+    /// Upper bound on the transparent meshes the camera draws per frame, as a safety valve
+    /// against runaway transparent counts. The nearest ones are kept (the first queued with
+    /// [`sort_accum`](Self::sort_accum) off) and a warning is logged once. `None` (default)
+    /// draws everything.
+    pub max_draws: Option<u32>,
+    /// Fewest transparent meshes the camera must queue in a frame for WBOIT to draw them.
+    /// Below it they are left to Bevy's sorted transparent pass, whose exact back-to-front
+    /// blending is both cheaper and more accurate for a handful of objects, and WBOIT takes
+    /// over again as soon as there are enough. Meshes only WBOIT draws, such as
+    /// `WboitMinimal` ones, are not drawn below it. `0` (default) always uses WBOIT.
+    pub min_transparents_for_wboit: u32,
+    /// Experimental: how transparents are combined. See [`WboitMode`].
+    pub mode: WboitMode,
+}
+
+/// Routes transparents on some render layers of a naive WBOIT camera to Bevy's sorted
+/// transparent pass instead of WBOIT, e.g. a hero object that needs exact ordering.
+///
+/// Sorted items are drawn in `MainTransparentPass`, before the WBOIT composite, so the WBOIT
+/// layer is always blended over them; they are exact among themselves but not relative to the
+/// WBOIT transparents. Meshes without a `RenderLayers` component are on layer 0.
+#[derive(Component, Clone, ExtractComponent, Reflect)]
+#[reflect(Component, Default)]
+pub struct WboitLayerConfig {
+    /// Transparents on any of these layers are drawn sorted instead of through WBOIT.
+    pub sorted_layers: RenderLayers,
+}
+
+impl Default for WboitLayerConfig {
+    /// Routes nothing to the sorted pass (unlike `RenderLayers::default()`, which is layer 0).
+    fn default() -> Self {
+        Self {
+            sorted_layers: RenderLayers::none(),
+        }
+    }
+}
 
-/// Enables histogram-equalized WBOIT on this camera. Requires `Msaa::Off`.
+/// Screen-space mask for the naive WBOIT composite of this camera. The composited transparent
+/// layer (color and coverage) is multiplied by the red channel of the image, stretched over
+/// the viewport, e.g. to confine transparents to a magic circle or a damage vignette.
+///
+/// Opaque geometry is unaffected. Until the image is loaded the composite is unmasked. Not
+/// supported on HE-WBOIT cameras.
+#[derive(Component, Clone, ExtractComponent, Reflect)]
+#[reflect(Component)]
+pub struct WboitCompositeMask(pub Handle<Image>);
+
+/// Puts a transparent mesh in a WBOIT group, for cameras with [`WboitGroups`]. Meshes without
+/// it are in group `0`; cameras without `WboitGroups` ignore groups.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Component, Default)]
+pub struct WboitGroup(pub u8);
+
+/// Splits the transparents of a naive WBOIT camera into [`WboitGroup`] strata that are each
+/// accumulated and composited on their own, in this order: transparents of a later group are
+/// layered over the earlier ones instead of blending with them (e.g. HUD glass over world
+/// smoke), while staying order independent within the group. Transparents in groups not
+/// listed are not drawn by this camera.
+///
+/// The first group goes through the regular accum and composite passes, and every other group
+/// through `WboitGroupsPass` right after, reusing the camera's accum targets (cleared for each
+/// group), so extra groups cost a pass pair each but no memory. Sorted transparents
+/// (`WboitLayerConfig`) are drawn before all groups. Only applies with
+/// `WboitTaaMode::BeforeTaa`; with the other modes all listed groups are drawn as one.
+///
+/// Usage:
+/// ```ignore
+/// commands.spawn((Camera3d::default(), WboitSettings::default(), WboitGroups(vec![0, 1])));
+/// commands.spawn((Mesh3d(hud), MeshMaterial3d(glass), WboitGroup(1)));
+/// ```
+#[derive(Component, Clone, Debug, PartialEq, Eq, ExtractComponent, Reflect)]
+#[reflect(Component, Default)]
+pub struct WboitGroups(pub Vec<u8>);
+
+impl WboitGroups {
+    /// Position of `group` in the composite order, or `None` if the camera does not draw it.
+    pub fn rank(&self, group: u8) -> Option<usize> {
+        self.0.iter().position(|&listed| listed == group)
+    }
+}
+
+impl Default for WboitGroups {
+    /// Only group `0`, which draws exactly what a camera without `WboitGroups` draws.
+    fn default() -> Self {
+        Self(vec![0])
+    }
+}
+
+/// Draws a transparent mesh through naive WBOIT without the opaque depth test, so it
+/// accumulates over everything (waypoints, always-on-top markers).
+///
+/// Depth-based effects (`thickness_absorption`, `soft_particle_distance`) are skipped for
+/// these meshes. They still weight by their own depth like other transparents.
+#[derive(Component, Clone, Copy, Default, Reflect)]
+#[reflect(Component, Default)]
+pub struct WboitAlwaysVisible;
+
+/// Per-instance opacity of a transparent mesh on naive WBOIT cameras, in `[0, 1]`, e.g. to
+/// fade each enemy's aura on its own while they all share one material. Scales the
+/// fragment's color and coverage like `WboitSettings::global_opacity` does for a camera.
+///
+/// Carried to the GPU in a `MeshTag` that WBOIT inserts and removes along with this component
+/// (as unorm16, next to any `WboitDepthOffset`). Entities that already have a `MeshTag` of
+/// their own keep it, and their opacity is ignored with a warning. Instances with per-instance
+/// data share one pipeline variant and batch with each other, but not with instances without
+/// it.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct WboitInstanceOpacity(pub f32);
+
+/// Moves a transparent mesh this many world units towards the camera when naive WBOIT
+/// rasterizes it, e.g. for a frost decal lying on a glass pane or a wall, so it reliably
+/// passes the depth test over the surface under it and weights as in front of a coplanar
+/// base layer. Unlike the material's `depth_bias`, which only changes the sort distance, this
+/// moves the rasterized depth. Lighting is unaffected.
+///
+/// Applied in the vertex shader from a WBOIT-owned `MeshTag`, like `WboitInstanceOpacity`, so
+/// each decal can have its own offset without a pipeline variant of its own. The offset is
+/// stored as a bfloat16, precise to about 1 part in 256.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct WboitDepthOffset(pub f32);
+
+/// Debug visualizations for the naive WBOIT path.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Default)]
+pub enum WboitDebug {
+    /// Normal composite.
+    #[default]
+    None,
+    /// Heatmap of how many transparent fragments landed on each pixel: blue for one layer,
+    /// through green and yellow, to red at 16 or more. Counted in an extra accum target.
+    Overdraw,
+    /// The resolved transparent layer alone, composited over a gray checkerboard instead of
+    /// the opaque scene (which is still rendered and still occludes transparents). Inspects
+    /// the final WBOIT result in isolation.
+    TransparencyOnly,
+    /// Heatmap of the largest weight any transparent fragment got on each pixel, before
+    /// accumulation, on a log scale: blue where the weight function (times alpha) nearly
+    /// vanishes, through green and yellow, to red where it saturates. For tuning the weight
+    /// function and `WboitWeightOverride`. Recorded in the same extra accum target as
+    /// `Overdraw`.
+    Weight,
+}
+
+impl WboitDebug {
+    /// Whether the mode records into the extra per-pixel accum target.
+    pub(crate) fn uses_debug_target(self) -> bool {
+        matches!(self, Self::Overdraw | Self::Weight)
+    }
+}
+
+/// Tonemap operator for `WboitSettings::composite_tonemap`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Default)]
+pub enum WboitCompositeTonemap {
+    /// `c / (1 + c)`: soft, never clips, but also darkens mid tones.
+    #[default]
+    Reinhard,
+    /// Narkowicz's fit of the ACES filmic curve: keeps mid tones closer to the input and
+    /// saturates around `10.0`.
+    Aces,
+}
+
+/// Overrides the weight function that `WboitSettings::quality` selects, for one naive WBOIT
+/// camera. Everything else the quality level controls (grazing-angle correction, the HE
+/// handoff at `3`) is kept, so cameras can share `WboitDefaults` and differ only in weighting.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash, ExtractComponent, Reflect)]
+#[reflect(Component)]
+pub enum WboitWeightOverride {
+    /// Plain coverage-weighted average with no depth term, as at quality `0`. Order
+    /// independent in the strict sense: moving layers never changes their relative influence.
+    Unweighted,
+    /// The standard exponential depth weight, as at quality `1` and `2`: nearer layers
+    /// dominate the average.
+    Depth,
+}
+
+/// How a naive WBOIT camera combines its transparent fragments.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Default)]
+pub enum WboitMode {
+    /// Weighted blended accumulation and composite (default).
+    #[default]
+    Weighted,
+    /// Exact per-pixel ordered blending through rasterizer-ordered views, without WBOIT's
+    /// weighting approximation. Experimental and capability-gated: the camera falls back to
+    /// [`Weighted`](Self::Weighted) when the device does not support ROV (see
+    /// [`WboitMode::is_supported`]), with a warning once per camera.
+    RovOrdered,
+}
+
+impl WboitMode {
+    /// Whether `render_device` can run this mode.
+    ///
+    /// `RovOrdered` is never supported yet: wgpu (as used by Bevy 0.16) exposes no
+    /// rasterizer-ordered view feature, on any backend, so there is nothing to detect.
+    pub fn is_supported(self, render_device: &RenderDevice) -> bool {
+        let _ = render_device;
+        match self {
+            Self::Weighted => true,
+            Self::RovOrdered => false,
+        }
+    }
+
+    /// The mode actually rendered on `render_device`: `self`, or `Weighted` as the fallback.
+    pub fn resolve(self, render_device: &RenderDevice) -> Self {
+        if self.is_supported(render_device) {
+            self
+        } else {
+            Self::Weighted
+        }
+    }
+}
+
+/// Placement of the naive WBOIT composite in the render graph: relative to Bevy's TAA node,
+/// or before the opaque pass.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Default)]
+pub enum WboitTaaMode {
+    /// Composite in the main pass, before TAA (default). Transparents are anti-aliased, but
+    /// they write no motion vectors, so TAA reprojects them with the motion of the opaque
+    /// surface behind them and moving transparents smear.
+    #[default]
+    BeforeTaa,
+    /// Composite after TAA (and before bloom/tonemapping). Transparents stay out of the TAA
+    /// history and never smear, but their edges are not anti-aliased. They are accumulated
+    /// without the TAA jitter, so they stay put on the resolved image; their opaque depth test
+    /// still uses the jittered depth buffer, which can shift occlusion edges by a subpixel.
+    AfterTaa,
+    /// Accumulate and composite before the main opaque pass, so every opaque surface (and a
+    /// `Skybox`, which draws in that pass) covers the transparent layer. For backdrop effects
+    /// such as distant haze. There is no opaque depth yet, so transparents are never occluded
+    /// and get no depth-based absorption or soft-particle fade, as with `WboitAlwaysVisible`.
+    BeforeOpaque,
+}
+
+impl Default for WboitSettings {
+    fn default() -> Self {
+        Self {
+            quality: 1,
+            thickness_absorption: 0.0,
+            sanitize_output: true,
+            accum_scale: 1.0,
+            composite_filter: FilterMode::Linear,
+            max_opacity: 1.0,
+            global_opacity: 1.0,
+            taa_mode: WboitTaaMode::BeforeTaa,
+            animated_weight: false,
+            debug: WboitDebug::None,
+            revealage_gamma: 1.0,
+            soft_particle_distance: 0.0,
+            composite_exposure: 1.0,
+            composite_tonemap: None,
+            max_distance: None,
+            depth_test_bias: 0.0,
+            nearest_depth_falloff: None,
+            output_alpha: false,
+            composite_blend_state: None,
+            accumulate_normals: false,
+            persistent_textures: false,
+            sort_accum: true,
+            max_draws: None,
+            min_transparents_for_wboit: 0,
+            mode: WboitMode::Weighted,
+        }
+    }
+}
+
+impl ExtractComponent for WboitSettings {
+    type QueryData = (&'static Self, Has<HEWboitSettings>);
+    type QueryFilter = ();
+    type Out = Self;
+
+    /// Cameras at quality 3 render through the HE path, so the naive render systems must not
+    /// see them. Neither must cameras that also have `HEWboitSettings`: HE-WBOIT takes
+    /// precedence, so a camera is never composited twice.
+    fn extract_component((settings, has_he): QueryItem<'_, Self::QueryData>) -> Option<Self::Out> {
+        (settings.uses_naive_path() && !has_he).then_some(*settings)
+    }
+}
+
+impl WboitSettings {
+    /// Quality level at which the camera is rendered with histogram-equalized WBOIT.
+    pub const QUALITY_HISTOGRAM: u8 = 3;
+
+    /// Whether this camera is rendered by the naive WBOIT path (quality below 3).
+    pub fn uses_naive_path(&self) -> bool {
+        self.quality < Self::QUALITY_HISTOGRAM
+    }
+
+    /// Whether the nearest-depth prepass runs (`nearest_depth_falloff` is set and positive).
+    pub fn uses_nearest_depth(&self) -> bool {
+        self.nearest_depth_falloff
+            .is_some_and(|falloff| falloff > 0.0)
+    }
+
+    /// Whether WBOIT draws a frame in which the camera queued `transparents` meshes for it, or
+    /// leaves them to the sorted transparent pass (see `min_transparents_for_wboit`).
+    pub fn uses_wboit_for(&self, transparents: usize) -> bool {
+        transparents >= self.min_transparents_for_wboit as usize
+    }
+
+    /// Whether the accum targets are smaller than the viewport.
+    pub fn is_accum_scaled(&self) -> bool {
+        self.accum_scale < 1.0
+    }
+
+    /// Estimated GPU memory, in bytes, of this camera's naive WBOIT targets for a physical
+    /// viewport and render target size (see [`Self::accum_size`]): accum and glow
+    /// (`Rgba16Float`), two revealage targets (`R8Unorm`), the overdraw count (`R16Float`), the
+    /// summed normals (`Rg16Float`) and the nearest transparent depth (`Depth32Float`) when
+    /// enabled, and the params uniform.
+    ///
+    /// `prepare_wboit_textures` logs the allocated size at debug level for comparison.
+    pub fn estimated_memory(&self, viewport: UVec2, target: UVec2) -> u64 {
+        let size = self.accum_size(viewport, target);
+        let pixels = u64::from(size.x) * u64::from(size.y);
+        let mut bytes_per_pixel = 8 + 8 + 2;
+        if self.debug.uses_debug_target() {
+            bytes_per_pixel += 2;
+        }
+        if self.accumulate_normals {
+            bytes_per_pixel += 4;
+        }
+        if self.uses_nearest_depth() {
+            bytes_per_pixel += 4;
+        }
+        pixels * bytes_per_pixel + 48
+    }
+
+    /// Size of the accum/revealage targets for a camera's physical viewport and render target
+    /// size.
+    ///
+    /// Full-resolution targets match the whole render target, so the accum pass can share the
+    /// opaque depth attachment and draw at the viewport's offset, and the composite can load
+    /// them at the same pixel coordinates. Reduced-resolution targets only cover the scaled
+    /// viewport and are sampled by viewport UV.
+    pub fn accum_size(&self, viewport: UVec2, target: UVec2) -> UVec2 {
+        if !self.is_accum_scaled() {
+            return target;
+        }
+        let scale = self.accum_scale.max(0.01);
+        (viewport.as_vec2() * scale)
+            .ceil()
+            .as_uvec2()
+            .max(UVec2::ONE)
+    }
+
+    /// Per-axis ratio of [`Self::accum_size`] to the viewport size: the scale the accum pass
+    /// actually draws the viewport at, after rounding the target size, rather than the
+    /// requested `accum_scale`. `Vec2::ONE` for full-resolution targets.
+    pub fn accum_ratio(&self, viewport: UVec2, target: UVec2) -> Vec2 {
+        if !self.is_accum_scaled() {
+            return Vec2::ONE;
+        }
+        self.accum_size(viewport, target).as_vec2() / viewport.max(UVec2::ONE).as_vec2()
+    }
+}
+
+/// App-wide default `WboitSettings` for cameras marked with [`InheritWboitDefaults`].
+///
+/// Editing this resource updates every inheriting camera on the next frame. The default
+/// value reproduces `WboitSettings::default()`.
+#[derive(Resource, Clone, Copy, Default, Reflect)]
+#[reflect(Resource, Default)]
+pub struct WboitDefaults(pub WboitSettings);
+
+/// Enables naive WBOIT on this camera using the global [`WboitDefaults`] instead of a
+/// per-camera `WboitSettings`. Requires `Msaa::Off`.
+///
+/// The camera's `WboitSettings` is managed by [`apply_wboit_defaults`]; remove this marker
+/// and insert a `WboitSettings` to give the camera its own configuration.
+///
+/// Usage:
+/// ```ignore
+/// commands.insert_resource(WboitDefaults(WboitSettings { accum_scale: 0.5, ..default() }));
+/// commands.spawn((Camera3d::default(), InheritWboitDefaults, Msaa::Off));
+/// ```
+#[derive(Component, Clone, Copy, Default, Reflect)]
+#[reflect(Component, Default)]
+pub struct InheritWboitDefaults;
+
+/// Copy `WboitDefaults` into the `WboitSettings` of inheriting cameras.
+///
+/// Only touches cameras that just started inheriting, or all of them when the resource changed,
+/// so `WboitSettings` change detection stays meaningful.
+pub fn apply_wboit_defaults(
+    mut commands: Commands,
+    defaults: Res<WboitDefaults>,
+    cameras: Query<Entity, With<InheritWboitDefaults>>,
+    added: Query<Entity, Added<InheritWboitDefaults>>,
+) {
+    if defaults.is_changed() {
+        for entity in &cameras {
+            commands.entity(entity).insert(defaults.0);
+        }
+    } else {
+        for entity in &added {
+            commands.entity(entity).insert(defaults.0);
+        }
+    }
+}
+
+/// Cameras accumulating their transparents into one shared set of naive WBOIT targets,
+/// resolved by a single composite, so they blend in one weighted average instead of being
+/// layered camera over camera (e.g. a mirror or portal view drawn into the same target).
+///
+/// The first camera owns the targets and clears them, even with nothing to draw; the others
+/// add their fragments on top and only the last one composites, over its own viewport. The
+/// cameras must render in list order (increasing `Camera::order`) into targets of the same
+/// size, and agree on the settings that change the set of targets (`debug`,
+/// `accumulate_normals`, `nearest_depth_falloff`) and on `accum_scale`; a camera that doesn't
+/// keeps its own targets, with a warning. Each camera still depth tests against its own
+/// opaque depth. HE-WBOIT cameras and `WboitGroups` are not supported.
+///
+/// Usage:
+/// ```ignore
+/// commands.insert_resource(WboitSharedTarget { cameras: vec![portal_camera, main_camera] });
+/// ```
+#[derive(Resource, Clone, Debug, Default, Reflect)]
+#[reflect(Resource, Default)]
+pub struct WboitSharedTarget {
+    /// The sharing cameras, in rendering order.
+    pub cameras: Vec<Entity>,
+}
+
+/// Marker for `HEWboitSettings` inserted by [`apply_wboit_quality`] (as opposed to user-added).
+#[derive(Component, Clone, Copy, Default)]
+pub struct WboitQualityManagedHE;
+
+/// Hand cameras at `WboitSettings::quality >= 3` over to the HE path, and back when the
+/// quality drops or `WboitSettings` is removed.
+///
+/// User-inserted `HEWboitSettings` are never touched.
+pub fn apply_wboit_quality(
+    mut commands: Commands,
+    cameras: Query<(
+        Entity,
+        &WboitSettings,
+        Has<HEWboitSettings>,
+        Has<WboitQualityManagedHE>,
+    )>,
+    managed: Query<Entity, (With<WboitQualityManagedHE>, Without<WboitSettings>)>,
+) {
+    for (entity, settings, has_he, is_managed) in &cameras {
+        if !settings.uses_naive_path() {
+            if !has_he {
+                let he_settings = HEWboitSettings {
+                    max_distance: settings.max_distance,
+                    ..default()
+                };
+                commands
+                    .entity(entity)
+                    .insert((he_settings, WboitQualityManagedHE));
+            }
+        } else if is_managed {
+            commands
+                .entity(entity)
+                .remove::<(HEWboitSettings, WboitQualityManagedHE)>();
+        }
+    }
+    for entity in &managed {
+        commands
+            .entity(entity)
+            .remove::<(HEWboitSettings, WboitQualityManagedHE)>();
+    }
+}
+
+/// Drops HE-WBOIT cameras to naive WBOIT while the frame time is over budget, and hands them
+/// back once it recovers, for scenes that cannot always afford the histogram passes. Insert it
+/// to enable; [`apply_wboit_adaptive_quality`] (added by `HEWboitPlugin`) does the switching.
+/// Degraded cameras render through `NaiveWboitPlugin`, which must be added.
+///
+/// Usage:
+/// ```ignore
+/// app.insert_resource(WboitAdaptiveQuality { budget_ms: 12.0, ..default() });
+/// ```
+#[derive(Resource, Clone, Copy, Debug, Reflect)]
+#[reflect(Resource, Default)]
+pub struct WboitAdaptiveQuality {
+    /// Frame time, in milliseconds, above which HE cameras are degraded.
+    pub budget_ms: f32,
+    /// Degraded cameras switch back once the frame time is below `budget_ms` times this, so a
+    /// frame time hovering around the budget does not flip the mode every frame.
+    pub recover_fraction: f32,
+    /// Minimum time, in seconds, between two switches.
+    pub min_switch_interval: f32,
+    /// External frame-time signal in milliseconds, e.g. the GPU time of a profiler. `None`
+    /// (default) uses the smoothed real-time frame delta.
+    pub frame_time_ms: Option<f32>,
+}
+
+impl Default for WboitAdaptiveQuality {
+    fn default() -> Self {
+        Self {
+            budget_ms: 16.0,
+            recover_fraction: 0.8,
+            min_switch_interval: 1.0,
+            frame_time_ms: None,
+        }
+    }
+}
+
+/// An HE-WBOIT camera temporarily rendered through naive WBOIT by
+/// [`apply_wboit_adaptive_quality`]. Holds what the camera had before, restored on recovery;
+/// removing `WboitAdaptiveQuality` restores every degraded camera.
+#[derive(Component, Clone, Copy)]
+pub struct WboitAdaptiveDegraded {
+    /// The camera's `HEWboitSettings`.
+    pub he: HEWboitSettings,
+    /// The camera's `WboitSettings`, if it had one (e.g. a quality 3 camera).
+    pub wboit: Option<WboitSettings>,
+    /// Whether `he` was managed by [`apply_wboit_quality`].
+    pub managed: bool,
+}
+
+/// Switch HE-WBOIT cameras to naive WBOIT (quality 2) and back according to
+/// [`WboitAdaptiveQuality`]. The switch is seamless: the HE path re-enters through its
+/// `warmup_frames`, as its textures are recreated.
+pub fn apply_wboit_adaptive_quality(
+    mut commands: Commands,
+    adaptive: Option<Res<WboitAdaptiveQuality>>,
+    time: Res<Time<Real>>,
+    mut smoothed_ms: Local<Option<f32>>,
+    mut last_switch: Local<f32>,
+    he_cameras: Query<
+        (
+            Entity,
+            &HEWboitSettings,
+            Option<&WboitSettings>,
+            Has<WboitQualityManagedHE>,
+        ),
+        Without<WboitAdaptiveDegraded>,
+    >,
+    degraded: Query<(Entity, &WboitAdaptiveDegraded)>,
+) {
+    let Some(adaptive) = adaptive else {
+        *smoothed_ms = None;
+        for (entity, stash) in &degraded {
+            restore_adaptive_degraded(&mut commands, entity, stash);
+        }
+        return;
+    };
+
+    let delta_ms = time.delta_secs() * 1000.0;
+    let measured = smoothed_ms.map_or(delta_ms, |smoothed| smoothed + (delta_ms - smoothed) * 0.1);
+    *smoothed_ms = Some(measured);
+    let frame_time = adaptive.frame_time_ms.unwrap_or(measured);
+
+    let now = time.elapsed_secs();
+    if now - *last_switch < adaptive.min_switch_interval {
+        return;
+    }
+    if frame_time > adaptive.budget_ms {
+        for (entity, he, wboit, managed) in &he_cameras {
+            let naive = match wboit {
+                Some(wboit) => WboitSettings {
+                    quality: wboit.quality.min(2),
+                    ..*wboit
+                },
+                None => WboitSettings {
+                    quality: 2,
+                    max_distance: he.max_distance,
+                    ..default()
+                },
+            };
+            commands
+                .entity(entity)
+                .remove::<(HEWboitSettings, WboitQualityManagedHE)>()
+                .insert((
+                    naive,
+                    WboitAdaptiveDegraded {
+                        he: *he,
+                        wboit: wboit.copied(),
+                        managed,
+                    },
+                ));
+            *last_switch = now;
+        }
+    } else if frame_time < adaptive.budget_ms * adaptive.recover_fraction {
+        for (entity, stash) in &degraded {
+            restore_adaptive_degraded(&mut commands, entity, stash);
+            *last_switch = now;
+        }
+    }
+}
+
+fn restore_adaptive_degraded(
+    commands: &mut Commands,
+    entity: Entity,
+    stash: &WboitAdaptiveDegraded,
+) {
+    let mut camera = commands.entity(entity);
+    camera.remove::<WboitAdaptiveDegraded>().insert(stash.he);
+    match stash.wboit {
+        Some(wboit) => camera.insert(wboit),
+        None => camera.remove::<WboitSettings>(),
+    };
+    if stash.managed {
+        camera.insert(WboitQualityManagedHE);
+    }
+}
+
+/// Enables histogram-equalized WBOIT on this camera. Requires `Msaa::Off`. Takes precedence
+/// over a `WboitSettings` on the same camera, which is then ignored.
 ///
 /// Usage:
 /// ```ignore
@@ -20,20 +761,197 @@ pub struct WboitSettings;
 #[derive(Component, Clone, Copy, ExtractComponent, Reflect)]
 #[reflect(Default)]
 pub struct HEWboitSettings {
+    /// Edge length, in pixels, of the screen tiles that each get their own depth CDF.
+    /// Clamped to at least `1`; a render target smaller than one tile uses a single tile.
+    /// Doubled (with a warning) while the tile grid would exceed the device's 3D texture,
+    /// compute dispatch or storage buffer limits.
     pub tile_size: u32,
+    /// Number of tiles per axis that share one depth histogram, clamped to at least `1`
+    /// (default, one histogram per tile). Each tile's CDF is built from the histograms around
+    /// it, interpolated by distance, so larger values shrink the histogram buffer (and the
+    /// atomic contention on it) by the square of this factor while the CDF stays at
+    /// `tile_size` granularity, at the cost of less local equalization.
+    pub histogram_downscale: u32,
+    /// Render target pixel the tile grid is anchored at: tile edges fall on it and every
+    /// `tile_size` pixels from it (histogram cells every `tile_size * histogram_downscale`).
+    /// `None` (default) anchors it at the camera's viewport origin, so the tiles of a
+    /// sub-viewport camera line up with its viewport rather than with the target's corner.
+    pub tile_origin: Option<UVec2>,
+    /// Number of depth bins per tile histogram, clamped to `[1, 64]`.
     pub num_bins: u32,
     /// Maximum scene depth (in world units) used to normalize linear depth into [0, 1]
     /// for histogram binning. Set this to approximately the farthest transparent object
-    /// in your scene. Equivalent to the `far` plane in the reference implementation.
+    /// in your scene. Equivalent to the `far` plane in the reference implementation. With
+    /// [`HEWboitDepthMode::Auto`] it is only the fallback while no transparents are visible.
     pub max_depth: f32,
+    /// Number of frames after the HE textures are (re)created, i.e. when HE is enabled or the
+    /// viewport or bin layout changes, during which the accum pass weights by plain depth. It
+    /// normally reads the previous frame's revealage and CDF, which hold no valid data yet for
+    /// those frames. `0` equalizes immediately.
+    pub warmup_frames: u32,
+    /// Transparents whose mesh origin is farther than this from the camera (view-space depth,
+    /// in world units) are not drawn. `None` draws everything. Same as
+    /// `WboitSettings::max_distance`.
+    pub max_distance: Option<f32>,
+    /// How much of the histogram equalization is applied to the depth used for weighting, in
+    /// `[0, 1]`. `1.0` (default) weights by the equalized (CDF) depth; `0.0` ignores the CDF
+    /// and weights by plain normalized depth, like naive WBOIT. Values in between blend the
+    /// two.
+    pub equalization_strength: f32,
+    /// Where the histogram's depth range ends. See [`HEWboitDepthMode`].
+    pub depth_mode: HEWboitDepthMode,
+    /// Color transmitted per world unit of view distance through the transparent layer, for
+    /// fog-like absorption. The composite reads the tile's depth CDF to find where the
+    /// layer's opacity actually sits (its optical-depth-weighted mean depth) and tints the
+    /// resolved color by this color raised to that distance, so a stack whose opacity is far
+    /// away is tinted more than one that is near, wherever its frontmost surface is. Alpha
+    /// scales the effect. `LinearRgba::WHITE` (default) absorbs nothing.
+    pub absorption_color: LinearRgba,
+}
+
+impl HEWboitSettings {
+    /// Estimated GPU memory, in bytes, of this camera's HE-WBOIT resources for a physical
+    /// render target size (HE targets always cover the whole target, like full-resolution
+    /// naive ones): accum (`Rgba16Float`) and two revealage targets (`R8Unorm`), plus a `u32`
+    /// bin per histogram and an `Rgba16Float` CDF texel per tile and bin, and the params
+    /// uniform.
+    ///
+    /// `prepare_histogram_wboit_textures` logs the allocated size at debug level.
+    pub fn estimated_memory(&self, target: UVec2) -> u64 {
+        let pixels = u64::from(target.x) * u64::from(target.y);
+        let tile_size = self.tile_size.max(1);
+        let downscale = self.histogram_downscale.max(1);
+        let (tiles_x, tiles_y) = (target.x.div_ceil(tile_size), target.y.div_ceil(tile_size));
+        let tiles = u64::from(tiles_x) * u64::from(tiles_y);
+        let histograms =
+            u64::from(tiles_x.div_ceil(downscale)) * u64::from(tiles_y.div_ceil(downscale));
+        let num_bins = u64::from(self.num_bins.clamp(1, 64));
+        pixels * (8 + 2) + (histograms * 4 + tiles * 8) * num_bins + 64
+    }
 }
 
 impl Default for HEWboitSettings {
     fn default() -> Self {
         Self {
             tile_size: 32,
+            histogram_downscale: 1,
+            tile_origin: None,
             num_bins: 64,
             max_depth: 100.0,
+            warmup_frames: 2,
+            max_distance: None,
+            equalization_strength: 1.0,
+            depth_mode: HEWboitDepthMode::Fixed,
+            absorption_color: LinearRgba::WHITE,
+        }
+    }
+}
+
+/// How an HE-WBOIT camera chooses the far end of the depth range its histogram bins cover.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Default)]
+pub enum HEWboitDepthMode {
+    /// Use [`HEWboitSettings::max_depth`] (default).
+    #[default]
+    Fixed,
+    /// Estimate it every frame from the view-space bounds of the transparent meshes the camera
+    /// sees (`HEWboitAutoDepth`), so the bins cover where transparents actually are rather
+    /// than the whole `max_depth` range. Falls back to `max_depth` while none are visible.
+    Auto,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn few_transparents_are_left_to_the_sorted_pass() {
+        let settings = WboitSettings {
+            min_transparents_for_wboit: 16,
+            ..default()
+        };
+        assert!(!settings.uses_wboit_for(2));
+        assert!(settings.uses_wboit_for(200));
+        assert!(WboitSettings::default().uses_wboit_for(0));
+    }
+
+    #[test]
+    fn estimated_memory_counts_the_normal_target() {
+        let (viewport, target) = (UVec2::new(100, 50), UVec2::new(100, 50));
+        let plain = WboitSettings::default();
+        let normals = WboitSettings {
+            accumulate_normals: true,
+            ..default()
+        };
+        assert_eq!(
+            normals.estimated_memory(viewport, target) - plain.estimated_memory(viewport, target),
+            100 * 50 * 4
+        );
+    }
+
+    #[test]
+    fn accum_ratio_maps_odd_viewports_onto_the_whole_rounded_target() {
+        let settings = WboitSettings {
+            accum_scale: 0.5,
+            ..default()
+        };
+        for viewport in [
+            UVec2::new(101, 37),
+            UVec2::new(1, 3),
+            UVec2::new(1921, 1081),
+        ] {
+            let size = settings.accum_size(viewport, viewport);
+            assert_eq!(size, (viewport + 1) / 2, "{viewport}");
+            // The viewport's far corner lands on the target's, not up to a pixel short of it.
+            let ratio = settings.accum_ratio(viewport, viewport);
+            let corner = viewport.as_vec2() * ratio;
+            assert!(
+                (corner - size.as_vec2()).abs().max_element() < 1e-3,
+                "{viewport}"
+            );
         }
+        let full = WboitSettings::default();
+        assert_eq!(
+            full.accum_ratio(UVec2::new(101, 37), UVec2::new(200, 100)),
+            Vec2::ONE
+        );
+    }
+
+    #[test]
+    fn he_estimated_memory_counts_one_histogram_per_downscaled_tile_block() {
+        // 8x4 tiles of 32 pixels.
+        let target = UVec2::new(256, 128);
+        let memory = |histogram_downscale| {
+            HEWboitSettings {
+                histogram_downscale,
+                ..default()
+            }
+            .estimated_memory(target)
+        };
+        let targets = 256 * 128 * (8 + 2);
+        let cdf = 8 * 4 * 8 * 64;
+        assert_eq!(memory(1), targets + 8 * 4 * 4 * 64 + cdf + 64);
+        assert_eq!(memory(0), memory(1));
+        assert_eq!(memory(2), targets + 4 * 2 * 4 * 64 + cdf + 64);
+        // Partial blocks at the edges still get a histogram.
+        assert_eq!(memory(3), targets + 3 * 2 * 4 * 64 + cdf + 64);
+    }
+
+    #[test]
+    fn he_estimated_memory_scales_with_the_clamped_bin_count() {
+        let target = UVec2::new(256, 128);
+        let memory = |num_bins| {
+            HEWboitSettings {
+                num_bins,
+                ..default()
+            }
+            .estimated_memory(target)
+        };
+        let targets = 256 * 128 * (8 + 2);
+        let per_bin = 8 * 4 * (4 + 8);
+        assert_eq!(memory(16), targets + per_bin * 16 + 64);
+        assert_eq!(memory(64), targets + per_bin * 64 + 64);
+        assert_eq!(memory(200), memory(64));
+        assert_eq!(memory(0), memory(1));
     }
 }
@@ -5,12 +5,26 @@ struct HistogramParams {
     tile_count_y: u32,
     num_bins: u32,
     tile_size: u32,
+    max_depth: f32,
+    equalization_strength: f32,
+    fallback_weighting: u32,
+    histogram_downscale: u32,
+    histogram_count_x: u32,
+    histogram_count_y: u32,
+    tile_origin: vec2<u32>,
+    absorption_color: vec4<f32>,
 }
 
-@group(0) @binding(0) var<storage, read_write> histogram: array<atomic<u32>>;
+// Layout built by `cdf_build_layout_entries` (histogram/pipeline.rs); a test checks both agree.
+@group(0) @binding(0) var<storage, read> histogram: array<u32>;
 @group(0) @binding(1) var cdf_out: texture_storage_3d<rgba16float, write>;
 @group(0) @binding(2) var<uniform> histo_params: HistogramParams;
 
+fn histogram_value(cell_x: u32, cell_y: u32, bin: u32) -> f32 {
+    let cell_idx = cell_y * histo_params.histogram_count_x + cell_x;
+    return f32(histogram[cell_idx * histo_params.num_bins + bin]) / OD_SCALE;
+}
+
 var<workgroup> buf_a: array<f32, 64>;
 var<workgroup> buf_b: array<f32, 64>;
 
@@ -23,12 +37,27 @@ fn main(
     let tile_y = wg.y;
     let bin = lid.x;
     let nb = histo_params.num_bins;
-    let tile_idx = tile_y * histo_params.tile_count_x + tile_x;
 
-    // Load and dequantize histogram value
+    // Load and dequantize the histogram value, bilinearly interpolated between the four
+    // histograms around the tile center. With histogram_downscale 1 this is exactly the
+    // tile's own histogram.
     var val: f32 = 0.0;
     if bin < nb {
-        val = f32(atomicLoad(&histogram[tile_idx * nb + bin])) / OD_SCALE;
+        let last = vec2f(
+            f32(histo_params.histogram_count_x - 1u),
+            f32(histo_params.histogram_count_y - 1u),
+        );
+        let cell = clamp(
+            (vec2f(f32(tile_x), f32(tile_y)) + 0.5) / f32(histo_params.histogram_downscale) - 0.5,
+            vec2f(0.0),
+            last,
+        );
+        let c0 = vec2u(floor(cell));
+        let c1 = vec2u(min(floor(cell) + 1.0, last));
+        let f = fract(cell);
+        let top = mix(histogram_value(c0.x, c0.y, bin), histogram_value(c1.x, c0.y, bin), f.x);
+        let bottom = mix(histogram_value(c0.x, c1.y, bin), histogram_value(c1.x, c1.y, bin), f.x);
+        val = mix(top, bottom, f.y);
     }
     buf_a[bin] = val;
     workgroupBarrier();
@@ -70,9 +99,10 @@ fn main(
         let total_od = buf_a[nb - 1u];
         var cdf_val: f32;
         if total_od > 0.0 {
-            cdf_val = buf_a[bin] / total_od;
+            // Clamp against rounding in the prefix sum pushing the last bins above 1.
+            cdf_val = min(buf_a[bin] / total_od, 1.0);
         } else {
-            // Linear fallback when no fragments in tile
+            // Neutral (linear) CDF when no fragments in tile, never 0/0
             cdf_val = f32(bin + 1u) / f32(nb);
         }
 
@@ -81,8 +111,5 @@ fn main(
             vec3i(i32(tile_x), i32(tile_y), i32(bin)),
             vec4f(cdf_val, 0.0, 0.0, 0.0)
         );
-
-        // Clear histogram for next frame
-        atomicStore(&histogram[tile_idx * nb + bin], 0u);
     }
 }
@@ -4,10 +4,12 @@
     pbr_fragment::pbr_input_from_standard_material,
     pbr_types::STANDARD_MATERIAL_FLAGS_UNLIT_BIT,
     forward_io::VertexOutput,
+    view_transformations::depth_ndc_to_view_z,
 }
 
-const TILE_SIZE: u32 = 32u;
 const OD_SCALE: f32 = 4096.0;
+// Stand-in for the previous frame's revealage while warming up.
+const FALLBACK_REVEALAGE: f32 = 0.01;
 
 struct HistogramParams {
     tile_count_x: u32,
@@ -15,11 +17,18 @@ struct HistogramParams {
     num_bins: u32,
     tile_size: u32,
     max_depth: f32,
-    _pad0: u32,
-    _pad1: u32,
-    _pad2: u32,
+    equalization_strength: f32,
+    fallback_weighting: u32,
+    histogram_downscale: u32,
+    histogram_count_x: u32,
+    histogram_count_y: u32,
+    tile_origin: vec2<u32>,
+    absorption_color: vec4<f32>,
 }
 
+// Layout built by `histo_data_layout_entries` (histogram/pipeline.rs); a test checks both agree.
+// Atomic: every fragment of a tile at a given depth adds to the same bin concurrently, and
+// plain read-modify-write stores would drop counts under overdraw.
 @group(3) @binding(0) var<storage, read_write> histogram: array<atomic<u32>>;
 @group(3) @binding(1) var cdf_texture: texture_3d<f32>;
 @group(3) @binding(2) var cdf_sampler: sampler;
@@ -31,6 +40,34 @@ struct WboitOutput {
     @location(1) revealage: f32,
 }
 
+// Quantize the optical depth of a fragment of coverage `alpha` and add it to histogram bin
+// `hist_idx`. The add saturates instead of wrapping: a wrapped bin would make the tile's CDF
+// non-monotonic under extreme overdraw.
+fn record_optical_depth(hist_idx: u32, alpha: f32) {
+    let optical_depth = -log(max(1.0 - alpha, 1e-6));
+    let quantized_od = u32(clamp(optical_depth * OD_SCALE, 0.0, 65535.0));
+    let prev_od = atomicAdd(&histogram[hist_idx], quantized_od);
+    if prev_od > 0xffffffffu - quantized_od {
+        atomicMax(&histogram[hist_idx], 0xffffffffu);
+    }
+}
+
+// View depth of a fragment at NDC depth `ndc_depth`, normalized to [0, 1] over `max_depth`,
+// so 0 is nearest the camera and the CDF grows away from it.
+// ndc_depth is Bevy's reversed-Z depth (1 at the near plane, 0 at infinity);
+// depth_ndc_to_view_z undoes it with the view's projection, for perspective and orthographic
+// cameras alike. View space looks down -Z, hence the negation. max_depth is analogous to the
+// far plane of a finite perspective camera.
+fn normalized_depth(ndc_depth: f32, max_depth: f32) -> f32 {
+    let linear_depth = -depth_ndc_to_view_z(ndc_depth);
+    return clamp(linear_depth / max_depth, 0.0, 1.0);
+}
+
+// Histogram bin of a fragment at `normalized_z`, out of `num_bins`.
+fn depth_bin(normalized_z: f32, num_bins: u32) -> u32 {
+    return min(u32(normalized_z * f32(num_bins)), num_bins - 1u);
+}
+
 @fragment
 fn fragment(
     vertex_output: VertexOutput,
@@ -61,34 +98,51 @@ fn fragment(
 
     let alpha = premul.a;
 
-    // Compute normalized depth [0,1] using linear eye-space depth.
-    // in.position.w = 1/w_clip = 1/eye_z in WGSL fragment shaders, so 1/w gives linear depth.
-    // We normalize by max_depth (analogous to the far plane in a finite perspective camera).
-    let linear_depth = 1.0 / in.position.w;
-    let normalized_z = clamp(linear_depth / histo_params.max_depth, 0.0, 1.0);
+    // Linear eye-space depth, so bin 0 is nearest the camera. The material's depth_bias is
+    // not applied here; it only affects CPU-side sorting.
+    let normalized_z = normalized_depth(in.position.z, histo_params.max_depth);
 
     // --- Histogram recording ---
     let nb = histo_params.num_bins;
-    let bin = min(u32(normalized_z * f32(nb)), nb - 1u);
-
-    let tile_x = u32(in.position.x) / TILE_SIZE;
-    let tile_y = u32(in.position.y) / TILE_SIZE;
-    let tile_idx = tile_y * histo_params.tile_count_x + tile_x;
-
-    // Quantize optical depth and accumulate
-    let optical_depth = -log(max(1.0 - alpha, 1e-6));
-    let quantized_od = u32(clamp(optical_depth * OD_SCALE, 0.0, 65535.0));
-    atomicAdd(&histogram[tile_idx * nb + bin], quantized_od);
+    let bin = depth_bin(normalized_z, nb);
+
+    // tile_size and histogram_downscale are at least 1 and there is at least one histogram
+    // per axis (see prepare_histogram_wboit_textures); the clamp keeps edge pixels in the
+    // last one. The grid starts `grid_shift` pixels before the target's corner so that its
+    // edges fall on tile_origin (already reduced modulo cell_size).
+    let tile_size = histo_params.tile_size;
+    let cell_size = tile_size * histo_params.histogram_downscale;
+    let grid_shift = (vec2(cell_size) - histo_params.tile_origin) % cell_size;
+    let grid_pos = vec2<u32>(in.position.xy) + grid_shift;
+    let cell_x = min(grid_pos.x / cell_size, histo_params.histogram_count_x - 1u);
+    let cell_y = min(grid_pos.y / cell_size, histo_params.histogram_count_y - 1u);
+    let cell_idx = cell_y * histo_params.histogram_count_x + cell_x;
+
+    record_optical_depth(cell_idx * nb + bin, alpha);
 
     // --- CDF-based weight ---
     // Sample CDF from previous frame (trilinear interpolation)
-    let u = in.position.x / f32(histo_params.tile_count_x * TILE_SIZE);
-    let v = in.position.y / f32(histo_params.tile_count_y * TILE_SIZE);
+    let u = (in.position.x + f32(grid_shift.x)) / f32(histo_params.tile_count_x * tile_size);
+    let v = (in.position.y + f32(grid_shift.y)) / f32(histo_params.tile_count_y * tile_size);
     let w_coord = normalized_z;
-    let equalized_z = textureSampleLevel(cdf_texture, cdf_sampler, vec3f(u, v, w_coord), 0.0).r;
+    // Tiles without transparent fragments last frame hold the linear (neutral) CDF, so this
+    // degrades to plain depth-based weighting there.
+    let cdf_z = clamp(
+        textureSampleLevel(cdf_texture, cdf_sampler, vec3f(u, v, w_coord), 0.0).r,
+        0.0,
+        1.0,
+    );
+    // Strength 0 ignores the CDF (plain depth weighting, like naive WBOIT), 1 fully equalizes.
+    var equalized_z = mix(normalized_z, cdf_z, histo_params.equalization_strength);
 
     // Transmittance weight using previous frame's revealage
-    let prev_R = textureLoad(prev_revealage_tex, vec2<i32>(in.position.xy), 0).r;
+    var prev_R = textureLoad(prev_revealage_tex, vec2<i32>(in.position.xy), 0).r;
+    if histo_params.fallback_weighting != 0u {
+        // Warming up: the CDF and previous revealage hold no valid data yet, so weight by plain
+        // depth against a fixed transmittance until they do.
+        equalized_z = normalized_z;
+        prev_R = FALLBACK_REVEALAGE;
+    }
     let wt = pow(max(prev_R, 1e-4), equalized_z);
 
     var out: WboitOutput;
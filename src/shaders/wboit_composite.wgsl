@@ -1,25 +1,252 @@
 #import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
 
+// Shared by the naive and the HE-WBOIT composite. The HE pipeline defines WBOIT_HISTOGRAM
+// and binds the accum and revealage textures (always full resolution), plus this frame's
+// depth CDF and histogram params for absorption.
+
+// `ACCUM_FORMAT_MAX` shader def set by both composite pipelines, as for the accum shaders.
+const ACCUM_FORMAT_MAX: f32 = f32(#{ACCUM_FORMAT_MAX}u);
+
 @group(0) @binding(0) var accum_tex: texture_2d<f32>;
 @group(0) @binding(1) var revealage_tex: texture_2d<f32>;
 
-@fragment
-fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
-    let coords = vec2<i32>(in.position.xy);
-    let accum = textureLoad(accum_tex, coords, 0);
-    let r = textureLoad(revealage_tex, coords, 0).r;
+#ifdef WBOIT_HISTOGRAM
+// Layout built in `HistoCompositePipeline::from_world`; keep both in sync.
+@group(0) @binding(2) var cdf_texture: texture_3d<f32>;
+@group(0) @binding(3) var<uniform> histo_params: HistogramParams;
 
-    // No transparent fragments at this pixel
-    if accum.a < 1e-5 {
-        discard;
+// Same layout as in histo_fragment.wgsl.
+struct HistogramParams {
+    tile_count_x: u32,
+    tile_count_y: u32,
+    num_bins: u32,
+    tile_size: u32,
+    max_depth: f32,
+    equalization_strength: f32,
+    fallback_weighting: u32,
+    histogram_downscale: u32,
+    histogram_count_x: u32,
+    histogram_count_y: u32,
+    tile_origin: vec2<u32>,
+    absorption_color: vec4<f32>,
+}
+
+// Optical-depth-weighted mean view distance of the tile's transparents, in world units:
+// the mean of a distribution over [0, 1] is the integral of 1 - CDF, summed per bin (the
+// CDF is inclusive, so half a bin is added back for bin centers).
+fn effective_depth(position: vec2<f32>) -> f32 {
+    let nb = histo_params.num_bins;
+    // Same grid as in histo_fragment.wgsl, anchored at tile_origin.
+    let cell_size = histo_params.tile_size * histo_params.histogram_downscale;
+    let grid_shift = (vec2(cell_size) - histo_params.tile_origin) % cell_size;
+    let tile = min(
+        (vec2<u32>(position) + grid_shift) / histo_params.tile_size,
+        vec2(histo_params.tile_count_x, histo_params.tile_count_y) - 1u,
+    );
+    var sum = 0.5;
+    for (var bin = 0u; bin < nb; bin++) {
+        sum += 1.0 - textureLoad(cdf_texture, vec3<u32>(tile, bin), 0).r;
+    }
+    return sum / f32(nb) * histo_params.max_depth;
+}
+
+// HEWboitSettings::absorption_color: transmitted color per world unit, applied to the
+// straight layer color over the layer's effective depth, scaled by its alpha.
+fn absorb(resolved: vec4<f32>, position: vec2<f32>) -> vec4<f32> {
+    let absorption = histo_params.absorption_color;
+    if all(absorption.rgb >= vec3(1.0)) || absorption.a <= 0.0 {
+        return resolved;
     }
+    let transmitted = pow(max(absorption.rgb, vec3(1e-4)), vec3(effective_depth(position)));
+    let tint = mix(vec3(1.0), transmitted, absorption.a);
+    return vec4(resolved.rgb * tint, resolved.a);
+}
+#endif
+
+#ifndef WBOIT_HISTOGRAM
+@group(0) @binding(2) var upsample_sampler: sampler;
+@group(0) @binding(3) var<uniform> wboit_params: WboitParams;
+// Fragment count per pixel; only meaningful with WBOIT_DEBUG_OVERDRAW.
+@group(0) @binding(4) var overdraw_tex: texture_2d<f32>;
+// Summed AlphaMode::Add light, added on top of the resolved layer.
+@group(0) @binding(5) var glow_tex: texture_2d<f32>;
+#ifdef WBOIT_COMPOSITE_MASK
+// WboitCompositeMask image; the red channel scales the whole transparent layer.
+@group(0) @binding(6) var mask_tex: texture_2d<f32>;
+#endif
+
+struct WboitParams {
+    thickness_absorption: f32,
+    sanitize_output: u32,
+    // Non-zero for reduced-resolution accum targets, which only cover the viewport.
+    accum_scaled: u32,
+    max_opacity: f32,
+    global_opacity: f32,
+    revealage_gamma: f32,
+    soft_particle_distance: f32,
+    composite_exposure: f32,
+    depth_test_bias: f32,
+    nearest_depth_falloff: f32,
+    // Per-axis ratio of the accum target size to the viewport size.
+    accum_scale: vec2<f32>,
+}
+#endif
 
+// Resolve the accumulated weighted sum and revealage into a premultiplied color.
+fn resolve(accum: vec4<f32>, r: f32, max_opacity: f32) -> vec4<f32> {
     // Recover average color from weighted sum
     let avg_color = accum.rgb / max(accum.a, 1e-5);
 
-    // Alpha from revealage (product of (1 - alpha_i))
-    let alpha = 1.0 - r;
+    // Alpha from revealage (product of (1 - alpha_i)), capped so dense overdraw cannot turn
+    // into a fully opaque wall.
+    let alpha = min(1.0 - r, max_opacity);
 
     // Output premultiplied alpha for compositing onto opaque
     return vec4(avg_color * alpha, alpha);
 }
+
+// WboitSettings::composite_tonemap, for LDR targets: rolls off values above 1.0 instead of
+// letting the target clip them. Identity without a tonemap def.
+fn tonemap_ldr(color: vec3<f32>) -> vec3<f32> {
+#ifdef WBOIT_COMPOSITE_TONEMAP_REINHARD
+    return color / (1.0 + color);
+#else ifdef WBOIT_COMPOSITE_TONEMAP_ACES
+    let x = max(color, vec3(0.0));
+    return saturate((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14));
+#else
+    return color;
+#endif
+}
+
+// Blue (0) -> green -> yellow -> red (1).
+fn heat(x: f32) -> vec3<f32> {
+    let t = clamp(x, 0.0, 1.0) * 3.0;
+    let blue = vec3(0.0, 0.2, 1.0);
+    let green = vec3(0.0, 1.0, 0.2);
+    let yellow = vec3(1.0, 1.0, 0.0);
+    let red = vec3(1.0, 0.0, 0.0);
+    if t < 1.0 {
+        return mix(blue, green, t);
+    } else if t < 2.0 {
+        return mix(green, yellow, t - 1.0);
+    }
+    return mix(yellow, red, t - 2.0);
+}
+
+#ifdef WBOIT_DEBUG_TRANSPARENCY_ONLY
+// Neutral 16 px gray checkerboard replacing the opaque scene.
+fn debug_background(position: vec2<f32>) -> vec3<f32> {
+    let cell = vec2<u32>(position / 16.0);
+    return select(vec3(0.18), vec3(0.3), ((cell.x + cell.y) & 1u) == 0u);
+}
+#endif
+
+// Final composite output. With WBOIT_DEBUG_TRANSPARENCY_ONLY the premultiplied layer is put
+// over the checkerboard with full alpha, so the blend replaces the opaque scene.
+fn finish(out: vec4<f32>, position: vec2<f32>) -> vec4<f32> {
+#ifdef WBOIT_DEBUG_TRANSPARENCY_ONLY
+    return vec4(out.rgb + debug_background(position) * (1.0 - out.a), 1.0);
+#else
+    return out;
+#endif
+}
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+#ifdef WBOIT_DEBUG_OVERDRAW
+    // Overdraw heatmap replaces the composite. The count target has the accum resolution:
+    // reduced-resolution targets cover the viewport, so map through uv (nearest texel);
+    // full-resolution ones cover the render target, like the fragment position.
+    var overdraw_coords = vec2<i32>(in.position.xy);
+    if wboit_params.accum_scaled != 0u {
+        let overdraw_size = vec2<i32>(textureDimensions(overdraw_tex));
+        overdraw_coords = min(vec2<i32>(in.uv * vec2<f32>(overdraw_size)), overdraw_size - 1);
+    }
+    let count = textureLoad(overdraw_tex, overdraw_coords, 0).r;
+#ifdef WBOIT_DEBUG_WEIGHT
+    // Largest fragment weight, on a log2 scale over the depth weight's range: 2^-13 (blue,
+    // vanishing) to 2^13 (red, saturated).
+    if count <= 0.0 {
+        discard;
+    }
+    return vec4(heat((log2(count) + 13.0) / 26.0), 1.0);
+#else
+    // Blue (1 layer) -> green (4) -> yellow (8) -> red (16+), on a log2 scale.
+    if count < 0.5 {
+        discard;
+    }
+    return vec4(heat(log2(count) / 4.0), 1.0);
+#endif
+#else
+    var accum: vec4<f32>;
+    var r: f32;
+    var max_opacity = 1.0;
+    var glow = vec3(0.0);
+    var exposure = 1.0;
+#ifdef WBOIT_HISTOGRAM
+    let coords = vec2<i32>(in.position.xy);
+    accum = textureLoad(accum_tex, coords, 0);
+    r = textureLoad(revealage_tex, coords, 0).r;
+#else
+    if wboit_params.accum_scaled != 0u {
+        // Reduced-resolution accum: upsample onto the full-res target (bilinear or nearest,
+        // per WboitSettings::composite_filter).
+        accum = textureSampleLevel(accum_tex, upsample_sampler, in.uv, 0.0);
+        r = textureSampleLevel(revealage_tex, upsample_sampler, in.uv, 0.0).r;
+        glow = textureSampleLevel(glow_tex, upsample_sampler, in.uv, 0.0).rgb;
+    } else {
+        let coords = vec2<i32>(in.position.xy);
+        accum = textureLoad(accum_tex, coords, 0);
+        r = textureLoad(revealage_tex, coords, 0).r;
+        glow = textureLoad(glow_tex, coords, 0).rgb;
+    }
+    max_opacity = wboit_params.max_opacity;
+    exposure = wboit_params.composite_exposure;
+    // Optional transmittance encoding; skipped at 1.0 so the default is exactly linear.
+    if wboit_params.revealage_gamma != 1.0 {
+        r = pow(r, wboit_params.revealage_gamma);
+    }
+#endif
+    // The accum shaders only cap single fragments, so more than ACCUM_LAYER_HEADROOM
+    // layers at the capped weight can blend past the format's range, to Inf; clamp the sum
+    // back so the average below stays finite.
+    accum = min(accum, vec4(ACCUM_FORMAT_MAX));
+
+    // No transparent fragments at this pixel
+    if accum.a < 1e-5 {
+        if all(glow == vec3(0.0)) {
+#ifdef WBOIT_DEBUG_TRANSPARENCY_ONLY
+            return finish(vec4(0.0), in.position.xy);
+#else
+            discard;
+#endif
+        }
+        // Only additive light: no coverage, so the background is untouched.
+        var glow_only = vec4(tonemap_ldr(glow * exposure), 0.0);
+#ifdef WBOIT_COMPOSITE_MASK
+        glow_only *= textureSampleLevel(mask_tex, upsample_sampler, in.uv, 0.0).r;
+#endif
+        return finish(glow_only, in.position.xy);
+    }
+
+#ifdef WBOIT_HISTOGRAM
+    let resolved = absorb(resolve(accum, r, max_opacity), in.position.xy);
+#else
+    let resolved = resolve(accum, r, max_opacity);
+#endif
+#ifdef WBOIT_COMPOSITE_TONEMAP
+    // Tonemap the straight layer color, not the premultiplied one, so thin layers are not
+    // compressed less than dense ones; the glow is added light and is tonemapped on its own.
+    let straight = resolved.rgb / max(resolved.a, 1e-5);
+    let layer = tonemap_ldr(straight * exposure) * resolved.a;
+    var out = vec4(layer + tonemap_ldr(glow * exposure), resolved.a);
+#else
+    var out = vec4((resolved.rgb + glow) * exposure, resolved.a);
+#endif
+#ifdef WBOIT_COMPOSITE_MASK
+    // Premultiplied output, so scaling every channel fades the layer towards the background.
+    out *= textureSampleLevel(mask_tex, upsample_sampler, in.uv, 0.0).r;
+#endif
+    return finish(out, in.position.xy);
+#endif
+}
@@ -4,11 +4,86 @@
     pbr_fragment::pbr_input_from_standard_material,
     pbr_types::STANDARD_MATERIAL_FLAGS_UNLIT_BIT,
     forward_io::VertexOutput,
+    view_transformations::depth_ndc_to_view_z,
+    mesh_view_bindings::{globals, view},
+    mesh_functions,
+}
+
+// Largest finite value of the accum target format (`WBOIT_ACCUM_FORMAT`), as the
+// `ACCUM_FORMAT_MAX` shader def set by `specialize_wboit_accum_targets`.
+const ACCUM_FORMAT_MAX: f32 = f32(#{ACCUM_FORMAT_MAX}u);
+// Number of maximally weighted layers that may add up in one pixel before the additive blend
+// could overflow the accum format.
+const ACCUM_LAYER_HEADROOM: f32 = 8.0;
+
+struct WboitParams {
+    thickness_absorption: f32,
+    sanitize_output: u32,
+    // Non-zero for reduced-resolution accum targets, which only cover the viewport.
+    accum_scaled: u32,
+    max_opacity: f32,
+    global_opacity: f32,
+    revealage_gamma: f32,
+    soft_particle_distance: f32,
+    composite_exposure: f32,
+    depth_test_bias: f32,
+    nearest_depth_falloff: f32,
+    // Per-axis ratio of the accum target size to the viewport size.
+    accum_scale: vec2<f32>,
+}
+
+@group(3) @binding(0) var<uniform> wboit_params: WboitParams;
+@group(3) @binding(1) var opaque_depth_tex: texture_depth_2d;
+// Nearest transparent depth (WboitSettings::nearest_depth_falloff), at accum target size.
+@group(3) @binding(2) var nearest_depth_tex: texture_depth_2d;
+
+// True for NaN and +/-Inf (all exponent bits set). Uses the bit pattern because
+// `x != x` style checks may be folded away by shader compilers.
+fn is_non_finite(x: f32) -> bool {
+    return (bitcast<u32>(x) & 0x7f800000u) == 0x7f800000u;
+}
+
+// Replace non-finite components with zero and clamp negatives, so additive blending
+// cannot propagate NaN/Inf across the whole pixel.
+fn sanitize(v: vec4<f32>) -> vec4<f32> {
+    var out = max(v, vec4(0.0));
+    for (var i = 0; i < 4; i++) {
+        if is_non_finite(v[i]) {
+            out[i] = 0.0;
+        }
+    }
+    return out;
+}
+
+// Replace non-finite components with zero. Unlike `sanitize`, keeps negatives (normals).
+fn zero_non_finite(v: vec2<f32>) -> vec2<f32> {
+    return select(v, vec2(0.0), vec2(is_non_finite(v.x), is_non_finite(v.y)));
+}
+
+// Cheap 2D -> 1D hash (Dave Hoskins), used for the animated weight noise.
+fn hash12(p: vec2<f32>) -> f32 {
+    var q = fract(p * vec2(0.1031, 0.1030));
+    q += dot(q, q.yx + 33.33);
+    return fract((q.x + q.y) * q.x);
 }
 
 struct WboitOutput {
     @location(0) accum: vec4<f32>,
     @location(1) revealage: f32,
+    // Additively blended light of AlphaMode::Add fragments, outside the weighted average.
+    @location(2) glow: vec4<f32>,
+#ifdef WBOIT_DEBUG_OVERDRAW
+    // Additively blended: one per fragment that survived depth test and alpha discard.
+    @location(3) overdraw: f32,
+#endif
+#ifdef WBOIT_ACCUM_NORMALS
+    // Additively blended: view-space normal xy times the accum weight (alpha * w).
+#ifdef WBOIT_DEBUG_OVERDRAW
+    @location(4) normal: vec2<f32>,
+#else
+    @location(3) normal: vec2<f32>,
+#endif
+#endif
 }
 
 @fragment
@@ -17,9 +92,55 @@ fn fragment(
     @builtin(front_facing) is_front: bool,
 ) -> WboitOutput {
     var in = vertex_output;
+    // Map the fragment position from (possibly scaled) accum target space back to full-res
+    // render target space, so lighting cluster lookups and depth sampling use the right pixel.
+    // Full-res targets cover the whole render target and are drawn at the viewport's offset
+    // already; scaled targets only cover the viewport, starting at their origin.
+    if wboit_params.accum_scaled != 0u {
+        in.position = vec4(
+            in.position.xy / wboit_params.accum_scale + view.viewport.xy,
+            in.position.zw,
+        );
+    }
+
+#ifdef WBOIT_MANUAL_DEPTH_TEST
+    // No depth attachment at reduced resolution (or the fixed-function test is disabled for
+    // a depth test bias): test against the full-res opaque depth (nearest sample).
+    let depth_coords = min(
+        vec2<u32>(in.position.xy),
+        textureDimensions(opaque_depth_tex) - vec2(1u),
+    );
+    let test_depth = textureLoad(opaque_depth_tex, depth_coords, 0);
+#ifdef WBOIT_DEPTH_TEST_BIAS
+    // Compare in view space so the bias is in world units. Reverse-Z depth 0 is the far plane
+    // (nothing opaque), which never occludes.
+    let behind_opaque = depth_ndc_to_view_z(test_depth) - depth_ndc_to_view_z(in.position.z);
+    if test_depth > 0.0 && behind_opaque > wboit_params.depth_test_bias {
+        discard;
+    }
+#else
+    // Reverse-Z, so occluded fragments have a smaller depth.
+    if in.position.z < test_depth {
+        discard;
+    }
+#endif
+#endif
+
     var pbr_input = pbr_input_from_standard_material(in, is_front);
     pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
 
+#ifdef WBOIT_NEAREST_DEPTH_PREPASS
+    // Depth only: nearly invisible and additive fragments don't count as the nearest surface.
+    let prepass_alpha_mode = pbr_input.material.flags
+        & pbr_types::STANDARD_MATERIAL_FLAGS_ALPHA_MODE_RESERVED_BITS;
+    if pbr_input.material.base_color.a < 0.01
+        || prepass_alpha_mode == pbr_types::STANDARD_MATERIAL_FLAGS_ALPHA_MODE_ADD {
+        discard;
+    }
+    var prepass_out: WboitOutput;
+    return prepass_out;
+#else
+
     var color: vec4<f32>;
     if (pbr_input.material.flags & STANDARD_MATERIAL_FLAGS_UNLIT_BIT) == 0u {
         color = apply_pbr_lighting(pbr_input);
@@ -41,14 +162,124 @@ fn fragment(
         premul = color;
     }
 
+#ifdef WBOIT_GRAZING_CORRECTION
+    // Grazing-angle correction: light crosses a thin transparent layer along a longer path at
+    // grazing angles, so coverage grows as 1 - (1 - a)^(1 / |N.V|). Color is rescaled to keep
+    // the premultiplied hue.
+    let n_dot_v = max(abs(dot(pbr_input.N, pbr_input.V)), 0.05);
+    let grazing_alpha = 1.0 - pow(max(1.0 - premul.a, 0.0), 1.0 / n_dot_v);
+    if premul.a > 1e-5 {
+        premul = vec4(premul.rgb * (grazing_alpha / premul.a), grazing_alpha);
+    }
+#endif
+
+#ifndef WBOIT_ALWAYS_VISIBLE
+    // Skipped for always-visible fragments: behind opaque geometry the thickness is negative,
+    // which would fade them out entirely.
+    if wboit_params.thickness_absorption > 0.0 || wboit_params.soft_particle_distance > 0.0 {
+        let opaque_ndc_depth = textureLoad(opaque_depth_tex, vec2<i32>(in.position.xy), 0);
+        // Reverse-Z: depth 0 is the far plane (no opaque geometry, e.g. skybox), skip it.
+        if opaque_ndc_depth > 0.0 {
+            let thickness = max(
+                depth_ndc_to_view_z(in.position.z) - depth_ndc_to_view_z(opaque_ndc_depth),
+                0.0,
+            );
+
+            // Thickness absorption: the farther the opaque surface behind this fragment, the
+            // more light is absorbed. Transmittance darkens the color and raises the coverage.
+            if wboit_params.thickness_absorption > 0.0 {
+                let transmittance = exp(-wboit_params.thickness_absorption * thickness);
+                premul = vec4(premul.rgb * transmittance, 1.0 - (1.0 - premul.a) * transmittance);
+            }
+
+            // Soft particles: fade out close to the opaque surface so intersections blend in.
+            if wboit_params.soft_particle_distance > 0.0 {
+                premul *= saturate(thickness / wboit_params.soft_particle_distance);
+            }
+        }
+    }
+#endif
+
+    // Per-view fade of the whole transparent layer.
+    premul *= wboit_params.global_opacity;
+
+#ifdef WBOIT_INSTANCE_DATA
+    // WboitInstanceOpacity, stored as unorm16 in the low half of the instance's MeshTag.
+    premul *= f32(mesh_functions::get_tag(in.instance_index) & 0xffffu) / 65535.0;
+#endif
+
+#ifdef WBOIT_ANIMATED_WEIGHT
+    // Dissolve: 8x8 pixel cells pulse with a random phase, between 20% and 100% coverage.
+    let phase = hash12(floor(in.position.xy / 8.0)) * 6.2831853;
+    let pulse = 0.5 + 0.5 * sin(globals.time * 3.0 + phase);
+    premul *= mix(0.2, 1.0, pulse);
+#endif
+
     // WBOIT weight function
-    // Bevy uses reverse-Z: near=1, far=0, so convert to linear [0,1] where 0=near, 1=far
+    // Uses the rasterized depth; the material's depth_bias only affects CPU-side sorting.
+    // Bevy uses reverse-Z: near=1, far=0, so flip it to [0,1] where 0=near, 1=far (not linear
+    // in view depth, but monotonic, which is all the weight needs to favor near layers).
     let d = 1.0 - in.position.z;
     let alpha = premul.a;
-    let w = alpha * clamp(exp2(13.0 - 26.0 * d), 1e-4, 8192.0);
+#ifdef WBOIT_UNWEIGHTED
+    // Quality 0: plain coverage-weighted average, no depth weighting.
+    var w = alpha;
+#else
+    var w = alpha * clamp(exp2(13.0 - 26.0 * d), 1e-4, 8192.0);
+#endif
+    // Half-float safeguard: keep this fragment's largest accum component within
+    // ACCUM_FORMAT_MAX / ACCUM_LAYER_HEADROOM, so bright HDR colors close to the camera cannot
+    // blend to Inf. For half floats that cap (8188) is just below the largest depth weight
+    // (8192), so the nearest layers are capped slightly even for colors in [0, 1]. Deeper
+    // stacks of capped layers can still overflow; the composite clamps the blended sum.
+    let peak = max(max(premul.r, premul.g), max(premul.b, alpha));
+#ifdef WBOIT_NEAREST_DEPTH_FALLOFF
+    // Fade the weight of fragments behind the nearest transparent surface of this pixel, so
+    // the front layer dominates deep stacks. Depth 0 is the clear value (nothing written).
+    let nearest_coords = min(
+        vec2<u32>(vertex_output.position.xy),
+        textureDimensions(nearest_depth_tex) - vec2(1u),
+    );
+    let nearest_ndc_depth = textureLoad(nearest_depth_tex, nearest_coords, 0);
+    if nearest_ndc_depth > 0.0 {
+        let behind_nearest = depth_ndc_to_view_z(nearest_ndc_depth)
+            - depth_ndc_to_view_z(in.position.z);
+        w *= exp(-max(behind_nearest, 0.0) / wboit_params.nearest_depth_falloff);
+    }
+#endif
+    w = min(w, ACCUM_FORMAT_MAX / ACCUM_LAYER_HEADROOM / max(peak, 1e-5));
 
     var out: WboitOutput;
-    out.accum = vec4(premul.rgb * w, alpha * w);
-    out.revealage = alpha;
+    if alpha_mode == pbr_types::STANDARD_MATERIAL_FLAGS_ALPHA_MODE_ADD {
+        // Additive: pure emitted light. Zero accum and zero revealage (the multiplicative blend
+        // keeps the destination), so the fragment neither joins the weighted average nor
+        // darkens what is behind it.
+        out.accum = vec4(0.0);
+        out.revealage = 0.0;
+        out.glow = vec4(premul.rgb, 0.0);
+    } else {
+        out.accum = vec4(premul.rgb * w, alpha * w);
+        out.revealage = alpha;
+        out.glow = vec4(0.0);
+    }
+    if wboit_params.sanitize_output != 0u {
+        out.accum = sanitize(out.accum);
+        out.revealage = clamp(sanitize(vec4(out.revealage)).x, 0.0, 1.0);
+        out.glow = sanitize(out.glow);
+    }
+#ifdef WBOIT_DEBUG_OVERDRAW
+#ifdef WBOIT_DEBUG_WEIGHT
+    // Additive fragments are not weighted at all.
+    out.overdraw = select(w, 0.0, alpha_mode == pbr_types::STANDARD_MATERIAL_FLAGS_ALPHA_MODE_ADD);
+#else
+    out.overdraw = 1.0;
+#endif
+#endif
+#ifdef WBOIT_ACCUM_NORMALS
+    // Shading normal (after normal mapping), in view space.
+    let view_normal = normalize((view.view_from_world * vec4(pbr_input.N, 0.0)).xyz);
+    out.normal = zero_non_finite(view_normal.xy * out.accum.a);
+#endif
     return out;
+#endif
 }
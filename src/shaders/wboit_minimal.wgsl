@@ -0,0 +1,135 @@
+#import bevy_pbr::{
+    forward_io::VertexOutput,
+    mesh_functions::get_tag,
+    mesh_view_bindings::view,
+    view_transformations::depth_ndc_to_view_z,
+}
+
+// Accum shader of `WboitMinimalPlugin`: no material bind group. Color is the vertex color
+// (white without one) times the entity's linear tint, packed as RGBA8 into its `MeshTag`.
+// Unlit, without absorption, soft particles or the animated weight of wboit_fragment.wgsl.
+
+// Same limits as wboit_fragment.wgsl.
+const ACCUM_FORMAT_MAX: f32 = f32(#{ACCUM_FORMAT_MAX}u);
+const ACCUM_LAYER_HEADROOM: f32 = 8.0;
+
+struct WboitParams {
+    thickness_absorption: f32,
+    sanitize_output: u32,
+    // Non-zero for reduced-resolution accum targets, which only cover the viewport.
+    accum_scaled: u32,
+    max_opacity: f32,
+    global_opacity: f32,
+    revealage_gamma: f32,
+    soft_particle_distance: f32,
+    composite_exposure: f32,
+    depth_test_bias: f32,
+    nearest_depth_falloff: f32,
+    // Per-axis ratio of the accum target size to the viewport size.
+    accum_scale: vec2<f32>,
+}
+
+// Layout is `WboitAccumDataLayout`, at group 2 since there is no material group.
+@group(2) @binding(0) var<uniform> wboit_params: WboitParams;
+@group(2) @binding(1) var opaque_depth_tex: texture_depth_2d;
+
+// True for NaN and +/-Inf (all exponent bits set).
+fn is_non_finite(x: f32) -> bool {
+    return (bitcast<u32>(x) & 0x7f800000u) == 0x7f800000u;
+}
+
+fn sanitize(v: vec4<f32>) -> vec4<f32> {
+    var out = max(v, vec4(0.0));
+    for (var i = 0; i < 4; i++) {
+        if is_non_finite(v[i]) {
+            out[i] = 0.0;
+        }
+    }
+    return out;
+}
+
+struct WboitOutput {
+    @location(0) accum: vec4<f32>,
+    @location(1) revealage: f32,
+    @location(2) glow: vec4<f32>,
+#ifdef WBOIT_DEBUG_OVERDRAW
+    @location(3) overdraw: f32,
+#endif
+#ifdef WBOIT_ACCUM_NORMALS
+#ifdef WBOIT_DEBUG_OVERDRAW
+    @location(4) normal: vec2<f32>,
+#else
+    @location(3) normal: vec2<f32>,
+#endif
+#endif
+}
+
+@fragment
+fn fragment(vertex_output: VertexOutput) -> WboitOutput {
+    var in = vertex_output;
+    if wboit_params.accum_scaled != 0u {
+        in.position = vec4(
+            in.position.xy / wboit_params.accum_scale + view.viewport.xy,
+            in.position.zw,
+        );
+    }
+
+#ifdef WBOIT_MANUAL_DEPTH_TEST
+    let depth_coords = min(
+        vec2<u32>(in.position.xy),
+        textureDimensions(opaque_depth_tex) - vec2(1u),
+    );
+    let test_depth = textureLoad(opaque_depth_tex, depth_coords, 0);
+#ifdef WBOIT_DEPTH_TEST_BIAS
+    let behind_opaque = depth_ndc_to_view_z(test_depth) - depth_ndc_to_view_z(in.position.z);
+    if test_depth > 0.0 && behind_opaque > wboit_params.depth_test_bias {
+        discard;
+    }
+#else
+    if in.position.z < test_depth {
+        discard;
+    }
+#endif
+#endif
+
+    var color = unpack4x8unorm(get_tag(in.instance_index));
+#ifdef VERTEX_COLORS
+    color *= in.color;
+#endif
+    var premul = vec4(color.rgb * color.a, color.a) * wboit_params.global_opacity;
+
+    let d = 1.0 - in.position.z;
+    let alpha = premul.a;
+#ifdef WBOIT_UNWEIGHTED
+    var w = alpha;
+#else
+    var w = alpha * clamp(exp2(13.0 - 26.0 * d), 1e-4, 8192.0);
+#endif
+    let peak = max(max(premul.r, premul.g), max(premul.b, alpha));
+    w = min(w, ACCUM_FORMAT_MAX / ACCUM_LAYER_HEADROOM / max(peak, 1e-5));
+
+    var out: WboitOutput;
+    out.accum = vec4(premul.rgb * w, alpha * w);
+    out.revealage = alpha;
+    out.glow = vec4(0.0);
+    if wboit_params.sanitize_output != 0u {
+        out.accum = sanitize(out.accum);
+        out.revealage = clamp(sanitize(vec4(out.revealage)).x, 0.0, 1.0);
+    }
+#ifdef WBOIT_DEBUG_OVERDRAW
+#ifdef WBOIT_DEBUG_WEIGHT
+    out.overdraw = w;
+#else
+    out.overdraw = 1.0;
+#endif
+#endif
+#ifdef WBOIT_ACCUM_NORMALS
+    // Same weighting as wboit_fragment.wgsl; meshes without normals face the camera.
+    var view_normal = vec3(0.0, 0.0, 1.0);
+#ifdef VERTEX_NORMALS
+    view_normal = normalize((view.view_from_world * vec4(in.world_normal, 0.0)).xyz);
+#endif
+    out.normal = view_normal.xy * out.accum.a;
+#endif
+    return out;
+}
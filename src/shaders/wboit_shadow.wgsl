@@ -0,0 +1,86 @@
+#import bevy_pbr::{
+    prepass_io::{VertexOutput, FragmentOutput},
+    pbr_prepass_functions,
+    pbr_bindings,
+    pbr_types,
+    mesh_view_bindings::view,
+}
+
+// Prepass fragment shader of WboitShadowMaterial. Only the shadow passes draw transparent
+// materials, so that is where the stochastic alpha test below matters; Opaque and Mask
+// materials keep Bevy's discard. The material is never bindless.
+
+struct WboitShadowExtension {
+    shadow_opacity: f32,
+}
+
+@group(2) @binding(100) var<uniform> wboit_shadow: WboitShadowExtension;
+
+// Interleaved gradient noise, a per texel threshold in [0, 1).
+fn shadow_threshold(frag_coord: vec2<f32>) -> f32 {
+    return fract(52.9829189 * fract(dot(frag_coord, vec2(0.06711056, 0.00583715))));
+}
+
+fn shadow_alpha(in: VertexOutput) -> f32 {
+    var alpha = pbr_bindings::material.base_color.a;
+#ifdef VERTEX_UVS
+#ifdef STANDARD_MATERIAL_BASE_COLOR_UV_B
+    var uv = in.uv_b;
+#else
+    var uv = in.uv;
+#endif
+    uv = (pbr_bindings::material.uv_transform * vec3(uv, 1.0)).xy;
+    let flags = pbr_bindings::material.flags;
+    if (flags & pbr_types::STANDARD_MATERIAL_FLAGS_BASE_COLOR_TEXTURE_BIT) != 0u {
+        alpha *= textureSampleBias(
+            pbr_bindings::base_color_texture,
+            pbr_bindings::base_color_sampler,
+            uv,
+            view.mip_bias
+        ).a;
+    }
+#endif
+    return alpha;
+}
+
+fn stochastic_alpha_discard(in: VertexOutput) {
+    let alpha_mode =
+        pbr_bindings::material.flags & pbr_types::STANDARD_MATERIAL_FLAGS_ALPHA_MODE_RESERVED_BITS;
+    if alpha_mode == pbr_types::STANDARD_MATERIAL_FLAGS_ALPHA_MODE_BLEND ||
+            alpha_mode == pbr_types::STANDARD_MATERIAL_FLAGS_ALPHA_MODE_PREMULTIPLIED ||
+            alpha_mode == pbr_types::STANDARD_MATERIAL_FLAGS_ALPHA_MODE_ADD {
+        let opacity = saturate(shadow_alpha(in) * wboit_shadow.shadow_opacity);
+        if opacity <= shadow_threshold(in.position.xy) {
+            discard;
+        }
+    } else {
+        pbr_prepass_functions::prepass_alpha_discard(in);
+    }
+}
+
+#ifdef PREPASS_FRAGMENT
+@fragment
+fn fragment(in: VertexOutput) -> FragmentOutput {
+    stochastic_alpha_discard(in);
+
+    var out: FragmentOutput;
+#ifdef UNCLIPPED_DEPTH_ORTHO_EMULATION
+    out.frag_depth = in.unclipped_depth;
+#endif
+#ifdef NORMAL_PREPASS
+    out.normal = vec4(normalize(in.world_normal) * 0.5 + vec3(0.5), 1.0);
+#endif
+#ifdef MOTION_VECTOR_PREPASS
+    out.motion_vector = pbr_prepass_functions::calculate_motion_vector(
+        in.world_position,
+        in.previous_world_position
+    );
+#endif
+    return out;
+}
+#else
+@fragment
+fn fragment(in: VertexOutput) {
+    stochastic_alpha_discard(in);
+}
+#endif
@@ -0,0 +1,140 @@
+#import bevy_pbr::{
+    mesh_bindings::mesh,
+    mesh_functions,
+    mesh_view_bindings::view,
+    skinning,
+    morph::morph,
+    forward_io::{Vertex, VertexOutput},
+}
+
+// Accum vertex shader: Bevy's `mesh.wgsl` vertex stage with two optional changes.
+//
+// WBOIT_UNJITTERED (`WboitTaaMode::AfterTaa`) projects with `view.unjittered_clip_from_world`.
+// The composite lands on TAA's resolved (unjittered) output there, so jittered transparents
+// would shimmer against it by a subpixel every frame. Without TAA both matrices are the same.
+//
+// WBOIT_INSTANCE_DATA moves the projected position along its view ray by the instance's
+// `WboitDepthOffset` world units towards the camera, so a decal passes the depth test over the
+// surface it lies on. Only the clip-space depth changes; lighting still uses the unmoved world
+// position. The offset is the bfloat16 in the high half of the instance's `MeshTag`.
+
+fn clip_position(world_position: vec3<f32>, instance_index: u32) -> vec4<f32> {
+    var position = world_position;
+#ifdef WBOIT_INSTANCE_DATA
+    // Towards the eye for perspective views, along the view's back axis for orthographic ones.
+    let is_orthographic = view.clip_from_view[3].w == 1.0;
+    let to_camera = select(
+        normalize(view.world_position - position),
+        view.world_from_view[2].xyz,
+        is_orthographic,
+    );
+    let depth_offset = bitcast<f32>(mesh_functions::get_tag(instance_index) & 0xffff0000u);
+    position += to_camera * depth_offset;
+#endif
+#ifdef WBOIT_UNJITTERED
+    return view.unjittered_clip_from_world * vec4(position, 1.0);
+#else
+    return view.clip_from_world * vec4(position, 1.0);
+#endif
+}
+
+#ifdef MORPH_TARGETS
+fn morph_vertex(vertex_in: Vertex) -> Vertex {
+    var vertex = vertex_in;
+    let first_vertex = mesh[vertex.instance_index].first_vertex_index;
+    let vertex_index = vertex.index - first_vertex;
+
+    let weight_count = bevy_pbr::morph::layer_count();
+    for (var i: u32 = 0u; i < weight_count; i ++) {
+        let weight = bevy_pbr::morph::weight_at(i);
+        if weight == 0.0 {
+            continue;
+        }
+        vertex.position += weight * morph(vertex_index, bevy_pbr::morph::position_offset, i);
+#ifdef VERTEX_NORMALS
+        vertex.normal += weight * morph(vertex_index, bevy_pbr::morph::normal_offset, i);
+#endif
+#ifdef VERTEX_TANGENTS
+        vertex.tangent += vec4(
+            weight * morph(vertex_index, bevy_pbr::morph::tangent_offset, i),
+            0.0
+        );
+#endif
+    }
+    return vertex;
+}
+#endif
+
+@vertex
+fn vertex(vertex_no_morph: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+
+#ifdef MORPH_TARGETS
+    var vertex = morph_vertex(vertex_no_morph);
+#else
+    var vertex = vertex_no_morph;
+#endif
+
+    // `vertex_no_morph.instance_index` throughout, like `mesh.wgsl` (wgpu dx12 workaround).
+    let mesh_world_from_local =
+        mesh_functions::get_world_from_local(vertex_no_morph.instance_index);
+
+#ifdef SKINNED
+    var world_from_local = skinning::skin_model(
+        vertex.joint_indices,
+        vertex.joint_weights,
+        vertex_no_morph.instance_index
+    );
+#else
+    var world_from_local = mesh_world_from_local;
+#endif
+
+#ifdef VERTEX_NORMALS
+#ifdef SKINNED
+    out.world_normal = skinning::skin_normals(world_from_local, vertex.normal);
+#else
+    out.world_normal = mesh_functions::mesh_normal_local_to_world(
+        vertex.normal,
+        vertex_no_morph.instance_index
+    );
+#endif
+#endif
+
+#ifdef VERTEX_POSITIONS
+    out.world_position = mesh_functions::mesh_position_local_to_world(
+        world_from_local,
+        vec4<f32>(vertex.position, 1.0)
+    );
+    out.position = clip_position(out.world_position.xyz, vertex_no_morph.instance_index);
+#endif
+
+#ifdef VERTEX_UVS_A
+    out.uv = vertex.uv;
+#endif
+#ifdef VERTEX_UVS_B
+    out.uv_b = vertex.uv_b;
+#endif
+
+#ifdef VERTEX_TANGENTS
+    out.world_tangent = mesh_functions::mesh_tangent_local_to_world(
+        world_from_local,
+        vertex.tangent,
+        vertex_no_morph.instance_index
+    );
+#endif
+
+#ifdef VERTEX_COLORS
+    out.color = vertex.color;
+#endif
+
+#ifdef VERTEX_OUTPUT_INSTANCE_INDEX
+    out.instance_index = vertex_no_morph.instance_index;
+#endif
+
+#ifdef VISIBILITY_RANGE_DITHER
+    out.visibility_range_dither = mesh_functions::get_visibility_range_dither_level(
+        vertex_no_morph.instance_index, mesh_world_from_local[3]);
+#endif
+
+    return out;
+}
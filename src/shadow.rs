@@ -0,0 +1,75 @@
+use bevy::asset::{load_internal_asset, weak_handle};
+use bevy::pbr::{ExtendedMaterial, MaterialExtension, MaterialPlugin};
+use bevy::prelude::*;
+use bevy::render::render_resource::{AsBindGroup, ShaderRef};
+
+use crate::material::{WboitAppExt, WboitMaterial};
+use crate::naive::NaiveWboitPlugin;
+
+pub const WBOIT_SHADOW_SHADER_HANDLE: Handle<Shader> =
+    weak_handle!("2f6b9c1e-7d3a-4e58-a1c4-5b8e0d9f3a62");
+
+/// `StandardMaterial` whose transparent meshes cast shadows proportional to their opacity:
+/// the shadow pass keeps each shadow map texel with probability `alpha * shadow_opacity`
+/// (a stochastic alpha test), so a 30% opaque pane lets about 70% of the light through once
+/// the shadow filter has averaged the pattern. Requires [`WboitShadowMaterialPlugin`].
+///
+/// Bevy's own shadow pass treats `AlphaMode::Blend`, `Premultiplied` and `Add` meshes as fully
+/// opaque casters, so plain `StandardMaterial` transparents cast solid shadows. `Opaque` and
+/// `Mask` materials keep their usual shadows. Add `NotShadowCaster` to cast none at all.
+///
+/// The material is drawn through naive WBOIT exactly like its `base`. It is never bindless.
+pub type WboitShadowMaterial = ExtendedMaterial<StandardMaterial, WboitShadowExtension>;
+
+/// The [`WboitShadowMaterial`] extension.
+#[derive(Asset, AsBindGroup, Reflect, Clone, Copy, Debug)]
+pub struct WboitShadowExtension {
+    /// Scales the material's alpha in the shadow pass: 0 casts no shadow, values above 1
+    /// darken the shadow of faint transparents.
+    #[uniform(100)]
+    pub shadow_opacity: f32,
+}
+
+impl Default for WboitShadowExtension {
+    fn default() -> Self {
+        Self {
+            shadow_opacity: 1.0,
+        }
+    }
+}
+
+impl MaterialExtension for WboitShadowExtension {
+    fn prepass_fragment_shader() -> ShaderRef {
+        WBOIT_SHADOW_SHADER_HANDLE.into()
+    }
+}
+
+impl WboitMaterial for WboitShadowMaterial {}
+
+/// Registers [`WboitShadowMaterial`] as a regular material and as a WBOIT material. Adds
+/// `NaiveWboitPlugin` if missing. Only the first instance is built; adding it again does
+/// nothing.
+pub struct WboitShadowMaterialPlugin;
+
+impl Plugin for WboitShadowMaterialPlugin {
+    fn build(&self, app: &mut App) {
+        if app.is_plugin_added::<Self>() {
+            return;
+        }
+        load_internal_asset!(
+            app,
+            WBOIT_SHADOW_SHADER_HANDLE,
+            "shaders/wboit_shadow.wgsl",
+            Shader::from_wgsl
+        );
+        if !app.is_plugin_added::<NaiveWboitPlugin>() {
+            app.add_plugins(NaiveWboitPlugin);
+        }
+        app.add_plugins(MaterialPlugin::<WboitShadowMaterial>::default())
+            .register_wboit_material::<WboitShadowMaterial>();
+    }
+
+    fn is_unique(&self) -> bool {
+        false
+    }
+}
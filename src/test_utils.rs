@@ -0,0 +1,757 @@
+//! Fixtures shared by the unit tests.
+
+use bevy::app::AppLabel;
+use bevy::asset::RenderAssetUsages;
+use bevy::core_pipeline::core_3d::Transparent3d;
+use bevy::core_pipeline::core_3d::graph::Core3d;
+use bevy::ecs::system::RunSystemOnce;
+use bevy::math::FloatOrd;
+use bevy::pbr::{
+    MeshPipelineKey, MeshTransforms, RenderMeshInstanceCpu, RenderMeshInstanceFlags,
+    RenderMeshInstanceShared, RenderMeshInstances, ViewKeyCache,
+};
+use bevy::prelude::*;
+use bevy::render::camera::{CameraProjection, ExtractedCamera, RenderTarget};
+use bevy::render::mesh::{
+    BaseMeshPipelineKey, MeshVertexBufferLayouts, PrimitiveTopology, RenderMesh,
+    RenderMeshBufferInfo,
+};
+use bevy::render::render_asset::RenderAssets;
+use bevy::render::render_graph::RenderSubGraph;
+use bevy::render::render_phase::{
+    DrawFunctions, PhaseItemExtraIndex, SortedPhaseItem, ViewSortedRenderPhases,
+};
+use bevy::render::render_resource::{
+    BufferDescriptor, BufferUsages, CachedPipelineState, CachedRenderPipelineId, Extent3d, LoadOp,
+    Maintain, MapMode, Operations, Origin3d, PipelineCache, RenderPassDepthStencilAttachment,
+    RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, StoreOp, TexelCopyBufferInfo,
+    TexelCopyBufferLayout, TexelCopyTextureInfo, Texture, TextureAspect, TextureDescriptor,
+    TextureDimension, TextureFormat, TextureUsages,
+};
+use bevy::render::renderer::{RenderDevice, RenderQueue};
+use bevy::render::settings::{Backends, RenderCreation, WgpuSettings};
+use bevy::render::sync_world::MainEntity;
+use bevy::render::texture::CachedTexture;
+use bevy::render::view::{ExtractedView, NoFrustumCulling, RetainedViewEntity, ViewDepthTexture};
+use bevy::render::{Render, RenderApp, RenderPlugin, RenderSet};
+use bevy::window::ExitCondition;
+use wgpu::util::DeviceExt;
+
+use crate::phase::{HistoAccum3d, WboitAccum3d, WboitNearestDepth3d};
+use crate::queue::TransparentDrawMaterial;
+
+/// A windowless app with the default plugins on whatever adapter is available, software
+/// rasterizers included, or `None` without one, so GPU-backed tests skip on such machines.
+/// With `WBOIT_GPU_TESTS` set, a missing adapter fails the test instead of skipping it, so
+/// machines meant to run the GPU tests cannot pass them vacuously.
+///
+/// Software adapters cannot compile every Bevy shader, so tests only `finish` the app and run
+/// render world systems by hand; they never `update` it.
+pub(crate) fn gpu_app() -> Option<App> {
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::all(),
+        ..default()
+    });
+    if instance
+        .enumerate_adapters(wgpu::Backends::all())
+        .is_empty()
+    {
+        assert!(
+            std::env::var_os("WBOIT_GPU_TESTS").is_none(),
+            "WBOIT_GPU_TESTS is set but no GPU adapter was found"
+        );
+        return None;
+    }
+    let mut app = App::new();
+    app.add_plugins(
+        DefaultPlugins
+            .build()
+            .disable::<bevy::winit::WinitPlugin>()
+            .disable::<bevy::render::pipelined_rendering::PipelinedRenderingPlugin>()
+            .disable::<bevy::log::LogPlugin>()
+            .set(WindowPlugin {
+                primary_window: None,
+                exit_condition: ExitCondition::DontExit,
+                close_when_requested: false,
+            })
+            .set(RenderPlugin {
+                render_creation: RenderCreation::Automatic(WgpuSettings {
+                    backends: Some(Backends::all()),
+                    ..default()
+                }),
+                synchronous_pipeline_compilation: true,
+                ..default()
+            }),
+    );
+    Some(app)
+}
+
+/// Hand every shader loaded in the main world to the render world's `PipelineCache`, as the
+/// first extract of an updated app would, so pipelines of a `gpu_app` can be compiled.
+pub(crate) fn extract_shaders(app: &mut App) {
+    let world = app.world_mut();
+    let ids: Vec<_> = world.resource::<Assets<Shader>>().ids().collect();
+    world.send_event_batch(ids.into_iter().map(|id| AssetEvent::Added { id }));
+    let sub_apps = app.sub_apps_mut();
+    sub_apps
+        .sub_apps
+        .get_mut(&RenderApp.intern())
+        .unwrap()
+        .extract(sub_apps.main.world_mut());
+}
+
+/// Queue `descriptor` and compile it right away, panicking if it fails. Needs the shaders of
+/// [`extract_shaders`].
+pub(crate) fn compile_render_pipeline(
+    world: &mut World,
+    descriptor: RenderPipelineDescriptor,
+) -> RenderPipeline {
+    let mut pipeline_cache = world.resource_mut::<PipelineCache>();
+    let id = pipeline_cache.queue_render_pipeline(descriptor);
+    loop {
+        pipeline_cache.process_queue();
+        match pipeline_cache.get_render_pipeline_state(id) {
+            CachedPipelineState::Ok(_) => {
+                return pipeline_cache.get_render_pipeline(id).unwrap().clone();
+            }
+            CachedPipelineState::Err(err) => panic!("pipeline failed to compile: {err}"),
+            CachedPipelineState::Queued | CachedPipelineState::Creating(_) => {}
+        }
+    }
+}
+
+/// `ExtractedView` of a perspective camera at `transform`, looking down its local -Z, for the
+/// main world camera `camera`.
+pub(crate) fn extracted_view(camera: Entity, transform: Transform) -> ExtractedView {
+    ExtractedView {
+        retained_view_entity: RetainedViewEntity::new(camera.into(), None, 0),
+        clip_from_view: PerspectiveProjection::default().get_clip_from_view(),
+        world_from_view: GlobalTransform::from(transform),
+        clip_from_world: None,
+        hdr: false,
+        viewport: UVec4::new(0, 0, 64, 64),
+        color_grading: default(),
+    }
+}
+
+/// `ExtractedCamera` of a `Core3d` camera whose viewport fills its `size` target.
+pub(crate) fn extracted_camera(size: UVec2) -> ExtractedCamera {
+    ExtractedCamera {
+        target: None,
+        physical_viewport_size: Some(size),
+        physical_target_size: Some(size),
+        viewport: None,
+        render_graph: Core3d.intern(),
+        order: 0,
+        output_mode: default(),
+        msaa_writeback: false,
+        clear_color: default(),
+        sorted_camera_index_for_target: 0,
+        exposure: 1.0,
+        hdr: false,
+    }
+}
+
+/// `ViewDepthTexture` of `size` and `format`, cleared to `depth` as the opaque pass would
+/// leave it.
+pub(crate) fn view_depth_texture(
+    world: &World,
+    size: UVec2,
+    format: TextureFormat,
+    depth: f32,
+) -> ViewDepthTexture {
+    let render_device = world.resource::<RenderDevice>();
+    let texture = render_device.create_texture(&TextureDescriptor {
+        label: None,
+        size: Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format,
+        usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+    let default_view = texture.create_view(&default());
+    let mut encoder = render_device.create_command_encoder(&default());
+    encoder.begin_render_pass(&RenderPassDescriptor {
+        label: None,
+        color_attachments: &[],
+        depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+            view: &default_view,
+            depth_ops: Some(Operations {
+                load: LoadOp::Clear(depth),
+                store: StoreOp::Store,
+            }),
+            stencil_ops: format.has_stencil_aspect().then_some(Operations {
+                load: LoadOp::Clear(0),
+                store: StoreOp::Store,
+            }),
+        }),
+        timestamp_writes: None,
+        occlusion_query_set: None,
+    });
+    world.resource::<RenderQueue>().submit([encoder.finish()]);
+    ViewDepthTexture::new(
+        CachedTexture {
+            texture,
+            default_view,
+        },
+        None,
+    )
+}
+
+/// Contents of the `size` color `texture` with `texel_size` bytes per texel, read back. Rows
+/// must be a multiple of 256 bytes, so that buffer copies need no padding.
+pub(crate) fn read_texture(
+    world: &World,
+    texture: &Texture,
+    size: UVec2,
+    texel_size: u32,
+) -> Vec<u8> {
+    let render_device = world.resource::<RenderDevice>();
+    let buffer = render_device.create_buffer(&BufferDescriptor {
+        label: None,
+        size: (size.element_product() * texel_size) as u64,
+        usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder = render_device.create_command_encoder(&default());
+    encoder.copy_texture_to_buffer(
+        TexelCopyTextureInfo {
+            texture,
+            mip_level: 0,
+            origin: Origin3d::ZERO,
+            aspect: TextureAspect::All,
+        },
+        TexelCopyBufferInfo {
+            buffer: &buffer,
+            layout: TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(size.x * texel_size),
+                rows_per_image: None,
+            },
+        },
+        Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        },
+    );
+    world.resource::<RenderQueue>().submit([encoder.finish()]);
+    render_device.map_buffer(&buffer.slice(..), MapMode::Read, |result| result.unwrap());
+    render_device.poll(Maintain::Wait);
+    buffer.slice(..).get_mapped_range().to_vec()
+}
+
+/// Depth values of the `size` `depth` texture, row by row. Loaded by a compute shader rather
+/// than copied, since downlevel adapters cannot copy depth textures to buffers.
+pub(crate) fn read_depth(world: &World, depth: &ViewDepthTexture, size: UVec2) -> Vec<f32> {
+    let shader = format!(
+        "@group(0) @binding(0) var depth: texture_2d<f32>;\n\
+         @group(0) @binding(1) var<storage, read_write> data: array<f32>;\n\
+         @compute @workgroup_size(1)\n\
+         fn main(@builtin(global_invocation_id) id: vec3<u32>) {{\n\
+         data[id.y * {}u + id.x] = textureLoad(depth, vec2<i32>(id.xy), 0).r;\n}}",
+        size.x
+    );
+    let device = world.resource::<RenderDevice>().wgpu_device();
+    let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(shader.into()),
+    });
+    // Depth formats bind as unfilterable floats, which the derived layout would not pick.
+    let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: None,
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: false },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ],
+    });
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[&layout],
+        push_constant_ranges: &[],
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: Some(&pipeline_layout),
+        module: &module,
+        entry_point: Some("main"),
+        compilation_options: default(),
+        cache: None,
+    });
+    let data_size = (size.element_product() * 4) as u64;
+    let data = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: data_size,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: data_size,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(depth.view()),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: data.as_entire_binding(),
+            },
+        ],
+    });
+
+    let mut encoder = device.create_command_encoder(&default());
+    {
+        let mut pass = encoder.begin_compute_pass(&default());
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.dispatch_workgroups(size.x, size.y, 1);
+    }
+    encoder.copy_buffer_to_buffer(&data, 0, &readback, 0, data_size);
+    world.resource::<RenderQueue>().submit([encoder.finish()]);
+    readback
+        .slice(..)
+        .map_async(wgpu::MapMode::Read, |result| result.unwrap());
+    device.poll(wgpu::Maintain::Wait);
+    let out = readback.slice(..).get_mapped_range();
+    out.chunks_exact(4)
+        .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+        .collect()
+}
+
+/// Render and main world entity pair of the `index`th test item, as phase items store them.
+pub(crate) fn item_entity(index: u32) -> (Entity, MainEntity) {
+    let entity = Entity::from_raw(1000 + index);
+    (entity, entity.into())
+}
+
+/// Render mesh of six position-only vertices with `topology`, its layout interned in `layouts`.
+pub(crate) fn render_mesh(
+    topology: PrimitiveTopology,
+    layouts: &mut MeshVertexBufferLayouts,
+) -> RenderMesh {
+    let mesh = Mesh::new(topology, RenderAssetUsages::default())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, vec![[0.0f32; 3]; 6]);
+    RenderMesh {
+        vertex_count: 6,
+        morph_targets: None,
+        buffer_info: RenderMeshBufferInfo::NonIndexed,
+        key_bits: BaseMeshPipelineKey::from_primitive_topology(topology),
+        layout: mesh.get_mesh_vertex_buffer_layout(layouts),
+    }
+}
+
+/// Render world input of `queue_wboit_meshes` and `queue_histo_wboit_meshes`, built by hand: a
+/// `gpu_app` with both WBOIT paths and one camera at the origin looking down -Z, whose
+/// `Transparent3d` phase holds the meshes added with [`QueueFixture::add_transparent`].
+pub(crate) struct QueueFixture {
+    app: App,
+    pub(crate) view: ExtractedView,
+    mesh: AssetId<Mesh>,
+}
+
+impl QueueFixture {
+    /// The camera gets `settings`, `WboitSettings` or `HEWboitSettings` for the path under
+    /// test. `None` without a GPU adapter.
+    pub(crate) fn new(settings: impl Component) -> Option<Self> {
+        let mut app = gpu_app()?;
+        app.add_plugins((crate::WboitPlugin, crate::HEWboitPlugin));
+        app.finish();
+        app.cleanup();
+        let world = app.get_sub_app_mut(RenderApp).unwrap().world_mut();
+
+        let camera = world.spawn_empty().id();
+        let view = extracted_view(camera, Transform::IDENTITY);
+        let retained = view.retained_view_entity;
+        world
+            .entity_mut(camera)
+            .insert((extracted_view(camera, Transform::IDENTITY), settings));
+        world
+            .resource_mut::<ViewKeyCache>()
+            .insert(retained, MeshPipelineKey::from_msaa_samples(1));
+        world
+            .resource_mut::<ViewSortedRenderPhases<Transparent3d>>()
+            .insert_or_clear(retained);
+        world
+            .resource_mut::<ViewSortedRenderPhases<WboitAccum3d>>()
+            .insert_or_clear(retained);
+        world
+            .resource_mut::<ViewSortedRenderPhases<WboitNearestDepth3d>>()
+            .insert_or_clear(retained);
+        world
+            .resource_mut::<ViewSortedRenderPhases<HistoAccum3d>>()
+            .insert_or_clear(retained);
+
+        let mesh = AssetId::<Mesh>::default();
+        let render_mesh = render_mesh(
+            PrimitiveTopology::TriangleList,
+            &mut world.resource_mut::<MeshVertexBufferLayouts>(),
+        );
+        world
+            .resource_mut::<RenderAssets<RenderMesh>>()
+            .insert(mesh, render_mesh);
+        world.insert_resource(RenderMeshInstances::CpuBuilding(default()));
+
+        Some(Self { app, view, mesh })
+    }
+
+    pub(crate) fn world(&mut self) -> &mut World {
+        self.app.get_sub_app_mut(RenderApp).unwrap().world_mut()
+    }
+
+    /// Add a `StandardMaterial` mesh at `translation` to the camera's `Transparent3d` phase,
+    /// at the distance `queue_material_meshes` gives it: the rangefinder distance plus the
+    /// material's `depth_bias`.
+    pub(crate) fn add_transparent(&mut self, translation: Vec3, depth_bias: f32) -> MainEntity {
+        let distance = self.view.rangefinder3d().distance_translation(&translation) + depth_bias;
+        let retained = self.view.retained_view_entity;
+        let mesh_asset_id = self.mesh;
+        let world = self.world();
+
+        let entity = world.spawn_empty().id();
+        let main_entity = MainEntity::from(entity);
+        let world_from_local =
+            || (&Transform::from_translation(translation).compute_affine()).into();
+        let RenderMeshInstances::CpuBuilding(instances) =
+            &mut *world.resource_mut::<RenderMeshInstances>()
+        else {
+            unreachable!("the fixture builds mesh instances on the CPU");
+        };
+        instances.insert(
+            main_entity,
+            RenderMeshInstanceCpu {
+                shared: RenderMeshInstanceShared {
+                    mesh_asset_id,
+                    material_bindings_index: default(),
+                    flags: RenderMeshInstanceFlags::empty(),
+                    lightmap_slab_index: None,
+                    tag: 0,
+                },
+                transforms: MeshTransforms {
+                    world_from_local: world_from_local(),
+                    previous_world_from_local: world_from_local(),
+                    flags: 0,
+                },
+            },
+        );
+
+        let draw_function = world
+            .resource::<DrawFunctions<Transparent3d>>()
+            .read()
+            .get_id::<TransparentDrawMaterial<StandardMaterial>>()
+            .unwrap();
+        world
+            .resource_mut::<ViewSortedRenderPhases<Transparent3d>>()
+            .get_mut(&retained)
+            .unwrap()
+            .add(Transparent3d {
+                distance,
+                pipeline: CachedRenderPipelineId::INVALID,
+                entity: (entity, main_entity),
+                draw_function,
+                batch_range: 0..1,
+                extra_index: PhaseItemExtraIndex::None,
+                indexed: false,
+            });
+        main_entity
+    }
+
+    /// `f` of each item in the camera's `I` phase, in phase order.
+    pub(crate) fn queued<I: SortedPhaseItem, T>(&mut self, f: impl Fn(&I) -> T) -> Vec<T> {
+        let retained = self.view.retained_view_entity;
+        self.world()
+            .resource::<ViewSortedRenderPhases<I>>()
+            .get(&retained)
+            .unwrap()
+            .items
+            .iter()
+            .map(f)
+            .collect()
+    }
+}
+
+/// A `gpu_app` with both WBOIT paths and a real scene, updated frame by frame up to the phase
+/// sort: the main world runs visibility, the render world extracts, specializes and queues for
+/// real. Preparing and rendering are disabled, as software adapters cannot compile every Bevy
+/// shader.
+pub(crate) struct SceneFixture {
+    app: App,
+    camera: Entity,
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+impl SceneFixture {
+    /// One camera at the origin looking down -Z, rendering to a 64x64 image, with `settings`
+    /// (`WboitSettings` or `HEWboitSettings`). `None` without a GPU adapter.
+    pub(crate) fn new(settings: impl Component) -> Option<Self> {
+        let mut app = gpu_app()?;
+        app.add_plugins((crate::WboitPlugin, crate::HEWboitPlugin));
+        app.finish();
+        app.cleanup();
+        app.sub_app_mut(RenderApp).configure_sets(
+            Render,
+            (RenderSet::Prepare, RenderSet::Render).run_if(|| false),
+        );
+
+        let world = app.world_mut();
+        let mut image = Image::new_uninit(
+            Extent3d {
+                width: 64,
+                height: 64,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            TextureFormat::bevy_default(),
+            RenderAssetUsages::default(),
+        );
+        image.texture_descriptor.usage |= TextureUsages::RENDER_ATTACHMENT;
+        let target = world.resource_mut::<Assets<Image>>().add(image);
+        let camera = world
+            .spawn((
+                Camera3d::default(),
+                Camera {
+                    target: RenderTarget::Image(target.into()),
+                    ..default()
+                },
+                Msaa::Off,
+                settings,
+            ))
+            .id();
+        let mesh = world.resource_mut::<Assets<Mesh>>().add(Cuboid::default());
+        let material = world
+            .resource_mut::<Assets<StandardMaterial>>()
+            .add(StandardMaterial {
+                base_color: Color::srgba(1.0, 1.0, 1.0, 0.5),
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            });
+        Some(Self {
+            app,
+            camera,
+            mesh,
+            material,
+        })
+    }
+
+    /// Spawn a transparent unit cube with `bundle`, a `Transform` at least.
+    pub(crate) fn spawn_transparent(&mut self, bundle: impl Bundle) -> Entity {
+        let components = (
+            Mesh3d(self.mesh.clone()),
+            MeshMaterial3d(self.material.clone()),
+        );
+        self.app.world_mut().spawn((components, bundle)).id()
+    }
+
+    /// Run one frame, up to the phase sort of the render world.
+    pub(crate) fn update(&mut self) {
+        self.app.update();
+    }
+
+    /// Record the main world entities of the camera's `I` phase items each frame, right after
+    /// `queue` filled it and before `drain` may take items out again, for [`Self::queued`].
+    pub(crate) fn record_queued<I: SortedPhaseItem, M1, M2>(
+        &mut self,
+        queue: impl IntoSystemSet<M1>,
+        drain: impl IntoSystemSet<M2>,
+    ) {
+        let camera = self.camera;
+        let record = move |phases: Res<ViewSortedRenderPhases<I>>,
+                           mut queued: ResMut<QueuedEntities>| {
+            let retained = RetainedViewEntity::new(camera.into(), None, 0);
+            queued.0 = phases.get(&retained).map_or(vec![], |phase| {
+                phase
+                    .items
+                    .iter()
+                    .map(|item| item.main_entity().id())
+                    .collect()
+            });
+            queued.0.sort();
+        };
+        self.app
+            .sub_app_mut(RenderApp)
+            .init_resource::<QueuedEntities>()
+            .add_systems(
+                Render,
+                record
+                    .in_set(RenderSet::QueueMeshes)
+                    .after(queue)
+                    .before(drain),
+            );
+    }
+
+    /// Entities recorded by [`Self::record_queued`] in the last frame, sorted.
+    pub(crate) fn queued(&self) -> Vec<Entity> {
+        let world = self.app.get_sub_app(RenderApp).unwrap().world();
+        world.resource::<QueuedEntities>().0.clone()
+    }
+}
+
+/// Phase items recorded by [`SceneFixture::record_queued`].
+#[derive(Resource, Default)]
+struct QueuedEntities(Vec<Entity>);
+
+// Queue behavior shared by `queue_wboit_meshes` and `queue_histo_wboit_meshes`, each checked
+// once per path with its settings, queue system and phase item `I`.
+
+/// `queue` keeps the `Transparent3d` sort distance of each item, the material's `depth_bias`
+/// included.
+pub(crate) fn assert_queue_keeps_depth_bias<I, M>(
+    settings: impl Component,
+    queue: impl IntoSystem<(), (), M>,
+) where
+    I: SortedPhaseItem<SortKey = FloatOrd>,
+{
+    let Some(mut fixture) = QueueFixture::new(settings) else {
+        return;
+    };
+    let translation = Vec3::new(0.0, 0.0, -4.0);
+    let plain = fixture.add_transparent(translation, 0.0);
+    let decal = fixture.add_transparent(translation, 0.5);
+
+    fixture.world().run_system_once(queue).unwrap();
+    let mut queued = fixture.queued(|item: &I| (item.main_entity(), item.sort_key().0));
+    queued.sort_by_key(|(_, distance)| FloatOrd(*distance));
+    assert_eq!(queued, [(plain, -4.0), (decal, -3.5)]);
+}
+
+/// `NoFrustumCulling` meshes outside the frustum, behind the camera or off to the side, reach
+/// `I` through visibility, extraction and `queue`, while a culled mesh does not. `drain` is the
+/// path's `Transparent3d` drain, which runs after the queue.
+pub(crate) fn assert_queues_no_frustum_culling_meshes<I: SortedPhaseItem, M1, M2>(
+    settings: impl Component,
+    queue: impl IntoSystemSet<M1>,
+    drain: impl IntoSystemSet<M2>,
+) {
+    let Some(mut fixture) = SceneFixture::new(settings) else {
+        return;
+    };
+    fixture.record_queued::<I, _, _>(queue, drain);
+    let behind = fixture.spawn_transparent((Transform::from_xyz(0.0, 0.0, 3.0), NoFrustumCulling));
+    let aside = fixture.spawn_transparent((Transform::from_xyz(50.0, 0.0, -1.0), NoFrustumCulling));
+    fixture.spawn_transparent(Transform::from_xyz(0.0, 0.0, 3.0));
+    // The first frames prepare the mesh and material assets.
+    for _ in 0..3 {
+        fixture.update();
+    }
+
+    let mut expected = [behind, aside];
+    expected.sort();
+    assert_eq!(fixture.queued(), expected);
+}
+
+/// `queue`, for a camera with `max_distance` 10 in `settings`, leaves out meshes past it.
+pub(crate) fn assert_queue_skips_meshes_past_max_distance<I: SortedPhaseItem, M>(
+    settings: impl Component,
+    queue: impl IntoSystem<(), (), M>,
+) {
+    let Some(mut fixture) = QueueFixture::new(settings) else {
+        return;
+    };
+    let near = fixture.add_transparent(Vec3::new(0.0, 0.0, -10.0), 0.0);
+    fixture.add_transparent(Vec3::new(0.0, 0.0, -10.5), 0.0);
+
+    fixture.world().run_system_once(queue).unwrap();
+    assert_eq!(fixture.queued(|item: &I| item.main_entity()), [near]);
+}
+
+/// Each of `inputs` through `fn compute(v: vec4<f32>) -> vec4<f32>` of the WGSL `source`, run
+/// in a compute shader: for functions copied out of shaders that need Bevy imports to compile.
+pub(crate) fn run_compute(world: &World, source: &str, inputs: &[[f32; 4]]) -> Vec<[f32; 4]> {
+    let shader = format!(
+        "{source}\n@group(0) @binding(0) var<storage, read_write> data: array<vec4<f32>>;\n\
+         @compute @workgroup_size(1)\n\
+         fn main(@builtin(global_invocation_id) id: vec3<u32>) {{\n\
+         data[id.x] = compute(data[id.x]);\n}}"
+    );
+
+    let device = world.resource::<RenderDevice>().wgpu_device();
+    let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(shader.into()),
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &module,
+        entry_point: Some("main"),
+        compilation_options: default(),
+        cache: None,
+    });
+    let bytes: Vec<u8> = inputs
+        .iter()
+        .flatten()
+        .flat_map(|x| x.to_le_bytes())
+        .collect();
+    let data = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: None,
+        contents: &bytes,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+    });
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: bytes.len() as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: data.as_entire_binding(),
+        }],
+    });
+
+    let mut encoder = device.create_command_encoder(&default());
+    {
+        let mut pass = encoder.begin_compute_pass(&default());
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.dispatch_workgroups(inputs.len() as u32, 1, 1);
+    }
+    encoder.copy_buffer_to_buffer(&data, 0, &readback, 0, bytes.len() as u64);
+    world.resource::<RenderQueue>().submit([encoder.finish()]);
+    readback
+        .slice(..)
+        .map_async(wgpu::MapMode::Read, |result| result.unwrap());
+    device.poll(wgpu::Maintain::Wait);
+    let out = readback.slice(..).get_mapped_range();
+    out.chunks_exact(16)
+        .map(|texel| {
+            core::array::from_fn(|i| {
+                f32::from_le_bytes(texel[i * 4..i * 4 + 4].try_into().unwrap())
+            })
+        })
+        .collect()
+}
@@ -1,41 +1,324 @@
+use bevy::ecs::entity::EntityHashMap;
 use bevy::prelude::*;
 use bevy::render::camera::ExtractedCamera;
 use bevy::render::render_resource::{
-    Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+    Buffer, BufferInitDescriptor, BufferUsages, Extent3d, TextureDescriptor, TextureDimension,
+    TextureFormat, TextureUsages, TextureViewDescriptor,
 };
-use bevy::render::renderer::RenderDevice;
+use bevy::render::renderer::{RenderDevice, RenderQueue};
 use bevy::render::texture::{CachedTexture, TextureCache};
+use bevy::render::{Render, RenderSet};
 
+use crate::naive::shared::WboitSharedViews;
 use crate::settings::WboitSettings;
 
-/// Per-camera WBOIT textures in the render world.
+/// Format of the revealage targets, shared by the naive and HE accum pipelines and textures.
+///
+/// Revealage is raw transmittance, so this must stay a linear (non-sRGB) format: an sRGB
+/// format would encode on write and decode on read, bending the multiplicative blend. The
+/// composite binds it as unfilterable float and reads it without any color-space transform.
+pub const WBOIT_REVEALAGE_FORMAT: TextureFormat = TextureFormat::R8Unorm;
+
+/// Format of the naive accum target. The accum shaders clamp fragment weights to its range
+/// (see [`accum_format_max`]).
+pub const WBOIT_ACCUM_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// Largest value `format` stores, rounded down to a `u32` for the `ACCUM_FORMAT_MAX` shader
+/// def (shader defs have no float variant): the half-float maximum, `u32::MAX` for 32-bit
+/// floats, which is far beyond any weight, and `1` for normalized formats.
+pub(crate) fn accum_format_max(format: TextureFormat) -> u32 {
+    match format {
+        TextureFormat::R16Float | TextureFormat::Rg16Float | TextureFormat::Rgba16Float => 65504,
+        TextureFormat::R32Float | TextureFormat::Rg32Float | TextureFormat::Rgba32Float => u32::MAX,
+        _ => 1,
+    }
+}
+
+/// Format of the nearest-transparent-depth target of `WboitSettings::nearest_depth_falloff`.
+pub const WBOIT_NEAREST_DEPTH_FORMAT: TextureFormat = TextureFormat::Depth32Float;
+
+/// GPU-side naive WBOIT parameters (must match WboitParams in wboit_fragment.wgsl and
+/// wboit_composite.wgsl). Bound in both the accum and the composite pass.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct WboitParams {
+    pub thickness_absorption: f32,
+    /// Non-zero when `WboitSettings::sanitize_output` is enabled.
+    pub sanitize_output: u32,
+    /// Non-zero when the accum targets are reduced-resolution (`WboitSettings::accum_scale`
+    /// below `1.0`) and only cover the viewport.
+    pub accum_scaled: u32,
+    /// Coverage cap applied in the composite (`WboitSettings::max_opacity`).
+    pub max_opacity: f32,
+    /// Opacity multiplier applied in the accum pass (`WboitSettings::global_opacity`).
+    pub global_opacity: f32,
+    /// Exponent applied to revealage in the composite (`WboitSettings::revealage_gamma`).
+    pub revealage_gamma: f32,
+    /// Soft-particle fade distance in the accum pass (`WboitSettings::soft_particle_distance`).
+    pub soft_particle_distance: f32,
+    /// Color multiplier applied in the composite (`WboitSettings::composite_exposure`).
+    pub composite_exposure: f32,
+    /// View-space offset of the accum pass's depth test (`WboitSettings::depth_test_bias`).
+    pub depth_test_bias: f32,
+    /// `WboitSettings::nearest_depth_falloff`, or `0.0` when it is off.
+    pub nearest_depth_falloff: f32,
+    /// Per-axis ratio of the accum target size to the viewport size
+    /// (`WboitSettings::accum_ratio`), `[1.0, 1.0]` at full resolution.
+    pub accum_scale: [f32; 2],
+}
+
+impl WboitParams {
+    /// Parameters of a camera with `settings`, for its physical viewport and render target
+    /// size.
+    pub fn from_settings(settings: &WboitSettings, viewport: UVec2, target: UVec2) -> Self {
+        Self {
+            thickness_absorption: settings.thickness_absorption,
+            sanitize_output: settings.sanitize_output as u32,
+            accum_scaled: settings.is_accum_scaled() as u32,
+            max_opacity: settings.max_opacity.clamp(0.0, 1.0),
+            global_opacity: settings.global_opacity.clamp(0.0, 1.0),
+            revealage_gamma: settings.revealage_gamma.max(0.0),
+            soft_particle_distance: settings.soft_particle_distance.max(0.0),
+            composite_exposure: settings.composite_exposure.max(0.0),
+            depth_test_bias: settings.depth_test_bias,
+            nearest_depth_falloff: settings.nearest_depth_falloff.unwrap_or(0.0).max(0.0),
+            accum_scale: settings.accum_ratio(viewport, target).to_array(),
+        }
+    }
+
+    pub(crate) fn as_bytes(&self) -> [u8; 48] {
+        let mut bytes = [0u8; 48];
+        bytes[0..4].copy_from_slice(&self.thickness_absorption.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.sanitize_output.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.accum_scaled.to_le_bytes());
+        bytes[12..16].copy_from_slice(&self.max_opacity.to_le_bytes());
+        bytes[16..20].copy_from_slice(&self.global_opacity.to_le_bytes());
+        bytes[20..24].copy_from_slice(&self.revealage_gamma.to_le_bytes());
+        bytes[24..28].copy_from_slice(&self.soft_particle_distance.to_le_bytes());
+        bytes[28..32].copy_from_slice(&self.composite_exposure.to_le_bytes());
+        bytes[32..36].copy_from_slice(&self.depth_test_bias.to_le_bytes());
+        bytes[36..40].copy_from_slice(&self.nearest_depth_falloff.to_le_bytes());
+        bytes[40..44].copy_from_slice(&self.accum_scale[0].to_le_bytes());
+        bytes[44..48].copy_from_slice(&self.accum_scale[1].to_le_bytes());
+        bytes
+    }
+}
+
+/// Per-camera uniform buffer holding `WboitParams` for the naive accum and composite passes.
 #[derive(Component)]
+pub struct WboitParamsBuffer(pub Buffer);
+
+/// Per-camera WBOIT textures in the render world.
+#[derive(Component, Clone)]
 pub struct WboitTextures {
-    /// Rgba16Float accumulation texture
+    /// `WBOIT_ACCUM_FORMAT` accumulation texture (`Rgba16Float` on the HE path). The naive
+    /// accum shaders clamp weights to the format's range through the `ACCUM_FORMAT_MAX`
+    /// shader def.
     pub accum: CachedTexture,
-    /// R8Unorm revealage textures, double-buffered for histogram variant
+    /// `WBOIT_REVEALAGE_FORMAT` revealage textures, double-buffered for histogram variant
     pub revealage: [CachedTexture; 2],
     /// Toggles 0/1 each frame for double buffering
     pub frame_index: usize,
+    /// Rgba16Float sum of `AlphaMode::Add` fragments, which add light without contributing
+    /// coverage. Naive path only (`None` on the HE path).
+    pub glow: Option<CachedTexture>,
+    /// R16Float per-pixel transparent fragment count (largest fragment weight for
+    /// `WboitDebug::Weight`), only allocated for `WboitDebug::Overdraw` and `WboitDebug::Weight`.
+    pub overdraw: Option<CachedTexture>,
+    /// Rg16Float weighted sum of the `xy` of the view-space transparent surface normals, only
+    /// allocated for `WboitSettings::accumulate_normals`. Weighted like the accum color, so
+    /// dividing by the accum alpha gives the average normal at the pixel; `z` is
+    /// `sqrt(1 - x² - y²)`, towards the camera. Zero where no transparents were drawn.
+    pub normal: Option<CachedTexture>,
+    /// `WBOIT_NEAREST_DEPTH_FORMAT` depth of the nearest transparent surface, written by the
+    /// nearest-depth prepass; only allocated for `WboitSettings::nearest_depth_falloff`.
+    pub nearest_depth: Option<CachedTexture>,
+    /// The textures are the camera's own rather than taken from `TextureCache`, for
+    /// `WboitSettings::persistent_textures`, and keep their contents between frames.
+    pub persistent: bool,
+    /// The camera owning these textures, for a camera drawing into them through a
+    /// `WboitSharedTarget`. Its accum pass adds to them instead of clearing them.
+    pub shared_with: Option<Entity>,
+}
+
+impl WboitTextures {
+    /// Bytes of GPU memory held by these textures.
+    pub fn allocated_bytes(&self) -> u64 {
+        [
+            Some(&self.accum),
+            self.glow.as_ref(),
+            self.overdraw.as_ref(),
+            self.normal.as_ref(),
+            self.nearest_depth.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .chain(&self.revealage)
+        .map(texture_bytes)
+        .sum()
+    }
+
+    /// Whether these are the camera's own persistent textures and still fit `settings` at
+    /// `size`, so `prepare_wboit_textures` keeps them.
+    fn reusable(&self, settings: &WboitSettings, size: UVec2) -> bool {
+        self.persistent
+            && settings.persistent_textures
+            && self.shared_with.is_none()
+            && self.fits(settings, size)
+    }
+
+    /// Whether these textures have the size and the optional targets `settings` needs at
+    /// `size`.
+    fn fits(&self, settings: &WboitSettings, size: UVec2) -> bool {
+        let accum_size = self.accum.texture.size();
+        UVec2::new(accum_size.width, accum_size.height) == size
+            && self.overdraw.is_some() == settings.debug.uses_debug_target()
+            && self.normal.is_some() == settings.accumulate_normals
+            && self.nearest_depth.is_some() == settings.uses_nearest_depth()
+    }
+}
+
+/// Allocate one of a camera's naive WBOIT textures: from `TextureCache`, or as a texture of
+/// its own when `persistent`.
+fn allocate_wboit_texture(
+    texture_cache: &mut TextureCache,
+    render_device: &RenderDevice,
+    persistent: bool,
+    descriptor: TextureDescriptor<'static>,
+) -> CachedTexture {
+    if !persistent {
+        return texture_cache.get(render_device, descriptor);
+    }
+    let texture = render_device.create_texture(&descriptor);
+    CachedTexture {
+        default_view: texture.create_view(&TextureViewDescriptor::default()),
+        texture,
+    }
+}
+
+/// Bytes of GPU memory held by a single-mip, single-sample texture.
+pub(crate) fn texture_bytes(texture: &CachedTexture) -> u64 {
+    let size = texture.texture.size();
+    let block_bytes = texture.texture.format().block_copy_size(None).unwrap_or(0);
+    u64::from(size.width)
+        * u64::from(size.height)
+        * u64::from(size.depth_or_array_layers)
+        * u64::from(block_bytes)
+}
+
+/// Render world event sent when a camera's WBOIT textures are allocated or resized, by
+/// `prepare_wboit_textures` and `prepare_histogram_wboit_textures` (in
+/// `RenderSet::PrepareResources`). Not sent for the per-frame revealage swap.
+///
+/// Read it in the render world (e.g. in a system in `RenderSet::PrepareBindGroups`) to rebuild
+/// resources derived from [`WboitTextures`]. Events are dropped in `RenderSet::Cleanup` of the
+/// following frame.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WboitTexturesRecreated {
+    /// Render world view entity holding the `WboitTextures`.
+    pub camera: Entity,
+    /// New size of the accum and revealage targets, in pixels.
+    pub size: UVec2,
+}
+
+/// Register [`WboitTexturesRecreated`] in the render world. Called by both `NaiveWboitPlugin`
+/// and `HEWboitPlugin`; only the first call does anything.
+pub(crate) fn add_wboit_textures_recreated_event(render_app: &mut SubApp) {
+    if render_app
+        .world()
+        .contains_resource::<Events<WboitTexturesRecreated>>()
+    {
+        return;
+    }
+    // The render schedule has no `First`, so the double buffer is swapped by hand.
+    render_app
+        .init_resource::<Events<WboitTexturesRecreated>>()
+        .add_systems(
+            Render,
+            update_wboit_textures_recreated_events.in_set(RenderSet::Cleanup),
+        );
+}
+
+fn update_wboit_textures_recreated_events(mut events: ResMut<Events<WboitTexturesRecreated>>) {
+    events.update();
 }
 
 /// Prepare (create/resize) WBOIT textures for cameras with `WboitSettings`.
+///
+/// Cameras drawing into another camera's targets through a `WboitSharedTarget` get a copy of
+/// the owner's `WboitTextures` instead, when they fit.
 pub fn prepare_wboit_textures(
     mut commands: Commands,
     render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
     mut texture_cache: ResMut<TextureCache>,
-    cameras: Query<(Entity, &ExtractedCamera), With<WboitSettings>>,
+    cameras: Query<(Entity, &ExtractedCamera, &WboitSettings)>,
     mut existing: Query<&mut WboitTextures>,
+    params_buffers: Query<&WboitParamsBuffer>,
+    mut recreated: EventWriter<WboitTexturesRecreated>,
+    shared_views: Res<WboitSharedViews>,
 ) {
-    for (entity, camera) in &cameras {
-        let Some(size) = camera.physical_viewport_size else {
+    // Owners of shared targets first, so the cameras sharing them find their textures.
+    let mut cameras: Vec<_> = cameras.iter().collect();
+    cameras.sort_by_key(|(entity, ..)| shared_views.owner_of(*entity).is_some());
+    let mut owner_textures = EntityHashMap::<WboitTextures>::default();
+
+    for (entity, camera, settings) in cameras {
+        let (Some(viewport_size), Some(target_size)) =
+            (camera.physical_viewport_size, camera.physical_target_size)
+        else {
             continue;
         };
+        let size = settings.accum_size(viewport_size, target_size);
         let width = size.x;
         let height = size.y;
 
-        let accum = texture_cache.get(
+        // Params buffer: create once, then rewrite each frame so settings edits apply.
+        let params = WboitParams::from_settings(settings, viewport_size, target_size);
+        if let Ok(params_buffer) = params_buffers.get(entity) {
+            render_queue.write_buffer(&params_buffer.0, 0, &params.as_bytes());
+        } else {
+            let buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
+                label: Some("wboit_params_buffer"),
+                contents: &params.as_bytes(),
+                usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            });
+            commands.entity(entity).insert(WboitParamsBuffer(buffer));
+        }
+
+        if let Some(owner) = shared_views.owner_of(entity) {
+            match owner_textures.get(&owner) {
+                Some(textures) if textures.fits(settings, size) => {
+                    commands.entity(entity).insert(WboitTextures {
+                        shared_with: Some(owner),
+                        ..textures.clone()
+                    });
+                    continue;
+                }
+                _ => warn_once!(
+                    "WboitSharedTarget camera {entity} doesn't fit the targets of {owner} \
+                     (size, accum_scale or target-changing settings differ); it keeps its own"
+                ),
+            }
+        }
+        let is_owner = shared_views.is_owner(entity);
+
+        // Persistent textures are kept as they are; only the revealage slots swap roles.
+        if let Ok(mut tex) = existing.get_mut(entity)
+            && tex.reusable(settings, size)
+        {
+            tex.frame_index = 1 - tex.frame_index;
+            if is_owner {
+                owner_textures.insert(entity, tex.clone());
+            }
+            continue;
+        }
+
+        let persistent = settings.persistent_textures;
+        let accum = allocate_wboit_texture(
+            &mut texture_cache,
             &render_device,
+            persistent,
             TextureDescriptor {
                 label: Some("wboit_accum"),
                 size: Extent3d {
@@ -46,14 +329,19 @@ pub fn prepare_wboit_textures(
                 mip_level_count: 1,
                 sample_count: 1,
                 dimension: TextureDimension::D2,
-                format: TextureFormat::Rgba16Float,
-                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                format: WBOIT_ACCUM_FORMAT,
+                // COPY_SRC for WboitPixelProbe.
+                usage: TextureUsages::RENDER_ATTACHMENT
+                    | TextureUsages::TEXTURE_BINDING
+                    | TextureUsages::COPY_SRC,
                 view_formats: &[],
             },
         );
 
-        let revealage_a = texture_cache.get(
+        let revealage_a = allocate_wboit_texture(
+            &mut texture_cache,
             &render_device,
+            persistent,
             TextureDescriptor {
                 label: Some("wboit_revealage_a"),
                 size: Extent3d {
@@ -64,14 +352,18 @@ pub fn prepare_wboit_textures(
                 mip_level_count: 1,
                 sample_count: 1,
                 dimension: TextureDimension::D2,
-                format: TextureFormat::R8Unorm,
-                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                format: WBOIT_REVEALAGE_FORMAT,
+                usage: TextureUsages::RENDER_ATTACHMENT
+                    | TextureUsages::TEXTURE_BINDING
+                    | TextureUsages::COPY_SRC,
                 view_formats: &[],
             },
         );
 
-        let revealage_b = texture_cache.get(
+        let revealage_b = allocate_wboit_texture(
+            &mut texture_cache,
             &render_device,
+            persistent,
             TextureDescriptor {
                 label: Some("wboit_revealage_b"),
                 size: Extent3d {
@@ -82,23 +374,269 @@ pub fn prepare_wboit_textures(
                 mip_level_count: 1,
                 sample_count: 1,
                 dimension: TextureDimension::D2,
-                format: TextureFormat::R8Unorm,
+                format: WBOIT_REVEALAGE_FORMAT,
+                usage: TextureUsages::RENDER_ATTACHMENT
+                    | TextureUsages::TEXTURE_BINDING
+                    | TextureUsages::COPY_SRC,
+                view_formats: &[],
+            },
+        );
+
+        let glow = allocate_wboit_texture(
+            &mut texture_cache,
+            &render_device,
+            persistent,
+            TextureDescriptor {
+                label: Some("wboit_glow"),
+                size: Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::Rgba16Float,
                 usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
                 view_formats: &[],
             },
         );
 
+        let overdraw = settings.debug.uses_debug_target().then(|| {
+            allocate_wboit_texture(
+                &mut texture_cache,
+                &render_device,
+                persistent,
+                TextureDescriptor {
+                    label: Some("wboit_overdraw"),
+                    size: Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: TextureDimension::D2,
+                    format: TextureFormat::R16Float,
+                    usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                },
+            )
+        });
+
+        let normal = settings.accumulate_normals.then(|| {
+            allocate_wboit_texture(
+                &mut texture_cache,
+                &render_device,
+                persistent,
+                TextureDescriptor {
+                    label: Some("wboit_normal"),
+                    size: Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: TextureDimension::D2,
+                    format: TextureFormat::Rg16Float,
+                    usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                },
+            )
+        });
+
+        let nearest_depth = settings.uses_nearest_depth().then(|| {
+            allocate_wboit_texture(
+                &mut texture_cache,
+                &render_device,
+                persistent,
+                TextureDescriptor {
+                    label: Some("wboit_nearest_depth"),
+                    size: Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: TextureDimension::D2,
+                    format: WBOIT_NEAREST_DEPTH_FORMAT,
+                    usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                },
+            )
+        });
+
         // Toggle frame index or initialize
         if let Ok(mut tex) = existing.get_mut(entity) {
+            let resized = tex.accum.texture.size() != accum.texture.size()
+                || tex.overdraw.is_some() != overdraw.is_some()
+                || tex.normal.is_some() != normal.is_some()
+                || tex.nearest_depth.is_some() != nearest_depth.is_some()
+                || tex.persistent != persistent
+                || tex.shared_with.is_some();
             tex.accum = accum;
             tex.revealage = [revealage_a, revealage_b];
             tex.frame_index = 1 - tex.frame_index;
+            tex.glow = Some(glow);
+            tex.overdraw = overdraw;
+            tex.normal = normal;
+            tex.nearest_depth = nearest_depth;
+            tex.persistent = persistent;
+            tex.shared_with = None;
+            if is_owner {
+                owner_textures.insert(entity, tex.clone());
+            }
+            if resized {
+                debug!(
+                    "WBOIT textures for {entity} resized to {width}x{height}: {} bytes",
+                    tex.allocated_bytes()
+                );
+                recreated.write(WboitTexturesRecreated {
+                    camera: entity,
+                    size,
+                });
+            }
         } else {
-            commands.entity(entity).insert(WboitTextures {
+            let textures = WboitTextures {
                 accum,
                 revealage: [revealage_a, revealage_b],
                 frame_index: 0,
+                glow: Some(glow),
+                overdraw,
+                normal,
+                nearest_depth,
+                persistent,
+                shared_with: None,
+            };
+            debug!(
+                "WBOIT textures for {entity} created at {width}x{height}: {} bytes",
+                textures.allocated_bytes()
+            );
+            if is_owner {
+                owner_textures.insert(entity, textures.clone());
+            }
+            commands.entity(entity).insert(textures);
+            recreated.write(WboitTexturesRecreated {
+                camera: entity,
+                size,
             });
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::render::RenderApp;
+
+    use crate::test_utils::{extracted_camera, gpu_app};
+
+    /// The `WboitParams` uniform of a camera with `settings` filling a 101x37 target.
+    fn packed(settings: &WboitSettings) -> [u8; 48] {
+        let size = UVec2::new(101, 37);
+        WboitParams::from_settings(settings, size, size).as_bytes()
+    }
+
+    /// The `f32` at byte `offset` of [`packed`].
+    fn packed_f32(settings: &WboitSettings, offset: usize) -> f32 {
+        f32::from_le_bytes(packed(settings)[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn revealage_gamma_is_packed_and_clamped() {
+        let gamma = |revealage_gamma| {
+            let settings = WboitSettings {
+                revealage_gamma,
+                ..default()
+            };
+            packed_f32(&settings, 20)
+        };
+        assert_eq!(gamma(2.2), 2.2);
+        assert_eq!(gamma(-1.0), 0.0);
+    }
+
+    #[test]
+    fn composite_exposure_is_packed_and_clamped() {
+        let exposure = |composite_exposure| {
+            let settings = WboitSettings {
+                composite_exposure,
+                ..default()
+            };
+            packed_f32(&settings, 28)
+        };
+        assert_eq!(exposure(4.0), 4.0);
+        assert_eq!(exposure(-1.0), 0.0);
+    }
+
+    #[test]
+    fn sanitize_output_is_packed_as_a_flag() {
+        let flag = |sanitize_output| {
+            let settings = WboitSettings {
+                sanitize_output,
+                ..default()
+            };
+            packed(&settings)[4..8].to_vec()
+        };
+        assert_eq!(flag(true), 1u32.to_le_bytes());
+        assert_eq!(flag(false), 0u32.to_le_bytes());
+    }
+
+    #[test]
+    fn accum_scale_is_packed_from_the_rounded_target_size() {
+        let full = WboitSettings::default();
+        assert_eq!(packed(&full)[8..12], 0u32.to_le_bytes());
+        assert_eq!([packed_f32(&full, 40), packed_f32(&full, 44)], [1.0, 1.0]);
+
+        // 51x19 targets for the 101x37 viewport.
+        let half = WboitSettings {
+            accum_scale: 0.5,
+            ..default()
+        };
+        assert_eq!(packed(&half)[8..12], 1u32.to_le_bytes());
+        assert_eq!(
+            [packed_f32(&half, 40), packed_f32(&half, 44)],
+            [51.0 / 101.0, 19.0 / 37.0]
+        );
+    }
+
+    /// Events `prepare_wboit_textures` sent in one run, drained.
+    fn prepare(world: &mut World) -> Vec<WboitTexturesRecreated> {
+        world.run_system_once(prepare_wboit_textures).unwrap();
+        world
+            .resource_mut::<Events<WboitTexturesRecreated>>()
+            .drain()
+            .collect()
+    }
+
+    #[test]
+    fn recreated_is_sent_once_per_resize_and_not_on_steady_frames() {
+        let Some(mut app) = gpu_app() else {
+            return;
+        };
+        app.add_plugins(crate::WboitPlugin);
+        app.finish();
+        app.cleanup();
+        let world = app.get_sub_app_mut(RenderApp).unwrap().world_mut();
+
+        let size = UVec2::new(64, 32);
+        let camera = world
+            .spawn((extracted_camera(size), WboitSettings::default()))
+            .id();
+        assert_eq!(prepare(world), [WboitTexturesRecreated { camera, size }]);
+        assert_eq!(prepare(world), []);
+
+        let resized = UVec2::new(80, 48);
+        world.entity_mut(camera).insert(extracted_camera(resized));
+        assert_eq!(
+            prepare(world),
+            [WboitTexturesRecreated {
+                camera,
+                size: resized
+            }]
+        );
+        assert_eq!(prepare(world), []);
+    }
+}